@@ -1,205 +1,71 @@
-use std::sync::{Arc, Mutex};
-
-use nconsole::{Command, CommandsRegister, Console, LogTypes};
+//! A tiny console player, type `help` to see the commands
+//!
+//! Usage: cargo run --example cli_player
 
 use n_audio::player::Player;
-
-struct PlayCommand {
-    player: Arc<Mutex<Player>>,
-}
-
-impl PlayCommand {
-    fn new(player: Arc<Mutex<Player>>) -> Self {
-        PlayCommand { player }
-    }
-}
-
-impl Command for PlayCommand {
-    fn get_command_name(&self) -> &str {
-        "play"
-    }
-
-    fn get_command_alias(&self) -> Vec<&str> {
-        vec!["p"]
-    }
-
-    fn get_help(&self) -> &str {
-        "Play a specific music file.\nUsage: play file.mp3"
-    }
-
-    fn on_command(&mut self, args: Vec<&str>) {
-        if args.len() == 1 {
-            let mut player = self.player.lock().unwrap();
-            if !player.has_ended() {
-                player.end_current().unwrap();
+use std::io::{BufRead, Write};
+
+const HELP: &str = "play <file>     plays a file (p)
+pause           toggles pause (s)
+volume <0..1>   sets the volume (v)
+seek <seconds>  seeks to the second (sk)
+speed <speed>   sets the playback speed (sp)
+time            shows where the track is (t)
+quit            exits (q)";
+
+#[tokio::main]
+async fn main() {
+    let mut player = Player::default();
+    let stdin = std::io::stdin();
+    print!(">>> ");
+    let _ = std::io::stdout().flush();
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let (command, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        let args = args.trim();
+
+        match command {
+            "play" | "p" => {
+                if player.is_playing() && !player.has_ended() {
+                    player.end_current().await.unwrap();
+                }
+                if let Err(e) = player.play_from_path(args) {
+                    eprintln!("can't play {args}: {e}");
+                }
             }
-
-            Console::log(LogTypes::INFO, String::from("Playing song"));
-            player.play_from_path(args[0]).unwrap();
-        }
-    }
-}
-
-struct StopCommand {
-    player: Arc<Mutex<Player>>,
-}
-
-impl StopCommand {
-    fn new(player: Arc<Mutex<Player>>) -> Self {
-        StopCommand { player }
-    }
-}
-
-impl Command for StopCommand {
-    fn get_command_name(&self) -> &str {
-        "stop"
-    }
-
-    fn get_command_alias(&self) -> Vec<&str> {
-        vec!["s"]
-    }
-
-    fn get_help(&self) -> &str {
-        "Toggle pause the music.\nUsage: stop"
-    }
-
-    fn on_command(&mut self, _args: Vec<&str>) {
-        let mut player = self.player.lock().unwrap();
-
-        Console::log(LogTypes::INFO, String::from("Toggling pause"));
-
-        if player.is_paused() {
-            player.unpause().unwrap();
-        } else {
-            player.pause().unwrap();
-        }
-    }
-}
-
-struct VolumeCommand {
-    player: Arc<Mutex<Player>>,
-}
-
-impl VolumeCommand {
-    fn new(player: Arc<Mutex<Player>>) -> Self {
-        VolumeCommand { player }
-    }
-}
-
-impl Command for VolumeCommand {
-    fn get_command_name(&self) -> &str {
-        "volume"
-    }
-
-    fn get_command_alias(&self) -> Vec<&str> {
-        vec!["v"]
-    }
-
-    fn get_help(&self) -> &str {
-        "Sets the volume (min: 0.0, max: 1.0).\nUsage: volume 0.5"
-    }
-
-    fn on_command(&mut self, args: Vec<&str>) {
-        if args.len() == 1 {
-            let mut player = self.player.lock().unwrap();
-            let mut volume = args[0].parse::<f32>().unwrap();
-            volume = volume.min(1.0);
-            volume = volume.max(0.0);
-
-            Console::log(LogTypes::INFO, String::from("Setting volume"));
-
-            player.set_volume(volume).unwrap();
+            "pause" | "s" => {
+                if player.is_paused() {
+                    player.unpause().await.unwrap();
+                } else {
+                    player.pause().await.unwrap();
+                }
+            }
+            "volume" | "v" => match args.parse::<f32>() {
+                Ok(volume) => player.set_volume(volume.clamp(0.0, 1.0)).await.unwrap(),
+                Err(_) => eprintln!("the volume must be a number between 0 and 1"),
+            },
+            "seek" | "sk" => match args.parse::<u64>() {
+                Ok(seconds) => player.seek_to(seconds, 0.0).await.unwrap(),
+                Err(_) => eprintln!("the position must be a number of seconds"),
+            },
+            "speed" | "sp" => match args.parse::<f32>() {
+                Ok(speed) => player.set_playback_speed(speed).await.unwrap(),
+                Err(_) => eprintln!("the speed must be a number"),
+            },
+            "time" | "t" => match player.get_time() {
+                Some(time) => println!("{} / {}", time.format_pos(), time.format_len()),
+                None => println!("nothing is playing"),
+            },
+            "quit" | "q" => break,
+            _ => println!("{HELP}"),
         }
-    }
-}
-
-struct SeekToCommand {
-    player: Arc<Mutex<Player>>,
-}
-
-impl SeekToCommand {
-    fn new(player: Arc<Mutex<Player>>) -> Self {
-        SeekToCommand { player }
-    }
-}
-
-impl Command for SeekToCommand {
-    fn get_command_name(&self) -> &str {
-        "seekto"
-    }
 
-    fn get_command_alias(&self) -> Vec<&str> {
-        vec!["seek", "sk", "skt"]
+        print!(">>> ");
+        let _ = std::io::stdout().flush();
     }
 
-    fn get_help(&self) -> &str {
-        "Seeks to the second specified.\nUsage: seek 10"
-    }
-
-    fn on_command(&mut self, args: Vec<&str>) {
-        if args.len() == 1 {
-            let player = self.player.lock().unwrap();
-            let seekto = args[0].parse::<u64>().unwrap();
-
-            Console::log(LogTypes::INFO, String::from("Seeking to"));
-
-            player.seek_to(seekto, 0.0).unwrap();
-        }
-    }
-}
-
-struct PlaybackSpeedCommand {
-    player: Arc<Mutex<Player>>,
-}
-
-impl PlaybackSpeedCommand {
-    fn new(player: Arc<Mutex<Player>>) -> Self {
-        PlaybackSpeedCommand { player }
-    }
-}
-
-impl Command for PlaybackSpeedCommand {
-    fn get_command_name(&self) -> &str {
-        "speed"
-    }
-
-    fn get_command_alias(&self) -> Vec<&str> {
-        vec!["sp"]
-    }
-
-    fn get_help(&self) -> &str {
-        "Sets the playback speed.\nUsage: speed 2.0"
-    }
-
-    fn on_command(&mut self, args: Vec<&str>) {
-        if args.len() == 1 {
-            let mut player = self.player.lock().unwrap();
-            let speed = args[0].parse::<f32>().unwrap();
-
-            Console::log(LogTypes::INFO, String::from("Setting playback speed"));
-
-            player.set_playback_speed(speed).unwrap();
-        }
-    }
-}
-
-fn main() {
-    let player = Arc::new(Mutex::new(Player::new(
-        String::from("N Cli Player"),
-        1.0,
-        1.0,
-    )));
-    let mut commands_register = CommandsRegister::new();
-
-    commands_register.register_command(PlayCommand::new(player.clone()));
-    commands_register.register_command(StopCommand::new(player.clone()));
-    commands_register.register_command(SeekToCommand::new(player.clone()));
-    commands_register.register_command(VolumeCommand::new(player.clone()));
-    commands_register.register_command(PlaybackSpeedCommand::new(player.clone()));
-
-    let mut console = Console::new(String::from(">>> "), commands_register);
-
-    loop {
-        console.update();
-    }
+    let _ = player.end_current().await;
 }
@@ -0,0 +1,52 @@
+//! Plays every file inside a folder in random order
+//!
+//! Usage: cargo run --example play_folder -- <folder>
+
+use n_audio::player::Player;
+use n_audio::queue::QueuePlayer;
+use std::io::Write;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let Some(folder) = std::env::args().nth(1) else {
+        eprintln!("usage: play_folder <folder>");
+        return Ok(());
+    };
+
+    let mut queue = QueuePlayer::with_player(folder.clone(), Player::builder().volume(0.8).build());
+    let files = std::fs::read_dir(&folder)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .map(|path| path.to_string_lossy().to_string());
+    queue.add_all(files).await?;
+    if queue.is_empty() {
+        eprintln!("{folder} is empty");
+        return Ok(());
+    }
+    queue.shuffle();
+
+    play_next(&mut queue).await?;
+    loop {
+        if queue.has_ended() {
+            play_next(&mut queue).await?;
+        }
+        if let (Some(name), Some(time)) = (queue.current_track_name().await, queue.get_time()) {
+            print!("\r{name} {} / {}", time.format_pos(), time.format_len());
+            let _ = std::io::stdout().flush();
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+/// Plays the next file that can be played, the ones that aren't audio are skipped
+async fn play_next(queue: &mut QueuePlayer) -> std::io::Result<()> {
+    for _ in 0..queue.len() {
+        match queue.play_next().await {
+            Ok(()) => return Ok(()),
+            Err(e) => eprintln!("skipping a file: {e}"),
+        }
+    }
+    Err(std::io::Error::other("none of the files can be played"))
+}
@@ -0,0 +1,44 @@
+//! Measures how long a file takes to be ready to play, from opening it to its first decoded packet
+//!
+//! Usage: cargo run --release --example startup_latency -- <file>...
+
+use n_audio::music_track::MusicTrack;
+use n_audio::CODEC_REGISTRY;
+use std::time::Instant;
+use symphonia::core::codecs::DecoderOptions;
+
+fn main() -> std::io::Result<()> {
+    let files = std::env::args().skip(1).collect::<Vec<_>>();
+    if files.is_empty() {
+        eprintln!("usage: startup_latency <file>...");
+        return Ok(());
+    }
+
+    for file in files {
+        let start = Instant::now();
+        let mut format = MusicTrack::new(file.clone())?.get_format()?;
+        let probed = start.elapsed();
+
+        let Some(track) = format.default_track() else {
+            eprintln!("{file} has no audio track");
+            continue;
+        };
+        let track_id = track.id;
+        let mut decoder = CODEC_REGISTRY
+            .make(&track.codec_params, &DecoderOptions::default())
+            .map_err(std::io::Error::other)?;
+        while let Ok(packet) = format.next_packet() {
+            if packet.track_id() == track_id && decoder.decode(&packet).is_ok() {
+                break;
+            }
+        }
+
+        println!(
+            "{file}: probed in {:.1} ms, first packet decoded in {:.1} ms",
+            probed.as_secs_f64() * 1000.0,
+            start.elapsed().as_secs_f64() * 1000.0
+        );
+    }
+
+    Ok(())
+}
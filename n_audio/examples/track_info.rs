@@ -0,0 +1,37 @@
+//! Prints the tags, the properties and the chapters of a file without playing it
+//!
+//! Usage: cargo run --example track_info -- <file>
+
+use n_audio::music_track::MusicTrack;
+
+fn main() -> std::io::Result<()> {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: track_info <file>");
+        return Ok(());
+    };
+    let track = MusicTrack::new(path)?;
+
+    let meta = track.get_meta()?;
+    println!("title:  {}", meta.title);
+    println!("artist: {}", meta.artist);
+    println!("album:  {}", meta.album);
+    println!("length: {}", meta.time.format_len());
+
+    let properties = track.get_properties()?;
+    println!(
+        "{}, {} Hz, {} channels, {} kbps",
+        properties.codec,
+        properties.sample_rate,
+        properties.channels,
+        properties.bitrate / 1000
+    );
+    for (key, value) in properties.tags {
+        println!("  {key}: {value}");
+    }
+
+    for chapter in track.get_chapters()? {
+        println!("{:>8.1}s {}", chapter.start, chapter.title);
+    }
+
+    Ok(())
+}
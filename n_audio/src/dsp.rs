@@ -0,0 +1,217 @@
+//! Equalizer and effects applied to the decoded samples before they reach the audio output
+
+use std::f32::consts::PI;
+
+/// Center frequencies of the equalizer bands, in Hz
+pub const BANDS: [f32; 10] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+/// Highest boost or cut of a band or of the preamp, in dB
+pub const MAX_GAIN: f32 = 12.0;
+
+const Q: f32 = 1.41;
+const CROSSFEED_CUTOFF: f32 = 700.0;
+const CROSSFEED_LEVEL: f32 = 0.3;
+// below it the center is kept, it's mostly bass and kick drum instead of vocals
+const KARAOKE_CUTOFF: f32 = 150.0;
+const LIMITER_THRESHOLD: f32 = 0.98;
+const LIMITER_RELEASE: f32 = 0.1;
+
+/// Settings of the effects chain, sent to the track thread with [`crate::Message::Dsp`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DspSettings {
+    pub enabled: bool,
+    /// Gain of each of the [`BANDS`], in dB
+    pub bands: [f32; BANDS.len()],
+    /// Gain applied before the equalizer, in dB
+    pub preamp: f32,
+    /// Mixes a bit of each channel into the other one, so headphones sound less wide
+    pub crossfeed: bool,
+    /// Removes what's in the center of stereo tracks, where the vocals usually are
+    pub karaoke: bool,
+    /// Lowers the volume instead of clipping when the equalizer boosts the signal too much
+    pub limiter: bool,
+}
+
+impl Default for DspSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bands: [0.0; BANDS.len()],
+            preamp: 0.0,
+            crossfeed: false,
+            karaoke: false,
+            limiter: true,
+        }
+    }
+}
+
+/// Peaking filter from the [Audio EQ Cookbook](https://www.w3.org/TR/audio-eq-cookbook/)
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    const IDENTITY: Self = Self {
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+        a1: 0.0,
+        a2: 0.0,
+    };
+
+    fn peaking(rate: f32, frequency: f32, gain: f32) -> Self {
+        // bands above the Nyquist frequency can't be represented
+        if gain == 0.0 || frequency >= rate / 2.0 {
+            return Self::IDENTITY;
+        }
+        let a = 10f32.powf(gain / 40.0);
+        let w0 = 2.0 * PI * frequency / rate;
+        let alpha = w0.sin() / (2.0 * Q);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: -2.0 * cos / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha / a) / a0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BiquadState {
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl BiquadState {
+    fn process(&mut self, filter: &Biquad, x: f32) -> f32 {
+        let y = filter.b0 * x + filter.b1 * self.x1 + filter.b2 * self.x2
+            - filter.a1 * self.y1
+            - filter.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Applies [`DspSettings`] to a stream with a fixed sample rate and channel count
+pub(crate) struct Dsp {
+    settings: DspSettings,
+    rate: f32,
+    channels: usize,
+    preamp: f32,
+    filters: [Biquad; BANDS.len()],
+    states: Vec<[BiquadState; BANDS.len()]>,
+    crossfeed: [f32; 2],
+    crossfeed_coefficient: f32,
+    // low passed center of the karaoke
+    center: f32,
+    center_coefficient: f32,
+    limiter_gain: f32,
+    limiter_coefficient: f32,
+}
+
+impl Dsp {
+    pub fn new(rate: u32, channels: usize) -> Self {
+        let rate = rate as f32;
+        Self {
+            settings: DspSettings::default(),
+            rate,
+            channels,
+            preamp: 1.0,
+            filters: [Biquad::IDENTITY; BANDS.len()],
+            states: vec![[BiquadState::default(); BANDS.len()]; channels],
+            crossfeed: [0.0; 2],
+            crossfeed_coefficient: 1.0 - (-2.0 * PI * CROSSFEED_CUTOFF / rate).exp(),
+            center: 0.0,
+            center_coefficient: 1.0 - (-2.0 * PI * KARAOKE_CUTOFF / rate).exp(),
+            limiter_gain: 1.0,
+            limiter_coefficient: 1.0 - (-1.0 / (LIMITER_RELEASE * rate)).exp(),
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: &DspSettings) {
+        self.preamp = 10f32.powf(settings.preamp / 20.0);
+        for (filter, (frequency, gain)) in self
+            .filters
+            .iter_mut()
+            .zip(BANDS.iter().zip(settings.bands))
+        {
+            *filter = Biquad::peaking(self.rate, *frequency, gain);
+        }
+        self.settings = settings.clone();
+    }
+
+    /// Returns false when processing wouldn't change the samples
+    pub fn is_active(&self) -> bool {
+        let settings = &self.settings;
+        settings.enabled
+            && (settings.preamp != 0.0
+                || settings.bands.iter().any(|gain| *gain != 0.0)
+                || ((settings.crossfeed || settings.karaoke) && self.channels == 2))
+    }
+
+    /// Processes interleaved samples in place
+    pub fn process(&mut self, samples: &mut [f32]) {
+        if self.channels == 0 {
+            return;
+        }
+        for frame in samples.chunks_exact_mut(self.channels) {
+            if self.settings.karaoke && self.channels == 2 {
+                let center = (frame[0] + frame[1]) / 2.0;
+                let side = (frame[0] - frame[1]) / 2.0;
+                self.center += self.center_coefficient * (center - self.center);
+                frame[0] = self.center + side;
+                frame[1] = self.center - side;
+            }
+
+            for (sample, states) in frame.iter_mut().zip(self.states.iter_mut()) {
+                let mut value = *sample * self.preamp;
+                for (filter, state) in self.filters.iter().zip(states.iter_mut()) {
+                    value = state.process(filter, value);
+                }
+                *sample = value;
+            }
+
+            if self.settings.crossfeed && self.channels == 2 {
+                for (low, sample) in self.crossfeed.iter_mut().zip(frame.iter()) {
+                    *low += self.crossfeed_coefficient * (sample - *low);
+                }
+                let (left, right) = (frame[0], frame[1]);
+                frame[0] = (left + CROSSFEED_LEVEL * self.crossfeed[1]) / (1.0 + CROSSFEED_LEVEL);
+                frame[1] = (right + CROSSFEED_LEVEL * self.crossfeed[0]) / (1.0 + CROSSFEED_LEVEL);
+            }
+
+            if self.settings.limiter {
+                let peak = frame
+                    .iter()
+                    .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+                let target = if peak > LIMITER_THRESHOLD {
+                    LIMITER_THRESHOLD / peak
+                } else {
+                    1.0
+                };
+                if target < self.limiter_gain {
+                    self.limiter_gain = target;
+                } else {
+                    self.limiter_gain += self.limiter_coefficient * (target - self.limiter_gain);
+                }
+                for sample in frame.iter_mut() {
+                    *sample *= self.limiter_gain;
+                }
+            }
+        }
+    }
+}
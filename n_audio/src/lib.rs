@@ -1,21 +1,60 @@
-use std::path::Path;
+//! A simple audio library built on [Symphonia](https://github.com/pdeljanov/Symphonia), used by
+//! N Music in place of Rodio
+//!
+//! - [`player::Player`] plays one track at a time on its own thread, with volume, speed, seeking
+//!   and the [`dsp`] effects
+//! - [`queue::QueuePlayer`] wraps a `Player` with a list of tracks inside a folder
+//! - [`music_track::MusicTrack`] reads the tags, the properties, the chapters and the waveform of a
+//!   file without playing it
+//! - [`loudness`] measures how loud tracks and albums are, to compute their ReplayGain
+//! - [`storage`] lets tracks be read from somewhere else than plain files
+//! - [`SnapcastSource`] sends the tracks to a Snapcast server instead of the audio device
+//!
+//! ```no_run
+//! use n_audio::player::Player;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> std::io::Result<()> {
+//! let mut player = Player::builder().volume(0.5).build();
+//! player.play_from_path("song.flac")?;
+//! while !player.has_ended() {
+//!     if let Some(time) = player.get_time() {
+//!         println!("{} / {}", time.format_pos(), time.format_len());
+//!     }
+//!     tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Every format enabled in Symphonia can be played, along with Opus, DCA and raw PCM files.
+
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
 use symphonia::core::codecs::CodecRegistry;
 
 use crate::dca::DcaReader;
+use crate::dsp::DspSettings;
 use crate::opus::OpusDecoder;
 use crate::raw::RawReader;
+use cpal::traits::{DeviceTrait, HostTrait};
 use once_cell::sync::Lazy;
 use symphonia::core::units::Time;
 use symphonia::default::{register_enabled_codecs, register_enabled_formats};
 use symphonia_core::probe::Probe;
 
 mod dca;
+pub mod dsp;
+pub mod loudness;
 pub mod music_track;
 mod opus;
 mod output;
 pub mod player;
 pub mod queue;
 mod raw;
+mod resampler;
+mod snapcast;
+pub mod storage;
 
 /// Default Symphonia [`CodecRegistry`], including the (audiopus-backed) Opus codec.
 pub static CODEC_REGISTRY: Lazy<CodecRegistry> = Lazy::new(|| {
@@ -25,6 +64,7 @@ pub static CODEC_REGISTRY: Lazy<CodecRegistry> = Lazy::new(|| {
     registry
 });
 
+/// Default Symphonia [`Probe`], including the DCA and raw PCM readers.
 pub static PROBE: Lazy<Probe> = Lazy::new(|| {
     let mut probe = Probe::default();
     probe.register_all::<DcaReader>();
@@ -33,21 +73,127 @@ pub static PROBE: Lazy<Probe> = Lazy::new(|| {
     probe
 });
 
+/// Name of the device the next tracks are played on, the default output of the system
+///
+/// It can take a while, it shouldn't be called from async tasks directly
+pub fn output_device() -> Option<String> {
+    cpal::default_host().default_output_device()?.name().ok()
+}
+
+/// Names of the output devices connected right now
+///
+/// It can take a while, it shouldn't be called from async tasks directly
+pub fn output_devices() -> Vec<String> {
+    cpal::default_host()
+        .output_devices()
+        .map(|devices| devices.filter_map(|device| device.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Errors that aren't about reading or decoding a file, those are [`std::io::Error`]s
 #[derive(Debug)]
 pub enum NError {
+    /// No track in the queue has the name looked for
     NoTrack,
 }
 
+impl Display for NError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NError::NoTrack => write!(f, "no track found"),
+        }
+    }
+}
+
+impl std::error::Error for NError {}
+
+/// Sample rate and sample format the output stream is opened with and how the samples are
+/// converted to it, sent to the track thread with [`Message::Output`]
+///
+/// By default the stream follows every track, some DACs misbehave when it changes so it can be
+/// fixed instead
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OutputFormat {
+    /// Rate in Hz the tracks are resampled to, `None` for the rate of each track
+    pub rate: Option<u32>,
+    /// `None` for the default format of the device
+    pub sample_format: Option<SampleFormat>,
+    /// Adds TPDF dither to the tracks with more bits than the output, so what's lost is noise
+    /// instead of distortion
+    pub dither: bool,
+    /// Where the tracks are sent instead of the audio device, the rate and the sample format are
+    /// then the ones of the Snapcast source
+    pub snapcast: Option<SnapcastSource>,
+}
+
+/// Source of a [Snapcast](https://github.com/badaix/snapcast) server, which plays what it gets in
+/// sync on all of its clients
+///
+/// The samples are sent as `48000:16:2`, the default `sampleformat` of the sources
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapcastSource {
+    /// The FIFO of a `pipe://` source, like `/tmp/snapfifo`
+    Pipe(PathBuf),
+    /// The address of a `tcp://` source with `mode=server`, like `192.168.1.2:4953`
+    Tcp(String),
+}
+
+impl SnapcastSource {
+    /// Reads the `source` of the server configuration, like `pipe:///tmp/snapfifo?name=default`
+    ///
+    /// # Example
+    /// ```
+    /// use n_audio::SnapcastSource;
+    /// use std::path::PathBuf;
+    /// assert_eq!(
+    ///     SnapcastSource::from_uri("pipe:///tmp/snapfifo?name=default"),
+    ///     Some(SnapcastSource::Pipe(PathBuf::from("/tmp/snapfifo")))
+    /// );
+    /// assert_eq!(
+    ///     SnapcastSource::from_uri("tcp://192.168.1.2:4953"),
+    ///     Some(SnapcastSource::Tcp("192.168.1.2:4953".to_string()))
+    /// );
+    /// assert_eq!(SnapcastSource::from_uri("/tmp/snapfifo"), None);
+    /// ```
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        let (scheme, rest) = uri.trim().split_once("://")?;
+        // the options are for the server
+        let location = rest.split('?').next().unwrap_or_default();
+        if location.is_empty() {
+            return None;
+        }
+        match scheme {
+            "pipe" => Some(Self::Pipe(PathBuf::from(location))),
+            "tcp" => Some(Self::Tcp(location.to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// Samples the output stream can be opened with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    I16,
+    I32,
+    F32,
+}
+
 /// Messages sent inside the `Player`
+///
+/// They're exposed because the `Player` methods return them back when the track thread is gone
 pub enum Message {
     Play,
     Pause,
     End,
+    /// The output device couldn't be opened, the track ended there
+    OutputFailed,
     Exit,
     Seek(Time),
     Time(TrackTime),
     Volume(f32),
     PlaybackSpeed(f32),
+    Dsp(DspSettings),
+    Output(OutputFormat),
 }
 
 /// Returns the file name without its extension
@@ -72,6 +218,14 @@ pub fn remove_ext<P: AsRef<Path>>(path: P) -> String {
     split[..split.len() - 1].to_vec().join(".")
 }
 
+/// Returns the last component of `path`
+///
+/// # Example
+/// ```
+/// use n_audio::strip_absolute_path;
+/// let path = std::path::Path::new("music").join("song.mp3");
+/// assert_eq!(strip_absolute_path(path.to_string_lossy().to_string()), "song.mp3");
+/// ```
 pub fn strip_absolute_path(path: String) -> String {
     let mut s = path
         .split(std::path::MAIN_SEPARATOR)
@@ -83,17 +237,24 @@ pub fn strip_absolute_path(path: String) -> String {
     s
 }
 
-/// Used to represent the timestamp
-///
-/// pos_* is used to represent the *current* timestamp (as in where is currently the player playing inside the track)
-/// len_* is used to represent the *entire* timestamp (as is how long is the track)
+/// Used to represent the timestamp, both values are in seconds
 #[derive(Copy, Clone, Debug, Default)]
 pub struct TrackTime {
+    /// Where the player is inside the track
     pub position: f64,
+    /// How long the whole track is
     pub length: f64,
 }
 
 impl TrackTime {
+    /// Returns the position as `mm:ss`
+    ///
+    /// # Example
+    /// ```
+    /// use n_audio::TrackTime;
+    /// let time = TrackTime { position: 83.5, length: 200.0 };
+    /// assert_eq!(time.format_pos(), "01:23");
+    /// ```
     pub fn format_pos(&self) -> String {
         format!(
             "{:02}:{:02}",
@@ -102,6 +263,7 @@ impl TrackTime {
         )
     }
 
+    /// Returns the length as `mm:ss`
     pub fn format_len(&self) -> String {
         format!(
             "{:02}:{:02}",
@@ -111,9 +273,44 @@ impl TrackTime {
     }
 }
 
+/// A chapter inside a track, taken from its cue points
+///
+/// `start` is expressed in seconds
 #[derive(Clone, Debug)]
+pub struct Chapter {
+    pub start: f64,
+    pub title: String,
+}
+
+/// Technical information about a track and all of its tags
+///
+/// `bitrate` is the average over the whole file in bits per second
+#[derive(Clone, Debug, Default)]
+pub struct TrackProperties {
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub bits_per_sample: u32,
+    pub bitrate: u64,
+    pub file_size: u64,
+    pub length: f64,
+    pub tags: Vec<(String, String)>,
+}
+
+/// The tags of a track, the strings are empty when the file doesn't have them
+///
+/// `title` falls back to the file name when it comes from [`music_track::MusicTrack::get_meta`]
+#[derive(Clone, Debug, Default)]
 pub struct Metadata {
+    /// The position is always 0
     pub time: TrackTime,
     pub artist: String,
     pub title: String,
+    pub album: String,
+    pub album_artist: String,
+    pub genre: String,
+    /// Position of the track in its album
+    pub track: Option<u32>,
+    /// Release date as it's written, usually the year or an ISO 8601 date
+    pub date: String,
 }
@@ -0,0 +1,320 @@
+//! Loudness of tracks and albums as defined by
+//! [ITU-R BS.1770](https://www.itu.int/rec/R-REC-BS.1770), used to compute their
+//! [ReplayGain](https://wiki.hydrogenaud.io/index.php?title=ReplayGain_2.0_specification)
+
+use std::f64::consts::PI;
+use symphonia_core::meta::{StandardTagKey, Tag};
+
+/// Loudness the ReplayGain brings every track to, in LUFS
+pub const REFERENCE: f64 = -18.0;
+/// Loudness the gains of the `R128_*` tags of Opus files are relative to, in LUFS
+const R128_REFERENCE: f64 = -23.0;
+
+const BLOCK_SECONDS: f64 = 0.4;
+// the blocks overlap by 75%, so a new one starts every quarter of a block
+const STEPS_PER_BLOCK: usize = 4;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+
+/// Gain and peak written in the ReplayGain tags of a track or of an album
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayGain {
+    /// Change of volume that brings the loudness to [`REFERENCE`], in dB
+    pub gain: f64,
+    /// Highest absolute sample, 1.0 is full scale
+    pub peak: f32,
+}
+
+impl ReplayGain {
+    /// Volume multiplier of the gain moved to the loudness of `target`, lowered when the peak
+    /// would clip if the target prevents it
+    pub fn amplitude(&self, target: GainTarget) -> f32 {
+        let amplitude = 10f64.powf((self.gain + target.loudness - REFERENCE) / 20.0) as f32;
+        if target.prevent_clipping && self.peak > 0.0 {
+            amplitude.min(1.0 / self.peak)
+        } else {
+            amplitude
+        }
+    }
+}
+
+/// Where the gain of the tags brings the tracks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GainTarget {
+    /// In LUFS, the tags are followed as they are at [`REFERENCE`]
+    pub loudness: f64,
+    /// Whether the gain is lowered when the peak of the track would go above full scale
+    pub prevent_clipping: bool,
+}
+
+impl Default for GainTarget {
+    fn default() -> Self {
+        Self {
+            loudness: REFERENCE,
+            prevent_clipping: true,
+        }
+    }
+}
+
+/// Which of the ReplayGain tags of a track its volume follows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GainMode {
+    /// The tags are ignored
+    #[default]
+    Off,
+    /// Every track is brought to the same loudness
+    Track,
+    /// The tracks of an album keep how loud they are compared to each other, like between the
+    /// songs of a live album
+    Album,
+}
+
+/// ReplayGain tags of a track, read by [`crate::music_track::MusicTrack::get_format_with_gain`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ReplayGainTags {
+    pub track: Option<ReplayGain>,
+    pub album: Option<ReplayGain>,
+}
+
+impl ReplayGainTags {
+    /// Volume multiplier of the track in `mode`, the album gain falls back to the track one for
+    /// the tracks scanned alone
+    pub fn amplitude(&self, mode: GainMode, target: GainTarget) -> f32 {
+        let gain = match mode {
+            GainMode::Off => None,
+            GainMode::Track => self.track,
+            GainMode::Album => self.album.or(self.track),
+        };
+        gain.map_or(1.0, |gain| gain.amplitude(target))
+    }
+
+    /// Reads the tags named as in the specification, the formats without a standard key for them
+    /// keep the name at the end of theirs, like `TXXX:REPLAYGAIN_TRACK_GAIN`
+    ///
+    /// The `R128_TRACK_GAIN` and `R128_ALBUM_GAIN` of Opus files are read too, they have no peak
+    pub(crate) fn read(&mut self, tags: &[Tag]) {
+        for tag in tags {
+            let key = tag.key.to_uppercase();
+            let is = |std_key: StandardTagKey, name: &str| {
+                tag.std_key == Some(std_key) || key.ends_with(name)
+            };
+            // the gains are written like "-6.54 dB"
+            let value = tag.value.to_string();
+            let Some(number) = value
+                .split_whitespace()
+                .next()
+                .and_then(|number| number.parse::<f64>().ok())
+            else {
+                continue;
+            };
+            if is(StandardTagKey::ReplayGainTrackGain, "REPLAYGAIN_TRACK_GAIN") {
+                self.track.get_or_insert(ReplayGain::default()).gain = number;
+            } else if is(StandardTagKey::ReplayGainTrackPeak, "REPLAYGAIN_TRACK_PEAK") {
+                self.track.get_or_insert(ReplayGain::default()).peak = number as f32;
+            } else if is(StandardTagKey::ReplayGainAlbumGain, "REPLAYGAIN_ALBUM_GAIN") {
+                self.album.get_or_insert(ReplayGain::default()).gain = number;
+            } else if is(StandardTagKey::ReplayGainAlbumPeak, "REPLAYGAIN_ALBUM_PEAK") {
+                self.album.get_or_insert(ReplayGain::default()).peak = number as f32;
+            } else if key == "R128_TRACK_GAIN" {
+                self.track.get_or_insert(ReplayGain::default()).gain = r128_gain(number);
+            } else if key == "R128_ALBUM_GAIN" {
+                self.album.get_or_insert(ReplayGain::default()).gain = r128_gain(number);
+            }
+        }
+    }
+}
+
+/// Loudness of a track, returned by [`crate::music_track::MusicTrack::get_loudness`]
+///
+/// The loudness of every block is kept instead of a single number, so the ones of the tracks of
+/// an album can be put together with [`Loudness::album`]
+#[derive(Debug, Clone, Default)]
+pub struct Loudness {
+    // mean square of the K-weighted samples of every 400 ms block
+    blocks: Vec<f64>,
+    peak: f32,
+}
+
+impl Loudness {
+    /// Loudness of the tracks played one after the other, as it's measured for the album gain
+    pub fn album<'a>(tracks: impl IntoIterator<Item = &'a Loudness>) -> Self {
+        let mut album = Self::default();
+        for track in tracks {
+            album.blocks.extend_from_slice(&track.blocks);
+            album.peak = album.peak.max(track.peak);
+        }
+        album
+    }
+
+    /// Integrated loudness in LUFS, `None` when everything is below the silence gate
+    pub fn integrated(&self) -> Option<f64> {
+        let above = |gate: f64| {
+            let blocks = self
+                .blocks
+                .iter()
+                .filter(|block| block_loudness(**block) > gate)
+                .collect::<Vec<_>>();
+            if blocks.is_empty() {
+                None
+            } else {
+                Some(blocks.iter().copied().sum::<f64>() / blocks.len() as f64)
+            }
+        };
+        let relative = block_loudness(above(ABSOLUTE_GATE)?) + RELATIVE_GATE;
+        above(relative.max(ABSOLUTE_GATE)).map(block_loudness)
+    }
+
+    /// Highest absolute sample, 1.0 is full scale
+    pub fn peak(&self) -> f32 {
+        self.peak
+    }
+
+    /// Momentary loudness in LUFS of every 400 ms block, one starts every 100 ms, to see how it
+    /// changes along the track. The silent blocks are at negative infinity
+    pub fn momentary(&self) -> impl Iterator<Item = f64> + '_ {
+        self.blocks.iter().copied().map(block_loudness)
+    }
+
+    /// `None` when the track is silent
+    pub fn replay_gain(&self) -> Option<ReplayGain> {
+        Some(ReplayGain {
+            gain: REFERENCE - self.integrated()?,
+            peak: self.peak,
+        })
+    }
+}
+
+/// The R128 gains are in 1/256 dB steps
+fn r128_gain(value: f64) -> f64 {
+    value / 256.0 + REFERENCE - R128_REFERENCE
+}
+
+fn block_loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Biquad {
+    /// High shelf of the K-weighting, modeling the effect of the head
+    fn shelf(rate: f64) -> Self {
+        let k = (PI * 1681.974450955533 / rate).tan();
+        let q = 0.7071752369554196;
+        let vh = 10f64.powf(3.999843853973347 / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        Self {
+            b0: (vh + vb * k / q + k * k) / a0,
+            b1: 2.0 * (k * k - vh) / a0,
+            b2: (vh - vb * k / q + k * k) / a0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+        }
+    }
+
+    /// High pass of the K-weighting, ignoring the lowest frequencies
+    fn high_pass(rate: f64) -> Self {
+        let k = (PI * 38.13547087602444 / rate).tan();
+        let q = 0.5003270373238773;
+        let a0 = 1.0 + k / q + k * k;
+        Self {
+            b0: 1.0,
+            b1: -2.0,
+            b2: 1.0,
+            a1: 2.0 * (k * k - 1.0) / a0,
+            a2: (1.0 - k / q + k * k) / a0,
+        }
+    }
+}
+
+/// Last two inputs and outputs of a [`Biquad`] for one channel
+#[derive(Debug, Clone, Copy, Default)]
+struct History {
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl History {
+    fn process(&mut self, filter: &Biquad, x: f64) -> f64 {
+        let y = filter.b0 * x + filter.b1 * self.x1 + filter.b2 * self.x2
+            - filter.a1 * self.y1
+            - filter.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Measures the [`Loudness`] of interleaved samples fed a packet at a time
+pub(crate) struct Meter {
+    shelf: Biquad,
+    high_pass: Biquad,
+    // one per channel for each filter
+    histories: Vec<(History, History)>,
+    step_len: usize,
+    // sum of the squares of the current step
+    energy: f64,
+    in_step: usize,
+    // the steps of the current block
+    steps: Vec<f64>,
+    loudness: Loudness,
+}
+
+impl Meter {
+    pub(crate) fn new(rate: u32, channels: usize) -> Self {
+        let rate = rate as f64;
+        Self {
+            shelf: Biquad::shelf(rate),
+            high_pass: Biquad::high_pass(rate),
+            histories: vec![Default::default(); channels],
+            step_len: ((rate * BLOCK_SECONDS) as usize / STEPS_PER_BLOCK).max(1),
+            energy: 0.0,
+            in_step: 0,
+            steps: Vec::with_capacity(STEPS_PER_BLOCK),
+            loudness: Loudness::default(),
+        }
+    }
+
+    pub(crate) fn process(&mut self, samples: &[f32]) {
+        let channels = self.histories.len();
+        for frame in samples.chunks_exact(channels) {
+            // the surround channels weigh a bit more in the standard, every channel is treated
+            // like the front ones here
+            for (sample, (shelf, high_pass)) in frame.iter().zip(self.histories.iter_mut()) {
+                self.loudness.peak = self.loudness.peak.max(sample.abs());
+                let weighted =
+                    high_pass.process(&self.high_pass, shelf.process(&self.shelf, *sample as f64));
+                self.energy += weighted * weighted;
+            }
+            self.in_step += 1;
+            if self.in_step == self.step_len {
+                if self.steps.len() == STEPS_PER_BLOCK {
+                    self.steps.remove(0);
+                }
+                self.steps.push(self.energy);
+                if self.steps.len() == STEPS_PER_BLOCK {
+                    let block =
+                        self.steps.iter().sum::<f64>() / (self.step_len * STEPS_PER_BLOCK) as f64;
+                    self.loudness.blocks.push(block);
+                }
+                self.energy = 0.0;
+                self.in_step = 0;
+            }
+        }
+    }
+
+    pub(crate) fn finish(self) -> Loudness {
+        self.loudness
+    }
+}
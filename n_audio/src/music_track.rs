@@ -1,22 +1,43 @@
-use crate::{remove_ext, Metadata, TrackTime, PROBE};
+use crate::loudness::{Loudness, Meter, ReplayGainTags};
+use crate::{
+    remove_ext, storage, Chapter, Metadata, TrackProperties, TrackTime, CODEC_REGISTRY, PROBE,
+};
 use multitag::Tag;
 use std::ffi::OsStr;
-use std::io::Cursor;
+use std::io;
 use std::path::Path;
-use std::{fs, io};
-use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CodecParameters, Decoder, DecoderOptions};
+use symphonia::core::formats::{FormatOptions, FormatReader, Track};
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
-use symphonia::core::probe::Hint;
+use symphonia::core::probe::{Hint, ProbeResult};
 use symphonia_core::meta::StandardTagKey;
 
 /// The basics where everything is built upon
+///
+/// Only the path is kept, every method reads the file again, so the file can change between calls
+///
+/// The methods return [`io::ErrorKind::InvalidData`] when the file isn't in a supported format
+/// or doesn't have any audio in it
+///
+/// # Example
+/// ```no_run
+/// use n_audio::music_track::MusicTrack;
+///
+/// let track = MusicTrack::new("song.mp3")?;
+/// let meta = track.get_meta()?;
+/// println!("{} - {} ({})", meta.artist, meta.title, meta.time.format_len());
+/// # Ok::<(), std::io::Error>(())
+/// ```
 pub struct MusicTrack {
     path: String,
     ext: String,
 }
 
 impl MusicTrack {
+    /// Fails with [`io::ErrorKind::Unsupported`] when the path has no extension, it's used to
+    /// guess the format
     pub fn new<P: AsRef<Path> + AsRef<OsStr> + Clone + Into<String>>(path: P) -> io::Result<Self> {
         let p = path.clone();
         let p = Path::new(&p);
@@ -33,11 +54,30 @@ impl MusicTrack {
 
     /// Returns the `FormatReader` provided by Symphonia
     pub fn get_format(&self) -> Result<Box<dyn FormatReader>, io::Error> {
-        let file = fs::read(&self.path)?;
-        let media_stream = MediaSourceStream::new(
-            Box::new(Cursor::new(file)),
-            std::default::Default::default(),
-        );
+        Ok(self.probe()?.format)
+    }
+
+    /// Returns the `FormatReader` along with the ReplayGain tags, read while the file is open
+    pub fn get_format_with_gain(&self) -> io::Result<(Box<dyn FormatReader>, ReplayGainTags)> {
+        let mut probed = self.probe()?;
+
+        let mut gain = ReplayGainTags::default();
+        if let Some(metadata) = probed.metadata.get() {
+            if let Some(revision) = metadata.current() {
+                gain.read(revision.tags());
+            }
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            gain.read(revision.tags());
+        }
+
+        Ok((probed.format, gain))
+    }
+
+    fn probe(&self) -> Result<ProbeResult, io::Error> {
+        // the stream only buffers a few packets, so big files on slow drives start right away
+        let media_stream =
+            MediaSourceStream::new(storage::open(&self.path)?, std::default::Default::default());
         let mut hint = Hint::new();
         hint.with_extension(self.ext.as_ref());
         let meta_ops = MetadataOptions::default();
@@ -45,31 +85,32 @@ impl MusicTrack {
             enable_gapless: true,
             ..Default::default()
         };
-        let probed = PROBE
+        PROBE
             .format(&hint, media_stream, &fmt_ops, &meta_ops)
-            .expect("Format not supported");
-        Ok(probed.format)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
     }
 
+    /// Returns the tags of the track along with its length, the title falls back to the file name
     pub fn get_meta(&self) -> Result<Metadata, io::Error> {
-        let mut format = self.get_format()?;
-        let track = format.default_track().expect("Can't load tracks");
-        let time_base = track.codec_params.time_base.unwrap();
+        let mut metadata = self.get_tags()?;
+        if metadata.title.is_empty() {
+            metadata.title = remove_ext(&self.path);
+        }
+        Ok(metadata)
+    }
 
-        let duration = track
-            .codec_params
-            .n_frames
-            .map(|frames| track.codec_params.start_ts + frames)
-            .unwrap();
-        let time = time_base.calc_time(duration);
-
-        let time = TrackTime {
-            position: 0.0,
-            length: time.seconds as f64 + time.frac,
-        };
+    /// Returns the tags of the track along with its length, the ones it doesn't have are empty
+    pub fn get_tags(&self) -> Result<Metadata, io::Error> {
+        let mut format = self.get_format()?;
+        let time = length(format.as_ref())?;
 
         let mut artist = String::new();
         let mut title = String::new();
+        let mut album = String::new();
+        let mut album_artist = String::new();
+        let mut genre = String::new();
+        let mut track_number = None;
+        let mut date = String::new();
 
         if let Some(metadata) = format.metadata().skip_to_latest() {
             for tag in metadata.tags() {
@@ -77,6 +118,23 @@ impl MusicTrack {
                     artist = tag.value.to_string();
                 } else if let Some(StandardTagKey::TrackTitle) = tag.std_key {
                     title = tag.value.to_string();
+                } else if let Some(StandardTagKey::Album) = tag.std_key {
+                    album = tag.value.to_string();
+                } else if let Some(StandardTagKey::AlbumArtist) = tag.std_key {
+                    album_artist = tag.value.to_string();
+                } else if let Some(StandardTagKey::Genre) = tag.std_key {
+                    genre = tag.value.to_string();
+                } else if let Some(StandardTagKey::TrackNumber) = tag.std_key {
+                    // some taggers write the number of tracks too, like "3/12"
+                    track_number = tag
+                        .value
+                        .to_string()
+                        .split('/')
+                        .next()
+                        .and_then(|number| number.trim().parse().ok());
+                } else if let Some(StandardTagKey::Date | StandardTagKey::ReleaseDate) = tag.std_key
+                {
+                    date = tag.value.to_string();
                 }
             }
         } else if let Ok(tag) = Tag::read_from_path(&self.path) {
@@ -86,34 +144,374 @@ impl MusicTrack {
             if let Some(a) = tag.artist() {
                 artist = a;
             }
-        }
-
-        if title.is_empty() {
-            title = remove_ext(&self.path);
+            if let Some(info) = tag.get_album_info() {
+                album = info.title.unwrap_or_default();
+                album_artist = info.artist.unwrap_or_default();
+            }
         }
 
         Ok(Metadata {
             time,
             artist,
             title,
+            album,
+            album_artist,
+            genre,
+            track: track_number,
+            date,
         })
     }
 
+    /// Returns the technical properties of the track along with every tag found in the file
+    pub fn get_properties(&self) -> Result<TrackProperties, io::Error> {
+        let file_size = storage::size(&self.path)?;
+        let mut probed = self.probe()?;
+
+        let mut tags = vec![];
+        if let Some(metadata) = probed.metadata.get() {
+            if let Some(revision) = metadata.current() {
+                tags.extend(
+                    revision
+                        .tags()
+                        .iter()
+                        .map(|tag| (tag.key.clone(), tag.value.to_string())),
+                );
+            }
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            tags.extend(
+                revision
+                    .tags()
+                    .iter()
+                    .map(|tag| (tag.key.clone(), tag.value.to_string())),
+            );
+        }
+
+        let track = default_track(probed.format.as_ref())?;
+        let params = &track.codec_params;
+        let length = match (params.time_base, params.n_frames) {
+            (Some(time_base), Some(frames)) => {
+                let time = time_base.calc_time(params.start_ts + frames);
+                time.seconds as f64 + time.frac
+            }
+            _ => 0.0,
+        };
+
+        Ok(TrackProperties {
+            codec: CODEC_REGISTRY
+                .get_codec(params.codec)
+                .map(|codec| codec.long_name.to_string())
+                .unwrap_or_default(),
+            sample_rate: params.sample_rate.unwrap_or(0),
+            channels: params.channels.map(|c| c.count()).unwrap_or(0),
+            bits_per_sample: params.bits_per_sample.unwrap_or(0),
+            bitrate: if length > 0.0 {
+                (file_size as f64 * 8.0 / length) as u64
+            } else {
+                0
+            },
+            file_size,
+            length,
+            tags,
+        })
+    }
+
+    /// Returns the chapters of the track, if its container has any cue points
+    pub fn get_chapters(&self) -> Result<Vec<Chapter>, io::Error> {
+        let format = self.get_format()?;
+        let time_base = default_track(format.as_ref())?
+            .codec_params
+            .time_base
+            .ok_or_else(no_audio)?;
+
+        Ok(format
+            .cues()
+            .iter()
+            .map(|cue| {
+                let start = time_base.calc_time(cue.start_ts);
+                let title = cue
+                    .tags
+                    .iter()
+                    .find(|tag| matches!(tag.std_key, Some(StandardTagKey::TrackTitle)))
+                    .map(|tag| tag.value.to_string())
+                    .unwrap_or_else(|| cue.index.to_string());
+                Chapter {
+                    start: start.seconds as f64 + start.frac,
+                    title,
+                }
+            })
+            .collect())
+    }
+
+    /// Decodes the whole track and returns `buckets` peak values normalized between 0 and 1
+    ///
+    /// Useful to draw a waveform of the track, it's slow so it's better to call it in a separate thread
+    pub fn get_peaks(&self, buckets: usize) -> Result<Vec<f32>, io::Error> {
+        if buckets == 0 {
+            return Ok(vec![]);
+        }
+
+        let (decoding, params) = self.decoding()?;
+        let frames = params.n_frames.unwrap_or(0).max(1);
+        let mut peaks = vec![0.0f32; buckets];
+        decoding.for_each(|samples, channels, ts| {
+            for (i, frame) in samples.chunks(channels).enumerate() {
+                let bucket = ((ts + i as u64) * buckets as u64 / frames) as usize;
+                let peak = frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+                if let Some(current) = peaks.get_mut(bucket.min(buckets - 1)) {
+                    *current = current.max(peak);
+                }
+            }
+            true
+        });
+
+        let max = peaks.iter().fold(0.0f32, |max, peak| max.max(*peak));
+        if max > 0.0 {
+            peaks.iter_mut().for_each(|peak| *peak /= max);
+        }
+
+        Ok(peaks)
+    }
+
+    /// Returns the length of the track, the position is always 0
     pub fn get_length(&self) -> Result<TrackTime, io::Error> {
+        length(self.get_format()?.as_ref())
+    }
+
+    /// Estimates the tempo of the track in beats per minute, `None` when there's no clear beat
+    ///
+    /// Only the first two minutes are decoded, it's still slow so it's better to call it in a
+    /// separate thread
+    pub fn get_bpm(&self) -> Result<Option<f32>, io::Error> {
+        let (decoding, params) = self.decoding()?;
+        let sample_rate = params.sample_rate.ok_or_else(no_audio)? as usize;
+
+        let hop = (sample_rate / LOUDNESS_RATE).max(1);
+        let limit = sample_rate * BPM_ANALYSIS_SECONDS;
+        let mut loudness = vec![];
+        let mut energy = 0.0f32;
+        let mut in_hop = 0;
+        let mut frames = 0;
+        decoding.for_each(|samples, channels, _| {
+            for frame in samples.chunks(channels) {
+                let sample = frame.iter().sum::<f32>() / channels as f32;
+                energy += sample * sample;
+                in_hop += 1;
+                if in_hop == hop {
+                    loudness.push((energy / hop as f32).sqrt());
+                    energy = 0.0;
+                    in_hop = 0;
+                }
+            }
+            frames += samples.len() / channels;
+            frames < limit
+        });
+
+        Ok(estimate_tempo(&loudness, sample_rate as f32 / hop as f32))
+    }
+
+    /// Decodes the whole track to measure its loudness, used to compute its ReplayGain
+    ///
+    /// It takes a while for long tracks, so it's better called from a blocking task
+    pub fn get_loudness(&self) -> Result<Loudness, io::Error> {
+        let (decoding, params) = self.decoding()?;
+        let sample_rate = params.sample_rate.ok_or_else(no_audio)?;
+
+        let mut meter = None;
+        decoding.for_each(|samples, channels, _| {
+            meter
+                .get_or_insert_with(|| Meter::new(sample_rate, channels))
+                .process(samples);
+            true
+        });
+
+        meter.map(Meter::finish).ok_or_else(no_audio)
+    }
+
+    /// Opens the default track to be decoded to floats, along with the parameters of its codec
+    fn decoding(&self) -> Result<(Decoding, CodecParameters), io::Error> {
         let format = self.get_format()?;
-        let track = format.default_track().expect("Can't load tracks");
-        let time_base = track.codec_params.time_base.unwrap();
+        let track = default_track(format.as_ref())?;
+        let track_id = track.id;
+        let params = track.codec_params.clone();
+        let decoder = CODEC_REGISTRY
+            .make(&params, &DecoderOptions::default())
+            .map_err(|_| io::Error::from(io::ErrorKind::Unsupported))?;
 
-        let duration = track
-            .codec_params
-            .n_frames
-            .map(|frames| track.codec_params.start_ts + frames)
-            .unwrap();
-        let time = time_base.calc_time(duration);
-
-        Ok(TrackTime {
-            position: 0.0,
-            length: time.seconds as f64 + time.frac,
+        Ok((
+            Decoding {
+                format,
+                decoder,
+                track_id,
+            },
+            params,
+        ))
+    }
+}
+
+/// A track being decoded to floats, see [`Decoding::for_each`]
+struct Decoding {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+}
+
+impl Decoding {
+    /// Hands `process` the interleaved samples of every packet, with the number of channels and
+    /// the timestamp of the packet, until it returns `false` or the track ends
+    ///
+    /// The packets that can't be decoded are skipped
+    fn for_each(mut self, mut process: impl FnMut(&[f32], usize, u64) -> bool) {
+        let mut sample_buf = None;
+
+        while let Ok(packet) = self.format.next_packet() {
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+
+            match self.decoder.decode(&packet) {
+                Ok(decoded) => {
+                    let channels = decoded.spec().channels.count();
+                    let buf = sample_buf.get_or_insert_with(|| {
+                        SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec())
+                    });
+                    if buf.capacity() < decoded.capacity() * channels {
+                        *buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+                    }
+                    buf.copy_interleaved_ref(decoded);
+                    if !process(buf.samples(), channels, packet.ts()) {
+                        break;
+                    }
+                }
+                Err(symphonia::core::errors::Error::DecodeError(_)) => continue,
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+/// Seconds of audio [`MusicTrack::get_bpm`] looks at
+const BPM_ANALYSIS_SECONDS: usize = 120;
+/// How many times per second the loudness is measured to find the beats
+const LOUDNESS_RATE: usize = 200;
+const MIN_BPM: f32 = 60.0;
+const MAX_BPM: f32 = 200.0;
+
+/// Finds the tempo from the loudness of a track measured `rate` times per second
+///
+/// The beats are where the loudness goes up, their period is the lag where they correlate the
+/// most with themselves. Tempos far from 120 BPM are weighted down, so half or double the tempo
+/// doesn't win by a hair
+fn estimate_tempo(loudness: &[f32], rate: f32) -> Option<f32> {
+    let mut onsets = loudness
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).max(0.0))
+        .collect::<Vec<_>>();
+    let mean = onsets.iter().sum::<f32>() / onsets.len().max(1) as f32;
+    if mean <= 0.0 {
+        return None;
+    }
+    onsets.iter_mut().for_each(|onset| *onset -= mean);
+
+    let min_lag = ((rate * 60.0 / MAX_BPM).floor() as usize).max(1);
+    let max_lag = (rate * 60.0 / MIN_BPM).ceil() as usize;
+    // a few beats at the slowest tempo at least
+    if onsets.len() < max_lag * 4 {
+        return None;
+    }
+    // one more lag on each side for the interpolation
+    let correlation = (min_lag - 1..=max_lag + 1)
+        .map(|lag| {
+            onsets
+                .iter()
+                .zip(&onsets[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+                / (onsets.len() - lag) as f32
         })
+        .collect::<Vec<_>>();
+    let weight = |lag: usize| {
+        let octaves = (rate * 60.0 / lag as f32 / 120.0).log2();
+        (-0.5 * octaves * octaves).exp()
+    };
+    let best = (1..correlation.len() - 1).max_by(|a, b| {
+        let a = correlation[*a] * weight(min_lag - 1 + a);
+        let b = correlation[*b] * weight(min_lag - 1 + b);
+        a.total_cmp(&b)
+    })?;
+    if correlation[best] <= 0.0 {
+        return None;
+    }
+
+    // the peak of the parabola going through the best lag and its neighbours
+    let (before, peak, after) = (
+        correlation[best - 1],
+        correlation[best],
+        correlation[best + 1],
+    );
+    let curvature = before - 2.0 * peak + after;
+    let offset = if curvature != 0.0 {
+        (0.5 * (before - after) / curvature).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let lag = (min_lag - 1 + best) as f32 + offset;
+    Some(rate * 60.0 / lag)
+}
+
+fn no_audio() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "the file has no audio track")
+}
+
+fn default_track(format: &dyn FormatReader) -> Result<&Track, io::Error> {
+    format.default_track().ok_or_else(no_audio)
+}
+
+fn length(format: &dyn FormatReader) -> Result<TrackTime, io::Error> {
+    let params = &default_track(format)?.codec_params;
+    let (Some(time_base), Some(frames)) = (params.time_base, params.n_frames) else {
+        return Err(no_audio());
+    };
+    let time = time_base.calc_time(params.start_ts + frames);
+
+    Ok(TrackTime {
+        position: 0.0,
+        length: time.seconds as f64 + time.frac,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loudness of a click every `period` measures, like a metronome
+    fn clicks(period: f32, seconds: usize) -> Vec<f32> {
+        let len = LOUDNESS_RATE * seconds;
+        let mut loudness = vec![0.0; len];
+        let mut click = 0.0f32;
+        while (click as usize) < len {
+            loudness[click.round() as usize % len] = 1.0;
+            click += period;
+        }
+        loudness
+    }
+
+    #[test]
+    fn tempo_of_clicks() {
+        let rate = LOUDNESS_RATE as f32;
+        for bpm in [90.0, 120.0, 150.0] {
+            let loudness = clicks(rate * 60.0 / bpm, 60);
+            let estimated = estimate_tempo(&loudness, rate).unwrap();
+            assert!(
+                (estimated - bpm).abs() < 1.0,
+                "{estimated} instead of {bpm}"
+            );
+        }
+    }
+
+    #[test]
+    fn no_tempo_in_silence() {
+        let loudness = vec![0.0; LOUDNESS_RATE * 60];
+        assert_eq!(estimate_tempo(&loudness, LOUDNESS_RATE as f32), None);
     }
 }
@@ -5,10 +5,20 @@
 /// Modifications: support for volume (for all platforms)
 /// Modifications: support for custom name app (only for PulseAudio)
 /// Modifications: completely removed pulseaudio in 1.3.0
+/// Modifications: equalizer and effects through [`Dsp`]
+/// Modifications: fixed sample rate and format through [`OutputFormat`]
+/// Modifications: TPDF dither when the output has less bits than the track
+/// Modifications: Snapcast sources in place of the device through [`crate::SnapcastSource`]
 use std::result;
 
+use crate::dsp::{Dsp, DspSettings};
+use crate::resampler::Resampler;
+use crate::snapcast::{self, SnapcastOutput};
+use crate::{OutputFormat, SampleFormat};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use dasp::sample::FromSample;
 use dasp::Sample;
+use rand::Rng;
 use rb::*;
 use symphonia::core::audio::{AudioBufferRef, RawSample, SampleBuffer, SignalSpec};
 use symphonia::core::conv::ConvertibleSample;
@@ -16,6 +26,7 @@ use symphonia::core::units::Duration;
 
 pub trait AudioOutput {
     fn write(&mut self, decoded: AudioBufferRef<'_>, volume: f32) -> Result<()>;
+    fn set_dsp(&mut self, settings: &DspSettings);
     #[allow(dead_code)]
     fn flush(&mut self);
 }
@@ -33,16 +44,35 @@ pub type Result<T> = result::Result<T, AudioOutputError>;
 
 pub struct CpalAudioOutput;
 
-trait AudioOutputSample: Sample + ConvertibleSample + RawSample + Send + 'static {}
+trait AudioOutputSample:
+    Sample<Float = f32> + FromSample<f32> + ConvertibleSample + RawSample + Send + 'static
+{
+    /// Bits of the integer samples, `None` for the floating point ones
+    const BITS: Option<u32>;
+}
 
-impl AudioOutputSample for f32 {}
+impl AudioOutputSample for f32 {
+    const BITS: Option<u32> = None;
+}
+
+impl AudioOutputSample for i16 {
+    const BITS: Option<u32> = Some(16);
+}
 
-impl AudioOutputSample for i16 {}
+impl AudioOutputSample for i32 {
+    const BITS: Option<u32> = Some(32);
+}
 
-impl AudioOutputSample for u16 {}
+impl AudioOutputSample for u16 {
+    const BITS: Option<u32> = Some(16);
+}
 
 impl CpalAudioOutput {
-    pub fn try_open(spec: SignalSpec, duration: Duration) -> Result<Box<dyn AudioOutput>> {
+    pub fn try_open(
+        spec: SignalSpec,
+        duration: Duration,
+        format: &OutputFormat,
+    ) -> Result<Box<dyn AudioOutput>> {
         // Get default host.
         let host = cpal::default_host();
 
@@ -66,16 +96,53 @@ impl CpalAudioOutput {
             }
         };
 
+        let sample_format = match format.sample_format {
+            Some(SampleFormat::I16) => cpal::SampleFormat::I16,
+            Some(SampleFormat::I32) => cpal::SampleFormat::I32,
+            Some(SampleFormat::F32) => cpal::SampleFormat::F32,
+            None => config.sample_format(),
+        };
+        let rate = format.rate.unwrap_or(spec.rate);
+        let dither = format.dither;
+        match Self::open(spec, duration, &device, sample_format, rate, dither) {
+            // the device doesn't support what was forced, playing is better than being silent
+            Err(err) if format.rate.is_some() || format.sample_format.is_some() => {
+                eprintln!("Can't open the audio output with {format:?}, using the default one");
+                Self::open(
+                    spec,
+                    duration,
+                    &device,
+                    config.sample_format(),
+                    spec.rate,
+                    dither,
+                )
+                .map_err(|_| err)
+            }
+            result => result,
+        }
+    }
+
+    fn open(
+        spec: SignalSpec,
+        duration: Duration,
+        device: &cpal::Device,
+        sample_format: cpal::SampleFormat,
+        rate: u32,
+        dither: bool,
+    ) -> Result<Box<dyn AudioOutput>> {
         // Select proper playback routine based on sample format.
-        match config.sample_format() {
+        match sample_format {
             cpal::SampleFormat::F32 => {
-                CpalAudioOutputImpl::<f32>::try_open(spec, duration, &device)
+                CpalAudioOutputImpl::<f32>::try_open(spec, duration, device, rate, dither)
             }
             cpal::SampleFormat::I16 => {
-                CpalAudioOutputImpl::<i16>::try_open(spec, duration, &device)
+                CpalAudioOutputImpl::<i16>::try_open(spec, duration, device, rate, dither)
+            }
+            cpal::SampleFormat::I32 => {
+                CpalAudioOutputImpl::<i32>::try_open(spec, duration, device, rate, dither)
             }
             cpal::SampleFormat::U16 => {
-                CpalAudioOutputImpl::<u16>::try_open(spec, duration, &device)
+                CpalAudioOutputImpl::<u16>::try_open(spec, duration, device, rate, dither)
             }
             _ => {
                 unimplemented!("sample format not yet implemented")
@@ -90,7 +157,13 @@ where
 {
     ring_buf_producer: Producer<T>,
     sample_buf: SampleBuffer<T>,
+    // the samples that are processed before being converted
+    float_buf: SampleBuffer<f32>,
     stream: cpal::Stream,
+    dsp: Dsp,
+    // when the stream rate isn't the one of the track
+    resampler: Option<Resampler>,
+    dither: bool,
 }
 
 impl<T: AudioOutputSample + cpal::SizedSample> CpalAudioOutputImpl<T> {
@@ -98,18 +171,20 @@ impl<T: AudioOutputSample + cpal::SizedSample> CpalAudioOutputImpl<T> {
         spec: SignalSpec,
         duration: Duration,
         device: &cpal::Device,
+        rate: u32,
+        dither: bool,
     ) -> Result<Box<dyn AudioOutput>> {
         let num_channels = spec.channels.count();
 
         // Output audio stream config.
         let config = cpal::StreamConfig {
             channels: num_channels as cpal::ChannelCount,
-            sample_rate: cpal::SampleRate(spec.rate),
+            sample_rate: cpal::SampleRate(rate),
             buffer_size: cpal::BufferSize::Default,
         };
 
         // Create a ring buffer with a capacity for up-to 250ms of audio.
-        let ring_len = ((250 * spec.rate as usize) / 1000) * num_channels;
+        let ring_len = ((250 * rate as usize) / 1000) * num_channels;
 
         let ring_buf = SpscRb::new(ring_len);
         let (ring_buf_producer, ring_buf_consumer) = (ring_buf.producer(), ring_buf.consumer());
@@ -143,11 +218,16 @@ impl<T: AudioOutputSample + cpal::SizedSample> CpalAudioOutputImpl<T> {
         }
 
         let sample_buf = SampleBuffer::<T>::new(duration, spec);
+        let float_buf = SampleBuffer::<f32>::new(duration, spec);
 
         Ok(Box::new(CpalAudioOutputImpl {
             ring_buf_producer,
             sample_buf,
+            float_buf,
             stream,
+            dsp: Dsp::new(rate, num_channels),
+            resampler: (rate != spec.rate).then(|| Resampler::new(spec.rate, rate, num_channels)),
+            dither,
         }))
     }
 }
@@ -159,15 +239,48 @@ impl<T: AudioOutputSample> AudioOutput for CpalAudioOutputImpl<T> {
             return Ok(());
         }
 
+        let dither = self.dither && T::BITS.is_some_and(|bits| source_bits(&decoded) > bits);
+
         // Audio samples must be interleaved for cpal. Interleave the samples in the audio
         // buffer into the sample buffer.
-        self.sample_buf.copy_interleaved_ref(decoded);
+        // the integer samples can't be made louder without overflowing
+        let float = self.resampler.is_some() || self.dsp.is_active() || dither || volume > 1.0;
+        let mut samples: Vec<T> = if float {
+            // converted once at the end, so the bits below the output ones are kept until then
+            self.float_buf.copy_interleaved_ref(decoded);
+            let mut processed = match &mut self.resampler {
+                Some(resampler) => resampler.process(self.float_buf.samples()),
+                None => self.float_buf.samples().to_vec(),
+            };
+            // the effects are set up for the rate of the stream
+            if self.dsp.is_active() {
+                self.dsp.process(&mut processed);
+            }
+            for sample in processed.iter_mut() {
+                *sample *= volume;
+            }
+            if let Some(bits) = T::BITS.filter(|_| dither) {
+                // triangular noise of one least significant bit of the output at most
+                let lsb = 1.0 / (1u64 << (bits - 1)) as f32;
+                let mut rng = rand::thread_rng();
+                for sample in processed.iter_mut() {
+                    *sample += (rng.gen::<f32>() - rng.gen::<f32>()) * lsb;
+                }
+            }
+            processed
+                .into_iter()
+                .map(<T as FromSample<f32>>::from_sample_)
+                .collect()
+        } else {
+            self.sample_buf.copy_interleaved_ref(decoded);
+            let mut samples = self.sample_buf.samples().to_vec();
+            for sample in samples.iter_mut() {
+                *sample = sample.mul_amp(volume.to_sample());
+            }
+            samples
+        };
 
         // Write all the interleaved samples to the ring buffer.
-        let mut samples: Vec<T> = self.sample_buf.samples().to_vec();
-        for sample in samples.iter_mut() {
-            *sample = sample.mul_amp(volume.to_sample());
-        }
 
         while let Some(written) = self.ring_buf_producer.write_blocking(samples.as_slice()) {
             samples = samples[written..].to_vec();
@@ -176,12 +289,40 @@ impl<T: AudioOutputSample> AudioOutput for CpalAudioOutputImpl<T> {
         Ok(())
     }
 
+    fn set_dsp(&mut self, settings: &DspSettings) {
+        self.dsp.set_settings(settings);
+    }
+
     fn flush(&mut self) {
         // Flush is best-effort, ignore the returned result.
         let _ = self.stream.pause();
     }
 }
 
-pub fn try_open(spec: SignalSpec, duration: Duration) -> Result<Box<dyn AudioOutput>> {
-    CpalAudioOutput::try_open(spec, duration)
+/// Bits of the samples of a decoded packet, the floating point ones count as 32
+pub(crate) fn source_bits(decoded: &AudioBufferRef) -> u32 {
+    match decoded {
+        AudioBufferRef::U8(_) | AudioBufferRef::S8(_) => 8,
+        AudioBufferRef::U16(_) | AudioBufferRef::S16(_) => 16,
+        AudioBufferRef::U24(_) | AudioBufferRef::S24(_) => 24,
+        _ => 32,
+    }
+}
+
+pub fn try_open(
+    spec: SignalSpec,
+    duration: Duration,
+    format: &OutputFormat,
+) -> Result<Box<dyn AudioOutput>> {
+    match &format.snapcast {
+        Some(source) => SnapcastOutput::try_open(spec, duration, source, format.dither),
+        None => CpalAudioOutput::try_open(spec, duration, format),
+    }
+}
+
+/// Closes what the outputs opened with `format` keep open between the tracks
+pub fn close(format: &OutputFormat) {
+    if format.snapcast.is_some() {
+        snapcast::disconnect();
+    }
 }
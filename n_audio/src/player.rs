@@ -1,28 +1,109 @@
+use crate::dsp::DspSettings;
 use crate::music_track::MusicTrack;
-use crate::{output, Message, TrackTime, CODEC_REGISTRY};
+use crate::output::{self, AudioOutput};
+use crate::{Message, OutputFormat, TrackTime, CODEC_REGISTRY};
 use flume::{Receiver, SendError, Sender};
 use std::ffi::OsStr;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::JoinHandle;
 use std::{io, thread};
 use symphonia::core::codecs::DecoderOptions;
 use symphonia::core::formats::{FormatReader, SeekMode, SeekTo};
 use symphonia::core::units::Time;
-// TODO: update docs
 
 /// The main actor for everything.
 ///
 /// Using this struct is really easy, just add a file you want to play (be sure of it being an audio file supported by Symphonia or it being an opus file) and call `Player::play` and you've done everything!
+///
+/// Every track is decoded on its own thread, the methods only send messages to it, so they're
+/// cheap to call from a UI
 #[derive(Debug)]
 pub struct Player {
     is_paused: bool,
     volume: f32,
+    // multiplies the volume of the tracks played from now on, like their ReplayGain
+    gain: f32,
     playback_speed: f32,
+    dsp: DspSettings,
+    output: OutputFormat,
     cached_get_time: Option<TrackTime>,
     thread: Option<JoinHandle<()>>,
     tx: Option<Sender<Message>>,
     rx_t: Option<Receiver<Message>>,
     rx_e: Option<Receiver<Message>>,
+    // set when a track ended with `Message::OutputFailed`, until it's taken
+    output_failed: AtomicBool,
+}
+
+/// Builds a [`Player`] with settings other than the defaults
+///
+/// # Example
+/// ```
+/// use n_audio::dsp::DspSettings;
+/// use n_audio::player::Player;
+///
+/// let player = Player::builder()
+///     .volume(0.5)
+///     .playback_speed(1.25)
+///     .dsp(DspSettings {
+///         enabled: true,
+///         ..Default::default()
+///     })
+///     .build();
+/// assert_eq!(player.get_volume(), 0.5);
+/// assert!(player.get_dsp().enabled);
+/// ```
+#[derive(Clone, Debug)]
+pub struct PlayerBuilder {
+    volume: f32,
+    playback_speed: f32,
+    dsp: DspSettings,
+    output: OutputFormat,
+}
+
+impl Default for PlayerBuilder {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            playback_speed: 1.0,
+            dsp: DspSettings::default(),
+            output: OutputFormat::default(),
+        }
+    }
+}
+
+impl PlayerBuilder {
+    /// Sets the output volume, between 0 and 1
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    /// Sets the playback speed, 1 is the normal speed
+    pub fn playback_speed(mut self, playback_speed: f32) -> Self {
+        self.playback_speed = playback_speed;
+        self
+    }
+
+    /// Sets the equalizer and effects
+    pub fn dsp(mut self, dsp: DspSettings) -> Self {
+        self.dsp = dsp;
+        self
+    }
+
+    /// Fixes the sample rate and format of the output stream
+    pub fn output(mut self, output: OutputFormat) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn build(self) -> Player {
+        let mut player = Player::new(self.volume, self.playback_speed);
+        player.dsp = self.dsp;
+        player.output = self.output;
+        player
+    }
 }
 
 impl Player {
@@ -31,15 +112,24 @@ impl Player {
         Player {
             is_paused: false,
             volume,
+            gain: 1.0,
             playback_speed,
+            dsp: DspSettings::default(),
+            output: OutputFormat::default(),
             cached_get_time: None,
             thread: None,
             tx: None,
             rx_t: None,
             rx_e: None,
+            output_failed: AtomicBool::new(false),
         }
     }
 
+    /// Returns a [`PlayerBuilder`] starting from the default settings
+    pub fn builder() -> PlayerBuilder {
+        PlayerBuilder::default()
+    }
+
     /// Pauses the current playing track, if any
     /// It only errors if it can't send the message (so something serious may have happened)
     pub async fn pause(&mut self) -> Result<(), SendError<Message>> {
@@ -69,6 +159,7 @@ impl Player {
         false
     }
 
+    /// Returns the output volume, between 0 and 1
     pub fn get_volume(&self) -> f32 {
         self.volume
     }
@@ -83,6 +174,12 @@ impl Player {
         Ok(())
     }
 
+    /// Sets a multiplier of the volume for the tracks played from now on, like the one of their
+    /// ReplayGain, the current track keeps its own
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain = gain;
+    }
+
     /// Sets the playback speed
     /// It only errors if it can't send the message (so something serious may have happened)
     pub async fn set_playback_speed(
@@ -97,6 +194,41 @@ impl Player {
         Ok(())
     }
 
+    /// Returns the equalizer and effects in use
+    pub fn get_dsp(&self) -> &DspSettings {
+        &self.dsp
+    }
+
+    /// Sets the equalizer and effects, they're kept for the next tracks too
+    /// It only errors if it can't send the message (so something serious may have happened)
+    pub async fn set_dsp(&mut self, dsp: DspSettings) -> Result<(), SendError<Message>> {
+        if let Some(tx) = &self.tx {
+            tx.send_async(Message::Dsp(dsp.clone())).await?;
+        }
+        self.dsp = dsp;
+        Ok(())
+    }
+
+    /// Returns the sample rate and format the output stream is opened with
+    pub fn get_output(&self) -> OutputFormat {
+        self.output.clone()
+    }
+
+    /// Fixes the sample rate and format of the output stream, the one of the current track is
+    /// opened again
+    /// It only errors if it can't send the message (so something serious may have happened)
+    pub async fn set_output(&mut self, output: OutputFormat) -> Result<(), SendError<Message>> {
+        if let Some(tx) = &self.tx {
+            tx.send_async(Message::Output(output.clone())).await?;
+        }
+        // the Snapcast source that's left is closed once the track is done with it
+        if output.snapcast != self.output.snapcast {
+            output::close(&self.output);
+        }
+        self.output = output;
+        Ok(())
+    }
+
     /// Seeks to the set timestamp
     /// Be aware that if the timestamp isn't valid the track thread will panic
     /// It only errors if it can't send the message (so something serious may have happened)
@@ -137,14 +269,24 @@ impl Player {
     pub fn has_ended(&self) -> bool {
         if let Some(rx_e) = &self.rx_e {
             while let Ok(message) = rx_e.try_recv() {
-                if let Message::End = message {
-                    return true;
+                match message {
+                    Message::End => return true,
+                    Message::OutputFailed => {
+                        self.output_failed.store(true, Ordering::Relaxed);
+                        return true;
+                    }
+                    _ => {}
                 }
             }
         }
         false
     }
 
+    /// Returns whether the last track that ended couldn't open the output device, only once
+    pub fn take_output_failure(&self) -> bool {
+        self.output_failed.swap(false, Ordering::Relaxed)
+    }
+
     /// Returns whether if any track is playing
     /// Note that this function doesn't check if the track is paused or not
     pub fn is_playing(&self) -> bool {
@@ -180,14 +322,28 @@ impl Player {
     /// Plays a certain track given its format
     pub fn play(&mut self, format: Box<dyn FormatReader>) {
         let volume = self.volume;
+        let gain = self.gain;
         let playback_speed = self.playback_speed;
+        let dsp = self.dsp.clone();
+        let output = self.output.clone();
 
         let (tx, rx) = flume::unbounded();
         let (tx_t, rx_t) = flume::unbounded();
         let (tx_e, rx_e) = flume::unbounded();
 
-        let thread =
-            thread::spawn(move || Self::thread_fn(format, rx, tx_t, tx_e, volume, playback_speed));
+        let thread = thread::spawn(move || {
+            Self::thread_fn(
+                format,
+                rx,
+                tx_t,
+                tx_e,
+                volume,
+                gain,
+                playback_speed,
+                dsp,
+                output,
+            )
+        });
 
         self.is_paused = false;
         self.rx_e = Some(rx_e);
@@ -196,13 +352,17 @@ impl Player {
         self.thread = Some(thread);
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn thread_fn(
         mut format: Box<dyn FormatReader>,
         rx: Receiver<Message>,
         tx_t: Sender<Message>,
         tx_e: Sender<Message>,
         mut volume: f32,
+        gain: f32,
         mut playback_speed: f32,
+        mut dsp: DspSettings,
+        mut output_format: OutputFormat,
     ) {
         // Vars used for audio output
         let track = format.default_track().expect("Can't load tracks");
@@ -217,7 +377,7 @@ impl Player {
         let mut decoder = CODEC_REGISTRY
             .make(&track.codec_params, &DecoderOptions::default())
             .expect("Can't load decoder");
-        let mut audio_output = None;
+        let mut audio_output: Option<Box<dyn AudioOutput>> = None;
 
         let mut spec = None;
         let mut dur = None;
@@ -237,6 +397,19 @@ impl Player {
                     Message::Pause => is_paused = true,
                     Message::Volume(v) => volume = v,
                     Message::PlaybackSpeed(speed) => playback_speed = speed,
+                    Message::Dsp(settings) => {
+                        if let Some(audio_output) = &mut audio_output {
+                            audio_output.set_dsp(&settings);
+                        }
+                        dsp = settings;
+                    }
+                    Message::Output(format) => {
+                        // opened again with the next packet
+                        if format != output_format {
+                            audio_output = None;
+                        }
+                        output_format = format;
+                    }
                     Message::Exit => {
                         exit = true;
                         break;
@@ -309,8 +482,16 @@ impl Player {
                             tmp_spec.rate = (tmp_spec.rate as f32 * playback_speed).round() as u32;
                             spec = Some(tmp_spec);
                             dur = Some(decoded.capacity() as u64);
-                            audio_output =
-                                Some(output::try_open(spec.unwrap(), dur.unwrap()).unwrap());
+                            let Ok(mut output) =
+                                output::try_open(spec.unwrap(), dur.unwrap(), &output_format)
+                            else {
+                                tx_e.send(Message::OutputFailed)
+                                    .expect("Can't send OutputFailed message");
+                                exit = true;
+                                break;
+                            };
+                            output.set_dsp(&dsp);
+                            audio_output = Some(output);
                         } else {
                             let mut new_spec = *decoded.spec();
                             new_spec.rate = (new_spec.rate as f32 * playback_speed).round() as u32;
@@ -325,13 +506,27 @@ impl Player {
                                 changed = true
                             }
                             if changed {
-                                audio_output =
-                                    Some(output::try_open(spec.unwrap(), dur.unwrap()).unwrap());
+                                let Ok(mut output) =
+                                    output::try_open(spec.unwrap(), dur.unwrap(), &output_format)
+                                else {
+                                    tx_e.send(Message::OutputFailed)
+                                        .expect("Can't send OutputFailed message");
+                                    exit = true;
+                                    break;
+                                };
+                                output.set_dsp(&dsp);
+                                audio_output = Some(output);
                             }
                         }
 
                         if let Some(audio_output) = &mut audio_output {
-                            audio_output.write(decoded, volume).unwrap()
+                            // like a Snapcast server that stopped
+                            if audio_output.write(decoded, volume * gain).is_err() {
+                                tx_e.send(Message::OutputFailed)
+                                    .expect("Can't send OutputFailed message");
+                                exit = true;
+                                break;
+                            }
                         }
                     }
                     Err(symphonia::core::errors::Error::DecodeError(err)) => {
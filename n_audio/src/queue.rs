@@ -1,22 +1,138 @@
+use crate::loudness::{GainMode, GainTarget};
 use crate::music_track::MusicTrack;
 use crate::player::Player;
-use crate::{remove_ext, strip_absolute_path, NError};
+use crate::{remove_ext, NError};
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
 use std::fs::File;
 use std::io;
 use std::io::{BufRead, BufReader, ErrorKind, Seek, SeekFrom, Write};
 use std::ops::{Deref, DerefMut};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// How long a track can take to open before it fails with [`ErrorKind::TimedOut`], like when the
+/// network share it's on stopped answering
+pub const OPEN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A track of the queue, where it's played from, so the sources of tracks can be mixed in the
+/// same queue
+///
+/// Only files can be played for now, [`QueuePlayer::play`] fails with [`ErrorKind::Unsupported`]
+/// on the others
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum QueueItem {
+    /// A file, with its full path
+    Local(PathBuf),
+    /// A stream played over HTTP, like an internet radio, with its URL
+    Stream(String),
+    /// A track of a Subsonic server, with its id
+    Subsonic(String),
+    /// A track of a cue sheet, cut from `file` between `start` and `end`, the end of the file when
+    /// it's `None`
+    Cue {
+        file: PathBuf,
+        /// Number of the track in the sheet
+        track: u16,
+        start: Duration,
+        end: Option<Duration>,
+    },
+}
+
+/// Scheme of the Subsonic tracks in the queue file
+const SUBSONIC_SCHEME: &str = "subsonic://";
+/// Scheme of the cue sheet tracks in the queue file, followed by the track, the start and the end
+/// in milliseconds, and the file
+const CUE_SCHEME: &str = "cue://";
+
+impl QueueItem {
+    /// The file of the track, when the whole file is the track
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            QueueItem::Local(path) => Some(path),
+            QueueItem::Stream(_) | QueueItem::Subsonic(_) | QueueItem::Cue { .. } => None,
+        }
+    }
+
+    /// A name for the track when its tags don't have a title
+    pub fn name(&self) -> String {
+        match self {
+            QueueItem::Local(path) => remove_ext(path),
+            QueueItem::Stream(url) => url.clone(),
+            QueueItem::Subsonic(id) => id.clone(),
+            QueueItem::Cue { file, track, .. } => format!("{} {track:02}", remove_ext(file)),
+        }
+    }
+
+    /// Reads a line of the queue file, the files are relative to `folder` unless they're outside
+    /// of it
+    ///
+    /// The paths found in the folder never have `//`, so they can't be mistaken for the URIs of
+    /// the other tracks
+    fn from_line(line: String, folder: &str) -> Self {
+        if line.starts_with("http://") || line.starts_with("https://") {
+            return QueueItem::Stream(line);
+        }
+        if let Some(id) = line.strip_prefix(SUBSONIC_SCHEME) {
+            return QueueItem::Subsonic(id.to_string());
+        }
+        if let Some(cue) = line.strip_prefix(CUE_SCHEME).and_then(|cue| {
+            let mut parts = cue.splitn(4, '/');
+            let track = parts.next()?.parse().ok()?;
+            let start = Duration::from_millis(parts.next()?.parse().ok()?);
+            let end = match parts.next()? {
+                "" => None,
+                end => Some(Duration::from_millis(end.parse().ok()?)),
+            };
+            let file = Path::new(folder).join(parts.next()?);
+            Some(QueueItem::Cue {
+                file,
+                track,
+                start,
+                end,
+            })
+        }) {
+            return cue;
+        }
+        QueueItem::Local(Path::new(folder).join(line))
+    }
+}
+
+/// A [`Player`] with a list of tracks inside a folder, it derefs to the `Player` for everything
+/// that isn't about the queue
+///
+/// The paths of the tracks are kept in a temporary file, so big libraries don't take much memory,
+/// and they're relative to the folder when they're inside it
+///
+/// # Example
+/// ```no_run
+/// use n_audio::player::Player;
+/// use n_audio::queue::QueuePlayer;
+///
+/// # #[tokio::main]
+/// # async fn main() -> std::io::Result<()> {
+/// let mut queue = QueuePlayer::with_player(
+///     String::from("/home/user/Music"),
+///     Player::builder().volume(0.8).build(),
+/// );
+/// queue.add("/home/user/Music/first.mp3").await?;
+/// queue.add("/home/user/Music/album/second.flac").await?;
+/// queue.shuffle();
+/// queue.play_index(0).await?;
+/// # Ok(())
+/// # }
+/// ```
 pub struct QueuePlayer {
     queue_file: Arc<RwLock<BufReader<File>>>,
     path: String,
     player: Player,
     index: u16,
     index_map: Vec<u64>,
+    gain_mode: GainMode,
+    gain_target: GainTarget,
 }
 
 impl Default for QueuePlayer {
@@ -26,8 +142,13 @@ impl Default for QueuePlayer {
 }
 
 impl QueuePlayer {
+    /// Creates an empty queue for the tracks inside `path`, playing with the default settings
     pub fn new(path: String) -> Self {
-        let player = Player::new(1.0, 1.0);
+        Self::with_player(path, Player::default())
+    }
+
+    /// Creates an empty queue for the tracks inside `path` played by `player`
+    pub fn with_player(path: String, player: Player) -> Self {
         let queue_file = Arc::new(RwLock::new(BufReader::new(tempfile::tempfile().unwrap())));
 
         QueuePlayer {
@@ -36,9 +157,12 @@ impl QueuePlayer {
             index: u16::MAX - 1,
             path,
             index_map: vec![],
+            gain_mode: GainMode::default(),
+            gain_target: GainTarget::default(),
         }
     }
 
+    /// Returns the position of the current track in the queue
     pub fn index(&self) -> u16 {
         self.index
     }
@@ -51,6 +175,7 @@ impl QueuePlayer {
         self.index_map.is_empty()
     }
 
+    /// Returns the folder the tracks are relative to
     pub fn path(&self) -> String {
         self.path.clone()
     }
@@ -59,16 +184,41 @@ impl QueuePlayer {
         self.path = path;
     }
 
+    /// Returns the track at position `i`
+    pub async fn item(&self, i: u16) -> Option<QueueItem> {
+        let line = self.read_line(*self.index_map.get(i as usize)?).await;
+        Some(QueueItem::from_line(line, &self.path))
+    }
+
+    /// Returns the full path of the track at position `i`, `None` when it isn't a file
     pub async fn get_path_for_file(&self, i: u16) -> Option<PathBuf> {
+        self.item(i).await?.path().map(Path::to_path_buf)
+    }
+
+    /// Reads the line of the queue file starting at `seek`, without the line break
+    async fn read_line(&self, seek: u64) -> String {
         let mut guard = self.queue_file.write().await;
-        guard
-            .seek(SeekFrom::Start(*self.index_map.get(i as usize)?))
-            .unwrap();
-        let mut name = String::new();
-        guard.read_line(&mut name).unwrap();
-        name = name.replace("\n", "");
+        guard.seek(SeekFrom::Start(seek)).unwrap();
+        let mut line = String::new();
+        guard.read_line(&mut line).unwrap();
+        line.replace("\n", "")
+    }
 
-        Some(PathBuf::from(&self.path).join(name))
+    /// Points a track to another file, the old path stays in the queue file so the indices of the
+    /// other tracks don't change
+    pub async fn set_path_for_file<P: Into<String>>(&mut self, i: u16, path: P) -> io::Result<()> {
+        if i as usize >= self.index_map.len() {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                "track not in the queue",
+            ));
+        }
+        let path = format!("{}\n", self.relative_path(path.into()));
+        let mut guard = self.queue_file.write().await;
+        let position = guard.seek(SeekFrom::End(0))?;
+        guard.get_mut().write_all(path.as_bytes())?;
+        self.index_map[i as usize] = position;
+        Ok(())
     }
 
     pub fn queue(&self) -> Arc<RwLock<BufReader<File>>> {
@@ -84,25 +234,50 @@ impl QueuePlayer {
         self.path.shrink_to_fit();
     }
 
+    /// Adds a track at the end of the queue
     #[inline]
     pub async fn add<P: Into<String>>(&mut self, path: P) -> io::Result<()> {
-        self.index_map
-            .push(self.queue_file.write().await.stream_position()?);
-        self.queue_file
-            .write()
+        self.add_item(QueueItem::Local(PathBuf::from(path.into())))
             .await
-            .get_mut()
-            .write_all(format!("{}\n", strip_absolute_path(path.into())).as_bytes())?;
+    }
+
+    /// Adds a track that may not be a file at the end of the queue
+    pub async fn add_item(&mut self, item: QueueItem) -> io::Result<()> {
+        let line = match item {
+            QueueItem::Local(path) => self.relative_path(path.to_string_lossy().to_string()),
+            QueueItem::Stream(url) => url,
+            QueueItem::Subsonic(id) => format!("{SUBSONIC_SCHEME}{id}"),
+            QueueItem::Cue {
+                file,
+                track,
+                start,
+                end,
+            } => format!(
+                "{CUE_SCHEME}{track}/{}/{}/{}",
+                start.as_millis(),
+                end.map(|end| end.as_millis().to_string())
+                    .unwrap_or_default(),
+                self.relative_path(file.to_string_lossy().to_string())
+            ),
+        };
+        let line = format!("{line}\n");
+        let mut guard = self.queue_file.write().await;
+        // reading the paths moves the cursor, so it may not be at the end anymore
+        let position = guard.seek(SeekFrom::End(0))?;
+        guard.get_mut().write_all(line.as_bytes())?;
+        self.index_map.push(position);
         Ok(())
     }
 
+    /// Adds many tracks at the end of the queue, faster than calling [`QueuePlayer::add`] for
+    /// each of them
     pub async fn add_all<P: Into<String>>(
         &mut self,
         paths: impl IntoIterator<Item = P>,
     ) -> io::Result<()> {
         let mut data = Vec::with_capacity(8192);
         for path in paths {
-            let path = format!("{}\n", strip_absolute_path(path.into()));
+            let path = format!("{}\n", self.relative_path(path.into()));
             let mut path = path.as_bytes().to_vec();
             self.index_map.push(data.len() as u64);
             data.append(&mut path);
@@ -114,14 +289,25 @@ impl QueuePlayer {
             .write_all(data.as_slice())
     }
 
+    /// The path inside the music directory, the files outside of it keep their full path
+    fn relative_path(&self, path: String) -> String {
+        match Path::new(&path).strip_prefix(&self.path) {
+            Ok(relative) => relative.to_string_lossy().to_string(),
+            Err(_) => path,
+        }
+    }
+
     #[inline]
     pub fn remove(&mut self, index: usize) {
         self.index_map.remove(index);
     }
 
-    #[inline]
+    /// Removes every track, the current one keeps playing until it's ended
+    ///
+    /// The paths are moved to a new file, what [`QueuePlayer::queue`] returned before still reads
+    /// the old ones
     pub fn clear(&mut self) {
-        self.queue_file.blocking_write().get_mut().rewind().unwrap();
+        self.queue_file = Arc::new(RwLock::new(BufReader::new(tempfile::tempfile().unwrap())));
         self.index_map.clear();
         self.index = u16::MAX - 1;
     }
@@ -131,32 +317,63 @@ impl QueuePlayer {
         self.index_map.shuffle(&mut thread_rng());
     }
 
+    /// Returns the path of the current track as stored in the queue, relative to the folder
     pub async fn current_track_name(&self) -> Option<String> {
-        let seek = if self.index >= self.len() as u16 {
-            self.index_map.first()?.clone()
+        let seek = *self.index_map.get(self.current_index() as usize)?;
+        Some(self.read_line(seek).await)
+    }
+
+    /// Returns the current track
+    pub async fn current_item(&self) -> Option<QueueItem> {
+        self.item(self.current_index()).await
+    }
+
+    /// The index of the current track, the first one when the index is past the end
+    fn current_index(&self) -> u16 {
+        if self.index >= self.len() as u16 {
+            0
         } else {
-            self.index_map.get(self.index as usize)?.clone()
-        };
+            self.index
+        }
+    }
 
-        let mut guard = self.queue_file.write().await;
-        guard.seek(SeekFrom::Start(seek)).unwrap();
-        let mut name = String::new();
-        guard.read_line(&mut name).unwrap();
-        name = name.replace("\n", "");
+    /// Returns which ReplayGain tags the volume of the tracks follows
+    pub fn gain_mode(&self) -> GainMode {
+        self.gain_mode
+    }
 
-        Some(name)
+    /// Sets which ReplayGain tags the volume of the tracks follows, from the next track played
+    pub fn set_gain_mode(&mut self, mode: GainMode) {
+        self.gain_mode = mode;
     }
 
+    /// Returns the loudness the ReplayGain tags bring the tracks to
+    pub fn gain_target(&self) -> GainTarget {
+        self.gain_target
+    }
+
+    /// Sets the loudness the ReplayGain tags bring the tracks to, from the next track played
+    pub fn set_gain_target(&mut self, target: GainTarget) {
+        self.gain_target = target;
+    }
+
+    /// Plays the current track from the start, at the volume of its ReplayGain tags
+    ///
+    /// The file is opened on a blocking thread, it's given up on after [`OPEN_TIMEOUT`]
     pub async fn play(&mut self) -> io::Result<()> {
-        let track = MusicTrack::new(
-            self.get_path_for_file(self.index)
-                .await
-                .ok_or(io::Error::from(ErrorKind::NotFound))?
-                .to_str()
-                .unwrap(),
-        )?;
-        let format = tokio::task::spawn_blocking(move || track.get_format()).await??;
+        let item = self
+            .item(self.index)
+            .await
+            .ok_or(io::Error::from(ErrorKind::NotFound))?;
+        let path = item.path().ok_or(io::Error::from(ErrorKind::Unsupported))?;
+        let track = MusicTrack::new(path.to_str().unwrap())?;
+        let opening = tokio::task::spawn_blocking(move || track.get_format_with_gain());
+        let (format, gain) = tokio::time::timeout(OPEN_TIMEOUT, opening)
+            .await
+            .map_err(|_| io::Error::from(ErrorKind::TimedOut))???;
 
+        self.player
+            .set_gain(gain.amplitude(self.gain_mode, self.gain_target));
         self.player.play(format);
         Ok(())
     }
@@ -167,6 +384,7 @@ impl QueuePlayer {
         self.play().await
     }
 
+    /// Plays the next track, going back to the first one after the last
     pub async fn play_next(&mut self) -> io::Result<()> {
         self.index += 1;
 
@@ -177,6 +395,7 @@ impl QueuePlayer {
         self.play().await
     }
 
+    /// Plays the previous track, going to the last one before the first
     pub async fn play_previous(&mut self) -> io::Result<()> {
         if self.index == 0 {
             self.index = self.len() as u16;
@@ -187,6 +406,9 @@ impl QueuePlayer {
         self.play().await
     }
 
+    /// Returns the position of the track whose file name, without extension, is `name`
+    ///
+    /// It blocks on the queue file, don't call it from inside an async task
     pub fn get_index_from_track_name(&self, name: &str) -> Result<usize, NError> {
         let mut guard = self.queue_file.blocking_write();
         for (index, seek) in self.index_map.iter().enumerate() {
@@ -0,0 +1,60 @@
+//! Converts interleaved samples from a sample rate to another, used when the output stream has a
+//! fixed rate, see [`crate::OutputFormat`]
+
+/// Cubic Hermite interpolation between the samples of consecutive packets
+///
+/// The last frames of a packet are kept, so there are no clicks where the next one starts
+pub(crate) struct Resampler {
+    channels: usize,
+    // input frames per output frame
+    step: f64,
+    // position of the next output frame in `frames`, there's always a frame before it
+    position: f64,
+    // frames not fully used yet, interleaved
+    frames: Vec<f32>,
+}
+
+impl Resampler {
+    pub(crate) fn new(from: u32, to: u32, channels: usize) -> Self {
+        Self {
+            channels,
+            step: from as f64 / to as f64,
+            position: 1.0,
+            frames: vec![0.0; channels],
+        }
+    }
+
+    pub(crate) fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let channels = self.channels;
+        self.frames.extend_from_slice(samples);
+        let len = self.frames.len() / channels;
+        let mut output = Vec::with_capacity((samples.len() as f64 / self.step) as usize + channels);
+        // every output frame needs the input frame before its position and the two after
+        while (self.position as usize) + 2 < len {
+            let i = self.position as usize;
+            let t = (self.position - i as f64) as f32;
+            for channel in 0..channels {
+                let frame = |k: usize| self.frames[k * channels + channel];
+                output.push(hermite(
+                    frame(i - 1),
+                    frame(i),
+                    frame(i + 1),
+                    frame(i + 2),
+                    t,
+                ));
+            }
+            self.position += self.step;
+        }
+        let used = (self.position as usize - 1).min(len);
+        self.frames.drain(..used * channels);
+        self.position -= used as f64;
+        output
+    }
+}
+
+fn hermite(y0: f32, y1: f32, y2: f32, y3: f32, t: f32) -> f32 {
+    let c1 = 0.5 * (y2 - y0);
+    let c2 = y0 - 2.5 * y1 + 2.0 * y2 - 0.5 * y3;
+    let c3 = 0.5 * (y3 - y0) + 1.5 * (y1 - y2);
+    ((c3 * t + c2) * t + c1) * t + y1
+}
@@ -0,0 +1,169 @@
+//! Output to a Snapcast source, see [`SnapcastSource`]
+//!
+//! The connection is kept between the tracks, the server would take a closed one as the end of
+//! the stream, and the outputs of two tracks share it while the first one is being ended
+
+use crate::dsp::{Dsp, DspSettings};
+use crate::output::{source_bits, AudioOutput, AudioOutputError, Result};
+use crate::resampler::Resampler;
+use crate::SnapcastSource;
+use dasp::Sample;
+use rand::Rng;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use symphonia::core::audio::{AudioBufferRef, SampleBuffer, SignalSpec};
+use symphonia::core::units::Duration as Frames;
+
+/// Sample rate of the source
+const RATE: u32 = 48000;
+/// How long the server has to take the connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Connection = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// The open connection and where it goes, shared by the outputs of the tracks
+static CONNECTION: Mutex<Option<(SnapcastSource, Connection)>> = Mutex::new(None);
+
+pub(crate) struct SnapcastOutput {
+    source: SnapcastSource,
+    connection: Connection,
+    channels: usize,
+    float_buf: SampleBuffer<f32>,
+    dsp: Dsp,
+    resampler: Option<Resampler>,
+    dither: bool,
+}
+
+impl SnapcastOutput {
+    pub(crate) fn try_open(
+        spec: SignalSpec,
+        duration: Frames,
+        source: &SnapcastSource,
+        dither: bool,
+    ) -> Result<Box<dyn AudioOutput>> {
+        let mut kept = CONNECTION.lock().unwrap();
+        let connection = match &*kept {
+            Some((open, connection)) if open == source => connection.clone(),
+            _ => {
+                let connection = Arc::new(Mutex::new(connect(source).map_err(|e| {
+                    eprintln!("Can't connect to the Snapcast source {source:?}: {e}");
+                    AudioOutputError::OpenStreamError
+                })?));
+                *kept = Some((source.clone(), connection.clone()));
+                connection
+            }
+        };
+        let channels = spec.channels.count();
+
+        Ok(Box::new(SnapcastOutput {
+            source: source.clone(),
+            connection,
+            channels,
+            float_buf: SampleBuffer::<f32>::new(duration, spec),
+            dsp: Dsp::new(RATE, channels),
+            resampler: (spec.rate != RATE).then(|| Resampler::new(spec.rate, RATE, channels)),
+            dither,
+        }))
+    }
+}
+
+/// Closes the connection once the tracks playing on it are done, when the next ones are played
+/// on the device again
+pub(crate) fn disconnect() {
+    CONNECTION.lock().unwrap().take();
+}
+
+fn connect(source: &SnapcastSource) -> std::io::Result<Box<dyn Write + Send>> {
+    match source {
+        SnapcastSource::Pipe(path) => {
+            // opening a FIFO waits for its reader, which isn't there when the server is stopped
+            let (tx, rx) = mpsc::channel();
+            let path = path.clone();
+            thread::spawn(move || {
+                let _ = tx.send(OpenOptions::new().write(true).open(path));
+            });
+            let pipe = rx
+                .recv_timeout(CONNECT_TIMEOUT)
+                .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))??;
+            Ok(Box::new(pipe))
+        }
+        SnapcastSource::Tcp(address) => {
+            let mut last = std::io::Error::from(std::io::ErrorKind::AddrNotAvailable);
+            for address in address.to_socket_addrs()? {
+                match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                    Ok(stream) => {
+                        stream.set_nodelay(true)?;
+                        return Ok(Box::new(stream));
+                    }
+                    Err(e) => last = e,
+                }
+            }
+            Err(last)
+        }
+    }
+}
+
+impl AudioOutput for SnapcastOutput {
+    fn write(&mut self, decoded: AudioBufferRef<'_>, volume: f32) -> Result<()> {
+        if decoded.frames() == 0 {
+            return Ok(());
+        }
+        let dither = self.dither && source_bits(&decoded) > 16;
+
+        self.float_buf.copy_interleaved_ref(decoded);
+        let mut processed = match &mut self.resampler {
+            Some(resampler) => resampler.process(self.float_buf.samples()),
+            None => self.float_buf.samples().to_vec(),
+        };
+        if self.dsp.is_active() {
+            self.dsp.process(&mut processed);
+        }
+
+        // the source is stereo, the mono tracks are on both sides and the others keep the front
+        let lsb = 1.0 / (1u64 << 15) as f32;
+        let mut rng = rand::thread_rng();
+        let mut bytes = Vec::with_capacity(processed.len() / self.channels * 4);
+        for frame in processed.chunks_exact(self.channels) {
+            let (left, right) = match frame {
+                [mono] => (*mono, *mono),
+                [left, right, ..] => (*left, *right),
+                [] => unreachable!(),
+            };
+            for sample in [left, right] {
+                let mut sample = sample * volume;
+                if dither {
+                    sample += (rng.gen::<f32>() - rng.gen::<f32>()) * lsb;
+                }
+                bytes.extend_from_slice(&i16::from_sample(sample).to_le_bytes());
+            }
+        }
+
+        // it blocks while the server is behind, like the ring buffer of the device
+        let written = self.connection.lock().unwrap().write_all(&bytes);
+        if let Err(e) = written {
+            eprintln!("Can't write to the Snapcast source {:?}: {e}", self.source);
+            // connected again by the next track
+            let mut kept = CONNECTION.lock().unwrap();
+            if kept
+                .as_ref()
+                .is_some_and(|(_, connection)| Arc::ptr_eq(connection, &self.connection))
+            {
+                kept.take();
+            }
+            return Err(AudioOutputError::StreamClosedError);
+        }
+        Ok(())
+    }
+
+    fn set_dsp(&mut self, settings: &DspSettings) {
+        self.dsp.set_settings(settings);
+    }
+
+    fn flush(&mut self) {
+        let _ = self.connection.lock().unwrap().flush();
+    }
+}
@@ -0,0 +1,98 @@
+//! Where the tracks are read from, plain files or a [Storage] registered by the app
+//!
+//! On Android the music directory chosen with the Storage Access Framework is a content URI, its
+//! tracks can't be opened as files. The app registers a [Storage] for them with [set_storage],
+//! then every function here asks it whether it [handles](Storage::handles) the path it's given
+//! and goes through it when it does, or through [std::fs] otherwise. There's only one storage,
+//! the paths it doesn't handle are always plain files.
+//!
+//! The next tracks of a library on a network share can also be read from local copies, see
+//! [set_cached].
+
+use once_cell::sync::{Lazy, OnceCell};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::{fs, io};
+
+/// Re-exported so storages can be written without depending on Symphonia
+pub use symphonia::core::io::MediaSource;
+
+static STORAGE: OnceCell<Box<dyn Storage>> = OnceCell::new();
+static CACHED: Lazy<RwLock<HashMap<String, PathBuf>>> = Lazy::new(Default::default);
+
+/// Somewhere tracks can be read from other than plain files, like the content URIs of the
+/// Android Storage Access Framework
+///
+/// Paths inside a storage still look like `root/folder/file.ext`, so they can be joined and
+/// stripped like plain paths
+pub trait Storage: Send + Sync {
+    /// Returns whether `path` belongs to this storage
+    fn handles(&self, path: &str) -> bool;
+    /// Returns whether `path` can still be accessed
+    fn exists(&self, path: &str) -> bool;
+    /// Returns the audio files inside `root` and its subfolders
+    fn audio_files(&self, root: &str) -> io::Result<Vec<String>>;
+    /// Opens the file at `path`, it's read a bit at a time while the track is decoded
+    fn open(&self, path: &str) -> io::Result<Box<dyn MediaSource>>;
+    /// Returns the size in bytes of the file at `path`
+    fn size(&self, path: &str) -> io::Result<u64>;
+}
+
+/// Registers the storage used for the paths it handles, it can only be set once
+pub fn set_storage(storage: Box<dyn Storage>) {
+    if STORAGE.set(storage).is_err() {
+        eprintln!("a storage was already set");
+    }
+}
+
+/// Returns the storage that handles `path`, `None` for plain files
+pub fn get(path: &str) -> Option<&'static dyn Storage> {
+    STORAGE
+        .get()
+        .map(|storage| storage.as_ref())
+        .filter(|storage| storage.handles(path))
+}
+
+pub fn exists(path: &str) -> bool {
+    match get(path) {
+        Some(storage) => storage.exists(path),
+        None => Path::new(path).exists(),
+    }
+}
+
+/// Makes [`open`] read `path` from a local copy, like the ones of the next tracks of a library on
+/// a network share, or from where it is again with `None`
+pub fn set_cached(path: &str, copy: Option<PathBuf>) {
+    let mut cached = CACHED.write().unwrap();
+    match copy {
+        Some(copy) => cached.insert(path.to_string(), copy),
+        None => cached.remove(path),
+    };
+}
+
+/// Makes the track read from the local copy `copy` be read from where it is again, once the copy
+/// is removed
+pub fn remove_copy(copy: &Path) {
+    CACHED.write().unwrap().retain(|_, cached| cached != copy);
+}
+
+/// The local copy is read when there's one, the file itself when it was removed since
+pub fn open(path: &str) -> io::Result<Box<dyn MediaSource>> {
+    let copy = CACHED.read().unwrap().get(path).cloned();
+    if let Some(file) = copy.and_then(|copy| File::open(copy).ok()) {
+        return Ok(Box::new(file));
+    }
+    match get(path) {
+        Some(storage) => storage.open(path),
+        None => Ok(Box::new(File::open(path)?)),
+    }
+}
+
+pub fn size(path: &str) -> io::Result<u64> {
+    match get(path) {
+        Some(storage) => storage.size(path),
+        None => Ok(fs::metadata(path)?.len()),
+    }
+}
@@ -1,21 +1,26 @@
 package com.enn3developer.n_music
 
+import android.Manifest.permission.POST_NOTIFICATIONS
 import android.Manifest.permission.READ_MEDIA_AUDIO
 import android.annotation.SuppressLint
 import android.app.NativeActivity
+import android.content.ComponentName
 import android.content.Intent
 import android.content.pm.PackageManager
 import android.net.Uri
 import android.os.Bundle
-import android.os.Looper
+import android.provider.DocumentsContract
+import android.provider.DocumentsContract.Document
 import android.widget.Toast
 import androidx.annotation.OptIn
 import androidx.core.app.ActivityCompat
-import androidx.core.app.NotificationCompat
 import androidx.core.content.ContextCompat
 import androidx.media3.common.util.UnstableApi
-import androidx.media3.session.MediaSession
-import androidx.media3.session.MediaStyleNotificationHelper
+import androidx.media3.session.MediaController
+import androidx.media3.session.SessionToken
+import com.google.common.util.concurrent.ListenableFuture
+import java.io.FileNotFoundException
+import java.util.concurrent.ConcurrentHashMap
 
 
 class MainActivity : NativeActivity() {
@@ -40,6 +45,12 @@ class MainActivity : NativeActivity() {
         const val REQUEST_PERMISSION_CODE = 1
     }
 
+    private var controller: ListenableFuture<MediaController>? = null
+
+    // the document ids of the files found by listAudioFiles, keyed by their tree and path, the
+    // native side reads files from many threads at once
+    private val documents = ConcurrentHashMap<String, String>()
+
     private external fun start(activity: MainActivity)
     private external fun gotDirectory(directory: String)
     private external fun gotFile(file: String)
@@ -74,22 +85,121 @@ class MainActivity : NativeActivity() {
         startActivity(browserIntent)
     }
 
+    @Suppress("unused")
+    private fun connectMediaSession() {
+        runOnUiThread {
+            val token =
+                SessionToken(applicationContext, ComponentName(this, PlaybackService::class.java))
+            // being connected starts the service, so it keeps playing when the app is closed
+            controller = MediaController.Builder(applicationContext, token).buildAsync()
+        }
+    }
+
     @OptIn(UnstableApi::class)
     @Suppress("unused")
-    private fun createNotification() {
-        val mediaSession =
-            MediaSession.Builder(
-                applicationContext,
-                NPlayer(Looper.getMainLooper())
-            )
-                .build()
-        var notification = NotificationCompat.Builder(applicationContext, "n_music")
-            .setVisibility(NotificationCompat.VISIBILITY_PUBLIC)
-            .setSmallIcon(R.mipmap.ic_launcher_round).setStyle(
-                MediaStyleNotificationHelper.MediaStyle(mediaSession)
-                    .setShowActionsInCompactView(1 /* #1: pause button */)
-            ).build()
+    private fun setPlaying(playing: Boolean) {
+        PlaybackService.player.setPlaying(playing)
+    }
+
+    @OptIn(UnstableApi::class)
+    @Suppress("unused")
+    private fun setMetadata(title: String, artist: String, artwork: String, lengthMs: Long) {
+        PlaybackService.player.setMetadata(title, artist, artwork, lengthMs)
+    }
+
+    @OptIn(UnstableApi::class)
+    @Suppress("unused")
+    private fun setPosition(positionMs: Long, lengthMs: Long) {
+        PlaybackService.player.setPosition(positionMs, lengthMs)
+    }
+
+    // Lists the audio files inside the folder picked by the user, the paths are relative to it
+    @Suppress("unused")
+    private fun listAudioFiles(tree: String): Array<String> {
+        val treeUri = Uri.parse(tree)
+        val files = mutableListOf<String>()
+        val folders = ArrayDeque<Pair<String, String>>()
+        folders.add(DocumentsContract.getTreeDocumentId(treeUri) to "")
+        while (folders.isNotEmpty()) {
+            val (id, folder) = folders.removeLast()
+            queryChildren(treeUri, id) { childId, name, mime ->
+                val path = folder + name
+                if (mime == Document.MIME_TYPE_DIR) {
+                    folders.add(childId to "$path/")
+                } else if (mime.startsWith("audio/")) {
+                    documents["$tree/$path"] = childId
+                    files.add(path)
+                }
+            }
+        }
+        return files.toTypedArray()
+    }
+
+    // Opens a file inside the folder picked by the user, the descriptor is owned by the native
+    // side, -1 if the file can't be opened
+    @Suppress("unused")
+    private fun openDocument(tree: String, path: String): Int {
+        val treeUri = Uri.parse(tree)
+        val id = findDocument(treeUri, tree, path) ?: return -1
+        return try {
+            val uri = DocumentsContract.buildDocumentUriUsingTree(treeUri, id)
+            contentResolver.openFileDescriptor(uri, "r")?.detachFd() ?: -1
+        } catch (e: FileNotFoundException) {
+            -1
+        } catch (e: SecurityException) {
+            -1
+        }
+    }
 
+    @Suppress("unused")
+    private fun documentExists(tree: String, path: String): Boolean {
+        val readable = contentResolver.persistedUriPermissions.any {
+            it.uri.toString() == tree && it.isReadPermission
+        }
+        return readable && (path.isEmpty() || findDocument(Uri.parse(tree), tree, path) != null)
+    }
+
+    // The library saved from a previous run wasn't listed yet, so its files are looked up by name
+    private fun findDocument(treeUri: Uri, tree: String, path: String): String? {
+        documents["$tree/$path"]?.let { return it }
+        var id = try {
+            DocumentsContract.getTreeDocumentId(treeUri)
+        } catch (e: IllegalArgumentException) {
+            return null
+        }
+        for (name in path.split('/').filter { it.isNotEmpty() }) {
+            var found: String? = null
+            queryChildren(treeUri, id) { childId, childName, _ ->
+                if (found == null && childName == name) {
+                    found = childId
+                }
+            }
+            id = found ?: return null
+        }
+        documents["$tree/$path"] = id
+        return id
+    }
+
+    private fun queryChildren(
+        treeUri: Uri,
+        id: String,
+        onChild: (id: String, name: String, mime: String) -> Unit
+    ) {
+        val children = DocumentsContract.buildChildDocumentsUriUsingTree(treeUri, id)
+        val columns = arrayOf(
+            Document.COLUMN_DOCUMENT_ID,
+            Document.COLUMN_DISPLAY_NAME,
+            Document.COLUMN_MIME_TYPE
+        )
+        try {
+            contentResolver.query(children, columns, null, null, null)?.use { cursor ->
+                while (cursor.moveToNext()) {
+                    onChild(cursor.getString(0), cursor.getString(1), cursor.getString(2))
+                }
+            }
+        } catch (e: SecurityException) {
+            // the permission on the folder was revoked
+        }
     }
 
     override fun onCreate(savedInstanceState: Bundle?) {
@@ -97,6 +207,11 @@ class MainActivity : NativeActivity() {
         start(this)
     }
 
+    override fun onDestroy() {
+        controller?.let { MediaController.releaseFuture(it) }
+        super.onDestroy()
+    }
+
     override fun onActivityResult(requestCode: Int, resultCode: Int, data: Intent?) {
         if (resultCode == RESULT_OK) {
             if (requestCode == ASK_DIRECTORY) {
@@ -105,10 +220,13 @@ class MainActivity : NativeActivity() {
                         val contentResolver = applicationContext.contentResolver
                         val takeFlags: Int = Intent.FLAG_GRANT_READ_URI_PERMISSION
                         contentResolver.takePersistableUriPermission(uri, takeFlags)
-                        val path = uri.path!!.replace("/tree/primary:", "/storage/emulated/0/")
-                        Toast.makeText(applicationContext, "Loading music...", Toast.LENGTH_LONG)
-                            .show()
-                        gotDirectory(path)
+                        Toast.makeText(
+                            applicationContext,
+                            R.string.loading_music,
+                            Toast.LENGTH_LONG
+                        ).show()
+                        // the native side reads the folder through the content resolver
+                        gotDirectory(uri.toString())
                     }
                 }
             } else if (requestCode == ASK_FILE) {
@@ -129,12 +247,18 @@ class MainActivity : NativeActivity() {
         when (requestCode) {
             REQUEST_PERMISSION_CODE -> if (grantResults.isNotEmpty()) {
                 if (grantResults[0] == PackageManager.PERMISSION_GRANTED) {
-                    Toast.makeText(applicationContext, "Permission granted", Toast.LENGTH_SHORT)
-                        .show()
+                    Toast.makeText(
+                        applicationContext,
+                        R.string.permission_granted,
+                        Toast.LENGTH_SHORT
+                    ).show()
                     askDirectoryWithPermission()
                 } else {
-                    Toast.makeText(applicationContext, "Permission denied", Toast.LENGTH_SHORT)
-                        .show()
+                    Toast.makeText(
+                        applicationContext,
+                        R.string.permission_denied,
+                        Toast.LENGTH_SHORT
+                    ).show()
                 }
             }
         }
@@ -150,7 +274,8 @@ class MainActivity : NativeActivity() {
     private fun requestPermissions() {
         ActivityCompat.requestPermissions(
             this,
-            arrayOf(READ_MEDIA_AUDIO),
+            // the notification of the media session needs its permission too
+            arrayOf(READ_MEDIA_AUDIO, POST_NOTIFICATIONS),
             REQUEST_PERMISSION_CODE
         )
     }
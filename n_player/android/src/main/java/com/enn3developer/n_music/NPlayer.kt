@@ -1,14 +1,114 @@
 package com.enn3developer.n_music
 
+import android.net.Uri
+import android.os.Handler
 import android.os.Looper
+import androidx.media3.common.C
+import androidx.media3.common.MediaMetadata
+import androidx.media3.common.Player
 import androidx.media3.common.SimpleBasePlayer
 import androidx.media3.common.util.UnstableApi
+import com.google.common.util.concurrent.Futures
+import com.google.common.util.concurrent.ListenableFuture
+import java.io.File
 
+// Mirrors the state of the native player so the media session can show it, the commands coming
+// from the session are sent to the native player
 @UnstableApi
 class NPlayer(looper: Looper) : SimpleBasePlayer(looper) {
+    companion object {
+        // same values as the ones read by the native side
+        const val PLAY = 0
+        const val PAUSE = 1
+        const val NEXT = 2
+        const val PREVIOUS = 3
+        const val SEEK = 4
+    }
+
+    private val handler = Handler(looper)
+    private var playing = false
+    private var metadata: MediaMetadata? = null
+    private var durationMs = C.TIME_UNSET
+    private var positionMs = 0L
+
+    // a new uid for every track so the controllers know it changed
+    private var track = 0L
+
+    private external fun command(command: Int, positionMs: Long)
+
+    // called by the native side from any thread, the state is only touched on the player looper
+    fun setPlaying(playing: Boolean) {
+        handler.post {
+            this.playing = playing
+            invalidateState()
+        }
+    }
+
+    fun setMetadata(title: String, artist: String, artwork: String, lengthMs: Long) {
+        handler.post {
+            val builder = MediaMetadata.Builder().setTitle(title)
+            if (artist.isNotEmpty()) {
+                builder.setArtist(artist)
+            }
+            if (artwork.isNotEmpty()) {
+                builder.setArtworkUri(Uri.fromFile(File(artwork)))
+            }
+            metadata = builder.build()
+            durationMs = lengthMs
+            positionMs = 0
+            track++
+            invalidateState()
+        }
+    }
+
+    fun setPosition(positionMs: Long, lengthMs: Long) {
+        handler.post {
+            this.positionMs = positionMs
+            durationMs = lengthMs
+            invalidateState()
+        }
+    }
+
     override fun getState(): State {
-        return State.Builder().setVolume(1.0f).setRepeatMode(REPEAT_MODE_ALL)
-            .setPlaybackState(STATE_IDLE)
+        val commands = Player.Commands.Builder().addAll(
+            COMMAND_PLAY_PAUSE,
+            COMMAND_SEEK_TO_NEXT,
+            COMMAND_SEEK_TO_PREVIOUS,
+            COMMAND_SEEK_IN_CURRENT_MEDIA_ITEM,
+            COMMAND_GET_CURRENT_MEDIA_ITEM,
+            COMMAND_GET_METADATA
+        ).build()
+        // the repeat mode makes next and previous always available with a single item
+        val builder = State.Builder().setAvailableCommands(commands).setVolume(1.0f)
+            .setRepeatMode(REPEAT_MODE_ALL)
+            .setPlayWhenReady(playing, PLAY_WHEN_READY_CHANGE_REASON_USER_REQUEST)
+        val metadata = metadata ?: return builder.setPlaybackState(STATE_IDLE).build()
+        val item = MediaItemData.Builder(track).setMediaMetadata(metadata)
+            .setDurationUs(if (durationMs == C.TIME_UNSET) C.TIME_UNSET else durationMs * 1000)
             .build()
+        return builder.setPlaybackState(STATE_READY).setPlaylist(listOf(item))
+            .setCurrentMediaItemIndex(0).setContentPositionMs(positionMs).build()
+    }
+
+    override fun handleSetPlayWhenReady(playWhenReady: Boolean): ListenableFuture<*> {
+        command(if (playWhenReady) PLAY else PAUSE, 0)
+        playing = playWhenReady
+        return Futures.immediateVoidFuture()
+    }
+
+    override fun handleSeek(
+        mediaItemIndex: Int,
+        positionMs: Long,
+        seekCommand: Int
+    ): ListenableFuture<*> {
+        when (seekCommand) {
+            COMMAND_SEEK_TO_NEXT, COMMAND_SEEK_TO_NEXT_MEDIA_ITEM -> command(NEXT, 0)
+            COMMAND_SEEK_TO_PREVIOUS, COMMAND_SEEK_TO_PREVIOUS_MEDIA_ITEM -> command(PREVIOUS, 0)
+            else -> {
+                command(SEEK, positionMs)
+                this.positionMs = positionMs
+            }
+        }
+        return Futures.immediateVoidFuture()
     }
-}
\ No newline at end of file
+}
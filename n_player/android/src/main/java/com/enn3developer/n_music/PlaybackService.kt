@@ -0,0 +1,112 @@
+package com.enn3developer.n_music
+
+import android.content.BroadcastReceiver
+import android.content.Context
+import android.content.Intent
+import android.content.IntentFilter
+import android.media.AudioAttributes
+import android.media.AudioFocusRequest
+import android.media.AudioManager
+import android.os.Looper
+import androidx.core.content.ContextCompat
+import androidx.media3.common.Player
+import androidx.media3.common.util.UnstableApi
+import androidx.media3.session.MediaSession
+import androidx.media3.session.MediaSessionService
+
+// Keeps the media session alive, media3 runs it in the foreground with its notification while
+// something is playing
+@UnstableApi
+class PlaybackService : MediaSessionService() {
+    companion object {
+        // created before the service so the native side can update it right away
+        val player by lazy { NPlayer(Looper.getMainLooper()) }
+    }
+
+    private var mediaSession: MediaSession? = null
+    private lateinit var audioManager: AudioManager
+    private lateinit var focusRequest: AudioFocusRequest
+
+    // paused because another app took the audio focus for a while
+    private var resumeOnGain = false
+
+    private val focusListener = AudioManager.OnAudioFocusChangeListener { focusChange ->
+        when (focusChange) {
+            AudioManager.AUDIOFOCUS_LOSS -> {
+                resumeOnGain = false
+                player.pause()
+            }
+
+            AudioManager.AUDIOFOCUS_LOSS_TRANSIENT,
+            AudioManager.AUDIOFOCUS_LOSS_TRANSIENT_CAN_DUCK -> if (player.isPlaying) {
+                resumeOnGain = true
+                player.pause()
+            }
+
+            AudioManager.AUDIOFOCUS_GAIN -> if (resumeOnGain) {
+                resumeOnGain = false
+                player.play()
+            }
+        }
+    }
+
+    private val playerListener = object : Player.Listener {
+        override fun onIsPlayingChanged(isPlaying: Boolean) {
+            if (isPlaying) {
+                if (audioManager.requestAudioFocus(focusRequest) != AudioManager.AUDIOFOCUS_REQUEST_GRANTED) {
+                    player.pause()
+                }
+            } else if (!resumeOnGain) {
+                audioManager.abandonAudioFocusRequest(focusRequest)
+            }
+        }
+    }
+
+    // headphones unplugged or a bluetooth device disconnected
+    private val noisyReceiver = object : BroadcastReceiver() {
+        override fun onReceive(context: Context, intent: Intent) {
+            if (intent.action == AudioManager.ACTION_AUDIO_BECOMING_NOISY) {
+                player.pause()
+            }
+        }
+    }
+
+    override fun onCreate() {
+        super.onCreate()
+        audioManager = getSystemService(AudioManager::class.java)
+        focusRequest = AudioFocusRequest.Builder(AudioManager.AUDIOFOCUS_GAIN)
+            .setAudioAttributes(
+                AudioAttributes.Builder().setUsage(AudioAttributes.USAGE_MEDIA)
+                    .setContentType(AudioAttributes.CONTENT_TYPE_MUSIC).build()
+            )
+            .setOnAudioFocusChangeListener(focusListener)
+            .build()
+        player.addListener(playerListener)
+        ContextCompat.registerReceiver(
+            this,
+            noisyReceiver,
+            IntentFilter(AudioManager.ACTION_AUDIO_BECOMING_NOISY),
+            ContextCompat.RECEIVER_NOT_EXPORTED
+        )
+        mediaSession = MediaSession.Builder(this, player).build()
+    }
+
+    override fun onGetSession(controllerInfo: MediaSession.ControllerInfo): MediaSession? {
+        return mediaSession
+    }
+
+    override fun onTaskRemoved(rootIntent: Intent?) {
+        if (!player.playWhenReady) {
+            stopSelf()
+        }
+    }
+
+    override fun onDestroy() {
+        unregisterReceiver(noisyReceiver)
+        player.removeListener(playerListener)
+        audioManager.abandonAudioFocusRequest(focusRequest)
+        mediaSession?.release()
+        mediaSession = null
+        super.onDestroy()
+    }
+}
@@ -1,12 +1,19 @@
+use serde_json::{Map, Value};
 use std::fs::File;
 use std::io::Write;
 use std::path::Path;
 
 fn main() {
     println!("cargo::rerun-if-changed=ui/");
+    println!("cargo::rerun-if-changed=assets/lang/");
     slint_build::compile("ui/window.slint").expect("Slint build failed");
-    let lang_dir = Path::new("assets").join("lang").read_dir().unwrap();
+    let lang_dir = Path::new("assets").join("lang");
+    let english: Map<String, Value> =
+        serde_json::from_str(&std::fs::read_to_string(lang_dir.join("en_English.json")).unwrap())
+            .unwrap();
+    let lang_dir = lang_dir.read_dir().unwrap();
     let mut localizations = String::from("const LOCALIZATIONS: [(&str, &str); {LEN}] = [");
+    let mut translated = String::from("const TRANSLATED: [(&str, u8); {LEN}] = [");
     let mut get_locale = String::from("pub fn get_locale(denominator: &str) -> Locale { serde_json::from_str(match denominator { ");
     let mut len = 0;
     for locale_file in lang_dir {
@@ -19,6 +26,34 @@ fn main() {
             let locale_name = split.next().unwrap();
             localizations.push_str(&format!("(\"{denominator}\", \"{locale_name}\"),"));
             len += 1;
+
+            // the missing strings are shown in english, tell translators which ones they are
+            let locale: Map<String, Value> =
+                serde_json::from_str(&std::fs::read_to_string(locale_file.path()).unwrap())
+                    .unwrap_or_else(|e| panic!("{name} isn't a valid locale: {e}"));
+            let missing = english
+                .keys()
+                .filter(|key| !locale.contains_key(*key))
+                .map(|key| key.as_str())
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                if missing.len() <= 10 {
+                    println!(
+                        "cargo::warning={name} is missing {} of {} strings: {}",
+                        missing.len(),
+                        english.len(),
+                        missing.join(", ")
+                    );
+                } else {
+                    println!(
+                        "cargo::warning={name} is missing {} of {} strings",
+                        missing.len(),
+                        english.len()
+                    );
+                }
+            }
+            let percentage = (english.len() - missing.len()) * 100 / english.len();
+            translated.push_str(&format!("(\"{denominator}\", {percentage}),"));
             if denominator != "en" {
                 get_locale.push_str(&format!("\"{denominator}\" => include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/assets/lang/{name}\")),"));
             }
@@ -26,9 +61,13 @@ fn main() {
     }
     localizations.push_str("];");
     localizations = localizations.replace("{LEN}", &len.to_string());
+    translated.push_str("];");
+    translated = translated.replace("{LEN}", &len.to_string());
     get_locale.push_str("_ => include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/assets/lang/en_English.json\"))}).unwrap()}");
     let mut content = localizations;
     content.push('\n');
+    content.push_str(&translated);
+    content.push('\n');
     content.push_str(&get_locale);
     let mut localization_file =
         File::create(Path::new(&std::env::var_os("OUT_DIR").unwrap()).join("localizations.rs"))
@@ -1,98 +1,140 @@
-use crate::localization::{get_locale_denominator, localize};
+use crate::cache::cache_tracks;
+use crate::importer::{read_library, ForeignTrack};
+use crate::localization::{
+    count, format_date, format_decimal, format_duration, format_number, format_size,
+    get_locale_denominator, load_user_locales, localize, text, Count, Text,
+};
+use crate::metadata::{metadata_chain, MetadataChain};
+use crate::musicbrainz::find_release;
 use crate::platform::Platform;
-use crate::runner::{run, Runner, RunnerMessage, RunnerSeek};
+use crate::plugins::{self, PluginTrack, PLUGIN_DIR};
+use crate::runner::{run, Runner, RunnerEvent, RunnerMessage, RunnerSeek, TrackEnd};
 use crate::settings::Settings;
+#[cfg(not(target_os = "android"))]
+use crate::tray;
+#[cfg(not(target_os = "android"))]
+use crate::OsdWindow;
 use crate::{
-    add_all_tracks_to_player, bus_server, get_image, AppData, FileTrack, Localization, MainWindow,
-    SettingsData, Theme, TrackData, WindowSize,
+    add_all_tracks_to_player, added_days, auto_dj_pick, bpm_range, bus_server, compare_tracks,
+    contrast_color, cover_color, decode_cover, embed_cover, encode_cover, export_csv, export_m3u,
+    file_format, folder_cover, folder_settings, get_image, group_tracks, in_folder, library_files,
+    listening_report, modified_time, normalize_genre, now, open_log, organized_path, parse_labels,
+    period_start, queue_progress, queue_text, read_play_count, relative_folder, searched_label,
+    set_added, subfolders, toast_error, toast_info, write_genre, write_play_count,
+    write_release_tags, write_replay_gain, Accent, AppData, BitDepth, ChapterData, Column,
+    ColumnData, CoverCache, Equalizer, EqualizerPreset, ExportedTrack, FileTrack, FolderData,
+    FolderSettings, Grouping, ImportMode, ListeningReport, Localization, LoudnessData, MainWindow,
+    PlayRecord, PropertiesData, RenameData, ReplayGainMode, SettingsData, StatData, TagChangeData,
+    TagData, TagFixData, Theme, ToastData, TrackData, WindowSize, CACHE_SIZES, COVER_SIZE,
+    LOUDNESS_TARGETS, OUTPUT_RATES, TOASTS, TRACK_SETTLE,
 };
 use flume::{Receiver, Sender};
+use n_audio::dsp::BANDS;
+use n_audio::loudness::{GainMode, GainTarget, Loudness, ReplayGain, ReplayGainTags};
 use n_audio::music_track::MusicTrack;
-use n_audio::queue::QueuePlayer;
-use n_audio::remove_ext;
-use rimage::codecs::webp::WebPDecoder;
-use rimage::operations::resize::{FilterType, ResizeAlg};
-use slint::{ComponentHandle, VecModel};
-use std::io::Cursor;
-use std::sync::Arc;
-use std::time::Duration;
-use tempfile::NamedTempFile;
+use n_audio::player::Player;
+use n_audio::queue::{QueuePlayer, OPEN_TIMEOUT};
+use n_audio::{output_device, output_devices, remove_ext, storage, Metadata};
+use slint::{Color, ComponentHandle, Model, ModelRc, SharedPixelBuffer, SharedString, VecModel};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::{Mutex, RwLock};
-use zune_core::bytestream::ZCursor;
-use zune_core::colorspace::ColorSpace;
-use zune_core::options::DecoderOptions;
-use zune_image::image::Image;
-use zune_image::traits::{DecoderTrait, OperationsTrait};
-use zune_imageprocs::crop::Crop;
 
 // TODO: possible idea for refactoring
 // make modules for each functionality and make them communicate between themselves
 pub async fn run_app<P: Platform + Send + 'static>(settings: Settings, platform: P) {
     let platform = Arc::new(Mutex::new(platform));
     let settings = Arc::new(Mutex::new(settings));
+    let log_path = platform.lock().await.internal_dir().await.join(LOG_FILE);
+    open_log(&log_path);
 
-    let tmp = NamedTempFile::new().unwrap();
     let (tx, rx) = flume::unbounded();
+    let (tx_dirty, rx_dirty) = flume::unbounded();
 
     let mut player = QueuePlayer::new(settings.lock().await.path.clone());
-    add_all_tracks_to_player(&mut player, settings.lock().await.path.clone()).await;
+    let files = settings.lock().await.files.clone();
+    add_all_tracks_to_player(&mut player, settings.lock().await.path.clone(), files).await;
     let len = player.len() as u16;
+    player
+        .set_dsp(settings.lock().await.equalizer.into())
+        .await
+        .unwrap();
+    player
+        .set_output(settings.lock().await.output_format())
+        .await
+        .unwrap();
 
-    let runner = Arc::new(RwLock::new(Runner::new(player)));
+    let (tx_events, rx_events) = flume::unbounded();
+    let runner = Arc::new(RwLock::new(Runner::new(player, tx_events)));
 
     let r = runner.clone();
     let tx_t = tx.clone();
 
-    let (tx_l, rx_l) = flume::unbounded();
+    let (tx_l, mut rx_l) = flume::unbounded();
+    // indices of the tracks on screen, their tags are read first
+    let visible = Arc::new(std::sync::Mutex::new(vec![]));
     let main_window = MainWindow::new().unwrap();
+    #[cfg(not(target_os = "android"))]
+    let osd_window = OsdWindow::new().unwrap();
 
+    // translators can drop their locale files here and see them without rebuilding the app
+    let lang_dir = platform.lock().await.internal_dir().await.join("lang");
+    if !lang_dir.exists() {
+        let _ = std::fs::create_dir(&lang_dir);
+    }
+    load_user_locales(&lang_dir);
+    let plugins_dir = platform.lock().await.internal_dir().await.join(PLUGIN_DIR);
+    if settings.lock().await.plugins {
+        if !plugins_dir.exists() {
+            let _ = std::fs::create_dir(&plugins_dir);
+        }
+        let dir = plugins_dir.clone();
+        let _ = tokio::task::spawn_blocking(move || plugins::load(&dir)).await;
+    }
     localize(
         settings.lock().await.locale.clone(),
         main_window.global::<Localization>(),
     );
 
-    let check_timestamp = settings.lock().await.check_timestamp().await;
-    let is_cached = check_timestamp && !settings.lock().await.tracks.is_empty();
+    let (mut tracks, to_load, last_index) = library_tracks(&runner, &settings).await;
 
+    let cached = tracks.len() - to_load.len();
+    let media_controls = settings.lock().await.media_controls;
     let p = platform.clone();
-    p.lock().await.add_runner(r.clone(), tx_t.clone()).await;
+    if media_controls {
+        p.lock().await.add_runner(r.clone(), tx_t.clone()).await;
+    }
+    p.lock().await.add_window(&main_window);
     let future = tokio::spawn(async move {
         let runner_future = tokio::task::spawn(run(r.clone(), rx));
-        let bus_future = tokio::task::spawn(bus_server::run(p, r.clone(), tmp));
-        if !is_cached {
-            let loader_future = tokio::task::spawn(loader(r.clone(), tx_l));
-
-            let _ = tokio::join!(runner_future, bus_future, loader_future);
-        } else {
-            let _ = tokio::join!(runner_future, bus_future);
-        }
+        // the platform only hears about the playback when it can control it
+        let bus_future = tokio::task::spawn(async move {
+            if media_controls {
+                bus_server::run(p, r).await;
+            }
+        });
+        let _ = tokio::join!(runner_future, bus_future);
     });
+    // replaced by the updater when the library changes
+    let chain = Arc::new(metadata_chain(&settings.lock().await));
+    let mut loading = (!to_load.is_empty()).then(|| {
+        tokio::task::spawn(loader(
+            runner.clone(),
+            chain,
+            tx_l,
+            to_load,
+            visible.clone(),
+        ))
+    });
+    let (tx_bpm, mut rx_bpm) = flume::unbounded();
+    let mut analyzing = tokio::task::spawn(bpm_analyzer(runner.clone(), settings.clone(), tx_bpm));
 
-    let mut tracks = vec![];
-    for i in 0..len {
-        let track_path = runner.read().await.get_path_for_file(i).await.unwrap();
-        if is_cached {
-            let track_without_ext = remove_ext(track_path);
-            if let Some(file_track) = settings
-                .lock()
-                .await
-                .tracks
-                .iter()
-                .find(|file_track| file_track.path == track_without_ext)
-            {
-                let mut track: TrackData = file_track.clone().into();
-                track.index = i as i32;
-                tracks.push(track)
-            }
-        } else {
-            tracks.push(TrackData {
-                artist: Default::default(),
-                cover: Default::default(),
-                time: Default::default(),
-                title: remove_ext(track_path).into(),
-                index: i as i32,
-            });
-        }
+    if settings.lock().await.autoplay && len > 0 {
+        let message = last_index.map_or(RunnerMessage::PlayNext, RunnerMessage::PlayTrack);
+        tx.send(message).unwrap();
     }
 
     let settings_data = main_window.global::<SettingsData>();
@@ -101,13 +143,103 @@ pub async fn run_app<P: Platform + Send + 'static>(settings: Settings, platform:
     #[cfg(target_os = "android")]
     app_data.set_android(true);
     app_data.set_version(env!("CARGO_PKG_VERSION").into());
+    app_data.set_onboarding(!settings.lock().await.onboarded);
+    app_data.set_queue_open(settings.lock().await.queue_open);
 
     settings_data.set_color_scheme(settings.lock().await.theme.into());
     settings_data.set_theme(i32::from(settings.lock().await.theme));
+    let accent_color = Color::from_argb_encoded(settings.lock().await.accent_color);
+    settings_data.set_accent(i32::from(settings.lock().await.accent));
+    settings_data.set_custom_accent(accent_color);
+    settings_data.set_custom_accent_foreground(contrast_color(accent_color));
     settings_data.set_width(settings.lock().await.window_size.width as f32);
     settings_data.set_height(settings.lock().await.window_size.height as f32);
     settings_data.set_save_window_size(settings.lock().await.save_window_size);
+    settings_data.set_show_remaining(settings.lock().await.show_remaining);
+    settings_data.set_auto_scroll(settings.lock().await.auto_scroll);
+    settings_data.set_folder_view(settings.lock().await.folder_view);
+    settings_data.set_close_to_tray(settings.lock().await.close_to_tray);
+    settings_data.set_confirm_exit(settings.lock().await.confirm_exit);
+    settings_data.set_start_minimized(settings.lock().await.start_minimized);
+    settings_data.set_autoplay(settings.lock().await.autoplay);
+    settings_data.set_auto_dj(settings.lock().await.auto_dj);
+    settings_data.set_sync_play_counts(settings.lock().await.sync_play_counts);
+    settings_data.set_osd(settings.lock().await.osd);
+    settings_data.set_preview(settings.lock().await.preview);
+    settings_data.set_media_controls(media_controls);
+    settings_data.set_plugins(settings.lock().await.plugins);
+    settings_data.set_plugins_path(plugins_dir.to_string_lossy().to_string().into());
+    settings_data.set_genre_rules(settings.lock().await.genre_rules.clone().into());
+    settings_data.set_write_genres(settings.lock().await.write_genres);
+    settings_data.set_online_metadata(settings.lock().await.online_metadata);
+    settings_data.set_discogs_token(settings.lock().await.discogs_token.clone().into());
+    settings_data.set_volume_step(settings.lock().await.volume_step as i32);
+    settings_data.set_seek_step(settings.lock().await.seek_step as i32);
+    settings_data.set_long_seek_step(settings.lock().await.long_seek_step as i32);
+    settings_data.set_pause_on_disconnect(settings.lock().await.pause_on_disconnect);
+    settings_data.set_resume_within(settings.lock().await.resume_within as i32);
+    settings_data.set_rewind_after(settings.lock().await.rewind_after as i32);
+    settings_data.set_resume_longer_than(settings.lock().await.resume_longer_than as i32);
+    settings_data.set_rewind_seconds(settings.lock().await.rewind_seconds as i32);
+    tx.send(RunnerMessage::SetRewind(settings.lock().await.rewind()))
+        .unwrap();
+    settings_data.set_embedded_cover_size(settings.lock().await.embedded_cover_size as i32);
+    let output_rate = settings.lock().await.output_rate;
+    let output_rate = OUTPUT_RATES.iter().position(|rate| *rate == output_rate);
+    settings_data.set_output_rate(output_rate.map_or(0, |i| i as i32 + 1));
+    settings_data.set_bit_depth(settings.lock().await.bit_depth.into());
+    settings_data.set_dither(settings.lock().await.dither);
+    settings_data.set_snapcast(settings.lock().await.snapcast.clone().into());
+    settings_data.set_replay_gain(settings.lock().await.replay_gain.into());
+    tx.send(RunnerMessage::SetReplayGain(
+        settings.lock().await.replay_gain,
+    ))
+    .unwrap();
+    let loudness_target = settings.lock().await.loudness_target;
+    let loudness_target = LOUDNESS_TARGETS
+        .iter()
+        .position(|target| *target == loudness_target);
+    settings_data.set_loudness_target(loudness_target.unwrap_or_default() as i32);
+    settings_data.set_prevent_clipping(settings.lock().await.prevent_clipping);
+    tx.send(RunnerMessage::SetGainTarget(
+        settings.lock().await.gain_target(),
+    ))
+    .unwrap();
+    let columns = settings
+        .lock()
+        .await
+        .columns
+        .iter()
+        .map(|column| ColumnData::from(*column))
+        .collect::<Vec<_>>();
+    settings_data.set_columns(VecModel::from_slice(&columns));
+    settings_data.set_sort_column(
+        settings
+            .lock()
+            .await
+            .sort_column
+            .map(i32::from)
+            .unwrap_or(-1),
+    );
+    settings_data.set_sort_ascending(settings.lock().await.sort_ascending);
     settings_data.set_current_path(settings.lock().await.path.clone().into());
+    settings_data.set_recent_folders(recent_folders(&settings.lock().await));
+    if let Some(font) = settings.lock().await.font.clone() {
+        settings_data.set_font_family(font.into());
+    }
+    set_equalizer(&settings_data, &settings.lock().await.equalizer);
+    settings_data.set_equalizer_presets(preset_names(&settings.lock().await.presets));
+    settings_data.set_organize_pattern(settings.lock().await.organize_pattern.clone().into());
+    settings_data.set_import_mode(i32::from(settings.lock().await.import_mode));
+    let watched_folder = settings.lock().await.watched_folder.clone();
+    settings_data.set_watched_folder(watched_folder.unwrap_or_default().into());
+    let cache_size = settings.lock().await.cache_size;
+    let cache_size = CACHE_SIZES.iter().position(|size| *size == cache_size);
+    settings_data.set_cache_size(cache_size.map_or(0, |i| i as i32 + 1));
+    settings_data.set_grouping(i32::from(settings.lock().await.grouping));
+    settings_data.set_backups(backup_names(
+        &Settings::backups(platform.lock().await).await,
+    ));
 
     let p = platform.clone();
     app_data.on_open_link(move |link| {
@@ -122,10 +254,7 @@ pub async fn run_app<P: Platform + Send + 'static>(settings: Settings, platform:
         .global::<Localization>()
         .on_set_locale(move |locale_name| {
             let denominator = get_locale_denominator(Some(locale_name.into()));
-            localize(
-                Some(denominator.to_string()),
-                window.global::<Localization>(),
-            );
+            relocalize(&window, Some(denominator.to_string()));
             let s = s.clone();
             let p = p.clone();
             slint::spawn_local(async move {
@@ -153,291 +282,4349 @@ pub async fn run_app<P: Platform + Send + 'static>(settings: Settings, platform:
         }
     });
     let s = settings.clone();
+    let window = main_window.clone_strong();
+    let p = platform.clone();
+    settings_data.on_change_accent_callback(move |accent, color| {
+        if let Ok(accent) = Accent::try_from(accent) {
+            window
+                .global::<SettingsData>()
+                .set_custom_accent_foreground(contrast_color(color));
+            let s = s.clone();
+            let p = p.clone();
+            slint::spawn_local(async move {
+                s.lock().await.accent = accent;
+                s.lock().await.accent_color = color.as_argb_encoded();
+                s.lock().await.save(p.lock().await).await;
+            })
+            .unwrap();
+        }
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
     settings_data.on_toggle_save_window_size(move |save| {
         let s = s.clone();
+        let d = d.clone();
         slint::spawn_local(async move {
             s.lock().await.save_window_size = save;
+            let _ = d.send(());
         })
-            .unwrap();
+        .unwrap();
     });
     let s = settings.clone();
-    let p = platform.clone();
-    settings_data.on_path(move || {
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_show_remaining(move |show| {
         let s = s.clone();
-        let p = p.clone();
+        let d = d.clone();
         slint::spawn_local(async move {
-            let path = p.lock().await.ask_music_dir().await;
-            s.lock().await.path = path.to_str().unwrap().to_string();
-            s.lock().await.save(p.lock().await).await;
+            s.lock().await.show_remaining = show;
+            let _ = d.send(());
         })
-            .unwrap();
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_auto_scroll(move |auto_scroll| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.auto_scroll = auto_scroll;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_close_to_tray(move |close_to_tray| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.close_to_tray = close_to_tray;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_confirm_exit(move |confirm_exit| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.confirm_exit = confirm_exit;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_start_minimized(move |start_minimized| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.start_minimized = start_minimized;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_autoplay(move |autoplay| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.autoplay = autoplay;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_auto_dj(move |auto_dj| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.auto_dj = auto_dj;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_sync_play_counts(move |sync_play_counts| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.sync_play_counts = sync_play_counts;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_osd(move |osd| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.osd = osd;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_preview(move |preview| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.preview = preview;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_media_controls(move |media_controls| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.media_controls = media_controls;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_plugins(move |plugins| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.plugins = plugins;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_genre_rules(move |rules| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.genre_rules = rules.to_string();
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_write_genres(move |write_genres| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.write_genres = write_genres;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_online_metadata(move |online_metadata| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.online_metadata = online_metadata;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_discogs_token(move |token| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.discogs_token = token.trim().to_string();
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_embedded_cover_size(move |size| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.embedded_cover_size = size.clamp(100, 3000) as u16;
+            let _ = d.send(());
+        })
+        .unwrap();
     });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_volume_step(move |step| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.volume_step = step.clamp(1, 100) as u8;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_seek_step(move |step| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.seek_step = step.clamp(1, 600) as u16;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_long_seek_step(move |step| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.long_seek_step = step.clamp(1, 600) as u16;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_pause_on_disconnect(move |pause_on_disconnect| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.pause_on_disconnect = pause_on_disconnect;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_resume_within(move |minutes| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.resume_within = minutes.clamp(0, 24 * 60) as u16;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_resume_longer_than(move |minutes| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.resume_longer_than = minutes.clamp(0, 24 * 60) as u16;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
     let t = tx.clone();
-    app_data.on_clicked(move |i| t.send(RunnerMessage::PlayTrack(i as u16)).unwrap());
+    settings_data.on_change_rewind(move |after, seconds| {
+        let s = s.clone();
+        let d = d.clone();
+        let t = t.clone();
+        slint::spawn_local(async move {
+            let mut settings = s.lock().await;
+            settings.rewind_after = after.clamp(0, 24 * 60) as u16;
+            settings.rewind_seconds = seconds.clamp(1, 600) as u16;
+            t.send(RunnerMessage::SetRewind(settings.rewind())).unwrap();
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
     let t = tx.clone();
-    app_data.on_play_previous(move || t.send(RunnerMessage::PlayPrevious).unwrap());
+    settings_data.on_change_output(move |rate, bit_depth| {
+        let s = s.clone();
+        let d = d.clone();
+        let t = t.clone();
+        slint::spawn_local(async move {
+            let mut settings = s.lock().await;
+            // 0 follows the tracks, the others are OUTPUT_RATES
+            settings.output_rate = (rate as usize)
+                .checked_sub(1)
+                .and_then(|i| OUTPUT_RATES.get(i))
+                .copied()
+                .unwrap_or_default();
+            settings.bit_depth = BitDepth::try_from(bit_depth).unwrap_or_default();
+            t.send(RunnerMessage::SetOutput(settings.output_format()))
+                .unwrap();
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
     let t = tx.clone();
-    app_data.on_toggle_pause(move || t.send(RunnerMessage::TogglePause).unwrap());
+    settings_data.on_toggle_dither(move |dither| {
+        let s = s.clone();
+        let d = d.clone();
+        let t = t.clone();
+        slint::spawn_local(async move {
+            let mut settings = s.lock().await;
+            settings.dither = dither;
+            t.send(RunnerMessage::SetOutput(settings.output_format()))
+                .unwrap();
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
     let t = tx.clone();
-    app_data.on_play_next(move || t.send(RunnerMessage::PlayNext).unwrap());
+    settings_data.on_change_snapcast(move |source| {
+        let s = s.clone();
+        let d = d.clone();
+        let t = t.clone();
+        slint::spawn_local(async move {
+            let mut settings = s.lock().await;
+            settings.snapcast = source.trim().to_string();
+            t.send(RunnerMessage::SetOutput(settings.output_format()))
+                .unwrap();
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
     let t = tx.clone();
-    app_data.on_seek(move |time| {
-        t.send(RunnerMessage::Seek(RunnerSeek::Absolute(time as f64)))
-            .unwrap()
+    settings_data.on_change_replay_gain(move |mode| {
+        let s = s.clone();
+        let d = d.clone();
+        let t = t.clone();
+        slint::spawn_local(async move {
+            let mode = ReplayGainMode::try_from(mode).unwrap_or_default();
+            s.lock().await.replay_gain = mode;
+            t.send(RunnerMessage::SetReplayGain(mode)).unwrap();
+            let _ = d.send(());
+        })
+        .unwrap();
     });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
     let t = tx.clone();
-    app_data.on_set_volume(move |volume| t.send(RunnerMessage::SetVolume(volume as f64)).unwrap());
-    let (tx_searching, rx_searching) = flume::unbounded();
-    app_data.on_searching(move |searching| tx_searching.send(searching.to_string()).unwrap());
-    let (tx_changing, rx_changing) = flume::unbounded();
-    app_data.on_changing(move || tx_changing.send(()).unwrap());
+    settings_data.on_change_gain_target(move |target, prevent_clipping| {
+        let s = s.clone();
+        let d = d.clone();
+        let t = t.clone();
+        slint::spawn_local(async move {
+            let mut settings = s.lock().await;
+            if let Some(target) = LOUDNESS_TARGETS.get(target as usize) {
+                settings.loudness_target = *target;
+            }
+            settings.prevent_clipping = prevent_clipping;
+            t.send(RunnerMessage::SetGainTarget(settings.gain_target()))
+                .unwrap();
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    let window = main_window.as_weak();
+    settings_data.on_resize_column(move |id, width| {
+        if let Some(window) = window.upgrade() {
+            update_column(&window, id, |column| column.width = width);
+        }
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            if let Ok(id) = Column::try_from(id) {
+                if let Some(column) = s.lock().await.columns.iter_mut().find(|c| c.column == id) {
+                    column.width = width;
+                }
+            }
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
     let window = main_window.as_weak();
+    settings_data.on_toggle_column(move |id, visible| {
+        if let Some(window) = window.upgrade() {
+            update_column(&window, id, |column| column.visible = visible);
+        }
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            if let Ok(id) = Column::try_from(id) {
+                if let Some(column) = s.lock().await.columns.iter_mut().find(|c| c.column == id) {
+                    column.visible = visible;
+                }
+            }
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let (tx_sorting, rx_sorting) = flume::unbounded();
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_sort_callback(move |column, ascending| {
+        let sort = Column::try_from(column)
+            .ok()
+            .map(|column| (column, ascending));
+        tx_sorting.send(sort).unwrap();
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.sort_column = sort.map(|(column, _)| column);
+            s.lock().await.sort_ascending = ascending;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
     let r = runner.clone();
     let s = settings.clone();
     let p = platform.clone();
-    let updater = tokio::task::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_millis(250));
-        let mut searching = String::new();
-        let mut old_index = u16::MAX;
-        let mut loaded = 0;
-        let threshold = num_cpus::get() * 4;
-        let mut saved = false;
-        loop {
-            interval.tick().await;
-            let guard = r.read().await;
-            let mut index = guard.index();
-            if index > len {
-                index = 0;
+    let window = main_window.as_weak();
+    settings_data.on_path(move || {
+        let r = r.clone();
+        let s = s.clone();
+        let p = p.clone();
+        let window = window.clone();
+        slint::spawn_local(async move {
+            let path = p.lock().await.ask_music_dir().await;
+            // the dialog was closed
+            if path.as_os_str().is_empty() {
+                return;
             }
-            let playback = guard.playback();
-            let time = guard.time();
-            let length = time.length;
-            let time_float = time.position;
-            let volume = guard.volume();
-            let position = time.format_pos();
-
-            let change_time = if let Ok(()) = rx_changing.try_recv() {
-                false
-            } else {
-                true
-            };
-
-            let mut new_loaded = false;
-            while let Ok(track_data) = rx_l.try_recv() {
-                if let Some((index, file_track)) = track_data {
-                    let file = file_track.clone();
-                    s.lock().await.tracks.push(file);
+            let path = path.to_str().unwrap().to_string();
+            open_library(path, r, &s, &p, &window).await;
+        })
+        .unwrap();
+    });
+    let r = runner.clone();
+    let s = settings.clone();
+    let p = platform.clone();
+    let window = main_window.as_weak();
+    settings_data.on_open_folder(move |path| {
+        let r = r.clone();
+        let s = s.clone();
+        let p = p.clone();
+        let window = window.clone();
+        slint::spawn_local(async move {
+            open_library(path.to_string(), r, &s, &p, &window).await;
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let p = platform.clone();
+    settings_data.on_change_font(move |font| {
+        let s = s.clone();
+        let p = p.clone();
+        slint::spawn_local(async move {
+            let font = font.trim();
+            s.lock().await.font = if font.is_empty() {
+                None
+            } else {
+                Some(font.to_string())
+            };
+            s.lock().await.save(p.lock().await).await;
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    let t = tx.clone();
+    let window = main_window.as_weak();
+    settings_data.on_change_equalizer(move || {
+        let Some(window) = window.upgrade() else {
+            return;
+        };
+        let equalizer = get_equalizer(&window.global::<SettingsData>());
+        t.send(RunnerMessage::SetDsp(equalizer.into())).unwrap();
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.equalizer = equalizer;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let p = platform.clone();
+    let window = main_window.as_weak();
+    settings_data.on_save_preset(move |name| {
+        let Some(window) = window.upgrade() else {
+            return;
+        };
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return;
+        }
+        let equalizer = get_equalizer(&window.global::<SettingsData>());
+        let s = s.clone();
+        let p = p.clone();
+        slint::spawn_local(async move {
+            let mut settings = s.lock().await;
+            let preset = EqualizerPreset {
+                name,
+                bands: equalizer.bands,
+                preamp: equalizer.preamp,
+            };
+            // saving with the name of an existing preset overwrites it
+            if let Some(existing) = settings
+                .presets
+                .iter_mut()
+                .find(|existing| existing.name == preset.name)
+            {
+                *existing = preset;
+            } else {
+                settings.presets.push(preset);
+            }
+            window
+                .global::<SettingsData>()
+                .set_equalizer_presets(preset_names(&settings.presets));
+            settings.save(p.lock().await).await;
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let window = main_window.as_weak();
+    settings_data.on_load_preset(move |index| {
+        let s = s.clone();
+        let window = window.clone();
+        slint::spawn_local(async move {
+            let Some(preset) = s.lock().await.presets.get(index as usize).cloned() else {
+                return;
+            };
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            let settings_data = window.global::<SettingsData>();
+            let mut equalizer = get_equalizer(&settings_data);
+            equalizer.bands = preset.bands;
+            equalizer.preamp = preset.preamp;
+            set_equalizer(&settings_data, &equalizer);
+            settings_data.invoke_change_equalizer();
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let p = platform.clone();
+    let window = main_window.as_weak();
+    settings_data.on_delete_preset(move |index| {
+        let s = s.clone();
+        let p = p.clone();
+        let window = window.clone();
+        slint::spawn_local(async move {
+            let mut settings = s.lock().await;
+            if index < 0 || index as usize >= settings.presets.len() {
+                return;
+            }
+            settings.presets.remove(index as usize);
+            if let Some(window) = window.upgrade() {
+                window
+                    .global::<SettingsData>()
+                    .set_equalizer_presets(preset_names(&settings.presets));
+            }
+            settings.save(p.lock().await).await;
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let p = platform.clone();
+    let window = main_window.as_weak();
+    settings_data.on_back_up(move || {
+        let s = s.clone();
+        let p = p.clone();
+        let window = window.clone();
+        slint::spawn_local(async move {
+            match s.lock().await.back_up(p.lock().await).await {
+                Ok(_) => toast_info(text(Text::BackedUp, &[])),
+                Err(e) => toast_error(text(Text::CantBackUp, &[("error", &e)])),
+            }
+            if let Some(window) = window.upgrade() {
+                window
+                    .global::<SettingsData>()
+                    .set_backups(backup_names(&Settings::backups(p.lock().await).await));
+            }
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let p = platform.clone();
+    settings_data.on_restore_backup(move |index| {
+        let s = s.clone();
+        let p = p.clone();
+        slint::spawn_local(async move {
+            let backups = Settings::backups(p.lock().await).await;
+            let Some(time) = backups.get(index as usize).copied() else {
+                return;
+            };
+            let restoring = s.lock().await.restore_backup(p.lock().await, time).await;
+            match restoring {
+                Ok(()) => toast_info(text(Text::Restored, &[])),
+                Err(e) => toast_error(text(
+                    Text::CantRestore,
+                    &[("date", &format_date(time)), ("error", &e)],
+                )),
+            }
+        })
+        .unwrap();
+    });
+    let p = platform.clone();
+    let window = main_window.as_weak();
+    settings_data.on_delete_backup(move |index| {
+        let p = p.clone();
+        let window = window.clone();
+        slint::spawn_local(async move {
+            let backups = Settings::backups(p.lock().await).await;
+            let Some(time) = backups.get(index as usize).copied() else {
+                return;
+            };
+            if let Err(e) = Settings::delete_backup(p.lock().await, time).await {
+                eprintln!("can't delete the backup: {e}");
+            }
+            if let Some(window) = window.upgrade() {
+                window
+                    .global::<SettingsData>()
+                    .set_backups(backup_names(&Settings::backups(p.lock().await).await));
+            }
+        })
+        .unwrap();
+    });
+    let window = main_window.as_weak();
+    settings_data.on_reset_equalizer(move || {
+        let Some(window) = window.upgrade() else {
+            return;
+        };
+        let settings_data = window.global::<SettingsData>();
+        let flat = Equalizer::default();
+        let mut equalizer = get_equalizer(&settings_data);
+        equalizer.bands = flat.bands;
+        equalizer.preamp = flat.preamp;
+        set_equalizer(&settings_data, &equalizer);
+        settings_data.invoke_change_equalizer();
+    });
+    let (tx_selecting, rx_selecting) = flume::unbounded();
+    let t = tx.clone();
+    let t_s = tx_selecting.clone();
+    app_data.on_clicked(move |i| {
+        t_s.send(Selection::Clear).unwrap();
+        t.send(RunnerMessage::PlayTrack(i as u16)).unwrap()
+    });
+    app_data.on_select(move |i, control, shift| {
+        let selection = if shift {
+            Selection::Range(i as u16)
+        } else if control {
+            Selection::Toggle(i as u16)
+        } else {
+            Selection::Clear
+        };
+        tx_selecting.send(selection).unwrap()
+    });
+    let t = tx.clone();
+    app_data.on_enqueue(move |i| t.send(RunnerMessage::Enqueue(i as u16)).unwrap());
+    let t = tx.clone();
+    app_data
+        .on_remove_upcoming(move |i| t.send(RunnerMessage::RemoveUpcoming(i as usize)).unwrap());
+    let t = tx.clone();
+    app_data.on_move_upcoming(move |from, to| {
+        t.send(RunnerMessage::MoveUpcoming(from as usize, to as usize))
+            .unwrap()
+    });
+    let t = tx.clone();
+    app_data.on_clear_queue(move || t.send(RunnerMessage::ClearQueue).unwrap());
+    let t = tx.clone();
+    app_data.on_undo_queue(move || t.send(RunnerMessage::UndoQueue).unwrap());
+    let t = tx.clone();
+    app_data.on_redo_queue(move || t.send(RunnerMessage::RedoQueue).unwrap());
+    let window = main_window.as_weak();
+    app_data.on_copy_queue(move || {
+        let Some(window) = window.upgrade() else {
+            return Default::default();
+        };
+        let tracks = shared_queue(&window.global::<AppData>());
+        toast_info(count(Count::QueueCopied, tracks.len(), &[]));
+        queue_text(&tracks).into()
+    });
+    let r = runner.clone();
+    let p = platform.clone();
+    let window = main_window.as_weak();
+    app_data.on_export_queue(move || {
+        let Some(window) = window.upgrade() else {
+            return;
+        };
+        let tracks = shared_queue(&window.global::<AppData>());
+        let r = r.clone();
+        let p = p.clone();
+        slint::spawn_local(async move {
+            let Some(file) = p.lock().await.ask_save_file("queue.m3u8".to_string()).await else {
+                return;
+            };
+            let mut entries = vec![];
+            for track in tracks {
+                if let Some(path) = r.read().await.get_path_for_file(track.index as u16).await {
+                    entries.push((path, track));
+                }
+            }
+            match tokio::fs::write(&file, export_m3u(&entries)).await {
+                Ok(()) => toast_info(count(Count::Exported, entries.len(), &[])),
+                Err(e) => toast_error(text(
+                    Text::CantExport,
+                    &[("path", &file.display()), ("error", &e)],
+                )),
+            }
+        })
+        .unwrap();
+    });
+    let t = tx.clone();
+    app_data.on_play_previous(move || t.send(RunnerMessage::PlayPrevious).unwrap());
+    let t = tx.clone();
+    app_data.on_toggle_pause(move || t.send(RunnerMessage::TogglePause).unwrap());
+    let t = tx.clone();
+    app_data.on_play_next(move || t.send(RunnerMessage::PlayNext).unwrap());
+    let t = tx.clone();
+    app_data.on_seek(move |time| {
+        t.send(RunnerMessage::Seek(RunnerSeek::Absolute(time as f64)))
+            .unwrap()
+    });
+    let t = tx.clone();
+    app_data.on_set_volume(move |volume| t.send(RunnerMessage::SetVolume(volume as f64)).unwrap());
+    let t = tx.clone();
+    app_data.on_toggle_mute(move || t.send(RunnerMessage::ToggleMute).unwrap());
+    let (tx_searching, rx_searching) = flume::unbounded();
+    app_data.on_searching(move |searching| tx_searching.send(searching.to_string()).unwrap());
+    let (tx_play_visible, rx_play_visible) = flume::unbounded();
+    app_data.on_play_visible(move || tx_play_visible.send(()).unwrap());
+    let (tx_replay_gain, rx_replay_gain) = flume::unbounded();
+    app_data.on_scan_replay_gain(move |i| tx_replay_gain.send(i as u16).unwrap());
+    let (tx_embed_cover, rx_embed_cover) = flume::unbounded();
+    app_data.on_embed_cover(move |i| tx_embed_cover.send(i as u16).unwrap());
+    let (tx_changing, rx_changing) = flume::unbounded();
+    app_data.on_changing(move || tx_changing.send(()).unwrap());
+    let (tx_jump, rx_jump) = flume::unbounded();
+    app_data.on_jump(move || tx_jump.send(()).unwrap());
+    let (tx_preview, rx_preview) = flume::unbounded();
+    let t = tx_preview.clone();
+    app_data.on_preview(move |i| t.send(Some(i as u16)).unwrap());
+    app_data.on_stop_preview(move || tx_preview.send(None).unwrap());
+    let (tx_dismiss, rx_dismiss) = flume::unbounded();
+    app_data.on_dismiss_toast(move |i| tx_dismiss.send(i as usize).unwrap());
+    let (tx_folder, rx_folder) = flume::unbounded();
+    if settings.lock().await.folder_view {
+        tx_folder.send(Some(String::new())).unwrap();
+    }
+    let t_f = tx_folder.clone();
+    app_data.on_open_folder(move |folder| t_f.send(Some(folder.to_string())).unwrap());
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_toggle_folder_view(move |folder_view| {
+        tx_folder.send(folder_view.then(String::new)).unwrap();
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.folder_view = folder_view;
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let (tx_play_folder, rx_play_folder) = flume::unbounded();
+    app_data.on_play_folder(move |folder| tx_play_folder.send(folder.to_string()).unwrap());
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    app_data.on_change_folder_settings(move |folder, remember_position, in_order| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            let overrides = FolderSettings {
+                remember_position,
+                in_order,
+            };
+            let mut settings = s.lock().await;
+            if overrides == FolderSettings::default() {
+                settings.folders.remove(folder.as_str());
+            } else {
+                settings.folders.insert(folder.to_string(), overrides);
+            }
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    let window = main_window.as_weak();
+    settings_data.on_change_organize_pattern(move |pattern| {
+        // the preview was made with the old pattern
+        if let Some(window) = window.upgrade() {
+            window
+                .global::<SettingsData>()
+                .set_organize_preview(Default::default());
+        }
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            s.lock().await.organize_pattern = pattern.to_string();
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let r = runner.clone();
+    let s = settings.clone();
+    let window = main_window.as_weak();
+    settings_data.on_preview_organize(move || {
+        let r = r.clone();
+        let s = s.clone();
+        let window = window.clone();
+        slint::spawn_local(async move {
+            if let Some(window) = window.upgrade() {
+                window.global::<SettingsData>().set_organizing(true);
+            }
+            let pattern = s.lock().await.organize_pattern.clone();
+            let plan = organize_plan(&r, &pattern).await;
+            let root = PathBuf::from(r.read().await.path());
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            let relative = |path: &Path| {
+                path.strip_prefix(&root)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .to_string()
+            };
+            let preview = plan
+                .iter()
+                .map(|(_, from, to)| RenameData {
+                    from: relative(from).into(),
+                    to: relative(to).into(),
+                })
+                .collect::<Vec<_>>();
+            let settings_data = window.global::<SettingsData>();
+            settings_data.set_organize_preview(VecModel::from_slice(&preview));
+            settings_data.set_organizing(false);
+            if preview.is_empty() {
+                toast_info(window.global::<Localization>().get_nothing_to_organize());
+            }
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_import_mode(move |mode| {
+        if let Ok(mode) = ImportMode::try_from(mode) {
+            let s = s.clone();
+            let d = d.clone();
+            slint::spawn_local(async move {
+                s.lock().await.import_mode = mode;
+                let _ = d.send(());
+            })
+            .unwrap();
+        }
+    });
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_cache_size(move |size| {
+        let s = s.clone();
+        let d = d.clone();
+        slint::spawn_local(async move {
+            // 0 doesn't copy the tracks, the others are CACHE_SIZES
+            s.lock().await.cache_size = (size as usize)
+                .checked_sub(1)
+                .and_then(|i| CACHE_SIZES.get(i))
+                .copied()
+                .unwrap_or_default();
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    let (tx_grouping, rx_grouping) = flume::unbounded();
+    // the headers are added to the list by the updater
+    let grouping = settings.lock().await.grouping;
+    if grouping != Grouping::None {
+        tx_grouping.send(grouping).unwrap();
+    }
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    settings_data.on_change_grouping(move |grouping| {
+        if let Ok(grouping) = Grouping::try_from(grouping) {
+            tx_grouping.send(grouping).unwrap();
+            let s = s.clone();
+            let d = d.clone();
+            slint::spawn_local(async move {
+                s.lock().await.grouping = grouping;
+                let _ = d.send(());
+            })
+            .unwrap();
+        }
+    });
+    let s = settings.clone();
+    let window = main_window.as_weak();
+    settings_data.on_show_statistics(move |period| {
+        let s = s.clone();
+        let window = window.clone();
+        slint::spawn_local(async move {
+            let report = listening_report(&s.lock().await.listening.history, period_start(period));
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            let settings_data = window.global::<SettingsData>();
+            settings_data.set_stats_summary(listening_summary(&report).into());
+            settings_data.set_top_artists(VecModel::from_slice(&stat_data(&report.artists)));
+            settings_data.set_top_tracks(VecModel::from_slice(&stat_data(&report.tracks)));
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let p = platform.clone();
+    let window = main_window.as_weak();
+    settings_data.on_export_statistics(move |period| {
+        let s = s.clone();
+        let p = p.clone();
+        let window = window.clone();
+        slint::spawn_local(async move {
+            let report = listening_report(&s.lock().await.listening.history, period_start(period));
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            let summary = statistics_text(&window.global::<Localization>(), period, &report);
+            let Some(file) = p
+                .lock()
+                .await
+                .ask_save_file("statistics.md".to_string())
+                .await
+            else {
+                return;
+            };
+            match tokio::fs::write(&file, summary).await {
+                Ok(()) => toast_info(text(Text::SummaryExported, &[("path", &file.display())])),
+                Err(e) => toast_error(text(
+                    Text::CantExport,
+                    &[("path", &file.display()), ("error", &e)],
+                )),
+            }
+        })
+        .unwrap();
+    });
+    let (tx_imported, rx_imported) = flume::unbounded();
+    let tx_new = tx_imported.clone();
+    let tx_rescan = tx_imported.clone();
+    let tx_added = tx_imported.clone();
+    let r = runner.clone();
+    let s = settings.clone();
+    let p = platform.clone();
+    app_data.on_import_files(move || {
+        let r = r.clone();
+        let s = s.clone();
+        let p = p.clone();
+        let tx_imported = tx_imported.clone();
+        slint::spawn_local(async move {
+            let files = p.lock().await.ask_audio_files().await;
+            if files.is_empty() {
+                return;
+            }
+            import_to_library(files, &r, &s, &tx_imported).await;
+        })
+        .unwrap();
+    });
+    let r = runner.clone();
+    let s = settings.clone();
+    let p = platform.clone();
+    let t = tx.clone();
+    app_data.on_add_files(move || {
+        let r = r.clone();
+        let s = s.clone();
+        let p = p.clone();
+        let t = t.clone();
+        let tx_added = tx_added.clone();
+        slint::spawn_local(async move {
+            let files = p.lock().await.ask_audio_files().await;
+            queue_files(files, &r, &s, &t, &tx_added).await;
+        })
+        .unwrap();
+    });
+    let p = platform.clone();
+    let path = log_path.clone();
+    app_data.on_open_log(move || {
+        let p = p.clone();
+        let path = path.clone();
+        slint::spawn_local(async move {
+            p.lock()
+                .await
+                .open_link(path.to_string_lossy().to_string())
+                .await;
+        })
+        .unwrap();
+    });
+    app_data.on_copy_log(move || {
+        std::fs::read_to_string(&log_path)
+            .unwrap_or_default()
+            .into()
+    });
+    let s = settings.clone();
+    let p = platform.clone();
+    app_data.on_finish_onboarding(move || {
+        let s = s.clone();
+        let p = p.clone();
+        slint::spawn_local(async move {
+            s.lock().await.onboarded = true;
+            s.lock().await.save(p.lock().await).await;
+        })
+        .unwrap();
+    });
+    let new_files = Arc::new(Mutex::new(vec![]));
+    let r = runner.clone();
+    let s = settings.clone();
+    let n = new_files.clone();
+    let window = main_window.as_weak();
+    app_data.on_import_new_files(move || {
+        let r = r.clone();
+        let s = s.clone();
+        let n = n.clone();
+        let tx_new = tx_new.clone();
+        if let Some(window) = window.upgrade() {
+            window.global::<AppData>().set_new_files(Default::default());
+        }
+        slint::spawn_local(async move {
+            let files = std::mem::take(&mut *n.lock().await);
+            import_to_library(files, &r, &s, &tx_new).await;
+        })
+        .unwrap();
+    });
+    let n = new_files.clone();
+    let window = main_window.as_weak();
+    app_data.on_ignore_new_files(move || {
+        let n = n.clone();
+        if let Some(window) = window.upgrade() {
+            window.global::<AppData>().set_new_files(Default::default());
+        }
+        slint::spawn_local(async move {
+            n.lock().await.clear();
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let p = platform.clone();
+    let window = main_window.as_weak();
+    settings_data.on_watch_folder(move || {
+        let s = s.clone();
+        let p = p.clone();
+        let window = window.clone();
+        slint::spawn_local(async move {
+            let path = p.lock().await.ask_music_dir().await;
+            // the dialog was closed
+            if path.as_os_str().is_empty() {
+                return;
+            }
+            let path = path.to_str().unwrap().to_string();
+            if let Some(window) = window.upgrade() {
+                window
+                    .global::<SettingsData>()
+                    .set_watched_folder(path.clone().into());
+            }
+            s.lock().await.watched_folder = Some(path);
+            s.lock().await.save(p.lock().await).await;
+        })
+        .unwrap();
+    });
+    let s = settings.clone();
+    let p = platform.clone();
+    let n = new_files.clone();
+    let window = main_window.as_weak();
+    settings_data.on_stop_watching(move || {
+        let s = s.clone();
+        let p = p.clone();
+        let n = n.clone();
+        if let Some(window) = window.upgrade() {
+            window
+                .global::<SettingsData>()
+                .set_watched_folder(Default::default());
+            window.global::<AppData>().set_new_files(Default::default());
+        }
+        slint::spawn_local(async move {
+            n.lock().await.clear();
+            s.lock().await.watched_folder = None;
+            s.lock().await.save(p.lock().await).await;
+        })
+        .unwrap();
+    });
+    let r = runner.clone();
+    let s = settings.clone();
+    let p = platform.clone();
+    settings_data.on_export_library(move |json| {
+        let r = r.clone();
+        let s = s.clone();
+        let p = p.clone();
+        slint::spawn_local(async move {
+            let name = if json { "library.json" } else { "library.csv" };
+            let Some(file) = p.lock().await.ask_save_file(name.to_string()).await else {
+                return;
+            };
+            let tracks = exported_tracks(&r, &s).await;
+            let data = if json {
+                serde_json::to_string_pretty(&tracks).unwrap()
+            } else {
+                export_csv(&tracks)
+            };
+            match tokio::fs::write(&file, data).await {
+                Ok(()) => toast_info(count(Count::Exported, tracks.len(), &[])),
+                Err(e) => toast_error(text(
+                    Text::CantExport,
+                    &[("path", &file.display()), ("error", &e)],
+                )),
+            }
+        })
+        .unwrap();
+    });
+    let (tx_plays, rx_plays) = flume::unbounded();
+    let r = runner.clone();
+    let s = settings.clone();
+    let p = platform.clone();
+    settings_data.on_import_play_counts(move || {
+        let r = r.clone();
+        let s = s.clone();
+        let p = p.clone();
+        let tx_plays = tx_plays.clone();
+        slint::spawn_local(async move {
+            let Some(file) = p.lock().await.ask_file().await.into_iter().next() else {
+                return;
+            };
+            let library = tokio::fs::read_to_string(&file)
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|xml| read_library(&xml));
+            let library = match library {
+                Ok(library) => library,
+                Err(e) => {
+                    toast_error(text(
+                        Text::CantImportPlayCounts,
+                        &[("path", &file.display()), ("error", &e)],
+                    ));
+                    return;
+                }
+            };
+            let imported = import_play_counts(&r, &s, library).await;
+            for plays in &imported {
+                tx_plays.send(*plays).unwrap();
+            }
+            s.lock().await.save(p.lock().await).await;
+            toast_info(count(Count::PlayCountsImported, imported.len(), &[]));
+        })
+        .unwrap();
+    });
+    let (tx_organized, rx_organized) = flume::unbounded();
+    let r = runner.clone();
+    let s = settings.clone();
+    let p = platform.clone();
+    let window = main_window.as_weak();
+    settings_data.on_organize(move || {
+        let r = r.clone();
+        let s = s.clone();
+        let p = p.clone();
+        let window = window.clone();
+        let tx_organized = tx_organized.clone();
+        slint::spawn_local(async move {
+            if let Some(window) = window.upgrade() {
+                window.global::<SettingsData>().set_organizing(true);
+            }
+            let pattern = s.lock().await.organize_pattern.clone();
+            let plan = organize_plan(&r, &pattern).await;
+            let root = r.read().await.path();
+            let moved = move_files(PathBuf::from(&root), plan).await;
+            {
+                let mut runner = r.write().await;
+                let mut settings = s.lock().await;
+                for (i, from, to) in &moved {
+                    if let Err(e) = runner.set_path_for_file(*i, to).await {
+                        toast_error(text(
+                            Text::CantUpdate,
+                            &[("path", &to.display()), ("error", &e)],
+                        ));
+                        continue;
+                    }
+                    settings.rename_track(from, to);
+                    tx_organized.send((*i, relative_folder(&root, to))).unwrap();
+                }
+                settings.save(p.lock().await).await;
+            }
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            let settings_data = window.global::<SettingsData>();
+            settings_data.set_organize_preview(Default::default());
+            settings_data.set_organizing(false);
+            if !moved.is_empty() {
+                toast_info(count(Count::Organized, moved.len(), &[]));
+            }
+        })
+        .unwrap();
+    });
+    let window = main_window.as_weak();
+    let scroll_timer = Rc::new(slint::Timer::default());
+    app_data.on_follow(move |row| {
+        let Some(window) = window.upgrade() else {
+            return;
+        };
+        let app_data = window.global::<AppData>();
+        let from = app_data.get_viewport_y();
+        let to = app_data.invoke_follow_target(row);
+        if from == to {
+            return;
+        }
+        let start = Instant::now();
+        let window = window.as_weak();
+        let timer = Rc::downgrade(&scroll_timer);
+        scroll_timer.start(
+            slint::TimerMode::Repeated,
+            Duration::from_millis(16),
+            move || {
+                let progress = start.elapsed().as_secs_f32() / SCROLL_DURATION.as_secs_f32();
+                let progress = progress.min(1.0);
+                // ease out cubic
+                let eased = 1.0 - (1.0 - progress).powi(3);
+                if let Some(window) = window.upgrade() {
+                    window
+                        .global::<AppData>()
+                        .set_viewport_y(from + (to - from) * eased);
+                }
+                if progress == 1.0 {
+                    if let Some(timer) = timer.upgrade() {
+                        timer.stop();
+                    }
+                }
+            },
+        );
+    });
+    let window = main_window.as_weak();
+    let v = visible.clone();
+    app_data.on_rows_shown(move |first, rows| {
+        let Some(window) = window.upgrade() else {
+            return;
+        };
+        let app_data = window.global::<AppData>();
+        let tracks = app_data.get_tracks();
+        let first = first.max(0) as usize;
+        let end = (first + rows.max(0) as usize).min(tracks.row_count());
+        if let Some(track) = tracks.row_data(first) {
+            app_data.set_current_group(track.group);
+        }
+        *v.lock().unwrap() = (first..end)
+            .filter_map(|row| tracks.row_data(row))
+            .map(|track| track.index as u16)
+            .collect();
+    });
+    let window = main_window.as_weak();
+    app_data.on_chapter_at(move |time| {
+        if let Some(window) = window.upgrade() {
+            let chapters = window.global::<AppData>().get_chapters();
+            if let Some(chapter) = chapters.iter().take_while(|c| c.start <= time).last() {
+                return chapter.title;
+            }
+        }
+        Default::default()
+    });
+    app_data.on_format_time(|time| format_duration(time as f64).into());
+    let window = main_window.as_weak();
+    let r = runner.clone();
+    let s = settings.clone();
+    app_data.on_show_properties(move |i| {
+        let window = window.clone();
+        let r = r.clone();
+        let s = s.clone();
+        slint::spawn_local(async move {
+            let Some(path) = r.read().await.get_path_for_file(i as u16).await else {
+                return;
+            };
+            let (stats, labels) = {
+                let settings = s.lock().await;
+                let path_without_ext = remove_ext(&path);
+                (
+                    settings
+                        .listening
+                        .stats
+                        .get(&path_without_ext)
+                        .copied()
+                        .unwrap_or_default(),
+                    settings
+                        .labels
+                        .get(&path_without_ext)
+                        .map(|labels| labels.join(", "))
+                        .unwrap_or_default(),
+                )
+            };
+            let p = path.clone();
+            let properties = tokio::task::spawn_blocking(move || {
+                MusicTrack::new(p.to_string_lossy().to_string())
+                    .and_then(|track| track.get_properties())
+            })
+            .await;
+            let properties = match properties {
+                Ok(Ok(properties)) => properties,
+                Ok(Err(e)) => {
+                    toast_error(text(
+                        Text::CantRead,
+                        &[("path", &path.display()), ("error", &e)],
+                    ));
+                    return;
+                }
+                Err(_) => return,
+            };
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            let app_data = window.global::<AppData>();
+            let title = app_data
+                .get_tracks()
+                .iter()
+                .find(|track| track.index == i)
+                .map(|track| track.title)
+                .unwrap_or_else(|| remove_ext(&path).into());
+            let tags = properties
+                .tags
+                .into_iter()
+                .map(|(key, value)| TagData {
+                    key: key.into(),
+                    value: value.into(),
+                })
+                .collect::<Vec<_>>();
+            app_data.set_properties(PropertiesData {
+                index: i,
+                title,
+                path: path.to_string_lossy().to_string().into(),
+                codec: properties.codec.into(),
+                sample_rate: format!("{} Hz", format_number(properties.sample_rate as u64)).into(),
+                channels: properties.channels as i32,
+                bit_depth: properties.bits_per_sample as i32,
+                bitrate: format!("{} kbps", format_number(properties.bitrate / 1000)).into(),
+                file_size: format_size(properties.file_size).into(),
+                duration: format_duration(properties.length).into(),
+                play_count: format_number(stats.count as u64).into(),
+                last_played: if stats.last_played == 0 {
+                    Default::default()
+                } else {
+                    format_date(stats.last_played).into()
+                },
+                labels: labels.into(),
+                tags: VecModel::from_slice(&tags),
+            });
+            app_data.set_properties_open(true);
+            // decoding the whole track takes a while, the rest is shown meanwhile
+            app_data.set_loudness_report(LoudnessData {
+                index: i,
+                ..Default::default()
+            });
+            let (mode, target) = {
+                let settings = s.lock().await;
+                (settings.replay_gain.resolve(false), settings.gain_target())
+            };
+            let measured = tokio::task::spawn_blocking(move || {
+                let track = MusicTrack::new(path.to_string_lossy().to_string())?;
+                let (_, tags) = track.get_format_with_gain()?;
+                Ok::<_, std::io::Error>((track.get_loudness()?, tags))
+            })
+            .await;
+            let (loudness, tags) = match measured {
+                Ok(Ok(measured)) => measured,
+                Ok(Err(e)) => {
+                    eprintln!("can't measure the loudness of track {i}: {e}");
+                    return;
+                }
+                Err(_) => return,
+            };
+            // the properties of another track may be open by now
+            if app_data.get_loudness_report().index == i {
+                app_data.set_loudness_report(loudness_report(i, &loudness, tags, mode, target));
+            }
+        })
+        .unwrap();
+    });
+    let (tx_labels, rx_labels) = flume::unbounded();
+    let r = runner.clone();
+    let s = settings.clone();
+    let d = tx_dirty.clone();
+    app_data.on_change_labels(move |i, labels| {
+        let r = r.clone();
+        let s = s.clone();
+        let d = d.clone();
+        let tx_labels = tx_labels.clone();
+        slint::spawn_local(async move {
+            let Some(path) = r.read().await.get_path_for_file(i as u16).await else {
+                return;
+            };
+            let labels = parse_labels(&labels);
+            let mut settings = s.lock().await;
+            if labels.is_empty() {
+                settings.labels.remove(&remove_ext(&path));
+            } else {
+                settings.labels.insert(remove_ext(&path), labels.clone());
+            }
+            tx_labels.send((i as u16, labels.join(", "))).unwrap();
+            let _ = d.send(());
+        })
+        .unwrap();
+    });
+    // what apply_tag_fixes writes, filled by fix_metadata
+    let tag_fixes = Arc::new(Mutex::new(vec![]));
+    let window = main_window.as_weak();
+    let r = runner.clone();
+    let f = tag_fixes.clone();
+    app_data.on_fix_metadata(move |i| {
+        let window = window.clone();
+        let r = r.clone();
+        let f = f.clone();
+        slint::spawn_local(async move {
+            let (files, metadata): (Vec<_>, Vec<_>) = album_files(&r, i as u16)
+                .await
+                .into_iter()
+                .map(|(index, path, metadata)| ((index, path), metadata))
+                .unzip();
+            let Some(clicked) = files.iter().position(|(index, _)| *index == i as u16) else {
+                toast_error(text(Text::NoAlbum, &[]));
+                return;
+            };
+            let album = metadata[clicked].album.clone();
+            let artist = metadata[clicked].artist.clone();
+            toast_info(text(Text::SearchingMusicBrainz, &[("album", &album)]));
+            let (a, n) = (album.clone(), files.len());
+            let release = tokio::task::spawn_blocking(move || find_release(&a, &artist, n))
+                .await
+                .unwrap();
+            let release = match release {
+                Ok(Some(release)) => release,
+                Ok(None) => {
+                    toast_info(text(Text::ReleaseNotFound, &[("album", &album)]));
+                    return;
+                }
+                Err(e) => {
+                    toast_error(text(Text::CantSearchMusicBrainz, &[("error", &e)]));
+                    return;
+                }
+            };
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            let localization = window.global::<Localization>();
+            let number = |number: Option<u32>| number.map(|n| n.to_string()).unwrap_or_default();
+            let mut fixes = vec![];
+            let mut preview = vec![];
+            let matches = release.match_files(&metadata);
+            for (((index, path), metadata), matched) in files.into_iter().zip(metadata).zip(matches)
+            {
+                let Some(matched) = matched else {
+                    continue;
+                };
+                let tags = release.tags(matched);
+                let changes = [
+                    (localization.get_title(), metadata.title, tags.title.clone()),
+                    (
+                        localization.get_artist(),
+                        metadata.artist,
+                        tags.artist.clone(),
+                    ),
+                    (localization.get_album(), metadata.album, tags.album.clone()),
+                    (
+                        localization.get_track_number(),
+                        number(metadata.track),
+                        tags.number.to_string(),
+                    ),
+                ]
+                .into_iter()
+                .filter(|(_, from, to)| from != to)
+                .map(|(key, from, to)| TagChangeData {
+                    key,
+                    from: from.into(),
+                    to: to.into(),
+                })
+                .collect::<Vec<_>>();
+                preview.push(TagFixData {
+                    file: path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                        .into(),
+                    changes: VecModel::from_slice(&changes),
+                });
+                fixes.push((index, path, tags));
+            }
+            if fixes.is_empty() {
+                toast_info(text(Text::ReleaseNotFound, &[("album", &album)]));
+                return;
+            }
+            *f.lock().await = fixes;
+            let app_data = window.global::<AppData>();
+            app_data.set_fix_release(format!("{} - {}", release.title, release.artist).into());
+            app_data.set_tag_fixes(VecModel::from_slice(&preview));
+            app_data.set_fix_open(true);
+        })
+        .unwrap();
+    });
+    let (tx_fixed, rx_fixed) = flume::unbounded();
+    app_data.on_apply_tag_fixes(move || {
+        let f = tag_fixes.clone();
+        let tx_fixed = tx_fixed.clone();
+        slint::spawn_local(async move {
+            let fixes = std::mem::take(&mut *f.lock().await);
+            let mut fixed = 0;
+            for (index, path, tags) in fixes {
+                let p = path.clone();
+                let written = tokio::task::spawn_blocking(move || {
+                    write_release_tags(&p, &tags).map(|()| (index, p, tags))
+                })
+                .await
+                .unwrap();
+                match written {
+                    Ok(written) => {
+                        tx_fixed.send(written).unwrap();
+                        fixed += 1;
+                    }
+                    Err(e) => toast_error(text(
+                        Text::CantWriteTags,
+                        &[("path", &path.display()), ("error", &e)],
+                    )),
+                }
+            }
+            if fixed > 0 {
+                toast_info(count(Count::TagsFixed, fixed, &[]));
+            }
+        })
+        .unwrap();
+    });
+    let r = runner.clone();
+    let p = platform.clone();
+    app_data.on_reveal_file(move |i| {
+        let r = r.clone();
+        let p = p.clone();
+        slint::spawn_local(async move {
+            if let Some(path) = r.read().await.get_path_for_file(i as u16).await {
+                p.lock().await.reveal_file(path).await;
+            }
+        })
+        .unwrap();
+    });
+    let window = main_window.as_weak();
+    let r = runner.clone();
+    let p = platform.clone();
+    let t = tx.clone();
+    app_data.on_delete_file(move |i| {
+        let window = window.clone();
+        let r = r.clone();
+        let p = p.clone();
+        let t = t.clone();
+        slint::spawn_local(async move {
+            let Some(path) = r.read().await.get_path_for_file(i as u16).await else {
+                return;
+            };
+            if p.lock().await.trash_file(path).await {
+                t.send(RunnerMessage::Remove(i as u16)).unwrap();
+                if let Some(window) = window.upgrade() {
+                    toast_info(window.global::<Localization>().get_moved_to_trash());
+                }
+            }
+        })
+        .unwrap();
+    });
+    let locales_watcher = tokio::task::spawn(watch_user_locales(
+        lang_dir,
+        main_window.as_weak(),
+        settings.clone(),
+    ));
+    let window = main_window.as_weak();
+    let r = runner.clone();
+    let s = settings.clone();
+    let p = platform.clone();
+    let tx_runner = tx.clone();
+    let saver = tokio::task::spawn(saver(
+        settings.clone(),
+        platform.clone(),
+        runner.clone(),
+        rx_dirty,
+    ));
+    let (tx_cache, rx_cache) = flume::unbounded();
+    let cache_dir = platform.lock().await.internal_dir().await.join("cache");
+    let cacher = tokio::task::spawn(cache_upcoming(
+        settings.clone(),
+        runner.clone(),
+        cache_dir,
+        rx_cache,
+    ));
+    let availability_watcher = tokio::task::spawn(watch_unavailable(runner.clone(), tx.clone()));
+    let rescanner = tokio::task::spawn(rescan_library(
+        runner.clone(),
+        settings.clone(),
+        tx.clone(),
+        tx_rescan,
+    ));
+    let folder_watcher = tokio::task::spawn(watch_new_files(
+        settings.clone(),
+        new_files,
+        main_window.as_weak(),
+    ));
+    let device_watcher = tokio::task::spawn(watch_output_device(
+        settings.clone(),
+        runner.clone(),
+        tx.clone(),
+    ));
+    let previewer = tokio::task::spawn(preview_tracks(runner.clone(), rx_preview));
+    let (tx_activity, rx_activity) = flume::unbounded();
+    #[cfg(not(target_os = "android"))]
+    let osd = osd_window.as_weak();
+    let updater = tokio::task::spawn(async move {
+        let mut activity = Activity::Focused;
+        let mut interval = tokio::time::interval(activity.update_interval());
+        let mut searching = String::new();
+        let mut old_index = u16::MAX;
+        let mut loading_index = u16::MAX;
+        // the track whose cover was cleared and since when it's playing, what's read for it waits
+        // for TRACK_SETTLE
+        let mut settling = (u16::MAX, Instant::now());
+        let mut recorded_index = u16::MAX;
+        #[cfg(not(target_os = "android"))]
+        let mut osd_index = u16::MAX;
+        // the track the auto DJ last queued a track for
+        let mut dj_index = u16::MAX;
+        // the track the next one of its folder was queued for, when the folder is played in order
+        let mut order_index = u16::MAX;
+        let mut positioned_index = u16::MAX;
+        // the playing track with its file name when its folder remembers where it was left
+        let mut positioned: Option<(u16, String)> = None;
+        let mut last_position = 0.0;
+        let mut upcoming_indices = vec![];
+        let mut removed = 0;
+        let mut unavailable = HashSet::new();
+        // None when showing the whole library
+        let mut folder: Option<String> = None;
+        let (tx_peaks, rx_peaks) = flume::unbounded();
+        let (tx_cover, rx_cover) = flume::unbounded();
+        let (tx_lyrics, rx_lyrics) = flume::unbounded();
+        let playing_covers = Arc::new(CoverCache::new(NOW_PLAYING_COVER_SIZE, PLAYING_COVERS));
+        let mut anchor = None;
+        let mut toasts: Vec<(Instant, ToastData)> = vec![];
+        let mut sort = {
+            let settings = s.lock().await;
+            settings
+                .sort_column
+                .map(|column| (column, settings.sort_ascending))
+        };
+        let mut grouping = s.lock().await.grouping;
+        let mut loaded = cached;
+        let threshold = num_cpus::get() * 4;
+        let mut saved = false;
+        // kept between iterations, the window doesn't get them while it's hidden
+        let mut new_loaded = false;
+        let mut updated_imported = false;
+        let mut updated_organized = false;
+        let mut updated_stats = false;
+        let mut updated_tags = false;
+        let mut updated_labels = false;
+        let mut updated_library = false;
+        let mut updated_bpm = false;
+        // the runner tells when it changes, like from the media controls
+        let mut changed_volume = Some(r.read().await.volume());
+        let mut analyzed = 0;
+        loop {
+            interval.tick().await;
+            while let Ok(new_activity) = rx_activity.try_recv() {
+                if new_activity != activity {
+                    activity = new_activity;
+                    interval = tokio::time::interval(activity.update_interval());
+                }
+            }
+            let mut library_changed = false;
+            let mut ended: Vec<(Option<u16>, PathBuf, TrackEnd)> = vec![];
+            while let Ok(event) = rx_events.try_recv() {
+                match event {
+                    RunnerEvent::LibraryChanged => {
+                        library_changed = true;
+                        // the indices of the tracks that ended before are of the old library
+                        for (index, _, _) in &mut ended {
+                            *index = None;
+                        }
+                    }
+                    RunnerEvent::VolumeChanged(volume) => changed_volume = Some(volume),
+                    RunnerEvent::TrackEnded { index, path, end } => {
+                        ended.push((Some(index), path, end))
+                    }
+                }
+            }
+            if library_changed {
+                let (new_tracks, to_load, _) = library_tracks(&r, &s).await;
+                tracks = new_tracks;
+                loaded = tracks.len() - to_load.len();
+                // the tags still being read are for the old library
+                if let Some(loading) = loading.take() {
+                    loading.abort();
+                }
+                let (tx_l, new_rx_l) = flume::unbounded();
+                rx_l = new_rx_l;
+                if !to_load.is_empty() {
+                    let chain = Arc::new(metadata_chain(&s.lock().await));
+                    loading = Some(tokio::task::spawn(loader(
+                        r.clone(),
+                        chain,
+                        tx_l,
+                        to_load,
+                        visible.clone(),
+                    )));
+                }
+                analyzing.abort();
+                let (tx_bpm, new_rx_bpm) = flume::unbounded();
+                rx_bpm = new_rx_bpm;
+                analyzing = tokio::task::spawn(bpm_analyzer(r.clone(), s.clone(), tx_bpm));
+                let _ = tx_dirty.send(());
+                saved = false;
+                old_index = u16::MAX;
+                loading_index = u16::MAX;
+                recorded_index = u16::MAX;
+                dj_index = u16::MAX;
+                order_index = u16::MAX;
+                positioned_index = u16::MAX;
+                positioned = None;
+                removed = 0;
+                anchor = None;
+                if folder.is_some() {
+                    folder = Some(String::new());
+                }
+                new_loaded = true;
+                updated_library = true;
+            }
+            let guard = r.read().await;
+            let mut index = guard.index();
+            if index as usize > guard.len() {
+                index = 0;
+            }
+            let playback = guard.playback();
+            let time = guard.time();
+            let length = time.length;
+            let time_float = time.position;
+            let position = format_duration(time.position);
+
+            let change_time = if let Ok(()) = rx_changing.try_recv() {
+                false
+            } else {
+                true
+            };
+
+            while let Ok(track_data) = rx_l.try_recv() {
+                if let Some((index, mut file_track)) = track_data {
+                    clean_genre(&r, &s, index, &mut file_track).await;
+                    let file = file_track.clone();
+                    s.lock().await.tracks.push(file);
+                    let _ = tx_dirty.send(());
+                    let selected = tracks[index as usize].selected;
+                    let mut plays = tracks[index as usize].plays;
+                    // the counts in the files are newer after the library was rebuilt
+                    if file_track.plays as i32 > plays && s.lock().await.sync_play_counts {
+                        plays = file_track.plays as i32;
+                        let mut settings = s.lock().await;
+                        settings
+                            .listening
+                            .stats
+                            .entry(remove_ext(&file_track.path))
+                            .or_default()
+                            .count = file_track.plays;
+                    }
+                    let bpm = tracks[index as usize].bpm;
+                    let labels = tracks[index as usize].labels.clone();
+                    let folder = tracks[index as usize].folder.clone();
+                    let added = tracks[index as usize].added.clone();
+                    let added_at = tracks[index as usize].added_at;
+                    let removed = tracks[index as usize].removed;
+                    let resume_at = tracks[index as usize].resume_at;
                     tracks[index as usize] = file_track.into();
                     tracks[index as usize].index = index as i32;
+                    tracks[index as usize].selected = selected;
+                    tracks[index as usize].plays = plays;
+                    tracks[index as usize].bpm = bpm;
+                    tracks[index as usize].labels = labels;
+                    tracks[index as usize].folder = folder;
+                    tracks[index as usize].added = added;
+                    tracks[index as usize].added_at = added_at;
+                    tracks[index as usize].removed = removed;
+                    tracks[index as usize].resume_at = resume_at;
+                    tracks[index as usize].unavailable = unavailable.contains(&index);
                     loaded += 1;
                     if loaded % threshold == 0 {
                         new_loaded = true;
                     }
                 } else {
-                    if !saved {
-                        saved = true;
-                        s.lock().await.save_timestamp().await;
-                        s.lock().await.save(p.lock().await).await;
+                    if !saved {
+                        saved = true;
+                        s.lock().await.save(p.lock().await).await;
+                    }
+                    // the files that can't be read never arrive
+                    loaded = tracks.len();
+                    new_loaded = true;
+                }
+            }
+            while let Ok((index, mut file_track, new_folder)) = rx_imported.try_recv() {
+                clean_genre(&r, &s, index, &mut file_track).await;
+                let added = now();
+                s.lock()
+                    .await
+                    .added
+                    .insert(remove_ext(&file_track.path), added);
+                s.lock().await.tracks.push(file_track.clone());
+                let _ = tx_dirty.send(());
+                let mut track: TrackData = file_track.into();
+                track.index = index as i32;
+                track.folder = new_folder.into();
+                set_added(&mut track, added);
+                if let Some(existing) = tracks.get_mut(index as usize) {
+                    *existing = track;
+                } else {
+                    tracks.push(track);
+                }
+                updated_imported = true;
+            }
+            while let Ok(analysis) = rx_bpm.try_recv() {
+                if let Some((index, bpm)) = analysis {
+                    let _ = tx_dirty.send(());
+                    if let Some(track) = tracks.get_mut(index as usize) {
+                        track.bpm = bpm;
+                    }
+                    analyzed += 1;
+                    if analyzed % BPM_REFRESH == 0 {
+                        updated_bpm = true;
+                    }
+                } else if analyzed > 0 {
+                    updated_bpm = true;
+                }
+            }
+            while let Ok((index, plays)) = rx_plays.try_recv() {
+                if let Some(track) = tracks.get_mut(index as usize) {
+                    track.plays = plays as i32;
+                    updated_stats = true;
+                }
+            }
+            while let Ok((index, labels)) = rx_labels.try_recv() {
+                if let Some(track) = tracks.get_mut(index as usize) {
+                    track.labels = labels.into();
+                    updated_labels = true;
+                }
+            }
+            while let Ok((index, path, tags)) = rx_fixed.try_recv() {
+                if let Some(track) = tracks.get_mut(index as usize) {
+                    track.title = tags.title.as_str().into();
+                    track.artist = tags.artist.as_str().into();
+                    track.album = tags.album.as_str().into();
+                    updated_tags = true;
+                }
+                // the cache would be read again anyway since the file changed
+                let file_path = path.to_string_lossy();
+                let mut settings = s.lock().await;
+                if let Some(file_track) = settings
+                    .tracks
+                    .iter_mut()
+                    .find(|file_track| file_track.path == file_path)
+                {
+                    file_track.modified = modified_time(&path);
+                    file_track.title = tags.title;
+                    file_track.artist = tags.artist;
+                    file_track.album = tags.album;
+                }
+            }
+            while let Ok((index, new_folder)) = rx_organized.try_recv() {
+                if let Some(track) = tracks.get_mut(index as usize) {
+                    track.folder = new_folder.into();
+                    updated_organized = true;
+                }
+            }
+            if playback && recorded_index != index {
+                recorded_index = index;
+                if let Some(file) = guard.get_path_for_file(index).await {
+                    s.lock().await.last_track = Some(remove_ext(&file));
+                    let _ = tx_dirty.send(());
+                }
+            }
+            // a track counts as played once it's over, the ones cut early count as skipped
+            for (ended_index, file, end) in ended {
+                let path = remove_ext(&file);
+                let file_path = file.to_string_lossy().to_string();
+                let mut settings = s.lock().await;
+                let sync_play_counts = settings.sync_play_counts;
+                let stats = settings.listening.stats.entry(path.clone()).or_default();
+                stats.last_played = now();
+                let _ = tx_dirty.send(());
+                if !end.is_play() {
+                    stats.skips += 1;
+                    continue;
+                }
+                stats.count += 1;
+                let count = stats.count;
+                // the file isn't played anymore, so it can be written
+                if sync_play_counts {
+                    tokio::task::spawn_blocking(move || {
+                        if let Err(e) = write_play_count(&file, count) {
+                            toast_error(text(
+                                Text::CantWriteTags,
+                                &[("path", &file.display()), ("error", &e)],
+                            ));
+                        }
+                    });
+                }
+                if let Some(track) = ended_index.and_then(|index| tracks.get_mut(index as usize)) {
+                    track.plays = count as i32;
+                    updated_stats = true;
+                }
+                let heard = match end {
+                    TrackEnd::Finished => None,
+                    TrackEnd::Skipped { position, .. } => Some(position),
+                };
+                let record = settings
+                    .tracks
+                    .iter()
+                    .find(|file_track| file_track.path == file_path)
+                    .map(|file_track| PlayRecord {
+                        title: file_track.title.clone(),
+                        artist: file_track.artist.clone(),
+                        played: now().saturating_sub(heard.unwrap_or(file_track.length) as u64),
+                        length: file_track.length as f32,
+                    });
+                settings.listening.history.extend(record);
+            }
+            if playback && positioned_index != index {
+                positioned_index = index;
+                if let Some((previous, path)) = positioned.take() {
+                    if let Some(track) = tracks.get_mut(previous as usize) {
+                        // listened to the end, it starts over next time
+                        if last_position >= track.length as f64 - FINISHED_MARGIN {
+                            s.lock().await.positions.remove(&path);
+                            track.resume_at = 0.0;
+                        } else {
+                            track.resume_at = last_position as f32;
+                        }
+                        updated_stats = true;
+                    }
+                }
+                let remember = match tracks.get(index as usize) {
+                    Some(track) => {
+                        let settings = s.lock().await;
+                        let long = settings.resume_longer_than > 0
+                            && track.length >= settings.resume_longer_than as f32 * 60.0;
+                        long || folder_settings(&settings.folders, &track.folder)
+                            .is_some_and(|overrides| overrides.remember_position)
+                    }
+                    None => false,
+                };
+                if remember {
+                    if let Some(path) = guard.get_path_for_file(index).await.map(remove_ext) {
+                        if let Some(position) = s.lock().await.positions.get(&path) {
+                            let seek = RunnerSeek::Absolute(*position);
+                            tx_runner.send(RunnerMessage::Seek(seek)).unwrap();
+                        }
+                        positioned = Some((index, path));
+                    }
+                }
+                let _ = tx_dirty.send(());
+            }
+            if let Some((_, path)) = positioned.as_ref().filter(|_| playback) {
+                s.lock().await.positions.insert(path.clone(), time_float);
+            }
+            last_position = time_float;
+            // once per track, only when nothing else is queued
+            let mut queued = guard.upcoming(1).first().is_some_and(|(_, queued)| *queued);
+            if playback && order_index != index && !queued {
+                order_index = index;
+                if let Some(track) = tracks.get(index as usize) {
+                    let in_order = folder_settings(&s.lock().await.folders, &track.folder)
+                        .is_some_and(|overrides| overrides.in_order);
+                    if in_order {
+                        let mut folder_tracks = vec![];
+                        for other in tracks
+                            .iter()
+                            .filter(|other| other.folder == track.folder && !other.removed)
+                        {
+                            let i = other.index as u16;
+                            if let Some(path) = guard.get_path_for_file(i).await {
+                                folder_tracks.push((path, i));
+                            }
+                        }
+                        folder_tracks.sort();
+                        let next = folder_tracks.iter().skip_while(|(_, i)| *i != index).nth(1);
+                        if let Some((_, next)) = next {
+                            tx_runner.send(RunnerMessage::Enqueue(*next)).unwrap();
+                            queued = true;
+                        }
+                    }
+                }
+            }
+            if playback && dj_index != index && !queued && s.lock().await.auto_dj {
+                dj_index = index;
+                let mut stats = Vec::with_capacity(tracks.len());
+                {
+                    let settings = s.lock().await;
+                    for i in 0..tracks.len() as u16 {
+                        let track_stats = guard.get_path_for_file(i).await.and_then(|path| {
+                            settings.listening.stats.get(&remove_ext(path)).copied()
+                        });
+                        stats.push(track_stats.unwrap_or_default());
+                    }
+                }
+                if let Some(pick) = auto_dj_pick(&tracks, &stats, index, now()) {
+                    tx_runner.send(RunnerMessage::Enqueue(pick)).unwrap();
+                }
+            }
+            // nobody can see the window, only the library and the stats are kept up to date
+            if activity == Activity::Hidden {
+                drop(guard);
+                p.lock().await.tick().await;
+                continue;
+            }
+            let mut updated_removed = false;
+            if guard.removed().len() != removed {
+                removed = guard.removed().len();
+                for i in guard.removed() {
+                    if let Some(track) = tracks.get_mut(*i as usize) {
+                        track.removed = true;
+                        track.selected = false;
+                    }
+                }
+                updated_removed = true;
+            }
+            let mut updated_unavailable = false;
+            if *guard.unavailable() != unavailable {
+                unavailable = guard.unavailable().clone();
+                for track in tracks.iter_mut() {
+                    track.unavailable = unavailable.contains(&(track.index as u16));
+                }
+                updated_unavailable = true;
+            }
+            let mut peaks = None;
+            let mut cover = None;
+            let mut chapters = None;
+            let mut lyrics = None;
+            if settling.0 != index {
+                settling = (index, Instant::now());
+                peaks = Some(vec![]);
+                cover = Some(vec![]);
+                chapters = Some(vec![]);
+                lyrics = Some(String::new());
+            }
+            let settled = settling.1.elapsed() >= TRACK_SETTLE;
+            #[cfg(not(target_os = "android"))]
+            {
+                if playback && settled && osd_index != index {
+                    osd_index = index;
+                    let track = tracks.get(index as usize).cloned();
+                    if let (Some(track), Activity::Unfocused | Activity::Hidden, true) =
+                        (track, activity, s.lock().await.osd)
+                    {
+                        osd.upgrade_in_event_loop(move |osd| show_osd(&osd, track))
+                            .unwrap();
+                    }
+                }
+            }
+            if settled && loading_index != index {
+                loading_index = index;
+                if let Some(path) = guard.get_path_for_file(index).await {
+                    let p = path.clone();
+                    let tx_cover = tx_cover.clone();
+                    let covers = playing_covers.clone();
+                    tokio::task::spawn_blocking(move || {
+                        let chapters = MusicTrack::new(p.to_string_lossy().to_string())
+                            .and_then(|track| track.get_chapters())
+                            .unwrap_or_default();
+                        let cover = covers.decode(&get_image(p));
+                        let _ = tx_cover.send((index, cover, chapters));
+                    });
+                    if let Some(track) = tracks.get(index as usize).filter(|_| plugins::loaded()) {
+                        let track = PluginTrack {
+                            file: path
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_default(),
+                            title: track.title.to_string(),
+                            artist: track.artist.to_string(),
+                            album: track.album.to_string(),
+                            genre: track.genre.to_string(),
+                            length: track.length as f64,
+                            ..Default::default()
+                        };
+                        let tx_lyrics = tx_lyrics.clone();
+                        tokio::task::spawn_blocking(move || {
+                            plugins::on_track_change(&track);
+                            let lyrics = plugins::provide_lyrics(&track).unwrap_or_default();
+                            let _ = tx_lyrics.send((index, lyrics));
+                        });
+                    }
+                    let tx_peaks = tx_peaks.clone();
+                    tokio::task::spawn_blocking(move || {
+                        if let Ok(track) = MusicTrack::new(path.to_string_lossy().to_string()) {
+                            if let Ok(peaks) = track.get_peaks(WAVEFORM_PEAKS) {
+                                let _ = tx_peaks.send((index, peaks));
+                            }
+                        }
+                    });
+                }
+            }
+            while let Ok((peaks_for, new_peaks)) = rx_peaks.try_recv() {
+                if peaks_for == loading_index {
+                    peaks = Some(new_peaks);
+                }
+            }
+            while let Ok((cover_for, new_cover, new_chapters)) = rx_cover.try_recv() {
+                if cover_for == loading_index {
+                    cover = Some(new_cover);
+                    chapters = Some(new_chapters);
+                }
+            }
+            while let Ok((lyrics_for, new_lyrics)) = rx_lyrics.try_recv() {
+                if lyrics_for == loading_index {
+                    lyrics = Some(new_lyrics);
+                }
+            }
+
+            let progress = if tracks.is_empty() {
+                1.0
+            } else {
+                loaded as f64 / tracks.len() as f64
+            };
+            let progress_label = if new_loaded {
+                let loaded = format_number(loaded as u64);
+                count(Count::LoadingTracks, tracks.len(), &[("loaded", &loaded)])
+            } else {
+                String::new()
+            };
+            let mut playing_track = None;
+            let mut upcoming = None;
+            let changed_track = old_index != index && old_index != u16::MAX;
+            if old_index != index || new_loaded {
+                if let Some(track) = tracks.get(index as usize) {
+                    playing_track = Some(track.clone());
+                    old_index = index;
+                }
+            }
+            let next = guard.upcoming(MAX_QUEUE_TRACKS);
+            if next != upcoming_indices || playing_track.is_some() {
+                // enough tracks to fill the queue duration, the lengths may not be loaded yet
+                let mut duration = 0.0;
+                let mut queue = vec![];
+                for (i, _) in &next {
+                    if duration >= QUEUE_DURATION && queue.len() >= UPCOMING_TRACKS {
+                        break;
+                    }
+                    if let Some(track) = tracks.get(*i as usize) {
+                        duration += track.length;
+                        queue.push(track.clone());
+                    }
+                }
+                let queued = next.iter().filter(|(_, queued)| *queued).count();
+                let queued_label = count(Count::QueuedTracks, queued, &[]);
+                upcoming = Some((queue, queued, queued_label));
+                let cached = next.iter().take(CACHED_TRACKS).map(|(i, _)| *i).collect();
+                let _ = tx_cache.send(cached);
+                upcoming_indices = next;
+            }
+            let queued_indices = upcoming_indices
+                .iter()
+                .filter(|(_, queued)| *queued)
+                .map(|(i, _)| *i)
+                .collect::<Vec<_>>();
+            let (queue_position, queue_total, queue_left) =
+                queue_progress(&tracks, index as usize, time_float, &queued_indices);
+            let progress_label = count(
+                Count::QueuePosition,
+                queue_total,
+                &[
+                    ("position", &queue_position),
+                    ("left", &format_duration(queue_left)),
+                ],
+            );
+
+            let mut updated_search = false;
+            while let Ok(search_string) = rx_searching.try_recv() {
+                searching = search_string;
+                updated_search = true;
+            }
+
+            let mut updated_selection = false;
+            while let Ok(selection) = rx_selecting.try_recv() {
+                match selection {
+                    Selection::Toggle(index) => {
+                        // the list may be from before the library changed
+                        let Some(track) = tracks.get_mut(index as usize) else {
+                            continue;
+                        };
+                        track.selected = !track.selected;
+                        anchor = Some(index);
+                    }
+                    Selection::Range(index) => {
+                        let visible = visible_tracks(&tracks, &searching, sort, folder.as_deref());
+                        let position =
+                            |i: u16| visible.iter().position(|track| track.index == i as i32);
+                        let end = position(index);
+                        let start = anchor.and_then(position).or(end);
+                        if let (Some(start), Some(end)) = (start, end) {
+                            for track in &visible[start.min(end)..=start.max(end)] {
+                                tracks[track.index as usize].selected = true;
+                            }
+                        }
+                        anchor = Some(index);
+                    }
+                    Selection::Clear => {
+                        anchor = None;
+                        if !tracks.iter().any(|track| track.selected) {
+                            continue;
+                        }
+                        tracks.iter_mut().for_each(|track| track.selected = false);
+                    }
+                }
+                updated_selection = true;
+            }
+
+            let mut updated_toasts = false;
+            while let Ok(i) = rx_dismiss.try_recv() {
+                if i < toasts.len() {
+                    toasts.remove(i);
+                    updated_toasts = true;
+                }
+            }
+            if toasts
+                .iter()
+                .any(|(shown, _)| shown.elapsed() >= TOAST_DURATION)
+            {
+                toasts.retain(|(shown, _)| shown.elapsed() < TOAST_DURATION);
+                updated_toasts = true;
+            }
+            let mut error_report = None;
+            while let Ok(toast) = TOASTS.try_recv() {
+                if toast.report {
+                    error_report = Some(toast.message);
+                    continue;
+                }
+                toasts.push((
+                    Instant::now(),
+                    ToastData {
+                        message: toast.message.into(),
+                        error: toast.error,
+                        undo: toast.undo,
+                    },
+                ));
+                updated_toasts = true;
+            }
+            if toasts.len() > MAX_TOASTS {
+                toasts.drain(..toasts.len() - MAX_TOASTS);
+            }
+            let new_toasts = if updated_toasts {
+                Some(
+                    toasts
+                        .iter()
+                        .map(|(_, toast)| toast.clone())
+                        .collect::<Vec<_>>(),
+                )
+            } else {
+                None
+            };
+
+            let mut updated_sort = false;
+            while let Ok(new_sort) = rx_sorting.try_recv() {
+                sort = new_sort;
+                updated_sort = true;
+            }
+
+            let mut updated_grouping = false;
+            while let Ok(new_grouping) = rx_grouping.try_recv() {
+                grouping = new_grouping;
+                updated_grouping = true;
+            }
+
+            let mut updated_folder = false;
+            while let Ok(new_folder) = rx_folder.try_recv() {
+                folder = new_folder;
+                updated_folder = true;
+            }
+            let folder_view =
+                if updated_folder || updated_organized || updated_imported || updated_library {
+                    let overrides = match &folder {
+                        Some(folder) => s.lock().await.folders.get(folder).cloned(),
+                        None => None,
+                    };
+                    folder.as_ref().map(|folder| {
+                        let folders = tracks
+                            .iter()
+                            .filter(|track| !track.removed)
+                            .map(|track| track.folder.as_str());
+                        let subfolders = subfolders(folders, folder)
+                            .into_iter()
+                            .map(|path| FolderData {
+                                name: path.rsplit('/').next().unwrap_or_default().into(),
+                                path: path.into(),
+                            })
+                            .collect::<Vec<_>>();
+                        let parent = folder
+                            .rsplit_once('/')
+                            .map(|(parent, _)| parent.to_string())
+                            .unwrap_or_default();
+                        let overrides = overrides.unwrap_or_default();
+                        (folder.clone(), parent, subfolders, overrides)
+                    })
+                } else {
+                    None
+                };
+            if rx_play_visible.try_iter().count() > 0 {
+                let indices = visible_tracks(&tracks, &searching, sort, folder.as_deref())
+                    .iter()
+                    .map(|track| track.index as u16)
+                    .collect::<Vec<_>>();
+                if !indices.is_empty() {
+                    tx_runner.send(RunnerMessage::PlayTracks(indices)).unwrap();
+                }
+            }
+            while let Ok(clicked) = rx_replay_gain.try_recv() {
+                tokio::task::spawn(replay_gain_scanner(
+                    r.clone(),
+                    album_groups(&tracks, clicked),
+                ));
+            }
+            while let Ok(clicked) = rx_embed_cover.try_recv() {
+                let indices = album_groups(&tracks, clicked)
+                    .into_iter()
+                    .flat_map(|(indices, _)| indices)
+                    .collect::<Vec<_>>();
+                let size = s.lock().await.embedded_cover_size as usize;
+                tokio::task::spawn(cover_embedder(r.clone(), indices, size));
+            }
+            while let Ok(play_folder) = rx_play_folder.try_recv() {
+                let indices = tracks
+                    .iter()
+                    .filter(|track| !track.removed && in_folder(&track.folder, &play_folder))
+                    .map(|track| track.index as u16)
+                    .collect::<Vec<_>>();
+                if !indices.is_empty() {
+                    tx_runner.send(RunnerMessage::PlayTracks(indices)).unwrap();
+                }
+            }
+
+            let jump_row = if rx_jump.try_iter().count() > 0 {
+                visible_tracks(&tracks, &searching, sort, folder.as_deref())
+                    .iter()
+                    .position(|track| track.index == index as i32)
+            } else {
+                None
+            };
+
+            let follow_row = if changed_track && s.lock().await.auto_scroll {
+                visible_tracks(&tracks, &searching, sort, folder.as_deref())
+                    .iter()
+                    .position(|track| track.index == index as i32)
+            } else {
+                None
+            };
+
+            let mut t = vec![];
+
+            let refresh = new_loaded
+                || updated_search
+                || updated_selection
+                || updated_sort
+                || updated_grouping
+                || updated_stats
+                || updated_tags
+                || updated_labels
+                || updated_folder
+                || updated_removed
+                || updated_unavailable
+                || updated_organized
+                || updated_imported
+                || updated_library
+                || updated_bpm;
+
+            let mut letters = vec![];
+            if refresh {
+                t = visible_tracks(&tracks, &searching, sort, folder.as_deref());
+                letters = group_tracks(&mut t, grouping);
+            }
+
+            let volume = changed_volume.take();
+            p.lock().await.tick().await;
+
+            window
+                .upgrade_in_event_loop(move |window| {
+                    let app_data = window.global::<AppData>();
+                    app_data.set_playing(index as i32);
+                    app_data.set_position_time(position.into());
+                    if change_time {
+                        app_data.set_time(time_float as f32);
                     }
-                    new_loaded = true;
+                    app_data.set_length(length as f32);
+                    app_data.set_playback(playback);
+                    if let Some(volume) = volume {
+                        app_data.set_volume(volume as f32);
+                    }
+
+                    if let Some(peaks) = peaks {
+                        app_data.set_peaks(VecModel::from_slice(&peaks));
+                    }
+
+                    if let Some(cover) = cover {
+                        app_data.set_playing_cover(if cover.is_empty() {
+                            Default::default()
+                        } else {
+                            slint::Image::from_rgb8(SharedPixelBuffer::clone_from_slice(
+                                &cover,
+                                NOW_PLAYING_COVER_SIZE as u32,
+                                NOW_PLAYING_COVER_SIZE as u32,
+                            ))
+                        });
+                    }
+
+                    if let Some(chapters) = chapters {
+                        let chapters = chapters
+                            .into_iter()
+                            .map(|chapter| ChapterData {
+                                start: chapter.start as f32,
+                                title: chapter.title.into(),
+                            })
+                            .collect::<Vec<_>>();
+                        app_data.set_chapters(VecModel::from_slice(&chapters));
+                    }
+
+                    if let Some(lyrics) = lyrics {
+                        app_data.set_lyrics(lyrics.into());
+                    }
+
+                    if let Some((queue, queued, queued_label)) = upcoming {
+                        let upcoming = queue
+                            .iter()
+                            .take(UPCOMING_TRACKS)
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        app_data.set_upcoming(VecModel::from_slice(&upcoming));
+                        app_data.set_queue(VecModel::from_slice(&queue));
+                        app_data.set_queued(queued as i32);
+                        app_data.set_queued_label(queued_label.into());
+                    }
+                    app_data.set_queue_progress(progress_label.into());
+
+                    if let Some(playing_track) = playing_track {
+                        let settings_data = window.global::<SettingsData>();
+                        if let Some(color) = cover_color(&playing_track.cover) {
+                            settings_data.set_cover_accent(color);
+                            settings_data.set_cover_accent_foreground(contrast_color(color));
+                            settings_data.set_has_cover_accent(true);
+                        } else {
+                            settings_data.set_has_cover_accent(false);
+                        }
+                        app_data.set_playing_track(playing_track);
+                    }
+
+                    if new_loaded {
+                        let progress = if progress == 1.0 {
+                            0.0
+                        } else {
+                            progress as f32
+                        };
+                        app_data.set_progress(progress);
+                        app_data.set_progress_label(progress_label.into());
+                    }
+
+                    if refresh {
+                        app_data.set_tracks(VecModel::from_slice(&t));
+                        app_data.set_letters(VecModel::from_slice(&letters));
+                        app_data.invoke_show_rows();
+                    }
+
+                    if let Some((folder, parent, subfolders, overrides)) = folder_view {
+                        app_data.set_folder(folder.into());
+                        app_data.set_parent_folder(parent.into());
+                        app_data.set_subfolders(VecModel::from_slice(&subfolders));
+                        app_data.set_folder_remember_position(overrides.remember_position);
+                        app_data.set_folder_in_order(overrides.in_order);
+                    }
+                    if updated_folder || updated_library {
+                        app_data.set_viewport_y(0.0);
+                    }
+
+                    if let Some(toasts) = new_toasts {
+                        app_data.set_toasts(VecModel::from_slice(&toasts));
+                    }
+                    if let Some(report) = error_report {
+                        app_data.set_error_report(report.into());
+                    }
+
+                    if let Some(row) = follow_row {
+                        app_data.invoke_follow(row as i32);
+                    }
+
+                    if let Some(row) = jump_row {
+                        app_data.invoke_scroll(row as i32);
+                        app_data.set_highlighted(index as i32);
+                        let window = window.as_weak();
+                        slint::Timer::single_shot(Duration::from_millis(1500), move || {
+                            if let Some(window) = window.upgrade() {
+                                window.global::<AppData>().set_highlighted(-1);
+                            }
+                        });
+                    }
+                })
+                .unwrap();
+            new_loaded = false;
+            updated_imported = false;
+            updated_organized = false;
+            updated_stats = false;
+            updated_tags = false;
+            updated_labels = false;
+            updated_library = false;
+            updated_bpm = false;
+        }
+    });
+
+    // android stops the app by itself when it's in the background
+    #[cfg(target_os = "android")]
+    drop(tx_activity);
+    #[cfg(target_os = "android")]
+    tokio::task::block_in_place(|| main_window.run().unwrap());
+    #[cfg(not(target_os = "android"))]
+    {
+        tray::run(&main_window, tx.clone());
+        let window = main_window.as_weak();
+        let activity_timer = slint::Timer::default();
+        let mut activity = Activity::Focused;
+        activity_timer.start(slint::TimerMode::Repeated, ACTIVITY_CHECK, move || {
+            if let Some(window) = window.upgrade() {
+                let new_activity = Activity::of(window.window());
+                if new_activity != activity {
+                    activity = new_activity;
+                    let _ = tx_activity.send(activity);
+                }
+            }
+        });
+        let start_minimized = settings.lock().await.start_minimized;
+        let position = settings.lock().await.window_position;
+        let maximized = settings.lock().await.maximized;
+        let window = main_window.as_weak();
+        slint::Timer::single_shot(Duration::ZERO, move || {
+            if let Some(window) = window.upgrade() {
+                if let Some((x, y)) = position {
+                    window
+                        .window()
+                        .set_position(slint::PhysicalPosition::new(x, y));
+                }
+                if !start_minimized || !tray::is_available() {
+                    window.show().unwrap();
+                }
+                window.window().set_maximized(maximized);
+            }
+        });
+        let window = main_window.as_weak();
+        main_window.window().on_close_requested(move || {
+            let Some(window) = window.upgrade() else {
+                return slint::CloseRequestResponse::HideWindow;
+            };
+            let settings_data = window.global::<SettingsData>();
+            if settings_data.get_close_to_tray() && tray::is_available() {
+                return slint::CloseRequestResponse::HideWindow;
+            }
+            let app_data = window.global::<AppData>();
+            if settings_data.get_confirm_exit() && app_data.get_playback() {
+                app_data.set_confirming_exit(true);
+                return slint::CloseRequestResponse::KeepWindowShown;
+            }
+            let _ = slint::quit_event_loop();
+            slint::CloseRequestResponse::HideWindow
+        });
+        main_window.global::<AppData>().on_quit(|| {
+            let _ = slint::quit_event_loop();
+        });
+        tokio::task::block_in_place(|| slint::run_event_loop_until_quit().unwrap());
+    }
+    runner.write().await.stop().await;
+    settings.lock().await.volume = runner.read().await.volume();
+    settings.lock().await.queue_open = main_window.global::<AppData>().get_queue_open();
+    if settings.lock().await.save_window_size {
+        let window = main_window.window();
+        let maximized = window.is_maximized();
+        // the size and the position of a maximized or minimized window aren't the ones to go back
+        // to, the last normal ones are kept
+        if !maximized && !window.is_minimized() {
+            let width = main_window.get_last_width() as usize;
+            let height = main_window.get_last_height() as usize;
+            let position = window.position();
+            let mut settings = settings.lock().await;
+            settings.window_size = WindowSize { width, height };
+            settings.window_position = Some((position.x, position.y));
+        }
+        settings.lock().await.maximized = maximized;
+    } else {
+        let mut settings = settings.lock().await;
+        settings.window_size = WindowSize::default();
+        settings.window_position = None;
+        settings.maximized = false;
+    }
+
+    updater.abort();
+    locales_watcher.abort();
+    saver.abort();
+    device_watcher.abort();
+    previewer.abort();
+    cacher.abort();
+    availability_watcher.abort();
+    rescanner.abort();
+    folder_watcher.abort();
+    future.abort();
+    bus_server::remove_covers().await;
+    settings.lock().await.save(platform.lock().await).await;
+}
+const WAVEFORM_PEAKS: usize = 120;
+/// Bars of the loudness graph in the properties
+const LOUDNESS_BARS: usize = 120;
+/// Bottom of the loudness graph in LUFS, the quieter moments are drawn there
+const LOUDNESS_FLOOR: f64 = -60.0;
+const NOW_PLAYING_COVER_SIZE: usize = 512;
+/// Covers of the playing track kept decoded, for skipping back and forth between tracks
+const PLAYING_COVERS: usize = 8;
+/// Covers of the track list kept decoded while the tags are read, a few albums' worth
+const LIST_COVERS: usize = 64;
+const UPCOMING_TRACKS: usize = 5;
+/// The tracks whose position is remembered stopped this close to their end, in seconds, start
+/// over next time
+const FINISHED_MARGIN: f64 = 5.0;
+const MAX_QUEUE_TRACKS: usize = 100;
+// seconds of music shown in the queue panel
+const QUEUE_DURATION: f32 = 30.0 * 60.0;
+const SCROLL_DURATION: Duration = Duration::from_millis(300);
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+/// Name of the log inside the internal directory, see [open_log]
+const LOG_FILE: &str = "n_music.log";
+const MAX_TOASTS: usize = 3;
+const LOCALES_CHECK: Duration = Duration::from_secs(2);
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+const DEVICE_CHECK: Duration = Duration::from_secs(2);
+/// How long a track is previewed for, from a third of its length
+const PREVIEW_LENGTH: Duration = Duration::from_secs(10);
+/// How long the pointer has to stay on a track for its preview to start
+const PREVIEW_DELAY: Duration = Duration::from_millis(400);
+/// Volume of the previews, under the one of the queue
+const PREVIEW_VOLUME: f32 = 0.3;
+const UNAVAILABLE_CHECK: Duration = Duration::from_secs(15);
+/// How often the watched folder is listed, a new file is offered when its size didn't change
+/// between two checks
+const WATCH_CHECK: Duration = Duration::from_secs(5);
+// upcoming tracks copied to the cache
+const CACHED_TRACKS: usize = 2;
+// tracks whose tempo is estimated before the list shows them
+const BPM_REFRESH: usize = 20;
+#[cfg(not(target_os = "android"))]
+const ACTIVITY_CHECK: Duration = Duration::from_millis(500);
+#[cfg(not(target_os = "android"))]
+const OSD_DURATION: Duration = Duration::from_secs(4);
+// same as the animation in osd_window.slint
+#[cfg(not(target_os = "android"))]
+const OSD_SLIDE: Duration = Duration::from_millis(300);
+/// Space between the overlay and the edges of the screen, in logical pixels
+#[cfg(not(target_os = "android"))]
+const OSD_MARGIN: f64 = 24.0;
+
+enum Selection {
+    Toggle(u16),
+    Range(u16),
+    Clear,
+}
+
+/// Shows `track` in the overlay for [OSD_DURATION], in the top right corner of the screen
+///
+/// Wayland doesn't let windows choose where they go, the compositor places it there
+#[cfg(not(target_os = "android"))]
+fn show_osd(osd: &OsdWindow, track: TrackData) {
+    use slint::winit_030::WinitWindowAccessor;
+
+    osd.set_track(track);
+    osd.set_shown(false);
+    osd.show().unwrap();
+    // the window only exists once it's shown
+    let position = osd
+        .window()
+        .with_winit_window(|window| {
+            let monitor = window
+                .current_monitor()
+                .or_else(|| window.primary_monitor())?;
+            let margin = (OSD_MARGIN * monitor.scale_factor()) as i32;
+            let size = window.outer_size();
+            Some(slint::PhysicalPosition::new(
+                monitor.position().x + monitor.size().width as i32 - size.width as i32 - margin,
+                monitor.position().y + margin,
+            ))
+        })
+        .flatten();
+    if let Some(position) = position {
+        osd.window().set_position(position);
+    }
+    osd.set_shown(true);
+    let osd = osd.as_weak();
+    OSD_TIMER.with(|timer| {
+        timer.start(slint::TimerMode::SingleShot, OSD_DURATION, move || {
+            let Some(window) = osd.upgrade() else {
+                return;
+            };
+            window.set_shown(false);
+            let osd = osd.clone();
+            // once it slid out, unless another track started meanwhile
+            slint::Timer::single_shot(OSD_SLIDE, move || {
+                if let Some(osd) = osd.upgrade().filter(|osd| !osd.get_shown()) {
+                    let _ = osd.hide();
+                }
+            });
+        });
+    });
+}
+
+#[cfg(not(target_os = "android"))]
+thread_local! {
+    // restarted by every track, so the overlay stays up for the whole duration of the last one
+    static OSD_TIMER: slint::Timer = slint::Timer::default();
+}
+
+/// How much the user can see of the window, the updater polls less when they can't
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(target_os = "android", allow(dead_code))]
+enum Activity {
+    Focused,
+    Unfocused,
+    /// Minimized or in the tray, the audio and the media controls keep running
+    Hidden,
+}
+
+impl Activity {
+    #[cfg(not(target_os = "android"))]
+    fn of(window: &slint::Window) -> Self {
+        use slint::winit_030::WinitWindowAccessor;
+
+        window
+            .with_winit_window(|window| {
+                // some platforms, like wayland, don't say whether the window is visible
+                if window.is_visible() == Some(false) || window.is_minimized() == Some(true) {
+                    Self::Hidden
+                } else if window.has_focus() {
+                    Self::Focused
+                } else {
+                    Self::Unfocused
+                }
+            })
+            .unwrap_or(Self::Hidden)
+    }
+
+    fn update_interval(self) -> Duration {
+        match self {
+            Self::Focused => Duration::from_millis(250),
+            Self::Unfocused | Self::Hidden => Duration::from_secs(1),
+        }
+    }
+}
+
+fn matches_search(track: &TrackData, searching: &str) -> bool {
+    if let Some((min, max)) = bpm_range(searching) {
+        return track.bpm >= min && track.bpm < max;
+    }
+    if let Some(days) = added_days(searching) {
+        return track.added_at as u64 * 60 >= now().saturating_sub(days * 86400);
+    }
+    if let Some(label) = searched_label(searching) {
+        return track
+            .labels
+            .split(", ")
+            .any(|track_label| track_label.to_lowercase() == label);
+    }
+    let search = searching.to_lowercase();
+    track.title.to_lowercase().contains(&search)
+        || track.artist.to_lowercase().contains(&search)
+        || track.genre.to_lowercase().contains(&search)
+}
+
+/// The playing track followed by the upcoming ones, as they're shared
+/// Plays and hours of listening of a report, like "12 plays, 0.8 hours of listening"
+fn listening_summary(report: &ListeningReport) -> String {
+    count(
+        Count::ListeningSummary,
+        report.plays,
+        &[("hours", &format_decimal(report.seconds / 3600.0, 1))],
+    )
+}
+
+/// The bars of a ranking are as long as the plays over the ones of the first
+fn stat_data(ranking: &[(String, usize)]) -> Vec<StatData> {
+    let most = ranking.first().map_or(1, |(_, plays)| *plays).max(1);
+    ranking
+        .iter()
+        .map(|(name, plays)| StatData {
+            name: name.into(),
+            plays: *plays as i32,
+            share: *plays as f32 / most as f32,
+        })
+        .collect()
+}
+
+/// Markdown summary of the listening statistics of `period`, as the export saves it
+fn statistics_text(localization: &Localization, period: i32, report: &ListeningReport) -> String {
+    let period = match period {
+        0 => localization.get_last_week(),
+        1 => localization.get_last_month(),
+        2 => localization.get_last_year(),
+        _ => localization.get_all_time(),
+    };
+    let mut summary = format!(
+        "# {} ({period})\n\n{}\n",
+        localization.get_listening_statistics(),
+        listening_summary(report)
+    );
+    let rankings = [
+        (localization.get_top_artists(), &report.artists),
+        (localization.get_top_tracks(), &report.tracks),
+    ];
+    for (title, ranking) in rankings {
+        summary.push_str(&format!("\n## {title}\n\n"));
+        for (i, (name, plays)) in ranking.iter().enumerate() {
+            summary.push_str(&format!("{}. {name} ({plays})\n", i + 1));
+        }
+    }
+    summary
+}
+
+fn shared_queue(app_data: &AppData) -> Vec<TrackData> {
+    let playing = app_data.get_playing_track();
+    (!playing.title.is_empty())
+        .then_some(playing)
+        .into_iter()
+        .chain(app_data.get_queue().iter())
+        .collect()
+}
+
+/// Tracks in the order they are shown in the list, after searching and sorting
+/// `folder` is the folder being browsed, searching looks into its subfolders too
+fn visible_tracks(
+    tracks: &[TrackData],
+    searching: &str,
+    sort: Option<(Column, bool)>,
+    folder: Option<&str>,
+) -> Vec<TrackData> {
+    let mut visible = tracks
+        .iter()
+        .filter(|track| !track.removed)
+        .filter(|track| match folder {
+            Some(folder) if searching.is_empty() => track.folder == folder,
+            Some(folder) => in_folder(&track.folder, folder),
+            None => true,
+        })
+        .filter(|track| matches_search(track, searching))
+        .cloned()
+        .collect::<Vec<_>>();
+    if let Some((column, ascending)) = sort {
+        visible.sort_by(|a, b| {
+            let ordering = compare_tracks(a, b, column);
+            if ascending {
+                ordering
+            } else {
+                ordering.reverse()
+            }
+        });
+    }
+    visible
+}
+
+/// Groups the tracks an action on the `clicked` one applies to by album
+///
+/// It's the selection when the clicked track is part of it, otherwise the clicked track. Every
+/// track of their albums is taken, even the ones not selected, the tracks without an album are
+/// alone in their group. The `bool` tells if the group is an album
+fn album_groups(tracks: &[TrackData], clicked: u16) -> Vec<(Vec<u16>, bool)> {
+    let clicked = &tracks[clicked as usize];
+    let targets = if clicked.selected {
+        tracks
+            .iter()
+            .filter(|track| track.selected && !track.removed)
+            .collect::<Vec<_>>()
+    } else {
+        vec![clicked]
+    };
+    let albums = targets
+        .iter()
+        .filter(|track| !track.album.is_empty())
+        .map(|track| (track.folder.as_str(), track.album.as_str()))
+        .collect::<HashSet<_>>();
+    let mut groups = albums
+        .into_iter()
+        .map(|(folder, album)| {
+            let indices = tracks
+                .iter()
+                .filter(|track| !track.removed && track.folder == folder && track.album == album)
+                .map(|track| track.index as u16)
+                .collect::<Vec<_>>();
+            (indices, true)
+        })
+        .collect::<Vec<_>>();
+    groups.extend(
+        targets
+            .iter()
+            .filter(|track| track.album.is_empty())
+            .map(|track| (vec![track.index as u16], false)),
+    );
+    groups
+}
+
+/// Returns the files in the folder of the track at `clicked` that are part of its album, with
+/// their tags, nothing when it has no album
+async fn album_files(runner: &RwLock<Runner>, clicked: u16) -> Vec<(u16, PathBuf, Metadata)> {
+    let mut paths = vec![];
+    {
+        let runner = runner.read().await;
+        let Some(folder) = runner
+            .get_path_for_file(clicked)
+            .await
+            .and_then(|path| path.parent().map(Path::to_path_buf))
+        else {
+            return vec![];
+        };
+        for i in 0..runner.len() as u16 {
+            if runner.removed().contains(&i) {
+                continue;
+            }
+            if let Some(path) = runner.get_path_for_file(i).await {
+                if path.parent() == Some(folder.as_path()) {
+                    paths.push((i, path));
+                }
+            }
+        }
+    }
+    tokio::task::spawn_blocking(move || {
+        let files = paths
+            .into_iter()
+            .filter_map(|(i, path)| {
+                let metadata = MusicTrack::new(path.to_string_lossy().to_string())
+                    .and_then(|track| track.get_meta())
+                    .ok()?;
+                Some((i, path, metadata))
+            })
+            .collect::<Vec<_>>();
+        let Some((_, _, clicked)) = files.iter().find(|(i, _, _)| *i == clicked) else {
+            return vec![];
+        };
+        if clicked.album.is_empty() {
+            return vec![];
+        }
+        let album = clicked.album.clone();
+        files
+            .into_iter()
+            .filter(|(_, _, metadata)| metadata.album == album)
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Returns the tracks of the library in the runner, the indices of the ones whose tags have to be
+/// read and the index of the last track played
+///
+/// The tags of the files that didn't change since they were read are taken from the cache, the
+/// files that aren't in the library anymore are dropped from it
+async fn library_tracks(
+    runner: &RwLock<Runner>,
+    settings: &Mutex<Settings>,
+) -> (Vec<TrackData>, Vec<u16>, Option<u16>) {
+    let mut paths = vec![];
+    let root = {
+        let runner = runner.read().await;
+        for i in 0..runner.len() as u16 {
+            paths.push(runner.get_path_for_file(i).await.unwrap());
+        }
+        runner.path()
+    };
+    let p = paths.clone();
+    let modified =
+        tokio::task::spawn_blocking(move || p.iter().map(modified_time).collect::<Vec<_>>())
+            .await
+            .unwrap_or_else(|_| vec![0; paths.len()]);
+    let mut cache = {
+        let mut settings = settings.lock().await;
+        let library = settings.libraries.remove(&root).unwrap_or_default();
+        std::mem::take(&mut settings.tracks)
+            .into_iter()
+            .chain(library)
+            .map(|file_track| (file_track.path.clone(), file_track))
+            .collect::<HashMap<_, _>>()
+    };
+    let mut to_load = vec![];
+
+    let mut tracks = vec![];
+    let mut last_index = None;
+    let last_track = settings.lock().await.last_track.clone();
+    // the files already there when the dates started being kept were added when they changed last
+    let first_dates = settings.lock().await.added.is_empty();
+    for (i, (track_path, modified)) in (0..).zip(paths.into_iter().zip(modified)) {
+        let track_without_ext = remove_ext(&track_path);
+        let folder = relative_folder(&root, &track_path);
+        if last_track.as_ref() == Some(&track_without_ext) {
+            last_index = Some(i);
+        }
+        let plays = settings
+            .lock()
+            .await
+            .listening
+            .stats
+            .get(&track_without_ext)
+            .map(|stats| stats.count as i32)
+            .unwrap_or_default();
+        let bpm = settings
+            .lock()
+            .await
+            .bpm
+            .get(&track_without_ext)
+            .copied()
+            .unwrap_or_default();
+        let labels = settings
+            .lock()
+            .await
+            .labels
+            .get(&track_without_ext)
+            .map(|labels| labels.join(", "))
+            .unwrap_or_default();
+        let resume_at = settings
+            .lock()
+            .await
+            .positions
+            .get(&track_without_ext)
+            .map_or(0.0, |position| *position as f32);
+        let added = *settings
+            .lock()
+            .await
+            .added
+            .entry(track_without_ext.clone())
+            .or_insert_with(|| {
+                if first_dates && modified > 0 {
+                    modified
+                } else {
+                    now()
+                }
+            });
+        // the ones of another file with the same name, or of the file before it changed, are read
+        // again
+        let cached = cache
+            .remove(&*track_path.to_string_lossy())
+            .filter(|file_track| file_track.modified == modified);
+        if let Some(file_track) = cached {
+            settings.lock().await.tracks.push(file_track.clone());
+            let mut track: TrackData = file_track.into();
+            track.index = i as i32;
+            track.plays = plays;
+            track.bpm = bpm;
+            track.labels = labels.into();
+            track.folder = folder.into();
+            track.resume_at = resume_at;
+            set_added(&mut track, added);
+            tracks.push(track)
+        } else {
+            to_load.push(i);
+            let mut track = TrackData {
+                artist: Default::default(),
+                cover: Default::default(),
+                time: Default::default(),
+                album: Default::default(),
+                genre: Default::default(),
+                length: 0.0,
+                plays,
+                bpm,
+                labels: labels.into(),
+                format: file_format(&track_path).into(),
+                folder: folder.into(),
+                added: Default::default(),
+                added_at: 0,
+                group: Default::default(),
+                header: false,
+                title: track_without_ext.into(),
+                index: i as i32,
+                selected: false,
+                removed: false,
+                unavailable: false,
+                resume_at,
+            };
+            set_added(&mut track, added);
+            tracks.push(track);
+        }
+    }
+    // the tracks left belong to the library opened before, they're kept while it's recent
+    let mut settings = settings.lock().await;
+    for file_track in cache.into_values() {
+        let folder = settings
+            .recent_folders
+            .iter()
+            .find(|folder| **folder != root && Path::new(&file_track.path).starts_with(folder))
+            .cloned();
+        if let Some(folder) = folder {
+            settings
+                .libraries
+                .entry(folder)
+                .or_default()
+                .push(file_track);
+        }
+    }
+    (tracks, to_load, last_index)
+}
+
+/// Makes `path` the music directory, the updater rebuilds the track list once the runner switches
+/// to the new library
+async fn open_library<P: Platform>(
+    path: String,
+    runner: Arc<RwLock<Runner>>,
+    settings: &Mutex<Settings>,
+    platform: &Mutex<P>,
+    window: &slint::Weak<MainWindow>,
+) {
+    {
+        let mut settings = settings.lock().await;
+        settings.open_folder(path.clone());
+        settings.save(platform.lock().await).await;
+        if let Some(window) = window.upgrade() {
+            let settings_data = window.global::<SettingsData>();
+            settings_data.set_current_path(path.clone().into());
+            settings_data.set_recent_folders(recent_folders(&settings));
+            toast_info(window.global::<Localization>().get_path_changed());
+        }
+    }
+    tokio::task::spawn(async move {
+        let files = library_files(&path).await;
+        runner.write().await.set_library(path, files).await;
+    });
+}
+
+/// Returns every track of the library with its cached tags, its stats and its tempo, the tracks
+/// whose tags weren't read yet only have their path and their format
+async fn exported_tracks(
+    runner: &RwLock<Runner>,
+    settings: &Mutex<Settings>,
+) -> Vec<ExportedTrack> {
+    let runner = runner.read().await;
+    let settings = settings.lock().await;
+    let cache = settings
+        .tracks
+        .iter()
+        .map(|file_track| (file_track.path.as_str(), file_track))
+        .collect::<HashMap<_, _>>();
+    let mut tracks = vec![];
+    for i in 0..runner.len() as u16 {
+        let Some(path) = runner.get_path_for_file(i).await else {
+            continue;
+        };
+        let track_without_ext = remove_ext(&path);
+        let stats = settings
+            .listening
+            .stats
+            .get(&track_without_ext)
+            .copied()
+            .unwrap_or_default();
+        let mut track = ExportedTrack {
+            path: path.to_string_lossy().to_string(),
+            title: String::new(),
+            artist: String::new(),
+            album: String::new(),
+            length: 0.0,
+            format: file_format(&path),
+            plays: stats.count,
+            last_played: stats.last_played,
+            bpm: settings
+                .bpm
+                .get(&track_without_ext)
+                .copied()
+                .filter(|bpm| *bpm > 0.0),
+            labels: settings
+                .labels
+                .get(&track_without_ext)
+                .cloned()
+                .unwrap_or_default(),
+        };
+        if let Some(file_track) = cache.get(&*path.to_string_lossy()) {
+            track.title = file_track.title.clone();
+            track.artist = file_track.artist.clone();
+            track.album = file_track.album.clone();
+            track.length = file_track.length;
+        }
+        tracks.push(track);
+    }
+    tracks
+}
+
+/// Merges the play counts kept by another player into the stats, returning the indices of the
+/// tracks whose count changed with the new count
+///
+/// The tracks are matched by path, or by title and artist when they were moved since
+async fn import_play_counts(
+    runner: &RwLock<Runner>,
+    settings: &Mutex<Settings>,
+    foreign: Vec<ForeignTrack>,
+) -> Vec<(u16, u32)> {
+    let runner = runner.read().await;
+    let mut settings = settings.lock().await;
+    let mut by_path = HashMap::new();
+    let mut by_tags = HashMap::new();
+    for track in foreign {
+        if let Some(path) = &track.path {
+            by_path.insert(path.clone(), track.clone());
+        }
+        if !track.title.is_empty() {
+            by_tags.insert(
+                (track.title.to_lowercase(), track.artist.to_lowercase()),
+                track,
+            );
+        }
+    }
+    let tags = settings
+        .tracks
+        .iter()
+        .map(|file_track| {
+            let tags = (
+                file_track.title.to_lowercase(),
+                file_track.artist.to_lowercase(),
+            );
+            (remove_ext(&file_track.path), tags)
+        })
+        .collect::<HashMap<_, _>>();
+    let mut changed = vec![];
+    for i in 0..runner.len() as u16 {
+        let Some(path) = runner.get_path_for_file(i).await else {
+            continue;
+        };
+        let track_without_ext = remove_ext(&path);
+        let foreign = by_path.get(&path).or_else(|| {
+            tags.get(&track_without_ext)
+                .and_then(|tags| by_tags.get(tags))
+        });
+        let Some(foreign) = foreign else {
+            continue;
+        };
+        let stats = settings
+            .listening
+            .stats
+            .entry(track_without_ext)
+            .or_default();
+        // the highest count is kept, so importing twice doesn't count the plays twice
+        if foreign.plays > stats.count {
+            stats.count = foreign.plays;
+            stats.last_played = stats.last_played.max(foreign.last_played);
+            changed.push((i, stats.count));
+        }
+    }
+    changed
+}
+
+/// Returns the tracks the file organizer would move, with where they are and where they'd go
+///
+/// Tracks whose place is taken, on the disk or by another track, are left where they are
+async fn organize_plan(runner: &RwLock<Runner>, pattern: &str) -> Vec<(u16, PathBuf, PathBuf)> {
+    let mut paths = vec![];
+    let root = {
+        let runner = runner.read().await;
+        for i in 0..runner.len() as u16 {
+            if runner.removed().contains(&i) {
+                continue;
+            }
+            if let Some(path) = runner.get_path_for_file(i).await {
+                paths.push((i, path));
+            }
+        }
+        PathBuf::from(runner.path())
+    };
+    let pattern = pattern.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut targets = HashSet::new();
+        paths
+            .into_iter()
+            .filter_map(|(i, path)| {
+                let metadata = MusicTrack::new(path.to_string_lossy().to_string())
+                    .and_then(|track| track.get_meta())
+                    .ok()?;
+                let relative = organized_path(&pattern, &metadata);
+                if relative.is_empty() {
+                    return None;
+                }
+                let target = organized_target(&root, &path, &relative);
+                if target.exists() || !targets.insert(target.clone()) {
+                    return None;
                 }
+                Some((i, path, target))
+            })
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Returns where `file` goes in the music directory given its [organized_path], keeping its
+/// extension
+fn organized_target(root: &Path, file: &Path, relative: &str) -> PathBuf {
+    // not with_extension, a dot in the title would be taken for one
+    match file.extension() {
+        Some(extension) => root.join(format!("{relative}.{}", extension.to_string_lossy())),
+        None => root.join(relative),
+    }
+}
+
+/// Copies or moves files into the music directory where the file organizer would put them,
+/// returning where they ended up along with their tags
+/// Imports the files with the organizer rules and adds them to the library, the updater gets
+/// their tags through `tx_imported`
+async fn import_to_library(
+    files: Vec<PathBuf>,
+    runner: &RwLock<Runner>,
+    settings: &Mutex<Settings>,
+    tx_imported: &Sender<(u16, FileTrack, String)>,
+) {
+    if files.is_empty() {
+        return;
+    }
+    let (pattern, mode) = {
+        let settings = settings.lock().await;
+        (settings.organize_pattern.clone(), settings.import_mode)
+    };
+    let root = runner.read().await.path();
+    let imported = import_files(PathBuf::from(&root), files, &pattern, mode).await;
+    let mut added = 0;
+    for (path, file_track) in imported {
+        match runner.write().await.add(&path).await {
+            Ok(index) => {
+                let folder = relative_folder(&root, &path);
+                tx_imported.send((index, file_track, folder)).unwrap();
+                added += 1;
             }
-            let progress = loaded as f64 / tracks.len() as f64;
-            let mut playing_track = None;
-            if old_index != index || new_loaded {
-                if let Some(track) = tracks.get(index as usize) {
-                    playing_track = Some(track.clone());
-                    old_index = index;
+            Err(e) => toast_error(text(
+                Text::CantAdd,
+                &[("path", &path.display()), ("error", &e)],
+            )),
+        }
+    }
+    if added > 0 {
+        toast_info(count(Count::Imported, added, &[]));
+    }
+}
+
+/// Adds files from anywhere to the library where they are and queues them, the music directory
+/// stays the same so they're gone once the library is read again
+async fn queue_files(
+    files: Vec<PathBuf>,
+    runner: &RwLock<Runner>,
+    settings: &Mutex<Settings>,
+    tx: &Sender<RunnerMessage>,
+    tx_imported: &Sender<(u16, FileTrack, String)>,
+) {
+    if files.is_empty() {
+        return;
+    }
+    let chain = metadata_chain(&settings.lock().await);
+    let tracks = tokio::task::spawn_blocking(move || {
+        files
+            .into_iter()
+            .filter_map(|file| match chain.read(&file) {
+                Ok(metadata) => {
+                    let file_track = file_track(&file, metadata);
+                    Some((file, file_track))
                 }
+                Err(e) => {
+                    toast_error(text(
+                        Text::CantRead,
+                        &[("path", &file.display()), ("error", &e)],
+                    ));
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap_or_default();
+    let root = runner.read().await.path();
+    for (path, file_track) in tracks {
+        match runner.write().await.add(&path).await {
+            Ok(index) => {
+                let folder = relative_folder(&root, &path);
+                tx_imported.send((index, file_track, folder)).unwrap();
+                tx.send(RunnerMessage::Enqueue(index)).unwrap();
             }
+            Err(e) => toast_error(text(
+                Text::CantAdd,
+                &[("path", &path.display()), ("error", &e)],
+            )),
+        }
+    }
+}
 
-            let mut updated_search = false;
-            while let Ok(search_string) = rx_searching.try_recv() {
-                searching = search_string;
-                updated_search = true;
-            }
+/// What the library keeps of a file it didn't have before, its cover is read here
+fn file_track(path: &Path, metadata: Metadata) -> FileTrack {
+    FileTrack {
+        path: path.to_string_lossy().to_string(),
+        modified: modified_time(path),
+        title: metadata.title,
+        artist: metadata.artist,
+        album: metadata.album,
+        genre: metadata.genre,
+        length: metadata.time.length,
+        format: file_format(path),
+        image: decode_cover(&get_image(path), COVER_SIZE),
+        plays: read_play_count(path).unwrap_or_default(),
+    }
+}
 
-            let mut t = vec![];
+async fn import_files(
+    root: PathBuf,
+    files: Vec<PathBuf>,
+    pattern: &str,
+    mode: ImportMode,
+) -> Vec<(PathBuf, FileTrack)> {
+    let pattern = pattern.to_string();
+    tokio::task::spawn_blocking(move || {
+        files
+            .into_iter()
+            .filter_map(|file| {
+                let metadata = match MusicTrack::new(file.to_string_lossy().to_string())
+                    .and_then(|track| track.get_meta())
+                {
+                    Ok(metadata) => metadata,
+                    Err(e) => {
+                        toast_error(text(
+                            Text::CantRead,
+                            &[("path", &file.display()), ("error", &e)],
+                        ));
+                        return None;
+                    }
+                };
+                let mut relative = organized_path(&pattern, &metadata);
+                if relative.is_empty() {
+                    relative = remove_ext(&file);
+                }
+                let target = organized_target(&root, &file, &relative);
+                if target.exists() {
+                    toast_error(text(Text::AlreadyInLibrary, &[("path", &target.display())]));
+                    return None;
+                }
+                let result = target
+                    .parent()
+                    .map_or(Ok(()), |parent| std::fs::create_dir_all(parent))
+                    .and_then(|_| match mode {
+                        ImportMode::Copy => std::fs::copy(&file, &target).map(|_| ()),
+                        // renaming doesn't work across disks
+                        ImportMode::Move => std::fs::rename(&file, &target).or_else(|_| {
+                            std::fs::copy(&file, &target).and_then(|_| std::fs::remove_file(&file))
+                        }),
+                    });
+                if let Err(e) = result {
+                    toast_error(text(
+                        Text::CantImport,
+                        &[("path", &file.display()), ("error", &e)],
+                    ));
+                    return None;
+                }
+                let file_track = file_track(&target, metadata);
+                Some((target, file_track))
+            })
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// Moves the files planned by [organize_plan] and removes the folders left empty, returning the
+/// ones that were moved
+async fn move_files(
+    root: PathBuf,
+    plan: Vec<(u16, PathBuf, PathBuf)>,
+) -> Vec<(u16, PathBuf, PathBuf)> {
+    tokio::task::spawn_blocking(move || {
+        plan.into_iter()
+            .filter(|(_, from, to)| {
+                let result = to
+                    .parent()
+                    .map_or(Ok(()), |parent| std::fs::create_dir_all(parent))
+                    .and_then(|_| std::fs::rename(from, to));
+                if let Err(e) = result {
+                    toast_error(text(
+                        Text::CantMove,
+                        &[("path", &from.display()), ("error", &e)],
+                    ));
+                    return false;
+                }
+                let mut folder = from.parent();
+                while let Some(dir) = folder {
+                    // fails when something is still in there
+                    if dir == root || std::fs::remove_dir(dir).is_err() {
+                        break;
+                    }
+                    folder = dir.parent();
+                }
+                true
+            })
+            .collect()
+    })
+    .await
+    .unwrap_or_default()
+}
+
+fn update_column(window: &MainWindow, id: i32, update: impl FnOnce(&mut ColumnData)) {
+    let columns = window.global::<SettingsData>().get_columns();
+    if let Some(row) = columns.iter().position(|column| column.id == id) {
+        let mut column = columns.row_data(row).unwrap();
+        update(&mut column);
+        columns.set_row_data(row, column);
+    }
+}
+
+fn get_equalizer(settings_data: &SettingsData) -> Equalizer {
+    let mut bands = [0.0; BANDS.len()];
+    for (band, gain) in bands
+        .iter_mut()
+        .zip(settings_data.get_equalizer_bands().iter())
+    {
+        *band = gain;
+    }
+    Equalizer {
+        enabled: settings_data.get_equalizer_enabled(),
+        bands,
+        preamp: settings_data.get_preamp(),
+        crossfeed: settings_data.get_crossfeed(),
+        karaoke: settings_data.get_karaoke(),
+        limiter: settings_data.get_limiter(),
+    }
+}
+
+fn set_equalizer(settings_data: &SettingsData, equalizer: &Equalizer) {
+    settings_data.set_equalizer_enabled(equalizer.enabled);
+    settings_data.set_equalizer_bands(VecModel::from_slice(&equalizer.bands));
+    settings_data.set_preamp(equalizer.preamp);
+    settings_data.set_crossfeed(equalizer.crossfeed);
+    settings_data.set_karaoke(equalizer.karaoke);
+    settings_data.set_limiter(equalizer.limiter);
+}
+
+fn preset_names(presets: &[EqualizerPreset]) -> ModelRc<SharedString> {
+    let names = presets
+        .iter()
+        .map(|preset| SharedString::from(preset.name.as_str()))
+        .collect::<Vec<_>>();
+    VecModel::from_slice(&names)
+}
+
+/// The recent folders that can be switched to, the current one isn't among them
+fn recent_folders(settings: &Settings) -> ModelRc<SharedString> {
+    let folders = settings
+        .recent_folders
+        .iter()
+        .filter(|folder| **folder != settings.path)
+        .map(|folder| SharedString::from(folder.as_str()))
+        .collect::<Vec<_>>();
+    VecModel::from_slice(&folders)
+}
+
+/// Names the backups after the date and the time (UTC) they were made at
+fn backup_names(backups: &[u64]) -> ModelRc<SharedString> {
+    let names = backups
+        .iter()
+        .map(|time| {
+            let name = format!(
+                "{} {:02}:{:02}",
+                format_date(*time),
+                time / 3600 % 24,
+                time / 60 % 60
+            );
+            SharedString::from(name)
+        })
+        .collect::<Vec<_>>();
+    VecModel::from_slice(&names)
+}
+
+/// Applies the locale to the whole window, the labels built from rust and the tray menu too
+///
+/// The loading progress label follows on the next batch of loaded tracks
+fn relocalize(window: &MainWindow, denominator: Option<String>) {
+    localize(denominator, window.global::<Localization>());
+    let app_data = window.global::<AppData>();
+    let queued = app_data.get_queued() as usize;
+    app_data.set_queued_label(count(Count::QueuedTracks, queued, &[]).into());
+    #[cfg(not(target_os = "android"))]
+    tray::relabel(window);
+}
 
-            let is_searching = !searching.is_empty();
+/// Saves the settings at most once every [SAVE_INTERVAL] while the library, the stats or the
+/// volume change, so a crash only loses the last few seconds
+async fn saver<P: Platform + Send + 'static>(
+    settings: Arc<Mutex<Settings>>,
+    platform: Arc<Mutex<P>>,
+    runner: Arc<RwLock<Runner>>,
+    rx_dirty: Receiver<()>,
+) {
+    let mut interval = tokio::time::interval(SAVE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let mut dirty = false;
+        while rx_dirty.try_recv().is_ok() {
+            dirty = true;
+        }
+        let volume = runner.read().await.volume();
+        let mut settings = settings.lock().await;
+        if settings.volume != volume {
+            settings.volume = volume;
+            dirty = true;
+        }
+        if dirty {
+            settings.save(platform.lock().await).await;
+        }
+    }
+}
 
-            if new_loaded || updated_search {
-                t = tracks.clone();
+/// Checks whether the tracks that didn't open in time can be reached again, like when the network
+/// share they're on comes back
+async fn watch_unavailable(runner: Arc<RwLock<Runner>>, tx: Sender<RunnerMessage>) {
+    let mut interval = tokio::time::interval(UNAVAILABLE_CHECK);
+    loop {
+        interval.tick().await;
+        let unavailable = runner.read().await.unavailable().clone();
+        for index in unavailable {
+            let Some(path) = runner.read().await.get_path_for_file(index).await else {
+                continue;
+            };
+            let path = path.to_string_lossy().to_string();
+            let checking = tokio::task::spawn_blocking(move || storage::exists(&path));
+            if let Ok(Ok(true)) = tokio::time::timeout(OPEN_TIMEOUT, checking).await {
+                tx.send(RunnerMessage::Available(index)).unwrap();
             }
+        }
+    }
+}
 
-            if is_searching && (updated_search || new_loaded) {
-                t = t
-                    .into_iter()
-                    .filter(|track| {
-                        let search = searching.to_lowercase();
-                        track.title.to_lowercase().contains(&search)
-                            || track.artist.to_lowercase().contains(&search)
-                    })
-                    .collect();
+/// Copies the upcoming tracks into `dir` when the cache is enabled
+async fn cache_upcoming(
+    settings: Arc<Mutex<Settings>>,
+    runner: Arc<RwLock<Runner>>,
+    dir: PathBuf,
+    rx: Receiver<Vec<u16>>,
+) {
+    while let Ok(mut indices) = rx.recv_async().await {
+        // the queue may have changed again while the last ones were copied
+        while let Ok(newer) = rx.try_recv() {
+            indices = newer;
+        }
+        let limit = settings.lock().await.cache_size as u64 * 1024 * 1024;
+        if limit == 0 {
+            continue;
+        }
+        let mut paths = vec![];
+        for index in indices {
+            if let Some(path) = runner.read().await.get_path_for_file(index).await {
+                paths.push(path);
             }
+        }
+        let dir = dir.clone();
+        if let Ok(Err(e)) =
+            tokio::task::spawn_blocking(move || cache_tracks(&dir, &paths, limit)).await
+        {
+            eprintln!("Can't cache the next tracks: {e}");
+        }
+    }
+}
 
-            p.lock().await.tick().await;
+/// Plays [PREVIEW_LENGTH] of the tracks sent, on a stream of its own so the queue goes on as it
+/// was, `None` stops the one playing
+async fn preview_tracks(runner: Arc<RwLock<Runner>>, rx: Receiver<Option<u16>>) {
+    let mut player = Player::builder().volume(PREVIEW_VOLUME).build();
+    let mut next = None;
+    loop {
+        let mut index = match next.take() {
+            Some(index) => index,
+            None => match rx.recv_async().await {
+                Ok(index) => index,
+                Err(_) => break,
+            },
+        };
+        // the pointer going down the list doesn't start a preview on every track it crosses
+        while let Ok(Ok(newer)) = tokio::time::timeout(PREVIEW_DELAY, rx.recv_async()).await {
+            index = newer;
+        }
+        let Some(index) = index else {
+            continue;
+        };
+        let Some(path) = runner.read().await.get_path_for_file(index).await else {
+            continue;
+        };
+        let Ok(track) = MusicTrack::new(path.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let opening = tokio::task::spawn_blocking(move || track.get_format());
+        match tokio::time::timeout(OPEN_TIMEOUT, opening).await {
+            Ok(Ok(Ok(format))) => player.play(format),
+            Ok(Ok(Err(e))) => {
+                eprintln!("Can't preview {}: {e}", path.display());
+                continue;
+            }
+            _ => continue,
+        }
+        // the length is known once the track thread sent the first position
+        let started = Instant::now();
+        while player.get_time().is_none() && started.elapsed() < OPEN_TIMEOUT {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        // the intros are often quiet, a third in is closer to what the track sounds like
+        if let Some(time) = player.get_time() {
+            let start = time.length / 3.0;
+            let _ = player.seek_to(start.trunc() as u64, start.fract()).await;
+        }
+        if let Ok(Ok(newer)) = tokio::time::timeout(PREVIEW_LENGTH, rx.recv_async()).await {
+            next = Some(newer);
+        }
+        let _ = player.end_current().await;
+    }
+}
 
-            window
-                .upgrade_in_event_loop(move |window| {
-                    let app_data = window.global::<AppData>();
-                    app_data.set_playing(index as i32);
-                    app_data.set_position_time(position.into());
-                    if change_time {
-                        app_data.set_time(time_float as f32);
-                    }
-                    app_data.set_length(length as f32);
-                    app_data.set_playback(playback);
-                    app_data.set_volume(volume as f32);
+/// Keeps the volume of every output device and restores it when the device changes, so the
+/// speakers don't play at the volume of the headphones
+///
+/// When the device that was playing is disconnected the playback is paused instead, and it
+/// resumes if the device comes back within [Settings::resume_within]
+async fn watch_output_device(
+    settings: Arc<Mutex<Settings>>,
+    runner: Arc<RwLock<Runner>>,
+    tx: Sender<RunnerMessage>,
+) {
+    let mut interval = tokio::time::interval(DEVICE_CHECK);
+    let mut device: Option<String> = None;
+    // the device that was disconnected while playing and when, until it's too late to resume
+    let mut disconnected: Option<(String, Instant)> = None;
+    loop {
+        interval.tick().await;
+        let Ok(Some(new_device)) = tokio::task::spawn_blocking(output_device).await else {
+            continue;
+        };
+        let (volume, playback, stopped) = {
+            let runner = runner.read().await;
+            (runner.volume(), runner.playback(), runner.stopped())
+        };
+        // the lock isn't held while the devices are listed below
+        let (resume_within, pause_on_disconnect, saved) = {
+            let mut settings = settings.lock().await;
+            if let Some(device) = &device {
+                settings.device_volumes.insert(device.clone(), volume);
+            }
+            (
+                Duration::from_secs(settings.resume_within as u64 * 60),
+                settings.pause_on_disconnect,
+                settings.device_volumes.get(&new_device).copied(),
+            )
+        };
+        if disconnected
+            .as_ref()
+            .is_some_and(|(_, at)| at.elapsed() >= resume_within)
+        {
+            disconnected = None;
+        }
+        if device.as_ref() == Some(&new_device) {
+            continue;
+        }
+        if let Some(saved) = saved {
+            if saved != volume {
+                tx.send(RunnerMessage::SetVolume(saved)).unwrap();
+            }
+        }
+        if let Some(old_device) = device.replace(new_device.clone()) {
+            // the default device also changes when another one is chosen, only the ones that
+            // are gone pause the playback
+            if playback && pause_on_disconnect {
+                let devices = tokio::task::spawn_blocking(output_devices)
+                    .await
+                    .unwrap_or_default();
+                if !devices.contains(&old_device) {
+                    tx.send(RunnerMessage::Pause).unwrap();
+                    disconnected = Some((old_device, Instant::now()));
+                    continue;
+                }
+            }
+        }
+        let came_back = disconnected
+            .as_ref()
+            .is_some_and(|(old_device, _)| *old_device == new_device);
+        if came_back {
+            disconnected = None;
+            // unless it was played or stopped since
+            if !playback && !stopped {
+                tx.send(RunnerMessage::Play).unwrap();
+            }
+        }
+    }
+}
 
-                    if let Some(playing_track) = playing_track {
-                        app_data.set_playing_track(playing_track);
-                    }
+/// Walks the music directory once the app started from the files found last time, the ones gone
+/// since are removed and the new ones are added with their tags
+async fn rescan_library(
+    runner: Arc<RwLock<Runner>>,
+    settings: Arc<Mutex<Settings>>,
+    tx: Sender<RunnerMessage>,
+    tx_imported: Sender<(u16, FileTrack, String)>,
+) {
+    let root = runner.read().await.path();
+    let files = library_files(&root).await;
+    let mut known = HashMap::new();
+    {
+        let runner = runner.read().await;
+        for i in 0..runner.len() as u16 {
+            if let Some(path) = runner.get_path_for_file(i).await {
+                known.insert(path, i);
+            }
+        }
+    }
+    let mut new_files = vec![];
+    for file in &files {
+        if known.remove(Path::new(file)).is_none() {
+            new_files.push(PathBuf::from(file));
+        }
+    }
+    for index in known.into_values() {
+        tx.send(RunnerMessage::Remove(index)).unwrap();
+    }
+    let chain = metadata_chain(&settings.lock().await);
+    let new_tracks = tokio::task::spawn_blocking(move || {
+        new_files
+            .into_iter()
+            .filter_map(|path| match chain.read(&path) {
+                Ok(metadata) => Some((file_track(&path, metadata), path)),
+                Err(e) => {
+                    eprintln!("can't read {}: {e}", path.display());
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .unwrap_or_default();
+    for (file_track, path) in new_tracks {
+        // the library may have been switched in the meantime
+        if runner.read().await.path() != root {
+            return;
+        }
+        match runner.write().await.add(&path).await {
+            Ok(index) => {
+                let folder = relative_folder(&root, &path);
+                tx_imported.send((index, file_track, folder)).unwrap();
+            }
+            Err(e) => eprintln!("can't add {}: {e}", path.display()),
+        }
+    }
+    let mut settings = settings.lock().await;
+    if settings.path == root {
+        settings.files = files;
+    }
+}
 
-                    if new_loaded {
-                        let progress = if progress == 1.0 {
-                            0.0
-                        } else {
-                            progress as f32
-                        };
-                        app_data.set_progress(progress);
-                    }
+/// Offers to import the audio files that appear in the watched folder, once they stopped growing
+/// so the downloads in progress are left alone
+async fn watch_new_files(
+    settings: Arc<Mutex<Settings>>,
+    new_files: Arc<Mutex<Vec<PathBuf>>>,
+    window: slint::Weak<MainWindow>,
+) {
+    let mut interval = tokio::time::interval(WATCH_CHECK);
+    let mut watched = None;
+    // the files there when the watching started and the ones already offered
+    let mut seen = HashSet::new();
+    // size of the new files at the last check
+    let mut growing = HashMap::new();
+    loop {
+        interval.tick().await;
+        let folder = settings.lock().await.watched_folder.clone();
+        let sizes = match &folder {
+            Some(folder) => file_sizes(folder).await,
+            None => HashMap::new(),
+        };
+        if folder != watched {
+            watched = folder;
+            seen = sizes.into_keys().collect();
+            growing.clear();
+            continue;
+        }
+        let Some(folder) = &watched else {
+            continue;
+        };
+        let mut found = vec![];
+        for (path, size) in &sizes {
+            if seen.contains(path) {
+                continue;
+            }
+            if growing.get(path) != Some(size) {
+                growing.insert(path.clone(), *size);
+                continue;
+            }
+            seen.insert(path.clone());
+            if let Ok(Some(mime)) = infer::get_from_path(path) {
+                if mime.mime_type().contains("audio") {
+                    found.push(path.clone());
+                }
+            }
+        }
+        seen.retain(|path| sizes.contains_key(path));
+        growing.retain(|path, _| sizes.contains_key(path) && !seen.contains(path));
+        if found.is_empty() {
+            continue;
+        }
+        let mut new_files = new_files.lock().await;
+        new_files.extend(found);
+        let message = count(Count::NewFiles, new_files.len(), &[("folder", folder)]);
+        let _ = window.upgrade_in_event_loop(move |window| {
+            window.global::<AppData>().set_new_files(message.into());
+        });
+    }
+}
 
-                    if new_loaded || updated_search {
-                        app_data.set_tracks(VecModel::from_slice(&t));
-                    }
-                })
-                .unwrap();
+/// Size of the files directly inside `folder` by path
+async fn file_sizes(folder: &str) -> HashMap<PathBuf, u64> {
+    let mut sizes = HashMap::new();
+    if let Ok(mut entries) = tokio::fs::read_dir(folder).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if let Ok(metadata) = entry.metadata().await {
+                if metadata.is_file() {
+                    sizes.insert(entry.path(), metadata.len());
+                }
+            }
         }
-    });
+    }
+    sizes
+}
 
-    tokio::task::block_in_place(|| main_window.run().unwrap());
-    settings.lock().await.volume = runner.read().await.volume();
-    if settings.lock().await.save_window_size {
-        let width = main_window.get_last_width() as usize;
-        let height = main_window.get_last_height() as usize;
-        settings.lock().await.window_size = WindowSize { width, height };
-    } else {
-        settings.lock().await.window_size = WindowSize::default();
+/// Reloads the locale files in `dir` when they change, the current locale is applied again so
+/// translators see their work right away
+async fn watch_user_locales(
+    dir: PathBuf,
+    window: slint::Weak<MainWindow>,
+    settings: Arc<Mutex<Settings>>,
+) {
+    let mut interval = tokio::time::interval(LOCALES_CHECK);
+    let mut modified = locales_modified(&dir).await;
+    loop {
+        interval.tick().await;
+        let new_modified = locales_modified(&dir).await;
+        if new_modified == modified {
+            continue;
+        }
+        modified = new_modified;
+        let d = dir.clone();
+        let _ = tokio::task::spawn_blocking(move || load_user_locales(&d)).await;
+        let locale = settings.lock().await.locale.clone();
+        let _ = window.upgrade_in_event_loop(move |window| relocalize(&window, locale));
     }
+}
 
-    updater.abort();
-    future.abort();
-    settings.lock().await.save(platform.lock().await).await;
+/// Returns the files in `dir` with the last time they were modified
+async fn locales_modified(dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let mut modified = vec![];
+    if let Ok(mut entries) = tokio::fs::read_dir(dir).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            if let Ok(time) = entry
+                .metadata()
+                .await
+                .and_then(|metadata| metadata.modified())
+            {
+                modified.push((entry.path(), time));
+            }
+        }
+    }
+    modified.sort();
+    modified
+}
+
+/// Indices of the tracks whose tags are still to be read, shared by the tasks of the loader
+struct LoadQueue {
+    pending: VecDeque<u16>,
+    left: HashSet<u16>,
+    visible: Arc<std::sync::Mutex<Vec<u16>>>,
+}
+
+impl LoadQueue {
+    /// The first track on screen that's left, or else the next one in the library
+    fn next(&mut self) -> Option<u16> {
+        let visible = self
+            .visible
+            .lock()
+            .unwrap()
+            .iter()
+            .copied()
+            .find(|index| self.left.contains(index));
+        let index = match visible {
+            Some(index) => index,
+            None => loop {
+                let index = self.pending.pop_front()?;
+                if self.left.contains(&index) {
+                    break index;
+                }
+            },
+        };
+        self.left.remove(&index);
+        Some(index)
+    }
 }
+
 async fn loader_task(
     runner: Arc<RwLock<Runner>>,
+    chain: Arc<MetadataChain>,
     tx: Sender<Option<(u16, FileTrack)>>,
-    rx_l: Arc<Mutex<Receiver<u16>>>,
+    queue: Weak<Mutex<LoadQueue>>,
+    covers: Arc<CoverCache>,
 ) {
     loop {
-        if let Ok(index) = rx_l.lock().await.recv_async().await {
-            if index == u16::MAX {
-                if let Err(e) = tx.send_async(None).await {
-                    eprintln!("error happened when signaling end of task, probably because the app was closed: {e}");
-                }
-                return;
+        // the loader was aborted, the library changed
+        let Some(queue) = queue.upgrade() else {
+            return;
+        };
+        let next = queue.lock().await.next();
+        drop(queue);
+        let Some(index) = next else {
+            if let Err(e) = tx.send_async(None).await {
+                eprintln!("error happened when signaling end of task, probably because the app was closed: {e}");
             }
-            if let Some(path) = runner.read().await.get_path_for_file(index).await {
-                if let Ok(track) = MusicTrack::new(path.to_string_lossy().to_string()) {
-                    if let Ok(Ok(meta)) =
-                        tokio::task::spawn_blocking(move || track.get_meta()).await
-                    {
-                        let p = path.clone();
-                        let image = if let Ok(image) =
-                            tokio::task::spawn_blocking(move || get_image(p)).await
-                        {
-                            if !image.is_empty() {
-                                let zune_image = if let Ok(image) =
-                                    Image::read(ZCursor::new(&image), DecoderOptions::new_fast())
-                                {
-                                    Some(image)
-                                } else if let Ok(mut webp_decoder) =
-                                    WebPDecoder::try_new(Cursor::new(&image))
-                                {
-                                    if let Ok(image) = webp_decoder.decode() {
-                                        Some(image)
-                                    } else {
-                                        None
-                                    }
-                                } else {
-                                    None
-                                };
-
-                                if let Some(mut zune_image) = zune_image {
-                                    zune_image.convert_color(ColorSpace::RGB).unwrap();
-                                    let (width, height) = zune_image.dimensions();
-                                    if width != height {
-                                        let difference = width.abs_diff(height);
-                                        let min = width.min(height);
-                                        let is_height = height < width;
-                                        let x = if is_height { difference / 2 } else { 0 };
-                                        let y = if !is_height { difference / 2 } else { 0 };
-                                        tokio::task::block_in_place(|| {
-                                            Crop::new(min, min, x, y)
-                                                .execute(&mut zune_image)
-                                                .unwrap()
-                                        });
-                                    }
-                                    tokio::task::block_in_place(|| {
-                                        rimage::operations::resize::Resize::new(
-                                            128,
-                                            128,
-                                            ResizeAlg::Convolution(FilterType::Hamming),
-                                        )
-                                            .execute(&mut zune_image)
-                                            .unwrap()
-                                    });
-                                    zune_image.flatten_to_u8()[0].clone()
-                                } else {
-                                    vec![]
-                                }
-                            } else {
-                                vec![]
-                            }
-                        } else {
-                            vec![]
-                        };
+            return;
+        };
+        if let Some(path) = runner.read().await.get_path_for_file(index).await {
+            let p = path.clone();
+            let chain = chain.clone();
+            match tokio::task::spawn_blocking(move || {
+                (chain.read(&p), modified_time(&p), read_play_count(&p))
+            })
+            .await
+            {
+                Ok((Ok(meta), modified, plays)) => {
+                    let p = path.clone();
+                    let covers = covers.clone();
+                    let image = tokio::task::spawn_blocking(move || covers.decode(&get_image(p)))
+                        .await
+                        .unwrap_or_default();
 
-                        if let Err(e) = tx
-                            .send_async(Some((
-                                index,
-                                FileTrack {
-                                    path: remove_ext(path),
-                                    title: meta.title,
-                                    artist: meta.artist,
-                                    length: meta.time.length,
-                                    image,
-                                },
-                            )))
-                            .await
-                        {
-                            eprintln!("error happened during metadata transfer, probably because the app was closed: {e}");
-                        }
+                    if let Err(e) = tx
+                        .send_async(Some((
+                            index,
+                            FileTrack {
+                                path: path.to_string_lossy().to_string(),
+                                modified,
+                                title: meta.title,
+                                artist: meta.artist,
+                                album: meta.album,
+                                genre: meta.genre,
+                                length: meta.time.length,
+                                format: file_format(&path),
+                                image,
+                                plays: plays.unwrap_or_default(),
+                            },
+                        )))
+                        .await
+                    {
+                        eprintln!("error happened during metadata transfer, probably because the app was closed: {e}");
                     }
                 }
+                Ok((Err(e), _, _)) => toast_error(text(
+                    Text::CantRead,
+                    &[("path", &path.display()), ("error", &e)],
+                )),
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+/// Estimates the tempo of the tracks that don't have one yet and saves it in the settings, sending
+/// `None` once all of them are done
+///
+/// It's one track at a time in the background, so playback and reading the tags aren't slowed down
+async fn bpm_analyzer(
+    runner: Arc<RwLock<Runner>>,
+    settings: Arc<Mutex<Settings>>,
+    tx: Sender<Option<(u16, f32)>>,
+) {
+    let len = runner.read().await.len() as u16;
+    for i in 0..len {
+        let Some(path) = runner.read().await.get_path_for_file(i).await else {
+            continue;
+        };
+        let name = remove_ext(&path);
+        if settings.lock().await.bpm.contains_key(&name) {
+            continue;
+        }
+        let bpm = tokio::task::spawn_blocking(move || {
+            MusicTrack::new(path.to_string_lossy().to_string()).and_then(|track| track.get_bpm())
+        })
+        .await;
+        // the tracks without a beat are saved too, so they aren't decoded again
+        let Ok(Ok(bpm)) = bpm else {
+            continue;
+        };
+        let bpm = bpm.unwrap_or_default();
+        settings.lock().await.bpm.insert(name, bpm);
+        if tx.send_async(Some((i, bpm))).await.is_err() {
+            return;
+        }
+    }
+    let _ = tx.send_async(None).await;
+}
+
+/// What the properties show of the loudness of track `index` and of the gain it's played with
+/// in `mode`
+fn loudness_report(
+    index: i32,
+    loudness: &Loudness,
+    tags: ReplayGainTags,
+    mode: GainMode,
+    target: GainTarget,
+) -> LoudnessData {
+    let decibels = |value: f64| {
+        let sign = if value > 0.0 { "+" } else { "" };
+        format!("{sign}{} dB", format_decimal(value, 2))
+    };
+    let tag = |gain: Option<ReplayGain>| gain.map(|gain| decibels(gain.gain)).unwrap_or_default();
+    let amplitude = tags.amplitude(mode, target);
+    let unlimited = tags.amplitude(
+        mode,
+        GainTarget {
+            prevent_clipping: false,
+            ..target
+        },
+    );
+    let level = |lufs: f64| ((lufs - LOUDNESS_FLOOR) / -LOUDNESS_FLOOR).clamp(0.0, 1.0) as f32;
+    let momentary = loudness.momentary().collect::<Vec<_>>();
+    let bars = LOUDNESS_BARS.min(momentary.len());
+    let levels = (0..bars)
+        .map(|bar| {
+            let blocks =
+                &momentary[bar * momentary.len() / bars..(bar + 1) * momentary.len() / bars];
+            // the loudest moment of the bar, like the peaks of the waveform
+            level(blocks.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+        })
+        .collect::<Vec<_>>();
+    LoudnessData {
+        index,
+        measured: true,
+        loudness: loudness
+            .integrated()
+            .map(|lufs| format!("{} LUFS", format_decimal(lufs, 1)))
+            .unwrap_or_default()
+            .into(),
+        peak: if loudness.peak() > 0.0 {
+            let peak = 20.0 * (loudness.peak() as f64).log10();
+            format!("{} dBFS", format_decimal(peak, 1)).into()
+        } else {
+            Default::default()
+        },
+        track_gain: tag(tags.track).into(),
+        album_gain: tag(tags.album).into(),
+        applied_gain: decibels(20.0 * (amplitude as f64).log10()).into(),
+        limited: amplitude < unlimited,
+        levels: VecModel::from_slice(&levels),
+        target: level(target.loudness),
+    }
+}
+
+/// Measures the loudness of every group of tracks and writes their ReplayGain in the files, the
+/// groups marked as an album get the album gain too
+async fn replay_gain_scanner(runner: Arc<RwLock<Runner>>, groups: Vec<(Vec<u16>, bool)>) {
+    let len = groups
+        .iter()
+        .map(|(indices, _)| indices.len())
+        .sum::<usize>();
+    toast_info(count(Count::ScanningReplayGain, len, &[]));
+    let mut written = 0;
+    for (indices, album) in groups {
+        let mut measured = vec![];
+        for i in indices {
+            let Some(path) = runner.read().await.get_path_for_file(i).await else {
+                continue;
+            };
+            let p = path.clone();
+            let loudness = tokio::task::spawn_blocking(move || {
+                MusicTrack::new(p.to_string_lossy().to_string())
+                    .and_then(|track| track.get_loudness())
+            })
+            .await
+            .unwrap();
+            match loudness {
+                Ok(loudness) => measured.push((path, loudness)),
+                Err(e) => toast_error(text(
+                    Text::CantRead,
+                    &[("path", &path.display()), ("error", &e)],
+                )),
             }
         }
+        let album_gain = if album {
+            Loudness::album(measured.iter().map(|(_, loudness)| loudness)).replay_gain()
+        } else {
+            None
+        };
+        for (path, loudness) in measured {
+            // silent tracks don't have a gain
+            let Some(track_gain) = loudness.replay_gain() else {
+                continue;
+            };
+            let p = path.clone();
+            match tokio::task::spawn_blocking(move || write_replay_gain(p, track_gain, album_gain))
+                .await
+                .unwrap()
+            {
+                Ok(()) => written += 1,
+                Err(e) => toast_error(text(
+                    Text::CantWriteTags,
+                    &[("path", &path.display()), ("error", &e)],
+                )),
+            }
+        }
+    }
+    toast_info(count(Count::ReplayGainWritten, written, &[]));
+}
+
+/// Embeds the cover image found next to the tracks in the ones without a cover of their own,
+/// re-encoded at most `size` pixels wide and high
+async fn cover_embedder(runner: Arc<RwLock<Runner>>, indices: Vec<u16>, size: usize) {
+    // every track of a folder gets the same cover, it's only encoded once
+    let mut covers: HashMap<PathBuf, Option<Vec<u8>>> = HashMap::new();
+    let mut embedded = 0;
+    for i in indices {
+        let Some(path) = runner.read().await.get_path_for_file(i).await else {
+            continue;
+        };
+        let Some(folder) = path.parent().map(Path::to_path_buf) else {
+            continue;
+        };
+        let p = path.clone();
+        if !tokio::task::spawn_blocking(move || get_image(p).is_empty())
+            .await
+            .unwrap()
+        {
+            continue;
+        }
+        let cover = match covers.get(&folder) {
+            Some(cover) => cover.clone(),
+            None => {
+                let f = folder.clone();
+                let cover = tokio::task::spawn_blocking(move || {
+                    let image = std::fs::read(folder_cover(f)?).ok()?;
+                    encode_cover(&image, size)
+                })
+                .await
+                .unwrap();
+                covers.insert(folder, cover.clone());
+                cover
+            }
+        };
+        let Some(cover) = cover else {
+            continue;
+        };
+        let p = path.clone();
+        match tokio::task::spawn_blocking(move || embed_cover(p, cover))
+            .await
+            .unwrap()
+        {
+            Ok(()) => embedded += 1,
+            Err(e) => toast_error(text(
+                Text::CantWriteTags,
+                &[("path", &path.display()), ("error", &e)],
+            )),
+        }
+    }
+    if embedded > 0 {
+        toast_info(count(Count::CoversEmbedded, embedded, &[]));
+    } else {
+        toast_info(text(Text::NoFolderCover, &[]));
+    }
+}
+
+/// Cleans up the genre of a track that was just read with the rules in the settings, writing it
+/// to the file when the user asked to
+async fn clean_genre(
+    runner: &RwLock<Runner>,
+    settings: &Mutex<Settings>,
+    index: u16,
+    file_track: &mut FileTrack,
+) {
+    let (genre, write_genres) = {
+        let settings = settings.lock().await;
+        (
+            normalize_genre(&file_track.genre, &settings.genre_rules),
+            settings.write_genres,
+        )
+    };
+    if genre == file_track.genre {
+        return;
+    }
+    file_track.genre = genre;
+    if !write_genres || file_track.genre.is_empty() {
+        return;
+    }
+    let Some(path) = runner.read().await.get_path_for_file(index).await else {
+        return;
+    };
+    let genre = file_track.genre.clone();
+    let p = path.clone();
+    match tokio::task::spawn_blocking(move || write_genre(&p, &genre).map(|()| modified_time(&p)))
+        .await
+        .unwrap()
+    {
+        // the cached tags are still the ones of the file
+        Ok(modified) => file_track.modified = modified,
+        Err(e) => toast_error(text(
+            Text::CantWriteTags,
+            &[("path", &path.display()), ("error", &e)],
+        )),
     }
 }
 
-async fn loader(runner: Arc<RwLock<Runner>>, tx: Sender<Option<(u16, FileTrack)>>) {
-    let len = runner.read().await.len();
+/// Reads the tags of the tracks at `indices`, the ones in `visible` first, sending `None` once all
+/// of them are done
+async fn loader(
+    runner: Arc<RwLock<Runner>>,
+    chain: Arc<MetadataChain>,
+    tx: Sender<Option<(u16, FileTrack)>>,
+    indices: Vec<u16>,
+    visible: Arc<std::sync::Mutex<Vec<u16>>>,
+) {
     let mut tasks = vec![];
-    let (tx_l, rx_l) = flume::unbounded();
-    let rx_l = Arc::new(Mutex::new(rx_l));
+    let queue = Arc::new(Mutex::new(LoadQueue {
+        left: indices.iter().copied().collect(),
+        pending: indices.into(),
+        visible,
+    }));
+    let covers = Arc::new(CoverCache::new(COVER_SIZE, LIST_COVERS));
     let cpus = num_cpus::get() * 4;
     for _ in 0..cpus {
         let runner = runner.clone();
+        let chain = chain.clone();
         let tx = tx.clone();
-        let rx_l = rx_l.clone();
-        tasks.push(tokio::task::spawn(loader_task(runner, tx, rx_l)));
-    }
-    for i in 0..len {
-        tx_l.send_async(i as u16).await.unwrap();
-    }
-    for _ in 0..cpus {
-        tx_l.send_async(u16::MAX).await.unwrap();
+        let queue = Arc::downgrade(&queue);
+        let covers = covers.clone();
+        tasks.push(tokio::task::spawn(loader_task(
+            runner, chain, tx, queue, covers,
+        )));
     }
     for task in tasks {
         task.await.unwrap();
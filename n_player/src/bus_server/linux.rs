@@ -1,5 +1,6 @@
+use crate::bus_server::cover_path;
+use crate::runner;
 use crate::runner::{Runner, RunnerMessage};
-use crate::{get_image, runner};
 use flume::Sender;
 use mpris_server::zbus::fdo;
 use mpris_server::zbus::zvariant::ObjectPath;
@@ -8,11 +9,8 @@ use mpris_server::{
     TrackId, Volume,
 };
 use n_audio::music_track::MusicTrack;
-use n_audio::remove_ext;
-use std::io::{Seek, Write};
-use std::path::PathBuf;
+use std::path::Path;
 use std::sync::Arc;
-use tempfile::NamedTempFile;
 use tokio::sync::RwLock;
 
 pub struct MPRISBridge {
@@ -32,11 +30,11 @@ impl RootInterface for MPRISBridge {
     }
 
     async fn quit(&self) -> fdo::Result<()> {
-        Ok(())
+        slint::quit_event_loop().map_err(|e| fdo::Error::Failed(e.to_string()))
     }
 
     async fn can_quit(&self) -> fdo::Result<bool> {
-        Ok(false)
+        Ok(true)
     }
 
     async fn fullscreen(&self) -> fdo::Result<bool> {
@@ -104,6 +102,7 @@ impl PlayerInterface for MPRISBridge {
     }
 
     async fn stop(&self) -> fdo::Result<()> {
+        self.tx.send_async(RunnerMessage::Stop).await.unwrap();
         Ok(())
     }
 
@@ -115,14 +114,26 @@ impl PlayerInterface for MPRISBridge {
     async fn seek(&self, offset: Time) -> fdo::Result<()> {
         self.tx
             .send_async(RunnerMessage::Seek(runner::RunnerSeek::Relative(
-                offset.as_secs() as f64 + (offset.as_millis() as f64 / 1000.0),
+                offset.as_millis() as f64 / 1000.0,
             )))
             .await
             .unwrap();
         Ok(())
     }
 
-    async fn set_position(&self, _track_id: TrackId, position: Time) -> fdo::Result<()> {
+    async fn set_position(&self, track_id: TrackId, position: Time) -> fdo::Result<()> {
+        let current = {
+            let runner = self.runner.read().await;
+            let index = runner.index();
+            runner
+                .current_item()
+                .await
+                .map(|item| crate::bus_server::track_id(index, &item))
+        };
+        // the request is for a track that isn't playing anymore
+        if current.as_deref() != Some(track_id.as_str()) {
+            return Ok(());
+        }
         self.tx
             .send_async(RunnerMessage::Seek(runner::RunnerSeek::Absolute(
                 position.as_millis() as f64 / 1000.0,
@@ -137,8 +148,10 @@ impl PlayerInterface for MPRISBridge {
     }
 
     async fn playback_status(&self) -> fdo::Result<PlaybackStatus> {
-        let playback = self.runner.read().await.playback();
-        if playback {
+        let runner = self.runner.read().await;
+        if runner.stopped() {
+            Ok(PlaybackStatus::Stopped)
+        } else if runner.playback() {
             Ok(PlaybackStatus::Playing)
         } else {
             Ok(PlaybackStatus::Paused)
@@ -170,45 +183,31 @@ impl PlayerInterface for MPRISBridge {
     }
 
     async fn metadata(&self) -> fdo::Result<Metadata> {
-        let path = self.runner.read().await.path();
-        let track_name = &self.runner.read().await.current_track().await.unwrap();
-        let mut path_buf = PathBuf::new();
-        path_buf.push(&path);
-        path_buf.push(track_name);
-        let track = MusicTrack::new(path_buf.to_str().unwrap())
-            .expect("can't get track for currently playing song");
-        let meta = track.get_meta();
-        let image = get_image(path_buf);
-        let mut tmp = NamedTempFile::new().expect("can't create tmp file for mpris bridge");
-        let image_path = if image.is_empty() {
-            None
-        } else {
-            tmp.rewind().expect("can't rewind tmp file");
-            tmp.write_all(&image)
-                .expect("can't write image data to tmp file");
-            Some(tmp.path().to_str().unwrap().to_string())
+        // read together so the trackid is the one of the entry the tags are read from
+        let (item, index) = {
+            let runner = self.runner.read().await;
+            (runner.current_item().await, runner.index())
         };
+        // nothing is playing while the queue is empty
+        let Some(item) = item else {
+            return Ok(Metadata::new());
+        };
+        // the tracks without a file only have a name
+        let Some(path) = item.path().map(Path::to_path_buf) else {
+            let meta = n_audio::Metadata::default();
+            return Ok(crate::bus_server::Metadata::new(&item, index, meta, None).into());
+        };
+        let p = path.clone();
+        let meta =
+            tokio::task::spawn_blocking(move || MusicTrack::new(p.to_str()?).ok()?.get_meta().ok())
+                .await
+                .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+        let Some(meta) = meta else {
+            return Ok(Metadata::new());
+        };
+        let image_path = cover_path(path).await;
 
-        let mut metadata = Metadata::new();
-        if let Ok(meta) = meta {
-            metadata.set_title(Some(if !meta.title.is_empty() {
-                meta.title
-            } else {
-                remove_ext(track_name)
-            }));
-            metadata.set_artist(if meta.artist.is_empty() {
-                None
-            } else {
-                Some(vec![meta.artist])
-            });
-            metadata.set_length(Some(Time::from_millis(
-                (meta.time.length * 1000.0).floor() as i64
-            )));
-            metadata.set_trackid(Some(ObjectPath::from_static_str_unchecked("/n_music")));
-            metadata.set_art_url(image_path);
-        }
-
-        Ok(metadata)
+        Ok(crate::bus_server::Metadata::new(&item, index, meta, image_path).into())
     }
 
     async fn volume(&self) -> fdo::Result<Volume> {
@@ -217,8 +216,10 @@ impl PlayerInterface for MPRISBridge {
     }
 
     async fn set_volume(&self, volume: Volume) -> zbus::Result<()> {
+        // bluetooth devices with absolute volume set it through here, MPRIS allows values out of
+        // the range the player uses
         self.tx
-            .send_async(RunnerMessage::SetVolume(volume))
+            .send_async(RunnerMessage::SetVolume(volume.clamp(0.0, 1.0)))
             .await
             .unwrap();
         Ok(())
@@ -263,3 +264,23 @@ impl PlayerInterface for MPRISBridge {
         Ok(true)
     }
 }
+
+impl From<crate::bus_server::Metadata> for Metadata {
+    fn from(value: crate::bus_server::Metadata) -> Self {
+        let mut metadata = Metadata::new();
+        metadata.set_title(value.title);
+        metadata.set_artist(value.artists);
+        metadata.set_album(value.album);
+        metadata.set_album_artist(value.album_artists);
+        metadata.set_track_number(value.track_number);
+        metadata.set_genre(value.genres);
+        metadata.set_url(value.url);
+        metadata.set_content_created(value.content_created);
+        metadata.set_length(Some(Time::from_millis(
+            (value.length * 1000.0).floor() as i64
+        )));
+        metadata.set_art_url(value.image_path);
+        metadata.set_trackid(Some(ObjectPath::from_string_unchecked(value.id)));
+        metadata
+    }
+}
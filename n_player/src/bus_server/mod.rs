@@ -1,94 +1,269 @@
-use crate::get_image;
 use crate::platform::Platform;
 use crate::runner::Runner;
+use crate::{get_image, TRACK_SETTLE};
 use n_audio::music_track::MusicTrack;
-use n_audio::remove_ext;
-use std::io::{Seek, Write};
+use n_audio::queue::QueueItem;
+use once_cell::sync::Lazy;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::mem;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
-use tempfile::NamedTempFile;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
 use tokio::sync::{Mutex, RwLock};
 
 #[cfg(target_os = "linux")]
 pub mod linux;
+#[cfg(target_os = "windows")]
+pub mod windows;
 
 pub enum Property {
     Playing(bool),
+    /// The playback was stopped, [Property::Playing] comes when it starts again
+    Stopped,
     Metadata(Metadata),
     Volume(f64),
+    /// Position and length of the current track in seconds
+    Position(f64, f64),
+    /// The position jumped somewhere else in the current track, in seconds
+    Seeked(f64),
 }
 
 pub struct Metadata {
     pub title: Option<String>,
     pub artists: Option<Vec<String>>,
+    pub album: Option<String>,
+    pub album_artists: Option<Vec<String>>,
+    pub track_number: Option<i32>,
+    pub genres: Option<Vec<String>>,
+    /// Where the track is played from, a `file://` URL for the local ones
+    pub url: Option<String>,
+    /// Release date of the track as it's tagged
+    pub content_created: Option<String>,
     pub length: f64,
     pub id: String,
     pub image_path: Option<String>,
 }
 
-pub async fn run<P: Platform + Send>(
-    platform: Arc<Mutex<P>>,
-    runner: Arc<RwLock<Runner>>,
-    mut tmp: NamedTempFile,
-) {
+/// Characters escaped in the paths of `file://` URLs
+const URL_PATH: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+impl Metadata {
+    /// What the media controls show of the track at `index`, the empty tags are left out
+    pub fn new(
+        item: &QueueItem,
+        index: u16,
+        meta: n_audio::Metadata,
+        image_path: Option<String>,
+    ) -> Self {
+        let tag = |value: String| (!value.is_empty()).then_some(value);
+        Self {
+            title: Some(tag(meta.title).unwrap_or_else(|| item.name())),
+            artists: tag(meta.artist).map(|artist| vec![artist]),
+            album: tag(meta.album),
+            album_artists: tag(meta.album_artist).map(|artist| vec![artist]),
+            track_number: meta.track.map(|track| track as i32),
+            genres: tag(meta.genre).map(|genre| vec![genre]),
+            url: match item {
+                QueueItem::Stream(url) => Some(url.clone()),
+                _ => item.path().map(|path| {
+                    let path = path.to_string_lossy();
+                    format!("file://{}", utf8_percent_encode(&path, URL_PATH))
+                }),
+            },
+            content_created: tag(meta.date),
+            length: meta.time.length,
+            id: track_id(index, item),
+            image_path,
+        }
+    }
+}
+
+/// Difference in seconds between the position and where playback should be to count as a seek
+const SEEK_TOLERANCE: f64 = 1.0;
+
+/// Returns the id of the entry at `index` of the queue, a different one for every entry so media
+/// controls notice when it changes
+///
+/// The track is part of it too, so the ids of the old entries aren't reused when the library is
+/// replaced
+pub fn track_id(index: u16, item: &QueueItem) -> String {
+    let mut hasher = DefaultHasher::new();
+    item.hash(&mut hasher);
+    format!("/n_music/track/{index}_{:x}", hasher.finish())
+}
+
+/// Covers kept in files, the ones of the tracks played before are reused when they come back
+const ART_CACHE: usize = 16;
+
+static ART: Lazy<Mutex<ArtCache>> = Lazy::new(|| Mutex::new(ArtCache::new()));
+
+/// Covers of the tracks written to files, the media controls get their path
+struct ArtCache {
+    dir: Option<TempDir>,
+    // the most recently used last, `None` for the tracks without a cover
+    covers: VecDeque<(PathBuf, Option<String>)>,
+}
+
+impl ArtCache {
+    fn new() -> Self {
+        let dir = tempfile::Builder::new()
+            .prefix("n_music")
+            .tempdir()
+            .inspect_err(|e| eprintln!("can't create the folder of the covers: {e}"))
+            .ok();
+        Self {
+            dir,
+            covers: VecDeque::new(),
+        }
+    }
+}
+
+/// Path of a file with the cover of the track at `path`, `None` when it has none
+///
+/// The tags are read outside of the async tasks, so the D-Bus calls asking for the metadata
+/// don't wait on the disk
+pub async fn cover_path(path: PathBuf) -> Option<String> {
+    let mut art = ART.lock().await;
+    if let Some(i) = art.covers.iter().position(|(track, _)| *track == path) {
+        let cover = art.covers.remove(i).unwrap();
+        let image_path = cover.1.clone();
+        art.covers.push_back(cover);
+        return image_path;
+    }
+    let dir = art.dir.as_ref()?.path().to_path_buf();
+    let p = path.clone();
+    let image_path = tokio::task::spawn_blocking(move || {
+        let image = get_image(&p);
+        if image.is_empty() {
+            return None;
+        }
+        let mut hasher = DefaultHasher::new();
+        p.hash(&mut hasher);
+        let file = dir.join(format!("{:x}", hasher.finish()));
+        match std::fs::write(&file, image) {
+            Ok(()) => file.to_str().map(str::to_string),
+            Err(e) => {
+                eprintln!("can't write the cover of {}: {e}", p.display());
+                None
+            }
+        }
+    })
+    .await
+    .ok()
+    .flatten();
+    if art.covers.len() >= ART_CACHE {
+        if let Some((_, Some(old))) = art.covers.pop_front() {
+            let _ = std::fs::remove_file(old);
+        }
+    }
+    art.covers.push_back((path, image_path.clone()));
+    image_path
+}
+
+/// Deletes the files written by [cover_path], called when quitting
+pub async fn remove_covers() {
+    let mut art = ART.lock().await;
+    art.covers.clear();
+    art.dir.take();
+}
+
+pub async fn run<P: Platform + Send>(platform: Arc<Mutex<P>>, runner: Arc<RwLock<Runner>>) {
     let mut interval = tokio::time::interval(Duration::from_millis(250));
     let mut properties = vec![];
     let mut playback = false;
+    let mut stopped = false;
     let mut volume = 1.0;
+    let mut position = u64::MAX;
+    let mut last_position: Option<(f64, Instant)> = None;
     let mut index = runner.read().await.index();
-    let path = runner.read().await.path();
+    // when the track changed, its metadata is sent once it settled
+    let mut changed_at: Option<Instant> = None;
 
     loop {
         interval.tick().await;
         let guard = runner.read().await;
 
-        if playback != guard.playback() {
+        if playback != guard.playback() || stopped != guard.stopped() {
             playback = guard.playback();
-            properties.push(Property::Playing(playback));
+            stopped = guard.stopped();
+            properties.push(if stopped {
+                Property::Stopped
+            } else {
+                Property::Playing(playback)
+            });
         }
         if volume != guard.volume() {
             volume = guard.volume();
             properties.push(Property::Volume(volume))
         }
 
+        let time = guard.time();
+        // remote controls, like bluetooth devices, move the position on their own while playing
+        // and only need to know when it jumps
+        if let Some((last, at)) = last_position {
+            let expected = if playback {
+                last + at.elapsed().as_secs_f64()
+            } else {
+                last
+            };
+            if index == guard.index() && (time.position - expected).abs() > SEEK_TOLERANCE {
+                properties.push(Property::Seeked(time.position));
+            }
+        }
+        last_position = Some((time.position, Instant::now()));
+        if position != time.position as u64 {
+            position = time.position as u64;
+            properties.push(Property::Position(time.position, time.length));
+        }
+
         if index != guard.index() {
             index = guard.index();
-            let track_name = &guard.current_track().await.unwrap();
-            let mut path_buf = PathBuf::new();
-            path_buf.push(&path);
-            path_buf.push(track_name);
-            let track = MusicTrack::new(path_buf.to_str().unwrap())
-                .expect("can't get track for currently playing song");
-            let meta = track.get_meta();
-            let image = tokio::task::spawn_blocking(|| get_image(path_buf))
-                .await
-                .unwrap();
-            let image_path = if image.is_empty() {
-                None
-            } else {
-                tmp.rewind().expect("can't rewind tmp file");
-                tmp.write_all(&image)
-                    .expect("can't write image data to tmp file");
-                Some(tmp.path().to_str().unwrap().to_string())
+            changed_at = Some(Instant::now());
+        }
+        let item = if changed_at.is_some_and(|at| at.elapsed() >= TRACK_SETTLE) {
+            changed_at = None;
+            guard.current_item().await
+        } else {
+            None
+        };
+        // the tags are read without holding the runner, the track that can't be read has no
+        // metadata
+        drop(guard);
+        if let Some(item) = item {
+            let metadata = match item.path().map(Path::to_path_buf) {
+                Some(path) => {
+                    let p = path.clone();
+                    let meta = tokio::task::spawn_blocking(move || {
+                        MusicTrack::new(p.to_str()?).ok()?.get_meta().ok()
+                    })
+                    .await
+                    .ok()
+                    .flatten();
+                    match meta {
+                        Some(meta) => Some((meta, cover_path(path).await)),
+                        None => None,
+                    }
+                }
+                // the tracks without a file only have a name
+                None => Some((n_audio::Metadata::default(), None)),
             };
-            if let Ok(meta) = meta {
-                properties.push(Property::Metadata(Metadata {
-                    id: String::from("/n_music"),
-                    title: Some(if !meta.title.is_empty() {
-                        meta.title
-                    } else {
-                        remove_ext(track_name)
-                    }),
-                    artists: if meta.artist.is_empty() {
-                        None
-                    } else {
-                        Some(vec![meta.artist])
-                    },
-                    length: meta.time.length,
-                    image_path,
-                }));
+            if let Some((meta, image_path)) = metadata {
+                properties.push(Property::Metadata(Metadata::new(
+                    &item, index, meta, image_path,
+                )));
             }
         }
 
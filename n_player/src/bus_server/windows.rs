@@ -0,0 +1,242 @@
+use crate::bus_server::Property;
+use crate::runner::RunnerMessage;
+use crate::{Localization, MainWindow};
+use flume::Sender;
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use slint::ComponentHandle;
+use std::cell::RefCell;
+use std::time::Duration;
+use windows::core::w;
+use windows::Win32::Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM};
+use windows::Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER};
+use windows::Win32::UI::Shell::{
+    DefSubclassProc, ITaskbarList3, SetWindowSubclass, TaskbarList, TBPF_NOPROGRESS, TBPF_NORMAL,
+    TBPF_PAUSED, THBF_ENABLED, THB_FLAGS, THB_ICON, THB_TOOLTIP, THUMBBUTTON,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CreateIcon, RegisterWindowMessageW, HICON, WM_COMMAND,
+};
+
+const PREVIOUS: usize = 0;
+const TOGGLE_PAUSE: usize = 1;
+const NEXT: usize = 2;
+const THBN_CLICKED: usize = 0x1800;
+const ICON_SIZE: i32 = 16;
+
+thread_local! {
+    // COM objects and slint timers can't leave the UI thread
+    static TASKBAR: RefCell<Option<Taskbar>> = const { RefCell::new(None) };
+    static UPDATER: RefCell<Option<slint::Timer>> = const { RefCell::new(None) };
+}
+
+/// Starts updating the taskbar button of `window` with the progress of the current track and
+/// adds previous/play-pause/next buttons to its thumbnail, must be called on the UI thread
+///
+/// Returns where to send the changed properties, they are applied on the UI thread
+pub fn run(window: &MainWindow, tx: Sender<RunnerMessage>) -> Sender<Property> {
+    let (tx_properties, rx_properties) = flume::unbounded();
+    let window = window.as_weak();
+    let timer = slint::Timer::default();
+    timer.start(
+        slint::TimerMode::Repeated,
+        Duration::from_millis(250),
+        move || {
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            TASKBAR.with(|taskbar| {
+                let Ok(mut taskbar) = taskbar.try_borrow_mut() else {
+                    return;
+                };
+                // the native window only exists once the window is shown
+                if taskbar.is_none() {
+                    *taskbar = Taskbar::new(&window, tx.clone());
+                }
+                if let Some(taskbar) = taskbar.as_mut() {
+                    while let Ok(property) = rx_properties.try_recv() {
+                        taskbar.update(property);
+                    }
+                }
+            });
+        },
+    );
+    UPDATER.with(|updater| *updater.borrow_mut() = Some(timer));
+    tx_properties
+}
+
+struct Taskbar {
+    list: ITaskbarList3,
+    hwnd: HWND,
+    tx: Sender<RunnerMessage>,
+    button_created: u32,
+    icons: [HICON; 4],
+    tips: [String; 4],
+    playing: bool,
+}
+
+impl Taskbar {
+    fn new(window: &MainWindow, tx: Sender<RunnerMessage>) -> Option<Self> {
+        let handle = window.window().window_handle();
+        let RawWindowHandle::Win32(handle) = handle.window_handle().ok()?.as_raw() else {
+            return None;
+        };
+        let hwnd = HWND(handle.hwnd.get() as *mut _);
+        let localization = window.global::<Localization>();
+        unsafe {
+            let list: ITaskbarList3 =
+                CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER).ok()?;
+            list.HrInit().ok()?;
+            let taskbar = Self {
+                list,
+                hwnd,
+                tx,
+                button_created: RegisterWindowMessageW(w!("TaskbarButtonCreated")),
+                icons: [
+                    icon(|x, y| next_icon(ICON_SIZE as f32 - x, y)),
+                    icon(play_icon),
+                    icon(pause_icon),
+                    icon(next_icon),
+                ],
+                tips: [
+                    localization.get_previous().into(),
+                    localization.get_play().into(),
+                    localization.get_pause().into(),
+                    localization.get_next().into(),
+                ],
+                playing: false,
+            };
+            let _ = SetWindowSubclass(hwnd, Some(subclass_proc), 0, 0);
+            // fails when the taskbar button doesn't exist yet, subclass_proc retries once it does
+            taskbar.add_buttons();
+            Some(taskbar)
+        }
+    }
+
+    fn buttons(&self) -> [THUMBBUTTON; 3] {
+        let toggle = if self.playing { 2 } else { 1 };
+        [
+            button(PREVIOUS, self.icons[0], &self.tips[0]),
+            button(TOGGLE_PAUSE, self.icons[toggle], &self.tips[toggle]),
+            button(NEXT, self.icons[3], &self.tips[3]),
+        ]
+    }
+
+    fn add_buttons(&self) {
+        unsafe {
+            let _ = self.list.ThumbBarAddButtons(self.hwnd, &self.buttons());
+        }
+    }
+
+    fn update(&mut self, property: Property) {
+        unsafe {
+            match property {
+                Property::Playing(playing) => {
+                    self.playing = playing;
+                    let state = if playing { TBPF_NORMAL } else { TBPF_PAUSED };
+                    let _ = self.list.SetProgressState(self.hwnd, state);
+                    let _ = self.list.ThumbBarUpdateButtons(self.hwnd, &self.buttons());
+                }
+                Property::Stopped => {
+                    self.playing = false;
+                    let _ = self.list.SetProgressState(self.hwnd, TBPF_NOPROGRESS);
+                    let _ = self.list.ThumbBarUpdateButtons(self.hwnd, &self.buttons());
+                }
+                Property::Position(position, length) => {
+                    if length > 0.0 {
+                        let _ = self.list.SetProgressValue(
+                            self.hwnd,
+                            (position * 1000.0) as u64,
+                            (length * 1000.0) as u64,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+unsafe extern "system" fn subclass_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+    _id: usize,
+    _data: usize,
+) -> LRESULT {
+    TASKBAR.with(|taskbar| {
+        let Ok(taskbar) = taskbar.try_borrow() else {
+            return;
+        };
+        let Some(taskbar) = taskbar.as_ref() else {
+            return;
+        };
+        if msg == WM_COMMAND && (wparam.0 >> 16) & 0xffff == THBN_CLICKED {
+            let message = match wparam.0 & 0xffff {
+                PREVIOUS => RunnerMessage::PlayPrevious,
+                TOGGLE_PAUSE => RunnerMessage::TogglePause,
+                NEXT => RunnerMessage::PlayNext,
+                _ => return,
+            };
+            let _ = taskbar.tx.send(message);
+        } else if msg == taskbar.button_created {
+            taskbar.add_buttons();
+        }
+    });
+    DefSubclassProc(hwnd, msg, wparam, lparam)
+}
+
+fn button(id: usize, icon: HICON, tip: &str) -> THUMBBUTTON {
+    let mut sz_tip = [0; 260];
+    for (c, tip) in sz_tip.iter_mut().zip(tip.encode_utf16().take(259)) {
+        *c = tip;
+    }
+    THUMBBUTTON {
+        dwMask: THB_ICON | THB_TOOLTIP | THB_FLAGS,
+        iId: id as u32,
+        iBitmap: 0,
+        hIcon: icon,
+        szTip: sz_tip,
+        dwFlags: THBF_ENABLED,
+    }
+}
+
+/// Draws a white icon, `inside` tells if the center of a pixel is part of the shape
+fn icon(inside: impl Fn(f32, f32) -> bool) -> HICON {
+    let mut color = vec![0u8; (ICON_SIZE * ICON_SIZE * 4) as usize];
+    for (i, pixel) in color.chunks_exact_mut(4).enumerate() {
+        let x = (i as i32 % ICON_SIZE) as f32 + 0.5;
+        let y = (i as i32 / ICON_SIZE) as f32 + 0.5;
+        if inside(x, y) {
+            pixel.copy_from_slice(&[255, 255, 255, 255]);
+        }
+    }
+    // the alpha channel of the color bits is used, so the mask is left empty
+    let mask = vec![0u8; (ICON_SIZE * ICON_SIZE / 8) as usize];
+    unsafe {
+        CreateIcon(
+            HINSTANCE::default(),
+            ICON_SIZE,
+            ICON_SIZE,
+            1,
+            32,
+            mask.as_ptr(),
+            color.as_ptr(),
+        )
+        .unwrap_or_default()
+    }
+}
+
+fn play_icon(x: f32, y: f32) -> bool {
+    (4.0..=13.0).contains(&x) && (y - 8.0).abs() <= 5.0 * (13.0 - x) / 9.0
+}
+
+fn pause_icon(x: f32, y: f32) -> bool {
+    ((4.0..7.0).contains(&x) || (9.0..12.0).contains(&x)) && (3.0..13.0).contains(&y)
+}
+
+fn next_icon(x: f32, y: f32) -> bool {
+    let triangle = (3.0..=11.0).contains(&x) && (y - 8.0).abs() <= 5.0 * (11.0 - x) / 8.0;
+    let bar = (11.0..13.0).contains(&x) && (3.0..13.0).contains(&y);
+    triangle || bar
+}
@@ -0,0 +1,73 @@
+//! Local copies of the next tracks, so a library on a network share keeps playing when the
+//! connection drops for a while
+//!
+//! The copies are read in place of the tracks through [n_audio::storage::set_cached]
+
+use n_audio::storage;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Copies the tracks at `paths` into `dir`, then removes the copies used the longest time ago
+/// until the ones left take at most `limit` bytes
+///
+/// It blocks until the tracks are copied, it has to be called outside of the async tasks
+pub fn cache_tracks(dir: &Path, paths: &[PathBuf], limit: u64) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let mut copies = vec![];
+    for path in paths {
+        let path = path.to_string_lossy();
+        let copy = dir.join(copy_name(&path));
+        let size = storage::size(&path)?;
+        if fs::metadata(&copy).is_ok_and(|metadata| metadata.len() == size) {
+            File::options()
+                .write(true)
+                .open(&copy)?
+                .set_modified(SystemTime::now())?;
+        } else {
+            // copied under another name first, so a copy cut short is never read
+            let part = dir.join(format!("{}.part", copy_name(&path)));
+            io::copy(&mut storage::open(&path)?, &mut File::create(&part)?)?;
+            fs::rename(&part, &copy)?;
+        }
+        storage::set_cached(&path, Some(copy.clone()));
+        copies.push(copy);
+    }
+    evict(dir, &copies, limit)
+}
+
+/// The hash of the path with the same extension, so the format can still be told from it
+fn copy_name(path: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    match Path::new(path).extension() {
+        Some(extension) => format!("{:016x}.{}", hasher.finish(), extension.to_string_lossy()),
+        None => format!("{:016x}", hasher.finish()),
+    }
+}
+
+fn evict(dir: &Path, kept: &[PathBuf], limit: u64) -> io::Result<()> {
+    let mut copies = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            Some((entry.path(), metadata.len(), metadata.modified().ok()?))
+        })
+        .collect::<Vec<_>>();
+    copies.sort_by_key(|(_, _, modified)| *modified);
+    let mut total = copies.iter().map(|(_, size, _)| size).sum::<u64>();
+    for (copy, size, _) in copies {
+        if total <= limit {
+            break;
+        }
+        // the ones still open can't be removed on Windows, they're tried again next time
+        if !kept.contains(&copy) && fs::remove_file(&copy).is_ok() {
+            storage::remove_copy(&copy);
+            total -= size;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Searches releases on [Discogs](https://www.discogs.com) for the albums and the genres of the
+//! tracks
+//!
+//! The database can only be searched with the personal access token of an account, made in its
+//! developer settings, and with at most 60 requests a minute
+
+use crate::metadata::MetadataProvider;
+use n_audio::Metadata;
+use serde::Deserialize;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const API: &str = "https://api.discogs.com";
+const USER_AGENT: &str = concat!(
+    "n_music/",
+    env!("CARGO_PKG_VERSION"),
+    " +https://github.com/Enn3Developer/n_music"
+);
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+/// When the last request was made, the ones made at the same time wait for their turn
+static LAST_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[derive(Deserialize)]
+struct SearchResult {
+    results: Vec<SearchedRelease>,
+}
+
+#[derive(Deserialize)]
+struct SearchedRelease {
+    /// Like "Artist - Album"
+    title: String,
+    #[serde(default)]
+    year: String,
+    #[serde(default)]
+    genre: Vec<String>,
+}
+
+/// Fills the album, the year and the genre from the first release with a track of the same title
+/// by the same artist
+pub struct DiscogsProvider {
+    pub token: String,
+}
+
+impl MetadataProvider for DiscogsProvider {
+    fn name(&self) -> &'static str {
+        "Discogs"
+    }
+
+    fn provide(&self, _: &Path, known: &Metadata) -> io::Result<Metadata> {
+        if known.title.is_empty() || known.artist.is_empty() {
+            return Ok(Metadata::default());
+        }
+        {
+            let mut last = LAST_REQUEST.lock().unwrap();
+            if let Some(elapsed) = last.map(|at| at.elapsed()) {
+                std::thread::sleep(REQUEST_INTERVAL.saturating_sub(elapsed));
+            }
+            *last = Some(Instant::now());
+        }
+        let result: SearchResult = ureq::get(&format!("{API}/database/search"))
+            .set("User-Agent", USER_AGENT)
+            .set("Authorization", &format!("Discogs token={}", self.token))
+            .timeout(Duration::from_secs(30))
+            .query("type", "release")
+            .query("artist", &known.artist)
+            .query("track", &known.title)
+            .query("per_page", "5")
+            .call()
+            .map_err(io::Error::other)?
+            .into_json()?;
+        let Some(release) = result.results.into_iter().next() else {
+            return Ok(Metadata::default());
+        };
+        let album = release
+            .title
+            .split_once(" - ")
+            .map_or(release.title.as_str(), |(_, album)| album);
+        Ok(Metadata {
+            album: album.to_string(),
+            genre: release.genre.into_iter().next().unwrap_or_default(),
+            date: release.year,
+            ..Default::default()
+        })
+    }
+}
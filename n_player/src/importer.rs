@@ -0,0 +1,117 @@
+//! Reads the play counts kept by other players, so they can be brought over to the library
+//!
+//! The `Library.xml` exported by iTunes and Apple Music and the `rhythmdb.xml` of Rhythmbox are
+//! supported
+
+use percent_encoding::percent_decode_str;
+use roxmltree::{Document, Node};
+use std::path::PathBuf;
+
+/// A track as another player knows it
+#[derive(Clone, Debug, Default)]
+pub struct ForeignTrack {
+    pub path: Option<PathBuf>,
+    pub title: String,
+    pub artist: String,
+    pub plays: u32,
+    /// Unix timestamp, 0 when it's unknown
+    pub last_played: u64,
+}
+
+/// Reads the tracks of a library exported by another player, telling its format from its root
+pub fn read_library(xml: &str) -> Result<Vec<ForeignTrack>, String> {
+    let document = Document::parse(xml).map_err(|e| e.to_string())?;
+    let root = document.root_element();
+    match root.tag_name().name() {
+        "plist" => Ok(read_itunes(root)),
+        "rhythmdb" => Ok(read_rhythmbox(root)),
+        name => Err(format!("unknown library format <{name}>")),
+    }
+}
+
+/// The tracks are the dictionaries inside the `Tracks` dictionary of the plist
+fn read_itunes(root: Node) -> Vec<ForeignTrack> {
+    let Some(library) = root.children().find(|node| node.has_tag_name("dict")) else {
+        return vec![];
+    };
+    let Some(tracks) = dict_value(library, "Tracks") else {
+        return vec![];
+    };
+    tracks
+        .children()
+        .filter(|node| node.has_tag_name("dict"))
+        .map(|track| {
+            let text = |key: &str| {
+                dict_value(track, key)
+                    .and_then(|value| value.text())
+                    .unwrap_or_default()
+            };
+            ForeignTrack {
+                path: file_url_path(text("Location")),
+                title: text("Name").to_string(),
+                artist: text("Artist").to_string(),
+                plays: text("Play Count").parse().unwrap_or_default(),
+                last_played: parse_date(text("Play Date UTC")).unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+/// Returns the element after `<key>key</key>` in a plist dictionary
+fn dict_value<'a, 'input>(dict: Node<'a, 'input>, key: &str) -> Option<Node<'a, 'input>> {
+    dict.children()
+        .find(|node| node.has_tag_name("key") && node.text() == Some(key))?
+        .next_sibling_element()
+}
+
+fn read_rhythmbox(root: Node) -> Vec<ForeignTrack> {
+    root.children()
+        .filter(|node| node.has_tag_name("entry") && node.attribute("type") == Some("song"))
+        .map(|entry| {
+            let text = |name: &str| {
+                entry
+                    .children()
+                    .find(|node| node.has_tag_name(name))
+                    .and_then(|node| node.text())
+                    .unwrap_or_default()
+            };
+            ForeignTrack {
+                path: file_url_path(text("location")),
+                title: text("title").to_string(),
+                artist: text("artist").to_string(),
+                plays: text("play-count").parse().unwrap_or_default(),
+                last_played: text("last-played").parse().unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+/// Turns a `file://` URL into a path, Windows drives included
+fn file_url_path(url: &str) -> Option<PathBuf> {
+    let path = url.strip_prefix("file://")?;
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+    let path = percent_decode_str(path).decode_utf8().ok()?;
+    // file:///C:/Music/...
+    let path = match path.as_bytes() {
+        [b'/', _, b':', ..] => &path[1..],
+        _ => &path[..],
+    };
+    Some(PathBuf::from(path))
+}
+
+/// Parses a UTC date like `2021-03-04T05:06:07Z` into a unix timestamp
+fn parse_date(date: &str) -> Option<u64> {
+    let (date, time) = date.trim_end_matches('Z').split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let mut time = time.splitn(3, ':').map(|part| part.parse::<i64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    // days_from_civil by Howard Hinnant
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    u64::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok()
+}
@@ -1,36 +1,71 @@
+use crate::musicbrainz::ReleaseTags;
 use bitcode::{Decode, Encode};
-#[cfg(target_os = "android")]
 use flume::{Receiver, RecvError, SendError, Sender, TryRecvError};
-use multitag::data::Picture;
+use id3::frame::{ExtendedText, Popularimeter, UniqueFileIdentifier};
+use id3::TagLike;
+use mp4ameta::{Data, FreeformIdent};
+use multitag::data::{Album, Picture};
 use multitag::Tag;
+use n_audio::dsp::{DspSettings, BANDS};
+use n_audio::loudness::{GainMode, ReplayGain};
 use n_audio::queue::QueuePlayer;
-#[cfg(target_os = "android")]
+use n_audio::storage;
+use n_audio::{Metadata, SampleFormat};
 use once_cell::sync::Lazy;
+use rimage::codecs::mozjpeg::MozJpegEncoder;
+use rimage::codecs::webp::WebPDecoder;
+use rimage::operations::resize::{FilterType, ResizeAlg};
+use serde::{Deserialize, Serialize};
 use slint::private_unstable_api::re_exports::ColorScheme;
-use slint::SharedPixelBuffer;
+use slint::{Color, SharedPixelBuffer, SharedString};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
 use std::ffi::OsStr;
 use std::fmt::Debug;
-use std::path::Path;
+use std::fs::{File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zune_core::bytestream::ZCursor;
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::DecoderOptions;
+use zune_image::image::Image;
+use zune_image::traits::{DecoderTrait, EncoderTrait, OperationsTrait};
+use zune_imageprocs::crop::Crop;
 
 slint::include_modules!();
 
 pub mod app;
 pub mod bus_server;
+pub mod cache;
+pub mod discogs;
+pub mod importer;
 pub mod localization;
+pub mod metadata;
+pub mod musicbrainz;
 pub mod platform;
+pub mod plugins;
 pub mod runner;
 pub mod settings;
+#[cfg(not(target_os = "android"))]
+pub mod tray;
 
 unsafe impl Send for TrackData {}
 unsafe impl Sync for TrackData {}
 
-#[cfg(target_os = "android")]
 pub struct SenderReceiver<M> {
     tx: Sender<M>,
     rx: Receiver<M>,
 }
 
-#[cfg(target_os = "android")]
+impl<M> Default for SenderReceiver<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<M> SenderReceiver<M> {
     pub fn new() -> Self {
         let (tx, rx) = flume::unbounded();
@@ -58,6 +93,7 @@ impl<M> SenderReceiver<M> {
     }
 }
 
+pub static TOASTS: Lazy<SenderReceiver<Toast>> = Lazy::new(|| SenderReceiver::new());
 #[cfg(target_os = "android")]
 pub static ANDROID_RX: Lazy<SenderReceiver<MessageRustToAndroid>> =
     Lazy::new(|| SenderReceiver::new());
@@ -65,11 +101,97 @@ pub static ANDROID_RX: Lazy<SenderReceiver<MessageRustToAndroid>> =
 pub static ANDROID_TX: Lazy<SenderReceiver<MessageAndroidToRust>> =
     Lazy::new(|| SenderReceiver::new());
 
+/// File the errors are written to, see [open_log]
+static LOG: Lazy<std::sync::Mutex<Option<File>>> = Lazy::new(|| std::sync::Mutex::new(None));
+/// Logs bigger than this in bytes are started over when they're opened
+const LOG_SIZE: u64 = 1024 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct Toast {
+    pub message: String,
+    pub error: bool,
+    /// Shown in a dialog leading to the log instead of a toast
+    pub report: bool,
+    /// Comes with a button that undoes the last change of the queue
+    pub undo: bool,
+}
+
+/// Shows a confirmation to the user as a toast
+pub fn toast_info<S: Into<String>>(message: S) {
+    let _ = TOASTS.send(Toast {
+        message: message.into(),
+        error: false,
+        report: false,
+        undo: false,
+    });
+}
+
+/// Shows a recoverable error to the user as a toast, it's also printed to stderr and to the log
+pub fn toast_error<S: Into<String>>(message: S) {
+    let message = message.into();
+    log(&message);
+    let _ = TOASTS.send(Toast {
+        message,
+        error: true,
+        report: false,
+        undo: false,
+    });
+}
+
+/// Shows a change of the queue to the user as a toast, with a button that undoes it
+pub fn toast_undo<S: Into<String>>(message: S) {
+    let _ = TOASTS.send(Toast {
+        message: message.into(),
+        error: false,
+        report: false,
+        undo: true,
+    });
+}
+
+/// Shows an error that keeps a feature from working in a dialog, with a way to get to the log
+/// since stderr isn't seen on Windows
+pub fn report_error<S: Into<String>>(message: S) {
+    let message = message.into();
+    log(&message);
+    let _ = TOASTS.send(Toast {
+        message,
+        error: true,
+        report: true,
+        undo: false,
+    });
+}
+
+/// Writes the errors to the file at `path` from now on, after the ones of the last times the app
+/// ran
+pub fn open_log(path: &Path) {
+    let append = std::fs::metadata(path).is_ok_and(|metadata| metadata.len() < LOG_SIZE);
+    let file = OpenOptions::new()
+        .create(true)
+        .append(append)
+        .write(true)
+        .truncate(!append)
+        .open(path);
+    match file {
+        Ok(file) => *LOG.lock().unwrap() = Some(file),
+        Err(e) => eprintln!("can't open the log {}: {e}", path.display()),
+    }
+}
+
+/// Prints a message to stderr and to the log opened with [open_log]
+pub fn log(message: &str) {
+    eprintln!("{message}");
+    if let Some(file) = LOG.lock().unwrap().as_mut() {
+        let _ = writeln!(file, "[{}] {message}", now());
+    }
+}
+
 #[cfg(target_os = "android")]
 pub enum MessageAndroidToRust {
     Directory(String),
     File(String),
     Start(jni::JavaVM, jni::objects::GlobalRef),
+    /// A command from the media session, like the lockscreen or a bluetooth device
+    Runner(crate::runner::RunnerMessage),
 }
 #[cfg(target_os = "android")]
 pub enum MessageRustToAndroid {
@@ -85,6 +207,7 @@ fn android_main(app: slint::android::AndroidApp) {
     use crate::platform::Platform;
     use crate::settings::Settings;
     use slint::platform::WindowEvent;
+    use slint::ComponentHandle;
     use std::sync::Arc;
 
     slint::android::init(app.clone()).unwrap();
@@ -107,8 +230,12 @@ fn android_main(app: slint::android::AndroidApp) {
             let settings = Arc::new(tokio::sync::Mutex::new(
                 Settings::read_saved(platform.lock().await).await,
             ));
-            if !Path::new(&settings.lock().await.path).exists() {
+            if !storage::exists(&settings.lock().await.path) {
                 let window = AndroidWindow::new().unwrap();
+                crate::localization::localize(
+                    settings.lock().await.locale.clone(),
+                    window.global::<Localization>(),
+                );
                 let handle = window.as_weak();
                 let settings = settings.clone();
                 let platform = platform.clone();
@@ -161,14 +288,226 @@ pub fn get_image<P: AsRef<Path> + Debug>(path: P) -> Vec<u8> {
     vec![]
 }
 
+/// Size of the covers shown in the track list
+pub const COVER_SIZE: usize = 128;
+
+/// How long a track has to stay the playing one before its cover, its waveform and its metadata
+/// are read, so skipping through many tracks quickly only loads the last one
+pub const TRACK_SETTLE: Duration = Duration::from_millis(600);
+
+/// Extensions of the files the file dialogs show when they ask for music
+pub const AUDIO_EXTENSIONS: [&str; 12] = [
+    "mp3", "flac", "ogg", "oga", "opus", "wav", "m4a", "aac", "aif", "aiff", "mka", "webm",
+];
+
+/// Decodes a cover, crops it to a square and resizes it to `size`x`size`
+///
+/// Returns the RGB data of the resulting image or an empty vec if it can't be decoded
+pub fn decode_cover(image: &[u8], size: usize) -> Vec<u8> {
+    if let Some(mut zune_image) = read_image(image) {
+        let (width, height) = zune_image.dimensions();
+        if width != height {
+            let difference = width.abs_diff(height);
+            let min = width.min(height);
+            let is_height = height < width;
+            let x = if is_height { difference / 2 } else { 0 };
+            let y = if !is_height { difference / 2 } else { 0 };
+            Crop::new(min, min, x, y).execute(&mut zune_image).unwrap();
+        }
+        rimage::operations::resize::Resize::new(
+            size,
+            size,
+            ResizeAlg::Convolution(FilterType::Hamming),
+        )
+        .execute(&mut zune_image)
+        .unwrap();
+        zune_image.flatten_to_u8()[0].clone()
+    } else {
+        vec![]
+    }
+}
+
+/// Covers decoded by [decode_cover] at one size, shared by the threads that decode them
+///
+/// The tracks of an album usually embed the same image, so it's decoded once for all of them, and
+/// only the `capacity` most recently used covers are kept
+pub struct CoverCache {
+    size: usize,
+    capacity: usize,
+    // keyed by the hash of the encoded image, the most recently used last
+    recent: std::sync::Mutex<(VecDeque<u64>, HashMap<u64, Vec<u8>>)>,
+}
+
+impl CoverCache {
+    pub fn new(size: usize, capacity: usize) -> Self {
+        Self {
+            size,
+            capacity,
+            recent: Default::default(),
+        }
+    }
+
+    /// Decodes `image` like [decode_cover] unless it's one of the recent ones
+    ///
+    /// It blocks while decoding, the other threads can still use the cache in the meantime
+    pub fn decode(&self, image: &[u8]) -> Vec<u8> {
+        if image.is_empty() {
+            return vec![];
+        }
+        let mut hasher = DefaultHasher::new();
+        image.hash(&mut hasher);
+        let key = hasher.finish();
+        {
+            let (order, covers) = &mut *self.recent.lock().unwrap();
+            if let Some(cover) = covers.get(&key) {
+                order.retain(|recent| *recent != key);
+                order.push_back(key);
+                return cover.clone();
+            }
+        }
+        let cover = decode_cover(image, self.size);
+        let (order, covers) = &mut *self.recent.lock().unwrap();
+        if covers.insert(key, cover.clone()).is_none() {
+            order.push_back(key);
+            while order.len() > self.capacity {
+                if let Some(oldest) = order.pop_front() {
+                    covers.remove(&oldest);
+                }
+            }
+        }
+        cover
+    }
+}
+
+/// Decodes an image in any of the formats covers come in, converted to RGB
+fn read_image(image: &[u8]) -> Option<Image> {
+    if image.is_empty() {
+        return None;
+    }
+
+    let mut zune_image =
+        if let Ok(image) = Image::read(ZCursor::new(image), DecoderOptions::new_fast()) {
+            image
+        } else {
+            WebPDecoder::try_new(Cursor::new(image))
+                .ok()?
+                .decode()
+                .ok()?
+        };
+    zune_image.convert_color(ColorSpace::RGB).ok()?;
+    Some(zune_image)
+}
+
+/// Names of the images, without the extension, that are taken as the cover of the tracks in
+/// their folder
+const FOLDER_COVER_NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+const IMAGE_EXTENSIONS: [&str; 5] = ["jpg", "jpeg", "png", "webp", "bmp"];
+
+/// Returns the image in `folder` that's the cover of its tracks, like `folder.jpg`
+///
+/// When none of the images has one of the usual names, the only image of the folder is taken
+pub fn folder_cover<P: AsRef<Path>>(folder: P) -> Option<PathBuf> {
+    let images = std::fs::read_dir(folder)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect::<Vec<_>>();
+    let named = images.iter().find(|path| {
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| FOLDER_COVER_NAMES.contains(&stem.to_lowercase().as_str()))
+    });
+    match (named, images.as_slice()) {
+        (Some(named), _) => Some(named.clone()),
+        ([only], _) => Some(only.clone()),
+        _ => None,
+    }
+}
+
+/// Re-encodes a cover as a JPEG at most `size` pixels wide and high, to be embedded in the tags
+pub fn encode_cover(image: &[u8], size: usize) -> Option<Vec<u8>> {
+    let mut zune_image = read_image(image)?;
+    let (width, height) = zune_image.dimensions();
+    if width > size || height > size {
+        let scale = size as f64 / width.max(height) as f64;
+        let width = ((width as f64 * scale).round() as usize).max(1);
+        let height = ((height as f64 * scale).round() as usize).max(1);
+        rimage::operations::resize::Resize::new(
+            width,
+            height,
+            ResizeAlg::Convolution(FilterType::Hamming),
+        )
+        .execute(&mut zune_image)
+        .ok()?;
+    }
+    let mut data = vec![];
+    MozJpegEncoder::new().encode(&zune_image, &mut data).ok()?;
+    Some(data)
+}
+
+/// Writes a JPEG as the front cover of a track, replacing the one it had
+pub fn embed_cover<P: AsRef<Path>>(path: P, jpeg: Vec<u8>) -> multitag::Result<()> {
+    let mut tag = Tag::read_from_path(path.as_ref())?;
+    tag.set_album_info(Album {
+        cover: Some(Picture {
+            data: jpeg,
+            mime_type: "image/jpeg".to_string(),
+        }),
+        ..Default::default()
+    })?;
+    tag.write_to_path(path)
+}
+
+/// Adds the audio files inside `path` to the player in a random order, the folder is only walked
+/// when there are no `cached` files from the last time
 pub async fn add_all_tracks_to_player<P: AsRef<Path> + AsRef<OsStr> + From<String>>(
     player: &mut QueuePlayer,
     path: P,
+    cached: Vec<String>,
 ) {
-    if let Ok(mut dir) = tokio::fs::read_dir(path).await {
-        let mut paths = vec![];
+    let paths = if cached.is_empty() {
+        library_files(&path).await
+    } else {
+        cached
+    };
+    player.add_all(paths).await.unwrap();
+    player.shrink_to_fit();
+
+    player.shuffle();
+}
+
+/// Returns the audio files inside `path` and its subfolders
+pub async fn library_files<P: AsRef<Path> + AsRef<OsStr>>(path: P) -> Vec<String> {
+    let mut paths = vec![];
+    let mut dirs = vec![Path::new(&path).to_path_buf()];
+    // folders picked through the Storage Access Framework can't be walked with the filesystem
+    if let Some(root) = Path::new(&path).to_str() {
+        if let Some(storage) = storage::get(root) {
+            match storage.audio_files(root) {
+                Ok(files) => paths = files,
+                Err(e) => eprintln!("can't list the files in {root}: {e}"),
+            }
+            dirs.clear();
+        }
+    }
+    while let Some(dir) = dirs.pop() {
+        let Ok(mut dir) = tokio::fs::read_dir(dir).await else {
+            continue;
+        };
         while let Ok(Some(file)) = dir.next_entry().await {
-            if file.file_type().await.unwrap().is_file() {
+            let Ok(file_type) = file.file_type().await else {
+                continue;
+            };
+            if file_type.is_dir() {
+                dirs.push(file.path());
+            } else if file_type.is_file() {
                 if let Ok(Some(mime)) = infer::get_from_path(&file.path()) {
                     if mime.mime_type().contains("audio") {
                         let mut p = file.path().to_str().unwrap().to_string();
@@ -178,14 +517,325 @@ pub async fn add_all_tracks_to_player<P: AsRef<Path> + AsRef<OsStr> + From<Strin
                 }
             }
         }
-        player.add_all(paths).await.unwrap();
-        player.shrink_to_fit();
+    }
+    paths
+}
+
+/// Returns the folder of `path` relative to the music directory `root`, with `/` as separator
+/// and an empty string for the music directory itself
+pub fn relative_folder<P: AsRef<Path>>(root: &str, path: P) -> String {
+    path.as_ref()
+        .parent()
+        .and_then(|parent| parent.strip_prefix(root).ok())
+        .map(|folder| {
+            folder
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the folders directly inside `folder`, sorted by name
+pub fn subfolders<'a>(folders: impl Iterator<Item = &'a str>, folder: &str) -> Vec<String> {
+    let mut subfolders = folders
+        .filter_map(|other| {
+            let rest = if folder.is_empty() {
+                other
+            } else {
+                other.strip_prefix(folder)?.strip_prefix('/')?
+            };
+            let name = rest.split('/').next()?;
+            if name.is_empty() {
+                return None;
+            }
+            Some(if folder.is_empty() {
+                name.to_string()
+            } else {
+                format!("{folder}/{name}")
+            })
+        })
+        .collect::<Vec<_>>();
+    subfolders.sort_by(|a, b| a.to_lowercase().cmp(&b.to_lowercase()).then(a.cmp(b)));
+    subfolders.dedup();
+    subfolders
+}
+
+/// Returns whether `folder` is `parent` or one of its subfolders
+pub fn in_folder(folder: &str, parent: &str) -> bool {
+    parent.is_empty()
+        || folder == parent
+        || folder
+            .strip_prefix(parent)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Playback overrides for the tracks of a folder and of its subfolders
+#[derive(Clone, Debug, Default, PartialEq, Decode, Deserialize, Encode, Serialize)]
+pub struct FolderSettings {
+    /// Starts the tracks where they were left, like the chapters of an audiobook
+    pub remember_position: bool,
+    /// Plays the next track of the folder, in the order of the file names, after each of them
+    pub in_order: bool,
+}
+
+/// Returns the overrides of the closest folder among `folders` that `folder` is in
+pub fn folder_settings<'a>(
+    folders: &'a HashMap<String, FolderSettings>,
+    folder: &str,
+) -> Option<&'a FolderSettings> {
+    folders
+        .iter()
+        .filter(|(parent, _)| in_folder(folder, parent))
+        .max_by_key(|(parent, _)| parent.len())
+        .map(|(_, settings)| settings)
+}
+
+/// Pattern used by the file organizer until the user changes it
+pub const DEFAULT_ORGANIZE_PATTERN: &str = "{artist}/{album}/{track} - {title}";
+
+/// Returns where the file organizer moves a track, relative to the music directory and without
+/// the extension
+///
+/// `pattern` can use `{artist}`, `{album}`, `{track}` and `{title}`, every `/` starts a folder.
+/// The tags can't add folders of their own and the missing ones get a placeholder, except for
+/// the track number that is left out
+pub fn organized_path(pattern: &str, metadata: &Metadata) -> String {
+    let tag = |value: &str, fallback: &str| {
+        let value = sanitize_file_name(value);
+        if value.is_empty() {
+            fallback.to_string()
+        } else {
+            value
+        }
+    };
+    let artist = tag(&metadata.artist, "Unknown Artist");
+    let album = tag(&metadata.album, "Unknown Album");
+    let title = tag(&metadata.title, "Unknown Title");
+    let track = metadata
+        .track
+        .map(|track| format!("{track:02}"))
+        .unwrap_or_default();
+    pattern
+        .split('/')
+        .map(|component| {
+            component
+                .replace("{artist}", &artist)
+                .replace("{album}", &album)
+                .replace("{track}", &track)
+                .replace("{title}", &title)
+                // what's left of the separators around a missing track number
+                .trim_matches(|c: char| c.is_whitespace() || c == '-' || c == '.')
+                .to_string()
+        })
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
 
-        player.shuffle();
+/// Replaces the characters that aren't allowed in file names on some platform
+fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Writes the ReplayGain of a track, and of its album when there's one, in its tags
+///
+/// The tags are named as in the ReplayGain 2.0 specification, so other players find them too
+pub fn write_replay_gain<P: AsRef<Path>>(
+    path: P,
+    track: ReplayGain,
+    album: Option<ReplayGain>,
+) -> multitag::Result<()> {
+    let mut values = vec![
+        ("REPLAYGAIN_TRACK_GAIN", format!("{:.2} dB", track.gain)),
+        ("REPLAYGAIN_TRACK_PEAK", format!("{:.6}", track.peak)),
+    ];
+    if let Some(album) = album {
+        values.push(("REPLAYGAIN_ALBUM_GAIN", format!("{:.2} dB", album.gain)));
+        values.push(("REPLAYGAIN_ALBUM_PEAK", format!("{:.6}", album.peak)));
     }
+    // the album tags of an earlier scan would be wrong for a track scanned alone
+    let removed = ["REPLAYGAIN_ALBUM_GAIN", "REPLAYGAIN_ALBUM_PEAK"];
+
+    let mut tag = Tag::read_from_path(path.as_ref())?;
+    match &mut tag {
+        Tag::Id3Tag { inner } => {
+            for key in removed {
+                inner.remove_extended_text(Some(key), None);
+                inner.remove_extended_text(Some(&key.to_lowercase()), None);
+            }
+            for (key, value) in values {
+                inner.remove_extended_text(Some(&key.to_lowercase()), None);
+                inner.add_frame(ExtendedText {
+                    description: key.to_string(),
+                    value,
+                });
+            }
+        }
+        Tag::VorbisFlacTag { inner } => {
+            for key in removed {
+                inner.remove_vorbis(key);
+            }
+            for (key, value) in values {
+                inner.set_vorbis(key, vec![value]);
+            }
+        }
+        Tag::Mp4Tag { inner } => {
+            for key in removed {
+                inner.remove_data_of(&FreeformIdent::new(ITUNES_MEAN, &key.to_lowercase()));
+            }
+            for (key, value) in values {
+                let name = key.to_lowercase();
+                inner.set_data(FreeformIdent::new(ITUNES_MEAN, &name), Data::Utf8(value));
+            }
+        }
+        // Opus has its own gain in the header, the tags are still read by most players
+        Tag::OpusTag { inner } => {
+            for key in removed {
+                inner.remove_entries(key.to_string());
+            }
+            for (key, value) in values {
+                inner.remove_entries(key.to_string());
+                inner.add_one(key.to_string(), value);
+            }
+        }
+    }
+    tag.write_to_path(path)
+}
+
+/// Owner of the UFID frame with the recording ID in ID3 tags, as Picard writes it
+const MUSICBRAINZ_OWNER: &str = "http://musicbrainz.org";
+
+/// Writes the tags a track has on its MusicBrainz release, with the IDs of the release and of
+/// the recording
+pub fn write_release_tags<P: AsRef<Path>>(path: P, tags: &ReleaseTags) -> multitag::Result<()> {
+    let values = [
+        ("TITLE", tags.title.clone()),
+        ("ARTIST", tags.artist.clone()),
+        ("ALBUM", tags.album.clone()),
+        ("ALBUMARTIST", tags.album_artist.clone()),
+        ("TRACKNUMBER", tags.number.to_string()),
+        ("MUSICBRAINZ_ALBUMID", tags.release_id.clone()),
+        ("MUSICBRAINZ_TRACKID", tags.recording_id.clone()),
+    ];
+
+    let mut tag = Tag::read_from_path(path.as_ref())?;
+    match &mut tag {
+        Tag::Id3Tag { inner } => {
+            inner.set_title(&tags.title);
+            inner.set_artist(&tags.artist);
+            inner.set_album(&tags.album);
+            inner.set_album_artist(&tags.album_artist);
+            inner.set_track(tags.number);
+            inner.remove_extended_text(Some("MusicBrainz Album Id"), None);
+            inner.add_frame(ExtendedText {
+                description: "MusicBrainz Album Id".to_string(),
+                value: tags.release_id.clone(),
+            });
+            inner.remove_unique_file_identifier_by_owner_identifier(MUSICBRAINZ_OWNER);
+            inner.add_frame(UniqueFileIdentifier {
+                owner_identifier: MUSICBRAINZ_OWNER.to_string(),
+                identifier: tags.recording_id.clone().into_bytes(),
+            });
+        }
+        Tag::VorbisFlacTag { inner } => {
+            for (key, value) in values {
+                inner.set_vorbis(key, vec![value]);
+            }
+        }
+        Tag::Mp4Tag { inner } => {
+            inner.set_title(&tags.title);
+            inner.set_artist(&tags.artist);
+            inner.set_album(&tags.album);
+            inner.set_album_artist(&tags.album_artist);
+            inner.set_track_number(tags.number.min(u16::MAX as u32) as u16);
+            for (name, value) in [
+                ("MusicBrainz Album Id", &tags.release_id),
+                ("MusicBrainz Track Id", &tags.recording_id),
+            ] {
+                inner.set_data(
+                    FreeformIdent::new(ITUNES_MEAN, name),
+                    Data::Utf8(value.clone()),
+                );
+            }
+        }
+        Tag::OpusTag { inner } => {
+            for (key, value) in values {
+                inner.remove_entries(key.to_string());
+                inner.add_one(key.to_string(), value);
+            }
+        }
+    }
+    tag.write_to_path(path)
+}
+
+/// Where iTunes and the other players keep the freeform tags of MP4 files
+const ITUNES_MEAN: &str = "com.apple.iTunes";
+/// Who the play count belongs to in the popularimeter of ID3 tags
+const POPM_USER: &str = "n_music";
+/// Tag with the play count in the other formats
+const PLAY_COUNT_KEY: &str = "PLAYCOUNT";
+
+/// Reads the play count saved in the tags of a file by [write_play_count]
+pub fn read_play_count<P: AsRef<Path>>(path: P) -> Option<u32> {
+    let count = match Tag::read_from_path(path).ok()? {
+        Tag::Id3Tag { inner } => inner
+            .frames()
+            .filter_map(|frame| frame.content().popularimeter())
+            .find(|popularimeter| popularimeter.user == POPM_USER)?
+            .counter
+            .to_string(),
+        Tag::VorbisFlacTag { inner } => inner.get_vorbis(PLAY_COUNT_KEY)?.next()?.to_string(),
+        Tag::Mp4Tag { inner } => inner
+            .strings_of(&FreeformIdent::new(ITUNES_MEAN, PLAY_COUNT_KEY))
+            .next()?
+            .to_string(),
+        Tag::OpusTag { inner } => inner.get(PLAY_COUNT_KEY.to_string())?.first()?.clone(),
+    };
+    count.trim().parse().ok()
+}
+
+/// Saves the play count of a track in its tags, so it's kept when the library is rebuilt and
+/// other players can read it
+pub fn write_play_count<P: AsRef<Path>>(path: P, count: u32) -> multitag::Result<()> {
+    let mut tag = Tag::read_from_path(path.as_ref())?;
+    match &mut tag {
+        Tag::Id3Tag { inner } => {
+            // the rating of the popularimeter is kept as it is
+            let rating = inner
+                .frames()
+                .filter_map(|frame| frame.content().popularimeter())
+                .find(|popularimeter| popularimeter.user == POPM_USER)
+                .map_or(0, |popularimeter| popularimeter.rating);
+            inner.add_frame(Popularimeter {
+                user: POPM_USER.to_string(),
+                rating,
+                counter: count as u64,
+            });
+        }
+        Tag::VorbisFlacTag { inner } => inner.set_vorbis(PLAY_COUNT_KEY, vec![count.to_string()]),
+        Tag::Mp4Tag { inner } => inner.set_data(
+            FreeformIdent::new(ITUNES_MEAN, PLAY_COUNT_KEY),
+            Data::Utf8(count.to_string()),
+        ),
+        Tag::OpusTag { inner } => {
+            inner.remove_entries(PLAY_COUNT_KEY.to_string());
+            inner.add_one(PLAY_COUNT_KEY.to_string(), count.to_string());
+        }
+    }
+    tag.write_to_path(path)
 }
 
-#[derive(Copy, Clone, Debug, Decode, Encode)]
+#[derive(Copy, Clone, Debug, Decode, Deserialize, Encode, Serialize)]
 pub struct WindowSize {
     pub width: usize,
     pub height: usize,
@@ -200,7 +850,121 @@ impl Default for WindowSize {
     }
 }
 
-#[derive(Copy, Clone, Debug, Default, Decode, Encode)]
+/// What happens to the files imported from outside the music directory
+#[derive(Copy, Clone, Debug, Default, Decode, Deserialize, Encode, Serialize)]
+pub enum ImportMode {
+    #[default]
+    Copy,
+    Move,
+}
+
+impl From<ImportMode> for i32 {
+    fn from(value: ImportMode) -> Self {
+        match value {
+            ImportMode::Copy => 0,
+            ImportMode::Move => 1,
+        }
+    }
+}
+
+impl TryFrom<i32> for ImportMode {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        if value == 0 {
+            Ok(Self::Copy)
+        } else if value == 1 {
+            Ok(Self::Move)
+        } else {
+            Err(format!("{value} is not a valid import mode"))
+        }
+    }
+}
+
+/// How the rows of the track list are put together under headers
+#[derive(Copy, Clone, Debug, Default, PartialEq, Decode, Deserialize, Encode, Serialize)]
+pub enum Grouping {
+    #[default]
+    None,
+    Album,
+    Artist,
+    Letter,
+}
+
+impl From<Grouping> for i32 {
+    fn from(value: Grouping) -> Self {
+        match value {
+            Grouping::None => 0,
+            Grouping::Album => 1,
+            Grouping::Artist => 2,
+            Grouping::Letter => 3,
+        }
+    }
+}
+
+impl TryFrom<i32> for Grouping {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Album),
+            2 => Ok(Self::Artist),
+            3 => Ok(Self::Letter),
+            _ => Err(format!("{value} is not a valid grouping")),
+        }
+    }
+}
+
+/// Name of the group of `track`, the tracks without an album or an artist are together in the
+/// group with an empty name
+pub fn group_name(track: &TrackData, grouping: Grouping) -> String {
+    match grouping {
+        Grouping::None => String::new(),
+        Grouping::Album => track.album.to_string(),
+        Grouping::Artist => track.artist.to_string(),
+        Grouping::Letter => first_letter(&track.title),
+    }
+}
+
+/// The first letter of `name` in uppercase, `#` when it starts with a digit or a symbol
+pub fn first_letter(name: &str) -> String {
+    match name.trim_start().chars().next() {
+        Some(c) if c.is_alphabetic() => c.to_uppercase().collect(),
+        _ => "#".to_string(),
+    }
+}
+
+/// Marks the first track of every group of the list as the one showing its header
+///
+/// Only the consecutive tracks of a group are together, so the list is meant to be sorted by
+/// what it's grouped by. Returns the first row of every letter the groups start with, in the
+/// order they're found, for the strip next to the list
+pub fn group_tracks(tracks: &mut [TrackData], grouping: Grouping) -> Vec<LetterData> {
+    let mut letters: Vec<LetterData> = vec![];
+    if grouping == Grouping::None {
+        return letters;
+    }
+    let mut previous = None;
+    for (row, track) in tracks.iter_mut().enumerate() {
+        let group = SharedString::from(group_name(track, grouping));
+        track.header = previous.as_ref() != Some(&group);
+        if track.header {
+            let letter = first_letter(&group);
+            if letters.iter().all(|data| data.letter != letter) {
+                letters.push(LetterData {
+                    letter: letter.into(),
+                    row: row as i32,
+                });
+            }
+        }
+        track.group = group.clone();
+        previous = Some(group);
+    }
+    letters
+}
+
+#[derive(Copy, Clone, Debug, Default, Decode, Deserialize, Encode, Serialize)]
 pub enum Theme {
     #[default]
     System,
@@ -269,35 +1033,749 @@ impl TryFrom<i32> for Theme {
     }
 }
 
-#[derive(Clone, Debug, Decode, Encode)]
+#[derive(Copy, Clone, Debug, Default, Decode, Deserialize, Encode, Serialize)]
+pub enum Accent {
+    #[default]
+    System,
+    Custom,
+    Cover,
+}
+
+impl From<Accent> for i32 {
+    fn from(value: Accent) -> Self {
+        match value {
+            Accent::System => 0,
+            Accent::Custom => 1,
+            Accent::Cover => 2,
+        }
+    }
+}
+
+impl TryFrom<i32> for Accent {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::System),
+            1 => Ok(Self::Custom),
+            2 => Ok(Self::Cover),
+            _ => Err(format!("{value} is not a valid accent")),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Decode, Deserialize, Encode, Serialize, PartialEq)]
+pub enum Column {
+    Title,
+    Artist,
+    Album,
+    Duration,
+    Plays,
+    Format,
+    Bpm,
+    Genre,
+    Labels,
+    Added,
+}
+
+impl From<Column> for i32 {
+    fn from(value: Column) -> Self {
+        match value {
+            Column::Title => 0,
+            Column::Artist => 1,
+            Column::Album => 2,
+            Column::Duration => 3,
+            Column::Plays => 4,
+            Column::Format => 5,
+            Column::Bpm => 6,
+            Column::Genre => 7,
+            Column::Labels => 8,
+            Column::Added => 9,
+        }
+    }
+}
+
+impl TryFrom<i32> for Column {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Title),
+            1 => Ok(Self::Artist),
+            2 => Ok(Self::Album),
+            3 => Ok(Self::Duration),
+            4 => Ok(Self::Plays),
+            5 => Ok(Self::Format),
+            6 => Ok(Self::Bpm),
+            7 => Ok(Self::Genre),
+            8 => Ok(Self::Labels),
+            9 => Ok(Self::Added),
+            _ => Err(format!("{value} is not a valid column")),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Decode, Deserialize, Encode, Serialize)]
+pub struct ColumnSettings {
+    pub column: Column,
+    pub visible: bool,
+    pub width: f32,
+}
+
+/// Columns of the track list in the order they are shown, with their default widths
+pub fn default_columns() -> Vec<ColumnSettings> {
+    [
+        (Column::Title, true, 250.0),
+        (Column::Artist, true, 180.0),
+        (Column::Album, false, 180.0),
+        (Column::Duration, true, 70.0),
+        (Column::Plays, false, 60.0),
+        (Column::Format, false, 70.0),
+        (Column::Bpm, false, 60.0),
+        (Column::Genre, false, 120.0),
+        (Column::Labels, false, 150.0),
+        (Column::Added, false, 110.0),
+    ]
+    .into_iter()
+    .map(|(column, visible, width)| ColumnSettings {
+        column,
+        visible,
+        width,
+    })
+    .collect()
+}
+
+impl From<ColumnSettings> for ColumnData {
+    fn from(value: ColumnSettings) -> Self {
+        Self {
+            id: value.column.into(),
+            visible: value.visible,
+            width: value.width,
+        }
+    }
+}
+
+/// Returns the uppercase extension of `path`, used as the format of a track
+pub fn file_format<P: AsRef<Path>>(path: P) -> String {
+    path.as_ref()
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_uppercase())
+        .unwrap_or_default()
+}
+
+/// Rules used until the user changes them, see [normalize_genre]
+pub const DEFAULT_GENRE_RULES: &str = "Hip Hop, Rap = Hip-Hop
+RnB, R and B = R&B
+Drum and Bass, DnB = Drum & Bass
+Electronica = Electronic";
+
+/// Cleans up a genre read from the tags
+///
+/// The spaces are trimmed and the genres matching a rule get its name. `rules` has a rule per
+/// line, like `Hip Hop, Rap = Hip-Hop`, genres are the same when their letters and digits are,
+/// ignoring the case. The others get capitalized words when they're all lowercase
+pub fn normalize_genre(genre: &str, rules: &str) -> String {
+    let genre = genre.split_whitespace().collect::<Vec<_>>().join(" ");
+    let key = genre_key(&genre);
+    if key.is_empty() {
+        return genre;
+    }
+    let rule = rules.lines().find_map(|rule| {
+        let (aliases, name) = rule.split_once('=')?;
+        let name = name.trim();
+        let matches = genre_key(name) == key || aliases.split(',').any(|a| genre_key(a) == key);
+        (matches && !name.is_empty()).then_some(name)
+    });
+    if let Some(name) = rule {
+        return name.to_string();
+    }
+    if genre != genre.to_lowercase() {
+        return genre;
+    }
+    let mut previous = ' ';
+    genre
+        .chars()
+        .map(|c| {
+            let starts_word = !previous.is_alphanumeric() && previous != '\'';
+            previous = c;
+            if starts_word {
+                c.to_uppercase().to_string()
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+fn genre_key(genre: &str) -> String {
+    genre
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Replaces the genre in the tags of a file, used to save the ones cleaned up by
+/// [normalize_genre]
+pub fn write_genre<P: AsRef<Path>>(path: P, genre: &str) -> multitag::Result<()> {
+    let mut tag = Tag::read_from_path(path.as_ref())?;
+    match &mut tag {
+        Tag::Id3Tag { inner } => inner.set_genre(genre),
+        Tag::VorbisFlacTag { inner } => inner.set_vorbis("GENRE", vec![genre]),
+        Tag::Mp4Tag { inner } => {
+            // the numbered genres of old files would be read before the new one
+            inner.remove_standard_genres();
+            inner.set_genre(genre);
+        }
+        Tag::OpusTag { inner } => {
+            inner.remove_entries("GENRE".to_string());
+            inner.add_one("GENRE".to_string(), genre.to_string());
+        }
+    }
+    tag.write_to_path(path)
+}
+
+/// Orders two tracks by the value shown in `column`, text is compared case-insensitively
+pub fn compare_tracks(a: &TrackData, b: &TrackData, column: Column) -> Ordering {
+    match column {
+        Column::Title => a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+        Column::Artist => a.artist.to_lowercase().cmp(&b.artist.to_lowercase()),
+        Column::Album => a.album.to_lowercase().cmp(&b.album.to_lowercase()),
+        Column::Duration => a.length.total_cmp(&b.length),
+        Column::Plays => a.plays.cmp(&b.plays),
+        Column::Format => a.format.cmp(&b.format),
+        Column::Bpm => a.bpm.total_cmp(&b.bpm),
+        Column::Genre => a.genre.to_lowercase().cmp(&b.genre.to_lowercase()),
+        Column::Labels => a.labels.to_lowercase().cmp(&b.labels.to_lowercase()),
+        Column::Added => a.added_at.cmp(&b.added_at),
+    }
+}
+
+/// How long a track has to wait before the auto DJ picks it because of its artist, in seconds
+pub const AUTO_DJ_REPEAT: u64 = 60 * 60;
+
+/// Picks the track the auto DJ queues after `current`, `stats` are the ones of every track
+///
+/// A track by the same artist that wasn't played in the last [AUTO_DJ_REPEAT] seconds comes
+/// first, otherwise it's the least recently played track of the library. The tracks skipped more
+/// often than they're played only come when there's nothing else. Ties go to the first track
+/// after `current` in the library order, that is shuffled
+pub fn auto_dj_pick(
+    tracks: &[TrackData],
+    stats: &[PlayStats],
+    current: u16,
+    now: u64,
+) -> Option<u16> {
+    let current = current as usize;
+    let artist = tracks.get(current)?.artist.to_lowercase();
+    let len = tracks.len();
+    let stats_of = |i: &usize| stats.get(*i).copied().unwrap_or_default();
+    let last_played = |i: &usize| stats_of(i).last_played;
+    let liked = |i: &usize| stats_of(i).skips <= stats_of(i).count;
+    let candidates = (1..len)
+        .map(|offset| (current + offset) % len)
+        .filter(|i| !tracks[*i].removed);
+    candidates
+        .clone()
+        .filter(liked)
+        .filter(|i| !artist.is_empty() && tracks[*i].artist.to_lowercase() == artist)
+        .filter(|i| now.saturating_sub(last_played(i)) >= AUTO_DJ_REPEAT)
+        .min_by_key(last_played)
+        .or_else(|| candidates.clone().filter(liked).min_by_key(last_played))
+        .or_else(|| candidates.min_by_key(last_played))
+        .map(|i| i as u16)
+}
+
+/// Returns the tempo range of a search like `bpm:120-140`, or `bpm:128` for a single tempo
+pub fn bpm_range(search: &str) -> Option<(f32, f32)> {
+    let range = search.trim().strip_prefix("bpm:")?.trim();
+    let (min, max) = range.split_once('-').unwrap_or((range, range));
+    let min = min.trim().parse::<f32>().ok()?;
+    let max = max.trim().parse::<f32>().ok()?;
+    // the list shows the tempo rounded
+    Some((min.min(max) - 0.5, min.max(max) + 0.5))
+}
+
+/// Returns the label of a search like `label:workout`, in lowercase
+pub fn searched_label(search: &str) -> Option<String> {
+    let label = search.trim().strip_prefix("label:")?.trim();
+    (!label.is_empty()).then(|| label.to_lowercase())
+}
+
+/// Returns the days of a search like `added:30`, for the tracks added to the library since then
+pub fn added_days(search: &str) -> Option<u64> {
+    search.trim().strip_prefix("added:")?.trim().parse().ok()
+}
+
+/// Sets when `track` entered the library, `added` is a unix timestamp
+pub fn set_added(track: &mut TrackData, added: u64) {
+    track.added = crate::localization::format_date(added).into();
+    track.added_at = (added / 60) as i32;
+}
+
+/// Splits the labels typed by the user at the commas, without the empty ones and the repeated
+/// ones
+pub fn parse_labels(labels: &str) -> Vec<String> {
+    let mut parsed: Vec<String> = vec![];
+    for label in labels.split(',').map(str::trim) {
+        if !label.is_empty()
+            && !parsed
+                .iter()
+                .any(|p| p.to_lowercase() == label.to_lowercase())
+        {
+            parsed.push(label.to_string());
+        }
+    }
+    parsed
+}
+
+/// Returns black or white, whichever is more readable on top of `color`
+pub fn contrast_color(color: Color) -> Color {
+    let luminance =
+        0.299 * color.red() as f32 + 0.587 * color.green() as f32 + 0.114 * color.blue() as f32;
+    if luminance > 150.0 {
+        Color::from_rgb_u8(0, 0, 0)
+    } else {
+        Color::from_rgb_u8(255, 255, 255)
+    }
+}
+
+/// Computes an accent color from a cover, favoring saturated pixels over greys
+pub fn cover_color(cover: &slint::Image) -> Option<Color> {
+    let buffer = cover.to_rgb8()?;
+    let (mut red, mut green, mut blue, mut total) = (0.0, 0.0, 0.0, 0.0);
+    for pixel in buffer.as_slice() {
+        let max = pixel.r.max(pixel.g).max(pixel.b);
+        let min = pixel.r.min(pixel.g).min(pixel.b);
+        let weight = (max - min) as f64 + 1.0;
+        red += pixel.r as f64 * weight;
+        green += pixel.g as f64 * weight;
+        blue += pixel.b as f64 * weight;
+        total += weight;
+    }
+    if total == 0.0 {
+        return None;
+    }
+    Some(Color::from_rgb_u8(
+        (red / total) as u8,
+        (green / total) as u8,
+        (blue / total) as u8,
+    ))
+}
+
+#[derive(Copy, Clone, Debug, Decode, Deserialize, Encode, Serialize, PartialEq)]
+pub struct Equalizer {
+    pub enabled: bool,
+    pub bands: [f32; BANDS.len()],
+    pub preamp: f32,
+    pub crossfeed: bool,
+    pub karaoke: bool,
+    pub limiter: bool,
+}
+
+impl Default for Equalizer {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bands: [0.0; BANDS.len()],
+            preamp: 0.0,
+            crossfeed: false,
+            karaoke: false,
+            limiter: true,
+        }
+    }
+}
+
+impl From<Equalizer> for DspSettings {
+    fn from(value: Equalizer) -> Self {
+        Self {
+            enabled: value.enabled,
+            bands: value.bands,
+            preamp: value.preamp,
+            crossfeed: value.crossfeed,
+            karaoke: value.karaoke,
+            limiter: value.limiter,
+        }
+    }
+}
+
+/// Sizes in megabytes the cache of the next tracks can be limited to
+pub const CACHE_SIZES: [u32; 4] = [256, 512, 1024, 2048];
+
+/// Sample rates the output stream can be fixed to, in Hz
+pub const OUTPUT_RATES: [u32; 5] = [44100, 48000, 88200, 96000, 192000];
+
+/// Loudness the ReplayGain can bring the tracks to, in LUFS
+pub const LOUDNESS_TARGETS: [i8; 4] = [-23, -18, -16, -14];
+
+/// Samples of the output stream, fixed for the devices that misbehave with their default
+#[derive(Copy, Clone, Debug, Default, PartialEq, Decode, Deserialize, Encode, Serialize)]
+pub enum BitDepth {
+    #[default]
+    Auto,
+    Sixteen,
+    ThirtyTwo,
+    Float,
+}
+
+impl From<BitDepth> for Option<SampleFormat> {
+    fn from(value: BitDepth) -> Self {
+        match value {
+            BitDepth::Auto => None,
+            BitDepth::Sixteen => Some(SampleFormat::I16),
+            BitDepth::ThirtyTwo => Some(SampleFormat::I32),
+            BitDepth::Float => Some(SampleFormat::F32),
+        }
+    }
+}
+
+impl From<BitDepth> for i32 {
+    fn from(value: BitDepth) -> Self {
+        match value {
+            BitDepth::Auto => 0,
+            BitDepth::Sixteen => 1,
+            BitDepth::ThirtyTwo => 2,
+            BitDepth::Float => 3,
+        }
+    }
+}
+
+impl TryFrom<i32> for BitDepth {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Auto),
+            1 => Ok(Self::Sixteen),
+            2 => Ok(Self::ThirtyTwo),
+            3 => Ok(Self::Float),
+            _ => Err(format!("{value} is not a valid bit depth")),
+        }
+    }
+}
+
+/// Which ReplayGain tags the volume of the tracks follows
+#[derive(Copy, Clone, Debug, Default, PartialEq, Decode, Deserialize, Encode, Serialize)]
+pub enum ReplayGainMode {
+    /// The album gain for the tracks played one after the other from the queue, like an album,
+    /// and the track gain for the shuffled ones
+    #[default]
+    Auto,
+    Track,
+    Album,
+    Off,
+}
+
+impl ReplayGainMode {
+    /// The mode of a track, `queued` when it's played from the queued tracks
+    pub fn resolve(self, queued: bool) -> GainMode {
+        match self {
+            ReplayGainMode::Auto if queued => GainMode::Album,
+            ReplayGainMode::Auto | ReplayGainMode::Track => GainMode::Track,
+            ReplayGainMode::Album => GainMode::Album,
+            ReplayGainMode::Off => GainMode::Off,
+        }
+    }
+}
+
+impl From<ReplayGainMode> for i32 {
+    fn from(value: ReplayGainMode) -> Self {
+        match value {
+            ReplayGainMode::Auto => 0,
+            ReplayGainMode::Track => 1,
+            ReplayGainMode::Album => 2,
+            ReplayGainMode::Off => 3,
+        }
+    }
+}
+
+impl TryFrom<i32> for ReplayGainMode {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Auto),
+            1 => Ok(Self::Track),
+            2 => Ok(Self::Album),
+            3 => Ok(Self::Off),
+            _ => Err(format!("{value} is not a valid ReplayGain mode")),
+        }
+    }
+}
+
+/// Band gains and preamp saved under a name, the effects toggles aren't part of a preset
+#[derive(Clone, Debug, Decode, Deserialize, Encode, Serialize)]
+pub struct EqualizerPreset {
+    pub name: String,
+    pub bands: [f32; BANDS.len()],
+    pub preamp: f32,
+}
+
+/// A track of the library as it's written by [export_csv] and in the exported JSON
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportedTrack {
+    pub path: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    /// In seconds
+    pub length: f64,
+    pub format: String,
+    pub plays: u32,
+    /// Unix timestamp, 0 when it was never played
+    pub last_played: u64,
+    /// `None` until the tempo is estimated or when there's no clear beat
+    pub bpm: Option<f32>,
+    pub labels: Vec<String>,
+}
+
+/// Writes the tracks as CSV with a header, the fields are quoted as in RFC 4180 when needed
+pub fn export_csv(tracks: &[ExportedTrack]) -> String {
+    let field = |value: &str| {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+    let mut csv =
+        String::from("path,title,artist,album,length,format,plays,last_played,bpm,labels\r\n");
+    for track in tracks {
+        let row = [
+            field(&track.path),
+            field(&track.title),
+            field(&track.artist),
+            field(&track.album),
+            format!("{:.3}", track.length),
+            field(&track.format),
+            track.plays.to_string(),
+            track.last_played.to_string(),
+            track.bpm.map(|bpm| format!("{bpm:.1}")).unwrap_or_default(),
+            field(&track.labels.join(";")),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+/// Returns where the playing track is among the tracks played in order, how many they are and
+/// the seconds left until they and the `queued` ones end, `elapsed` is the position in the playing
+/// track
+///
+/// The tracks removed from the disk or that didn't open in time aren't counted
+pub fn queue_progress(
+    tracks: &[TrackData],
+    current: usize,
+    elapsed: f64,
+    queued: &[u16],
+) -> (usize, usize, f64) {
+    let playable = |track: &&TrackData| !track.removed && !track.unavailable;
+    let total = tracks.iter().filter(playable).count();
+    let position = tracks.iter().take(current + 1).filter(playable).count();
+    let left = tracks
+        .get(current)
+        .map_or(0.0, |track| (track.length as f64 - elapsed).max(0.0));
+    let later = tracks
+        .iter()
+        .skip(current + 1)
+        .filter(playable)
+        .chain(queued.iter().filter_map(|i| tracks.get(*i as usize)))
+        .map(|track| track.length as f64)
+        .sum::<f64>();
+    (position, total, left + later)
+}
+
+/// Writes the tracks one per line as `Artist - Title`, to be shared as text
+pub fn queue_text(tracks: &[TrackData]) -> String {
+    tracks
+        .iter()
+        .map(|track| {
+            if track.artist.is_empty() {
+                track.title.to_string()
+            } else {
+                format!("{} - {}", track.artist, track.title)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Writes the tracks with the paths of their files as an extended M3U playlist
+pub fn export_m3u(tracks: &[(PathBuf, TrackData)]) -> String {
+    let mut m3u = String::from("#EXTM3U\n");
+    for (path, track) in tracks {
+        // the lines can't be broken inside an entry
+        let name = queue_text(std::slice::from_ref(track)).replace(['\n', '\r'], " ");
+        m3u.push_str(&format!("#EXTINF:{},{name}\n", track.length.round() as i64));
+        m3u.push_str(&path.to_string_lossy());
+        m3u.push('\n');
+    }
+    m3u
+}
+
+#[derive(Copy, Clone, Debug, Default, Decode, Deserialize, Encode, Serialize)]
+pub struct PlayStats {
+    pub count: u32,
+    pub last_played: u64,
+    /// Times it was cut before counting as a play, see [runner::TrackEnd::is_play]
+    pub skips: u32,
+}
+
+/// A play of a track, with its tags as they were so the history outlives the files
+#[derive(Clone, Debug, Decode, Deserialize, Encode, Serialize)]
+pub struct PlayRecord {
+    pub title: String,
+    pub artist: String,
+    /// Unix timestamp of when it started
+    pub played: u64,
+    /// Length of the track in seconds
+    pub length: f32,
+}
+
+/// How many entries of each ranking [listening_report] keeps
+pub const REPORT_ENTRIES: usize = 10;
+
+/// Plays and listening time of a period with the most played artists and tracks
+#[derive(Clone, Debug, Default)]
+pub struct ListeningReport {
+    pub plays: usize,
+    pub seconds: f64,
+    /// Name and plays, the most played first
+    pub artists: Vec<(String, usize)>,
+    /// `Artist - Title` and plays, the most played first
+    pub tracks: Vec<(String, usize)>,
+}
+
+/// Unix timestamp the listening statistics of `period` start from, 0 = last 7 days, 1 = last 30
+/// days, 2 = last 365 days and all time for the rest
+pub fn period_start(period: i32) -> u64 {
+    let days = match period {
+        0 => 7,
+        1 => 30,
+        2 => 365,
+        _ => return 0,
+    };
+    now().saturating_sub(days * 86400)
+}
+
+/// Puts together the plays of `history` since the unix timestamp `since`
+///
+/// The tracks are counted as listened to the end, skipping them isn't recorded
+pub fn listening_report(history: &[PlayRecord], since: u64) -> ListeningReport {
+    let mut report = ListeningReport::default();
+    let mut artists: HashMap<&str, usize> = HashMap::new();
+    let mut tracks: HashMap<(&str, &str), usize> = HashMap::new();
+    for record in history.iter().filter(|record| record.played >= since) {
+        report.plays += 1;
+        report.seconds += record.length as f64;
+        if !record.artist.is_empty() {
+            *artists.entry(&record.artist).or_default() += 1;
+        }
+        *tracks.entry((&record.artist, &record.title)).or_default() += 1;
+    }
+    let ranking = |counts: Vec<(String, usize)>| {
+        let mut counts = counts;
+        // ties in alphabetical order, so the report doesn't change every time it's shown
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(REPORT_ENTRIES);
+        counts
+    };
+    report.artists = ranking(
+        artists
+            .into_iter()
+            .map(|(artist, plays)| (artist.to_string(), plays))
+            .collect(),
+    );
+    report.tracks = ranking(
+        tracks
+            .into_iter()
+            .map(|((artist, title), plays)| {
+                let name = if artist.is_empty() {
+                    title.to_string()
+                } else {
+                    format!("{artist} - {title}")
+                };
+                (name, plays)
+            })
+            .collect(),
+    );
+    report
+}
+
+/// Returns the current unix timestamp in seconds
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Returns the unix timestamp in seconds of the last change to the file at `path`, 0 when it
+/// can't be read, like for the files of a [storage]
+pub fn modified_time<P: AsRef<Path>>(path: P) -> u64 {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[derive(Clone, Debug, Decode, Deserialize, Encode, Serialize)]
 pub struct FileTrack {
+    /// Full path of the file, the tags are cached for it
     pub path: String,
+    /// When the file was modified as the tags were read, see [modified_time]
+    pub modified: u64,
     pub title: String,
     pub artist: String,
+    pub album: String,
+    /// Cleaned up with [normalize_genre] as the tags are read
+    pub genre: String,
     pub length: f64,
+    pub format: String,
     pub image: Vec<u8>,
+    /// Play count saved in the tags, 0 when there's none, see [read_play_count]
+    pub plays: u32,
 }
 
 impl From<FileTrack> for TrackData {
     fn from(mut value: FileTrack) -> Self {
         value.artist.shrink_to_fit();
+        value.album.shrink_to_fit();
+        value.genre.shrink_to_fit();
         value.title.shrink_to_fit();
         value.image.shrink_to_fit();
         Self {
             artist: value.artist.into(),
+            album: value.album.into(),
+            genre: value.genre.into(),
             cover: if !value.image.is_empty() {
-                slint::Image::from_rgb8(SharedPixelBuffer::clone_from_slice(&value.image, 128, 128))
+                slint::Image::from_rgb8(SharedPixelBuffer::clone_from_slice(
+                    &value.image,
+                    COVER_SIZE as u32,
+                    COVER_SIZE as u32,
+                ))
             } else {
                 Default::default()
             },
             index: 0,
-            time: format!(
-                "{:02}:{:02}",
-                (value.length / 60.0).floor() as u64,
-                value.length.floor() as u64 % 60
-            )
-            .into(),
+            time: crate::localization::format_duration(value.length).into(),
             title: value.title.into(),
+            length: value.length as f32,
+            plays: 0,
+            bpm: 0.0,
+            labels: Default::default(),
+            format: value.format.into(),
+            folder: Default::default(),
+            added: Default::default(),
+            added_at: 0,
+            group: Default::default(),
+            header: false,
+            selected: false,
+            removed: false,
+            unavailable: false,
+            resume_at: 0.0,
         }
     }
 }
@@ -337,6 +1815,30 @@ pub extern "system" fn Java_com_enn3developer_n_1music_MainActivity_gotFile<'loc
         .unwrap()
 }
 
+#[cfg(target_os = "android")]
+#[no_mangle]
+pub extern "system" fn Java_com_enn3developer_n_1music_NPlayer_command<'local>(
+    _env: jni::JNIEnv<'local>,
+    _player: jni::objects::JObject<'local>,
+    command: jni::sys::jint,
+    position: jni::sys::jlong,
+) {
+    use crate::runner::{RunnerMessage, RunnerSeek};
+
+    // same values as the constants in NPlayer
+    let message = match command {
+        0 => RunnerMessage::Play,
+        1 => RunnerMessage::Pause,
+        2 => RunnerMessage::PlayNext,
+        3 => RunnerMessage::PlayPrevious,
+        4 => RunnerMessage::Seek(RunnerSeek::Absolute(position as f64 / 1000.0)),
+        _ => return,
+    };
+    ANDROID_TX
+        .send(MessageAndroidToRust::Runner(message))
+        .unwrap()
+}
+
 #[cfg(target_os = "android")]
 #[no_mangle]
 pub extern "system" fn Java_com_enn3developer_n_1music_MainActivity_start<'local>(
@@ -346,6 +1848,10 @@ pub extern "system" fn Java_com_enn3developer_n_1music_MainActivity_start<'local
 ) {
     let jvm = env.get_java_vm().unwrap();
     let callback = env.new_global_ref(callback).unwrap();
+    n_audio::storage::set_storage(Box::new(crate::platform::ContentStorage::new(
+        env.get_java_vm().unwrap(),
+        callback.clone(),
+    )));
     ANDROID_TX
         .send(MessageAndroidToRust::Start(jvm, callback))
         .unwrap()
@@ -1,44 +1,463 @@
-use crate::Localization;
+use crate::{toast_error, Localization};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use slint::{SharedString, VecModel};
+use std::ffi::OsStr;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::RwLock;
 
 include!(concat!(env!("OUT_DIR"), "/localizations.rs"));
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Locales read from the config folder, they take the place of the built-in ones with the same
+/// denominator
+static USER_LOCALES: Lazy<RwLock<Vec<UserLocale>>> = Lazy::new(|| RwLock::new(vec![]));
+/// Denominator and strings of the locale in use, with the missing ones taken from English, for
+/// the strings built outside of the UI
+static CURRENT: Lazy<RwLock<(String, Locale)>> =
+    Lazy::new(|| RwLock::new((String::from("en"), get_locale("en"))));
+
+struct UserLocale {
+    denominator: String,
+    name: String,
+    locale: Locale,
+}
+
+/// A string that changes with a number, a locale only needs the forms its language uses
+///
+/// The forms are the CLDR plural categories, `{n}` is replaced by the number
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Plural {
+    zero: Option<String>,
+    one: Option<String>,
+    two: Option<String>,
+    few: Option<String>,
+    many: Option<String>,
+    other: String,
+}
+
+impl Plural {
+    fn get(&self, category: PluralCategory) -> &str {
+        match category {
+            PluralCategory::Zero => self.zero.as_ref(),
+            PluralCategory::One => self.one.as_ref(),
+            PluralCategory::Two => self.two.as_ref(),
+            PluralCategory::Few => self.few.as_ref(),
+            PluralCategory::Many => self.many.as_ref(),
+            PluralCategory::Other => None,
+        }
+        .unwrap_or(&self.other)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum PluralCategory {
+    Zero,
+    One,
+    Two,
+    Few,
+    Many,
+    Other,
+}
+
+/// Strings that change with a number, built with [count]
+#[derive(Copy, Clone, Debug)]
+pub enum Count {
+    /// Takes `{loaded}` too
+    LoadingTracks,
+    QueuedTracks,
+    Organized,
+    Imported,
+    ScanningReplayGain,
+    ReplayGainWritten,
+    CoversEmbedded,
+    Exported,
+    PlayCountsImported,
+    TagsFixed,
+    QueueCopied,
+    ListeningSummary,
+    QueuePosition,
+    NewFiles,
+}
+
+/// Strings shown outside of the UI, like the errors in toasts, built with [text]
+#[derive(Copy, Clone, Debug)]
+pub enum Text {
+    CantAdd,
+    CantUpdate,
+    CantRead,
+    AlreadyInLibrary,
+    CantImport,
+    CantMove,
+    BadLocaleName,
+    CantReadLocale,
+    CantPlay,
+    CantSeek,
+    CantOpenLink,
+    CantReveal,
+    CantDelete,
+    CantStartMediaControls,
+    CantUpdateMediaControls,
+    RevealUnsupported,
+    DeleteUnsupported,
+    CantWriteTags,
+    NoFolderCover,
+    BackedUp,
+    CantBackUp,
+    Restored,
+    CantRestore,
+    CantExport,
+    ExportUnsupported,
+    CantImportPlayCounts,
+    NoAlbum,
+    SearchingMusicBrainz,
+    ReleaseNotFound,
+    CantSearchMusicBrainz,
+    SummaryExported,
+    TrackUnavailable,
+    CantOpenOutput,
+    RemovedFromQueue,
+    QueueReordered,
+    QueueCleared,
+    AudioFiles,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Locale {
     settings: Option<String>,
     search: Option<String>,
     theme: Option<String>,
     window_size: Option<String>,
     music_path: Option<String>,
+    recent_folders: Option<String>,
     language: Option<String>,
     theme_system: Option<String>,
     theme_light: Option<String>,
     theme_dark: Option<String>,
+    accent: Option<String>,
+    accent_system: Option<String>,
+    accent_custom: Option<String>,
+    accent_cover: Option<String>,
+    accent_color: Option<String>,
+    up_next: Option<String>,
+    copy_queue: Option<String>,
+    export_queue: Option<String>,
+    queue: Option<String>,
+    queued_tracks: Option<Plural>,
+    add_to_queue: Option<String>,
+    reveal_file: Option<String>,
+    delete_file: Option<String>,
+    scan_replay_gain: Option<String>,
+    embed_cover: Option<String>,
+    fix_metadata: Option<String>,
+    track_number: Option<String>,
+    unchanged: Option<String>,
+    moved_to_trash: Option<String>,
+    remove: Option<String>,
+    reorder: Option<String>,
+    folders: Option<String>,
+    parent_folder: Option<String>,
+    music_folder: Option<String>,
+    play_folder: Option<String>,
+    remember_position: Option<String>,
+    play_in_order: Option<String>,
+    volume_step: Option<String>,
+    seek_step: Option<String>,
+    long_seek_step: Option<String>,
+    rewind_after: Option<String>,
+    rewind_seconds: Option<String>,
+    embedded_cover_size: Option<String>,
+    auto_scroll: Option<String>,
+    grouping: Option<String>,
+    no_grouping: Option<String>,
+    first_letter: Option<String>,
+    unknown: Option<String>,
+    close_to_tray: Option<String>,
+    start_minimized: Option<String>,
+    autoplay: Option<String>,
+    auto_dj: Option<String>,
+    sync_play_counts: Option<String>,
+    osd: Option<String>,
+    show_window: Option<String>,
+    play_pause: Option<String>,
+    stop: Option<String>,
+    quit: Option<String>,
+    path_changed: Option<String>,
+    font: Option<String>,
+    font_default: Option<String>,
+    back: Option<String>,
+    previous: Option<String>,
+    next: Option<String>,
+    seek_back: Option<String>,
+    seek_forward: Option<String>,
+    play: Option<String>,
+    pause: Option<String>,
+    jump: Option<String>,
+    now_playing: Option<String>,
+    seek: Option<String>,
+    volume: Option<String>,
+    mute: Option<String>,
+    unmute: Option<String>,
+    properties: Option<String>,
+    file_path: Option<String>,
+    codec: Option<String>,
+    duration: Option<String>,
+    bitrate: Option<String>,
+    sample_rate: Option<String>,
+    bit_depth: Option<String>,
+    device_default: Option<String>,
+    bits_16: Option<String>,
+    bits_32: Option<String>,
+    bits_float: Option<String>,
+    dither: Option<String>,
+    output_hint: Option<String>,
+    snapcast: Option<String>,
+    snapcast_hint: Option<String>,
+    channels: Option<String>,
+    file_size: Option<String>,
+    play_count: Option<String>,
+    last_played: Option<String>,
+    never: Option<String>,
+    tags: Option<String>,
+    loudness: Option<String>,
+    measuring_loudness: Option<String>,
+    integrated_loudness: Option<String>,
+    peak: Option<String>,
+    track_gain: Option<String>,
+    album_gain: Option<String>,
+    applied_gain: Option<String>,
+    gain_limited: Option<String>,
+    loudness_graph_hint: Option<String>,
+    columns: Option<String>,
+    genres: Option<String>,
+    genre_rules_hint: Option<String>,
+    write_genres: Option<String>,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    plays: Option<String>,
+    format: Option<String>,
+    bpm: Option<String>,
+    genre: Option<String>,
+    labels: Option<String>,
+    date_added: Option<String>,
+    recently_added: Option<String>,
+    labels_hint: Option<String>,
+    equalizer: Option<String>,
+    replay_gain: Option<String>,
+    gain_automatic: Option<String>,
+    gain_track: Option<String>,
+    gain_album: Option<String>,
+    gain_off: Option<String>,
+    replay_gain_hint: Option<String>,
+    loudness_target: Option<String>,
+    prevent_clipping: Option<String>,
+    loudness_hint: Option<String>,
+    advanced_audio: Option<String>,
+    output_rate: Option<String>,
+    same_as_track: Option<String>,
+    output_bit_depth: Option<String>,
+    open: Option<String>,
+    equalizer_enabled: Option<String>,
+    preamp: Option<String>,
+    crossfeed: Option<String>,
+    karaoke: Option<String>,
+    limiter: Option<String>,
+    presets: Option<String>,
+    preset_name: Option<String>,
+    save: Option<String>,
+    load: Option<String>,
+    delete: Option<String>,
+    reset: Option<String>,
+    organize_files: Option<String>,
+    listening_statistics: Option<String>,
+    last_week: Option<String>,
+    last_month: Option<String>,
+    last_year: Option<String>,
+    all_time: Option<String>,
+    top_artists: Option<String>,
+    top_tracks: Option<String>,
+    export_summary: Option<String>,
+    backups: Option<String>,
+    back_up_hint: Option<String>,
+    back_up: Option<String>,
+    restore: Option<String>,
+    export_library: Option<String>,
+    import_play_counts: Option<String>,
+    import: Option<String>,
+    organize_pattern: Option<String>,
+    organize_hint: Option<String>,
+    preview: Option<String>,
+    preview_hint: Option<String>,
+    online_metadata: Option<String>,
+    online_metadata_hint: Option<String>,
+    discogs_token: Option<String>,
+    plugins: Option<String>,
+    plugins_hint: Option<String>,
+    apply: Option<String>,
+    nothing_to_organize: Option<String>,
+    organized: Option<Plural>,
+    import_files: Option<String>,
+    import_mode: Option<String>,
+    watch_folder: Option<String>,
+    stop_watching: Option<String>,
+    ignore: Option<String>,
+    error_report: Option<String>,
+    error_report_hint: Option<String>,
+    open_log: Option<String>,
+    copy_log: Option<String>,
+    close: Option<String>,
+    welcome: Option<String>,
+    welcome_hint: Option<String>,
+    choose_folder: Option<String>,
+    media_controls: Option<String>,
+    media_controls_hint: Option<String>,
+    library_ready: Option<String>,
+    start_listening: Option<String>,
+    confirm_exit: Option<String>,
+    stop_playback: Option<String>,
+    stop_playback_hint: Option<String>,
+    cancel: Option<String>,
+    undo: Option<String>,
+    clear_queue: Option<String>,
+    pause_on_disconnect: Option<String>,
+    resume_within: Option<String>,
+    add_files: Option<String>,
+    resume_longer_than: Option<String>,
+    preview_on_hover: Option<String>,
+    cache_size: Option<String>,
+    cache_hint: Option<String>,
+    copy_files: Option<String>,
+    move_files: Option<String>,
+    imported: Option<Plural>,
+    scanning_replay_gain: Option<Plural>,
+    replay_gain_written: Option<Plural>,
+    covers_embedded: Option<Plural>,
+    exported: Option<Plural>,
+    queue_copied: Option<Plural>,
+    listening_summary: Option<Plural>,
+    queue_position: Option<Plural>,
+    new_files: Option<Plural>,
+    play_counts_imported: Option<Plural>,
+    tags_fixed: Option<Plural>,
+    loading_tracks: Option<Plural>,
     credits: Option<String>,
     license: Option<String>,
+    give_permissions: Option<String>,
+    // the strings below are only built from rust, see [Text]
+    cant_add: Option<String>,
+    cant_update: Option<String>,
+    cant_read: Option<String>,
+    already_in_library: Option<String>,
+    cant_import: Option<String>,
+    cant_move: Option<String>,
+    bad_locale_name: Option<String>,
+    cant_read_locale: Option<String>,
+    cant_play: Option<String>,
+    cant_seek: Option<String>,
+    cant_open_link: Option<String>,
+    cant_reveal: Option<String>,
+    cant_delete: Option<String>,
+    cant_write_tags: Option<String>,
+    no_folder_cover: Option<String>,
+    backed_up: Option<String>,
+    cant_back_up: Option<String>,
+    restored: Option<String>,
+    cant_restore: Option<String>,
+    cant_export: Option<String>,
+    export_unsupported: Option<String>,
+    cant_import_play_counts: Option<String>,
+    no_album: Option<String>,
+    searching_musicbrainz: Option<String>,
+    release_not_found: Option<String>,
+    cant_search_musicbrainz: Option<String>,
+    summary_exported: Option<String>,
+    track_unavailable: Option<String>,
+    cant_open_output: Option<String>,
+    removed_from_queue: Option<String>,
+    queue_reordered: Option<String>,
+    queue_cleared: Option<String>,
+    audio_files: Option<String>,
+    cant_start_media_controls: Option<String>,
+    cant_update_media_controls: Option<String>,
+    reveal_unsupported: Option<String>,
+    delete_unsupported: Option<String>,
+    // how numbers, durations and dates are written, see [format_number] and the others
+    decimal_separator: Option<String>,
+    thousands_separator: Option<String>,
+    long_duration: Option<String>,
+    date: Option<String>,
+}
+
+impl Locale {
+    /// Fills the strings missing from this locale with the ones of `fallback`
+    fn or(self, fallback: Locale) -> Locale {
+        let (Ok(Value::Object(mut strings)), Ok(Value::Object(fallback_strings))) =
+            (serde_json::to_value(&self), serde_json::to_value(fallback))
+        else {
+            return self;
+        };
+        for (key, fallback_string) in fallback_strings {
+            match strings.get(&key) {
+                Some(string) if !string.is_null() => {}
+                _ => {
+                    strings.insert(key, fallback_string);
+                }
+            }
+        }
+        serde_json::from_value(Value::Object(strings)).unwrap_or(self)
+    }
 }
 
 pub fn localize(denominator: Option<String>, localization: Localization) {
-    let denominator = denominator.unwrap_or(
-        sys_locale::get_locale()
-            .unwrap()
-            .split('-')
-            .next()
-            .unwrap()
-            .to_string(),
-    );
-    let locale = get_locale(&denominator);
+    let tag = denominator
+        .or_else(sys_locale::get_locale)
+        .unwrap_or_else(|| String::from("en"));
+    // the first locale found is the one shown in the settings, the others fill its gaps
+    let chain = fallback_chain(&tag)
+        .iter()
+        .filter_map(|denominator| available_denominator(denominator))
+        .collect::<Vec<_>>();
+    let denominator = chain.first().cloned().unwrap_or_else(|| String::from("en"));
+    let locale = chain
+        .iter()
+        .map(|denominator| find_locale(denominator))
+        .reduce(Locale::or)
+        .unwrap_or_else(|| get_locale("en"));
     let english = get_locale("en");
+    *CURRENT.write().unwrap() = (denominator.clone(), locale.clone().or(get_locale("en")));
 
+    let user_locales = USER_LOCALES.read().unwrap();
     let mut localizations = LOCALIZATIONS
         .iter()
-        .map(|(_, name)| name.to_string())
-        .map(|name| name.into())
+        .filter(|(denominator, _)| {
+            !user_locales
+                .iter()
+                .any(|locale| locale.denominator == *denominator)
+        })
+        .map(|(denominator, name)| (denominator.to_string(), name.to_string()))
+        .chain(
+            user_locales
+                .iter()
+                .map(|locale| (locale.denominator.clone(), locale.name.clone())),
+        )
+        .collect::<Vec<_>>();
+    drop(user_locales);
+    localizations.sort_by(|a, b| a.1.cmp(&b.1));
+    let names = localizations
+        .iter()
+        .map(|(_, name)| name.into())
+        .collect::<Vec<SharedString>>();
+    let labels = localizations
+        .iter()
+        .map(|(denominator, name)| locale_label(denominator, name).into())
         .collect::<Vec<SharedString>>();
-    localizations.sort();
-    localization.set_localizations(VecModel::from_slice(&localizations));
-    localization.set_current_locale(get_locale_name(Some(&denominator)).into());
+    localization.set_localizations(VecModel::from_slice(&names));
+    localization.set_localization_labels(VecModel::from_slice(&labels));
+    localization.set_current_locale(
+        locale_label(&denominator, &get_locale_name(Some(&denominator))).into(),
+    );
     localization.set_settings(
         locale
             .settings
@@ -74,6 +493,13 @@ pub fn localize(denominator: Option<String>, localization: Localization) {
             .unwrap_or(english.music_path.as_ref().unwrap())
             .into(),
     );
+    localization.set_recent_folders(
+        locale
+            .recent_folders
+            .as_ref()
+            .unwrap_or(english.recent_folders.as_ref().unwrap())
+            .into(),
+    );
     localization.set_language(
         locale
             .language
@@ -102,35 +528,1750 @@ pub fn localize(denominator: Option<String>, localization: Localization) {
             .unwrap_or(english.theme_dark.as_ref().unwrap())
             .into(),
     );
-    localization.set_credits(
+    localization.set_accent(
         locale
-            .credits
+            .accent
             .as_ref()
-            .unwrap_or(english.credits.as_ref().unwrap())
+            .unwrap_or(english.accent.as_ref().unwrap())
             .into(),
     );
-    localization.set_license(
+    localization.set_accent_system(
         locale
-            .license
+            .accent_system
             .as_ref()
-            .unwrap_or(english.license.as_ref().unwrap())
+            .unwrap_or(english.accent_system.as_ref().unwrap())
             .into(),
     );
-}
-
-pub fn get_locale_name(denominator: Option<&str>) -> &str {
-    if let Some(denominator) = denominator {
-        for localization in LOCALIZATIONS {
-            if denominator == localization.0 {
-                return localization.1;
-            }
-        }
-    }
-    "English"
-}
-
-pub fn get_locale_denominator(name: Option<String>) -> String {
-    if let Some(name) = name.as_ref() {
+    localization.set_accent_custom(
+        locale
+            .accent_custom
+            .as_ref()
+            .unwrap_or(english.accent_custom.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_accent_cover(
+        locale
+            .accent_cover
+            .as_ref()
+            .unwrap_or(english.accent_cover.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_accent_color(
+        locale
+            .accent_color
+            .as_ref()
+            .unwrap_or(english.accent_color.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_up_next(
+        locale
+            .up_next
+            .as_ref()
+            .unwrap_or(english.up_next.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_copy_queue(
+        locale
+            .copy_queue
+            .as_ref()
+            .unwrap_or(english.copy_queue.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_export_queue(
+        locale
+            .export_queue
+            .as_ref()
+            .unwrap_or(english.export_queue.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_queue(
+        locale
+            .queue
+            .as_ref()
+            .unwrap_or(english.queue.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_add_to_queue(
+        locale
+            .add_to_queue
+            .as_ref()
+            .unwrap_or(english.add_to_queue.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_reveal_file(
+        locale
+            .reveal_file
+            .as_ref()
+            .unwrap_or(english.reveal_file.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_delete_file(
+        locale
+            .delete_file
+            .as_ref()
+            .unwrap_or(english.delete_file.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_scan_replay_gain(
+        locale
+            .scan_replay_gain
+            .as_ref()
+            .unwrap_or(english.scan_replay_gain.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_embed_cover(
+        locale
+            .embed_cover
+            .as_ref()
+            .unwrap_or(english.embed_cover.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_fix_metadata(
+        locale
+            .fix_metadata
+            .as_ref()
+            .unwrap_or(english.fix_metadata.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_track_number(
+        locale
+            .track_number
+            .as_ref()
+            .unwrap_or(english.track_number.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_unchanged(
+        locale
+            .unchanged
+            .as_ref()
+            .unwrap_or(english.unchanged.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_moved_to_trash(
+        locale
+            .moved_to_trash
+            .as_ref()
+            .unwrap_or(english.moved_to_trash.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_remove(
+        locale
+            .remove
+            .as_ref()
+            .unwrap_or(english.remove.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_reorder(
+        locale
+            .reorder
+            .as_ref()
+            .unwrap_or(english.reorder.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_folders(
+        locale
+            .folders
+            .as_ref()
+            .unwrap_or(english.folders.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_parent_folder(
+        locale
+            .parent_folder
+            .as_ref()
+            .unwrap_or(english.parent_folder.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_music_folder(
+        locale
+            .music_folder
+            .as_ref()
+            .unwrap_or(english.music_folder.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_play_folder(
+        locale
+            .play_folder
+            .as_ref()
+            .unwrap_or(english.play_folder.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_remember_position(
+        locale
+            .remember_position
+            .as_ref()
+            .unwrap_or(english.remember_position.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_play_in_order(
+        locale
+            .play_in_order
+            .as_ref()
+            .unwrap_or(english.play_in_order.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_volume_step(
+        locale
+            .volume_step
+            .as_ref()
+            .unwrap_or(english.volume_step.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_seek_step(
+        locale
+            .seek_step
+            .as_ref()
+            .unwrap_or(english.seek_step.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_long_seek_step(
+        locale
+            .long_seek_step
+            .as_ref()
+            .unwrap_or(english.long_seek_step.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_rewind_after(
+        locale
+            .rewind_after
+            .as_ref()
+            .unwrap_or(english.rewind_after.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_rewind_seconds(
+        locale
+            .rewind_seconds
+            .as_ref()
+            .unwrap_or(english.rewind_seconds.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_embedded_cover_size(
+        locale
+            .embedded_cover_size
+            .as_ref()
+            .unwrap_or(english.embedded_cover_size.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_auto_scroll(
+        locale
+            .auto_scroll
+            .as_ref()
+            .unwrap_or(english.auto_scroll.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_grouping(
+        locale
+            .grouping
+            .as_ref()
+            .unwrap_or(english.grouping.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_no_grouping(
+        locale
+            .no_grouping
+            .as_ref()
+            .unwrap_or(english.no_grouping.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_first_letter(
+        locale
+            .first_letter
+            .as_ref()
+            .unwrap_or(english.first_letter.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_unknown(
+        locale
+            .unknown
+            .as_ref()
+            .unwrap_or(english.unknown.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_close_to_tray(
+        locale
+            .close_to_tray
+            .as_ref()
+            .unwrap_or(english.close_to_tray.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_start_minimized(
+        locale
+            .start_minimized
+            .as_ref()
+            .unwrap_or(english.start_minimized.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_autoplay(
+        locale
+            .autoplay
+            .as_ref()
+            .unwrap_or(english.autoplay.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_auto_dj(
+        locale
+            .auto_dj
+            .as_ref()
+            .unwrap_or(english.auto_dj.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_sync_play_counts(
+        locale
+            .sync_play_counts
+            .as_ref()
+            .unwrap_or(english.sync_play_counts.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_osd(
+        locale
+            .osd
+            .as_ref()
+            .unwrap_or(english.osd.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_show_window(
+        locale
+            .show_window
+            .as_ref()
+            .unwrap_or(english.show_window.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_play_pause(
+        locale
+            .play_pause
+            .as_ref()
+            .unwrap_or(english.play_pause.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_stop(
+        locale
+            .stop
+            .as_ref()
+            .unwrap_or(english.stop.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_quit(
+        locale
+            .quit
+            .as_ref()
+            .unwrap_or(english.quit.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_path_changed(
+        locale
+            .path_changed
+            .as_ref()
+            .unwrap_or(english.path_changed.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_font(
+        locale
+            .font
+            .as_ref()
+            .unwrap_or(english.font.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_font_default(
+        locale
+            .font_default
+            .as_ref()
+            .unwrap_or(english.font_default.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_back(
+        locale
+            .back
+            .as_ref()
+            .unwrap_or(english.back.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_previous(
+        locale
+            .previous
+            .as_ref()
+            .unwrap_or(english.previous.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_next(
+        locale
+            .next
+            .as_ref()
+            .unwrap_or(english.next.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_seek_back(
+        locale
+            .seek_back
+            .as_ref()
+            .unwrap_or(english.seek_back.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_seek_forward(
+        locale
+            .seek_forward
+            .as_ref()
+            .unwrap_or(english.seek_forward.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_play(
+        locale
+            .play
+            .as_ref()
+            .unwrap_or(english.play.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_pause(
+        locale
+            .pause
+            .as_ref()
+            .unwrap_or(english.pause.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_jump(
+        locale
+            .jump
+            .as_ref()
+            .unwrap_or(english.jump.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_now_playing(
+        locale
+            .now_playing
+            .as_ref()
+            .unwrap_or(english.now_playing.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_seek(
+        locale
+            .seek
+            .as_ref()
+            .unwrap_or(english.seek.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_volume(
+        locale
+            .volume
+            .as_ref()
+            .unwrap_or(english.volume.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_mute(
+        locale
+            .mute
+            .as_ref()
+            .unwrap_or(english.mute.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_unmute(
+        locale
+            .unmute
+            .as_ref()
+            .unwrap_or(english.unmute.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_properties(
+        locale
+            .properties
+            .as_ref()
+            .unwrap_or(english.properties.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_file_path(
+        locale
+            .file_path
+            .as_ref()
+            .unwrap_or(english.file_path.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_codec(
+        locale
+            .codec
+            .as_ref()
+            .unwrap_or(english.codec.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_duration(
+        locale
+            .duration
+            .as_ref()
+            .unwrap_or(english.duration.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_bitrate(
+        locale
+            .bitrate
+            .as_ref()
+            .unwrap_or(english.bitrate.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_sample_rate(
+        locale
+            .sample_rate
+            .as_ref()
+            .unwrap_or(english.sample_rate.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_bit_depth(
+        locale
+            .bit_depth
+            .as_ref()
+            .unwrap_or(english.bit_depth.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_device_default(
+        locale
+            .device_default
+            .as_ref()
+            .unwrap_or(english.device_default.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_bits_16(
+        locale
+            .bits_16
+            .as_ref()
+            .unwrap_or(english.bits_16.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_bits_32(
+        locale
+            .bits_32
+            .as_ref()
+            .unwrap_or(english.bits_32.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_bits_float(
+        locale
+            .bits_float
+            .as_ref()
+            .unwrap_or(english.bits_float.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_dither(
+        locale
+            .dither
+            .as_ref()
+            .unwrap_or(english.dither.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_output_hint(
+        locale
+            .output_hint
+            .as_ref()
+            .unwrap_or(english.output_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_snapcast(
+        locale
+            .snapcast
+            .as_ref()
+            .unwrap_or(english.snapcast.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_snapcast_hint(
+        locale
+            .snapcast_hint
+            .as_ref()
+            .unwrap_or(english.snapcast_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_channels(
+        locale
+            .channels
+            .as_ref()
+            .unwrap_or(english.channels.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_file_size(
+        locale
+            .file_size
+            .as_ref()
+            .unwrap_or(english.file_size.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_play_count(
+        locale
+            .play_count
+            .as_ref()
+            .unwrap_or(english.play_count.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_last_played(
+        locale
+            .last_played
+            .as_ref()
+            .unwrap_or(english.last_played.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_never(
+        locale
+            .never
+            .as_ref()
+            .unwrap_or(english.never.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_tags(
+        locale
+            .tags
+            .as_ref()
+            .unwrap_or(english.tags.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_loudness(
+        locale
+            .loudness
+            .as_ref()
+            .unwrap_or(english.loudness.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_measuring_loudness(
+        locale
+            .measuring_loudness
+            .as_ref()
+            .unwrap_or(english.measuring_loudness.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_integrated_loudness(
+        locale
+            .integrated_loudness
+            .as_ref()
+            .unwrap_or(english.integrated_loudness.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_peak(
+        locale
+            .peak
+            .as_ref()
+            .unwrap_or(english.peak.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_track_gain(
+        locale
+            .track_gain
+            .as_ref()
+            .unwrap_or(english.track_gain.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_album_gain(
+        locale
+            .album_gain
+            .as_ref()
+            .unwrap_or(english.album_gain.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_applied_gain(
+        locale
+            .applied_gain
+            .as_ref()
+            .unwrap_or(english.applied_gain.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_gain_limited(
+        locale
+            .gain_limited
+            .as_ref()
+            .unwrap_or(english.gain_limited.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_loudness_graph_hint(
+        locale
+            .loudness_graph_hint
+            .as_ref()
+            .unwrap_or(english.loudness_graph_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_columns(
+        locale
+            .columns
+            .as_ref()
+            .unwrap_or(english.columns.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_genres(
+        locale
+            .genres
+            .as_ref()
+            .unwrap_or(english.genres.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_genre_rules_hint(
+        locale
+            .genre_rules_hint
+            .as_ref()
+            .unwrap_or(english.genre_rules_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_write_genres(
+        locale
+            .write_genres
+            .as_ref()
+            .unwrap_or(english.write_genres.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_title(
+        locale
+            .title
+            .as_ref()
+            .unwrap_or(english.title.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_artist(
+        locale
+            .artist
+            .as_ref()
+            .unwrap_or(english.artist.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_album(
+        locale
+            .album
+            .as_ref()
+            .unwrap_or(english.album.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_plays(
+        locale
+            .plays
+            .as_ref()
+            .unwrap_or(english.plays.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_format(
+        locale
+            .format
+            .as_ref()
+            .unwrap_or(english.format.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_bpm(
+        locale
+            .bpm
+            .as_ref()
+            .unwrap_or(english.bpm.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_genre(
+        locale
+            .genre
+            .as_ref()
+            .unwrap_or(english.genre.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_labels(
+        locale
+            .labels
+            .as_ref()
+            .unwrap_or(english.labels.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_date_added(
+        locale
+            .date_added
+            .as_ref()
+            .unwrap_or(english.date_added.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_recently_added(
+        locale
+            .recently_added
+            .as_ref()
+            .unwrap_or(english.recently_added.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_labels_hint(
+        locale
+            .labels_hint
+            .as_ref()
+            .unwrap_or(english.labels_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_equalizer(
+        locale
+            .equalizer
+            .as_ref()
+            .unwrap_or(english.equalizer.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_replay_gain(
+        locale
+            .replay_gain
+            .as_ref()
+            .unwrap_or(english.replay_gain.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_gain_automatic(
+        locale
+            .gain_automatic
+            .as_ref()
+            .unwrap_or(english.gain_automatic.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_gain_track(
+        locale
+            .gain_track
+            .as_ref()
+            .unwrap_or(english.gain_track.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_gain_album(
+        locale
+            .gain_album
+            .as_ref()
+            .unwrap_or(english.gain_album.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_gain_off(
+        locale
+            .gain_off
+            .as_ref()
+            .unwrap_or(english.gain_off.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_replay_gain_hint(
+        locale
+            .replay_gain_hint
+            .as_ref()
+            .unwrap_or(english.replay_gain_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_loudness_target(
+        locale
+            .loudness_target
+            .as_ref()
+            .unwrap_or(english.loudness_target.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_prevent_clipping(
+        locale
+            .prevent_clipping
+            .as_ref()
+            .unwrap_or(english.prevent_clipping.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_loudness_hint(
+        locale
+            .loudness_hint
+            .as_ref()
+            .unwrap_or(english.loudness_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_advanced_audio(
+        locale
+            .advanced_audio
+            .as_ref()
+            .unwrap_or(english.advanced_audio.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_output_rate(
+        locale
+            .output_rate
+            .as_ref()
+            .unwrap_or(english.output_rate.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_same_as_track(
+        locale
+            .same_as_track
+            .as_ref()
+            .unwrap_or(english.same_as_track.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_output_bit_depth(
+        locale
+            .output_bit_depth
+            .as_ref()
+            .unwrap_or(english.output_bit_depth.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_open(
+        locale
+            .open
+            .as_ref()
+            .unwrap_or(english.open.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_equalizer_enabled(
+        locale
+            .equalizer_enabled
+            .as_ref()
+            .unwrap_or(english.equalizer_enabled.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_preamp(
+        locale
+            .preamp
+            .as_ref()
+            .unwrap_or(english.preamp.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_crossfeed(
+        locale
+            .crossfeed
+            .as_ref()
+            .unwrap_or(english.crossfeed.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_karaoke(
+        locale
+            .karaoke
+            .as_ref()
+            .unwrap_or(english.karaoke.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_limiter(
+        locale
+            .limiter
+            .as_ref()
+            .unwrap_or(english.limiter.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_presets(
+        locale
+            .presets
+            .as_ref()
+            .unwrap_or(english.presets.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_preset_name(
+        locale
+            .preset_name
+            .as_ref()
+            .unwrap_or(english.preset_name.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_save(
+        locale
+            .save
+            .as_ref()
+            .unwrap_or(english.save.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_load(
+        locale
+            .load
+            .as_ref()
+            .unwrap_or(english.load.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_delete(
+        locale
+            .delete
+            .as_ref()
+            .unwrap_or(english.delete.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_reset(
+        locale
+            .reset
+            .as_ref()
+            .unwrap_or(english.reset.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_organize_files(
+        locale
+            .organize_files
+            .as_ref()
+            .unwrap_or(english.organize_files.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_listening_statistics(
+        locale
+            .listening_statistics
+            .as_ref()
+            .unwrap_or(english.listening_statistics.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_last_week(
+        locale
+            .last_week
+            .as_ref()
+            .unwrap_or(english.last_week.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_last_month(
+        locale
+            .last_month
+            .as_ref()
+            .unwrap_or(english.last_month.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_last_year(
+        locale
+            .last_year
+            .as_ref()
+            .unwrap_or(english.last_year.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_all_time(
+        locale
+            .all_time
+            .as_ref()
+            .unwrap_or(english.all_time.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_top_artists(
+        locale
+            .top_artists
+            .as_ref()
+            .unwrap_or(english.top_artists.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_top_tracks(
+        locale
+            .top_tracks
+            .as_ref()
+            .unwrap_or(english.top_tracks.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_export_summary(
+        locale
+            .export_summary
+            .as_ref()
+            .unwrap_or(english.export_summary.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_backups(
+        locale
+            .backups
+            .as_ref()
+            .unwrap_or(english.backups.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_back_up_hint(
+        locale
+            .back_up_hint
+            .as_ref()
+            .unwrap_or(english.back_up_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_back_up(
+        locale
+            .back_up
+            .as_ref()
+            .unwrap_or(english.back_up.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_restore(
+        locale
+            .restore
+            .as_ref()
+            .unwrap_or(english.restore.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_export_library(
+        locale
+            .export_library
+            .as_ref()
+            .unwrap_or(english.export_library.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_import_play_counts(
+        locale
+            .import_play_counts
+            .as_ref()
+            .unwrap_or(english.import_play_counts.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_import(
+        locale
+            .import
+            .as_ref()
+            .unwrap_or(english.import.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_organize_pattern(
+        locale
+            .organize_pattern
+            .as_ref()
+            .unwrap_or(english.organize_pattern.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_organize_hint(
+        locale
+            .organize_hint
+            .as_ref()
+            .unwrap_or(english.organize_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_preview(
+        locale
+            .preview
+            .as_ref()
+            .unwrap_or(english.preview.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_preview_hint(
+        locale
+            .preview_hint
+            .as_ref()
+            .unwrap_or(english.preview_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_online_metadata(
+        locale
+            .online_metadata
+            .as_ref()
+            .unwrap_or(english.online_metadata.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_online_metadata_hint(
+        locale
+            .online_metadata_hint
+            .as_ref()
+            .unwrap_or(english.online_metadata_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_discogs_token(
+        locale
+            .discogs_token
+            .as_ref()
+            .unwrap_or(english.discogs_token.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_plugins(
+        locale
+            .plugins
+            .as_ref()
+            .unwrap_or(english.plugins.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_plugins_hint(
+        locale
+            .plugins_hint
+            .as_ref()
+            .unwrap_or(english.plugins_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_apply(
+        locale
+            .apply
+            .as_ref()
+            .unwrap_or(english.apply.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_nothing_to_organize(
+        locale
+            .nothing_to_organize
+            .as_ref()
+            .unwrap_or(english.nothing_to_organize.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_import_files(
+        locale
+            .import_files
+            .as_ref()
+            .unwrap_or(english.import_files.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_import_mode(
+        locale
+            .import_mode
+            .as_ref()
+            .unwrap_or(english.import_mode.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_watch_folder(
+        locale
+            .watch_folder
+            .as_ref()
+            .unwrap_or(english.watch_folder.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_stop_watching(
+        locale
+            .stop_watching
+            .as_ref()
+            .unwrap_or(english.stop_watching.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_ignore(
+        locale
+            .ignore
+            .as_ref()
+            .unwrap_or(english.ignore.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_error_report(
+        locale
+            .error_report
+            .as_ref()
+            .unwrap_or(english.error_report.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_error_report_hint(
+        locale
+            .error_report_hint
+            .as_ref()
+            .unwrap_or(english.error_report_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_open_log(
+        locale
+            .open_log
+            .as_ref()
+            .unwrap_or(english.open_log.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_copy_log(
+        locale
+            .copy_log
+            .as_ref()
+            .unwrap_or(english.copy_log.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_close(
+        locale
+            .close
+            .as_ref()
+            .unwrap_or(english.close.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_welcome(
+        locale
+            .welcome
+            .as_ref()
+            .unwrap_or(english.welcome.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_welcome_hint(
+        locale
+            .welcome_hint
+            .as_ref()
+            .unwrap_or(english.welcome_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_choose_folder(
+        locale
+            .choose_folder
+            .as_ref()
+            .unwrap_or(english.choose_folder.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_media_controls(
+        locale
+            .media_controls
+            .as_ref()
+            .unwrap_or(english.media_controls.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_media_controls_hint(
+        locale
+            .media_controls_hint
+            .as_ref()
+            .unwrap_or(english.media_controls_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_library_ready(
+        locale
+            .library_ready
+            .as_ref()
+            .unwrap_or(english.library_ready.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_start_listening(
+        locale
+            .start_listening
+            .as_ref()
+            .unwrap_or(english.start_listening.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_confirm_exit(
+        locale
+            .confirm_exit
+            .as_ref()
+            .unwrap_or(english.confirm_exit.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_stop_playback(
+        locale
+            .stop_playback
+            .as_ref()
+            .unwrap_or(english.stop_playback.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_stop_playback_hint(
+        locale
+            .stop_playback_hint
+            .as_ref()
+            .unwrap_or(english.stop_playback_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_cancel(
+        locale
+            .cancel
+            .as_ref()
+            .unwrap_or(english.cancel.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_undo(
+        locale
+            .undo
+            .as_ref()
+            .unwrap_or(english.undo.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_clear_queue(
+        locale
+            .clear_queue
+            .as_ref()
+            .unwrap_or(english.clear_queue.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_pause_on_disconnect(
+        locale
+            .pause_on_disconnect
+            .as_ref()
+            .unwrap_or(english.pause_on_disconnect.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_resume_within(
+        locale
+            .resume_within
+            .as_ref()
+            .unwrap_or(english.resume_within.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_add_files(
+        locale
+            .add_files
+            .as_ref()
+            .unwrap_or(english.add_files.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_resume_longer_than(
+        locale
+            .resume_longer_than
+            .as_ref()
+            .unwrap_or(english.resume_longer_than.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_preview_on_hover(
+        locale
+            .preview_on_hover
+            .as_ref()
+            .unwrap_or(english.preview_on_hover.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_cache_size(
+        locale
+            .cache_size
+            .as_ref()
+            .unwrap_or(english.cache_size.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_cache_hint(
+        locale
+            .cache_hint
+            .as_ref()
+            .unwrap_or(english.cache_hint.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_copy_files(
+        locale
+            .copy_files
+            .as_ref()
+            .unwrap_or(english.copy_files.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_move_files(
+        locale
+            .move_files
+            .as_ref()
+            .unwrap_or(english.move_files.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_credits(
+        locale
+            .credits
+            .as_ref()
+            .unwrap_or(english.credits.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_license(
+        locale
+            .license
+            .as_ref()
+            .unwrap_or(english.license.as_ref().unwrap())
+            .into(),
+    );
+    localization.set_give_permissions(
+        locale
+            .give_permissions
+            .as_ref()
+            .unwrap_or(english.give_permissions.as_ref().unwrap())
+            .into(),
+    );
+}
+
+/// Returns the string for `n` in the locale in use, `{n}` and the `args` placeholders are filled
+pub fn count(count: Count, n: usize, args: &[(&str, &dyn Display)]) -> String {
+    let current = CURRENT.read().unwrap();
+    let (denominator, locale) = &*current;
+    let plural = match count {
+        Count::LoadingTracks => &locale.loading_tracks,
+        Count::QueuedTracks => &locale.queued_tracks,
+        Count::Organized => &locale.organized,
+        Count::Imported => &locale.imported,
+        Count::ScanningReplayGain => &locale.scanning_replay_gain,
+        Count::ReplayGainWritten => &locale.replay_gain_written,
+        Count::CoversEmbedded => &locale.covers_embedded,
+        Count::Exported => &locale.exported,
+        Count::QueueCopied => &locale.queue_copied,
+        Count::ListeningSummary => &locale.listening_summary,
+        Count::QueuePosition => &locale.queue_position,
+        Count::NewFiles => &locale.new_files,
+        Count::PlayCountsImported => &locale.play_counts_imported,
+        Count::TagsFixed => &locale.tags_fixed,
+    };
+    let template = plural
+        .as_ref()
+        .map(|plural| plural.get(plural_category(denominator, n as u64)))
+        .unwrap_or_default();
+    let number = group_digits(&n.to_string(), locale);
+    let mut args = args.to_vec();
+    args.push(("n", &number));
+    fill(template, &args)
+}
+
+/// Returns the string in the locale in use with the `args` placeholders filled
+pub fn text(text: Text, args: &[(&str, &dyn Display)]) -> String {
+    let current = CURRENT.read().unwrap();
+    let locale = &current.1;
+    let template = match text {
+        Text::CantAdd => &locale.cant_add,
+        Text::CantUpdate => &locale.cant_update,
+        Text::CantRead => &locale.cant_read,
+        Text::AlreadyInLibrary => &locale.already_in_library,
+        Text::CantImport => &locale.cant_import,
+        Text::CantMove => &locale.cant_move,
+        Text::BadLocaleName => &locale.bad_locale_name,
+        Text::CantReadLocale => &locale.cant_read_locale,
+        Text::CantPlay => &locale.cant_play,
+        Text::CantSeek => &locale.cant_seek,
+        Text::CantOpenLink => &locale.cant_open_link,
+        Text::CantReveal => &locale.cant_reveal,
+        Text::CantDelete => &locale.cant_delete,
+        Text::CantStartMediaControls => &locale.cant_start_media_controls,
+        Text::CantUpdateMediaControls => &locale.cant_update_media_controls,
+        Text::RevealUnsupported => &locale.reveal_unsupported,
+        Text::DeleteUnsupported => &locale.delete_unsupported,
+        Text::CantWriteTags => &locale.cant_write_tags,
+        Text::NoFolderCover => &locale.no_folder_cover,
+        Text::BackedUp => &locale.backed_up,
+        Text::CantBackUp => &locale.cant_back_up,
+        Text::Restored => &locale.restored,
+        Text::CantRestore => &locale.cant_restore,
+        Text::CantExport => &locale.cant_export,
+        Text::ExportUnsupported => &locale.export_unsupported,
+        Text::CantImportPlayCounts => &locale.cant_import_play_counts,
+        Text::NoAlbum => &locale.no_album,
+        Text::SearchingMusicBrainz => &locale.searching_musicbrainz,
+        Text::ReleaseNotFound => &locale.release_not_found,
+        Text::CantSearchMusicBrainz => &locale.cant_search_musicbrainz,
+        Text::SummaryExported => &locale.summary_exported,
+        Text::TrackUnavailable => &locale.track_unavailable,
+        Text::CantOpenOutput => &locale.cant_open_output,
+        Text::RemovedFromQueue => &locale.removed_from_queue,
+        Text::QueueReordered => &locale.queue_reordered,
+        Text::QueueCleared => &locale.queue_cleared,
+        Text::AudioFiles => &locale.audio_files,
+    };
+    fill(template.as_deref().unwrap_or_default(), args)
+}
+
+/// Writes `n` with the thousands separator of the locale in use
+pub fn format_number(n: u64) -> String {
+    group_digits(&n.to_string(), &CURRENT.read().unwrap().1)
+}
+
+/// Writes `value` with `decimals` digits after the decimal separator of the locale in use
+pub fn format_decimal(value: f64, decimals: usize) -> String {
+    let current = CURRENT.read().unwrap();
+    let locale = &current.1;
+    let formatted = format!("{value:.decimals$}");
+    match formatted.split_once('.') {
+        Some((integer, fraction)) => format!(
+            "{}{}{fraction}",
+            group_digits(integer, locale),
+            locale.decimal_separator.as_deref().unwrap_or_default()
+        ),
+        None => group_digits(&formatted, locale),
+    }
+}
+
+/// Writes a size in bytes using the biggest fitting unit
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", format_number(bytes))
+    } else {
+        format!("{} {}", format_decimal(size, 1), units[unit])
+    }
+}
+
+/// Writes a duration in seconds as `mm:ss`, the ones of an hour or more follow the locale in use
+pub fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.0).floor() as u64;
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours == 0 {
+        return format!("{minutes:02}:{seconds:02}");
+    }
+    let current = CURRENT.read().unwrap();
+    fill(
+        current.1.long_duration.as_deref().unwrap_or_default(),
+        &[
+            ("h", &hours),
+            ("mm", &format!("{minutes:02}")),
+            ("ss", &format!("{seconds:02}")),
+        ],
+    )
+}
+
+/// Writes a unix timestamp as a date (UTC) in the order of the locale in use
+pub fn format_date(timestamp: u64) -> String {
+    // civil_from_days by Howard Hinnant
+    let days = (timestamp / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let current = CURRENT.read().unwrap();
+    fill(
+        current.1.date.as_deref().unwrap_or_default(),
+        &[
+            ("year", &format!("{year:04}")),
+            ("month", &format!("{month:02}")),
+            ("day", &format!("{day:02}")),
+        ],
+    )
+}
+
+fn group_digits(digits: &str, locale: &Locale) -> String {
+    let separator = locale.thousands_separator.as_deref().unwrap_or_default();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Replaces every `{name}` in `template` with its value in `args`
+pub fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut filled = template.to_string();
+    for (name, value) in args {
+        filled = filled.replace(&format!("{{{name}}}"), &value.to_string());
+    }
+    filled
+}
+
+/// Returns the plural category of `n` in the language of `denominator`, following the CLDR rules
+/// of the languages with a translation or likely to get one
+fn plural_category(denominator: &str, n: u64) -> PluralCategory {
+    let language = denominator
+        .split('-')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let (last, last_two) = (n % 10, n % 100);
+    match language.as_str() {
+        "zh" | "ja" | "ko" | "vi" | "th" | "id" | "ms" => PluralCategory::Other,
+        "fr" | "pt" if n <= 1 => PluralCategory::One,
+        "fr" | "pt" => PluralCategory::Other,
+        "ru" | "uk" | "be" | "sr" | "hr" | "bs" => {
+            if last == 1 && last_two != 11 {
+                PluralCategory::One
+            } else if (2..=4).contains(&last) && !(12..=14).contains(&last_two) {
+                PluralCategory::Few
+            } else {
+                PluralCategory::Many
+            }
+        }
+        "pl" => {
+            if n == 1 {
+                PluralCategory::One
+            } else if (2..=4).contains(&last) && !(12..=14).contains(&last_two) {
+                PluralCategory::Few
+            } else {
+                PluralCategory::Many
+            }
+        }
+        "cs" | "sk" => match n {
+            1 => PluralCategory::One,
+            2..=4 => PluralCategory::Few,
+            _ => PluralCategory::Other,
+        },
+        "ar" => match (n, last_two) {
+            (0, _) => PluralCategory::Zero,
+            (1, _) => PluralCategory::One,
+            (2, _) => PluralCategory::Two,
+            (_, 3..=10) => PluralCategory::Few,
+            (_, 11..=99) => PluralCategory::Many,
+            _ => PluralCategory::Other,
+        },
+        _ if n == 1 => PluralCategory::One,
+        _ => PluralCategory::Other,
+    }
+}
+
+/// Reads the locale files in `dir`, named like the built-in ones (`xx_Name.json`), replacing the
+/// ones read before
+pub fn load_user_locales(dir: &Path) {
+    let mut locales = vec![];
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new("json")) {
+                continue;
+            }
+            let Some((denominator, name)) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.split_once('_'))
+            else {
+                toast_error(text(Text::BadLocaleName, &[("path", &path.display())]));
+                continue;
+            };
+            let locale = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|data| serde_json::from_str::<Locale>(&data).map_err(|e| e.to_string()));
+            match locale {
+                Ok(locale) => locales.push(UserLocale {
+                    denominator: denominator.to_string(),
+                    name: name.to_string(),
+                    locale,
+                }),
+                Err(e) => toast_error(text(
+                    Text::CantReadLocale,
+                    &[("path", &path.display()), ("error", &e)],
+                )),
+            }
+        }
+    }
+    *USER_LOCALES.write().unwrap() = locales;
+}
+
+/// Returns the denominators to try for the language tag `tag`, from the most specific to the
+/// least one, like `zh-Hant-TW`, `zh-Hant` and `zh`
+fn fallback_chain(tag: &str) -> Vec<String> {
+    // posix locales use underscores and can end with the encoding, like zh_TW.UTF-8
+    let tag = tag.replace('_', "-");
+    let tag = tag.split(['.', '@']).next().unwrap_or_default();
+    let mut subtags = tag
+        .split('-')
+        .filter(|subtag| !subtag.is_empty())
+        .collect::<Vec<_>>();
+    let mut chain = vec![subtags.join("-")];
+    // chinese is written in two scripts, the region tells which one when it's missing
+    if subtags.len() == 2 && subtags[0].eq_ignore_ascii_case("zh") && subtags[1].len() == 2 {
+        let script = match subtags[1].to_ascii_uppercase().as_str() {
+            "TW" | "HK" | "MO" => "Hant",
+            _ => "Hans",
+        };
+        subtags.insert(1, script);
+    }
+    for len in (1..=subtags.len()).rev() {
+        let denominator = subtags[..len].join("-");
+        if !chain.contains(&denominator) {
+            chain.push(denominator);
+        }
+    }
+    chain
+}
+
+/// Returns the denominator of the locale matching `denominator`, ignoring the case as language
+/// tags do
+fn available_denominator(denominator: &str) -> Option<String> {
+    let user_locales = USER_LOCALES.read().unwrap();
+    user_locales
+        .iter()
+        .map(|locale| locale.denominator.as_str())
+        .chain(LOCALIZATIONS.iter().map(|(denominator, _)| *denominator))
+        .find(|available| available.eq_ignore_ascii_case(denominator))
+        .map(|available| available.to_string())
+}
+
+/// Returns the locale with `denominator`, the ones read from the config folder come first
+fn find_locale(denominator: &str) -> Locale {
+    USER_LOCALES
+        .read()
+        .unwrap()
+        .iter()
+        .find(|locale| locale.denominator == denominator)
+        .map(|locale| locale.locale.clone())
+        .unwrap_or_else(|| get_locale(denominator))
+}
+
+/// Returns the name of the locale shown in the settings, with how much of it is translated when
+/// some strings are missing
+fn locale_label(denominator: &str, name: &str) -> String {
+    let translated = translated(denominator);
+    if translated < 100 {
+        format!("{name} ({translated}%)")
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns the percentage of the english strings translated by the locale, the built-in ones are
+/// counted by the build script
+fn translated(denominator: &str) -> usize {
+    let user_locales = USER_LOCALES.read().unwrap();
+    if let Some(locale) = user_locales
+        .iter()
+        .find(|locale| locale.denominator == denominator)
+    {
+        let english = count_strings(&get_locale("en")).max(1);
+        return count_strings(&locale.locale).min(english) * 100 / english;
+    }
+    TRANSLATED
+        .iter()
+        .find(|(built_in, _)| *built_in == denominator)
+        .map_or(100, |(_, translated)| *translated as usize)
+}
+
+fn count_strings(locale: &Locale) -> usize {
+    match serde_json::to_value(locale) {
+        Ok(Value::Object(strings)) => strings.values().filter(|value| !value.is_null()).count(),
+        _ => 0,
+    }
+}
+
+pub fn get_locale_name(denominator: Option<&str>) -> String {
+    if let Some(denominator) = denominator {
+        let user_locales = USER_LOCALES.read().unwrap();
+        if let Some(locale) = user_locales
+            .iter()
+            .find(|locale| locale.denominator == denominator)
+        {
+            return locale.name.clone();
+        }
+        for localization in LOCALIZATIONS {
+            if denominator == localization.0 {
+                return localization.1.to_string();
+            }
+        }
+    }
+    "English".to_string()
+}
+
+pub fn get_locale_denominator(name: Option<String>) -> String {
+    if let Some(name) = name.as_ref() {
+        let user_locales = USER_LOCALES.read().unwrap();
+        if let Some(locale) = user_locales.iter().find(|locale| &locale.name == name) {
+            return locale.denominator.clone();
+        }
         for localization in LOCALIZATIONS {
             if name == localization.1 {
                 return localization.0.to_string();
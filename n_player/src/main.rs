@@ -3,14 +3,18 @@
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
 #[tokio::main]
 async fn main() {
-    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    #[cfg(target_os = "macos")]
     use n_player::platform::DesktopPlatform;
     #[cfg(target_os = "linux")]
     use n_player::platform::LinuxPlatform;
+    #[cfg(target_os = "windows")]
+    use n_player::platform::WindowsPlatform;
     use n_player::settings::Settings;
 
-    #[cfg(any(target_os = "macos", target_os = "windows"))]
+    #[cfg(target_os = "macos")]
     let platform = DesktopPlatform {};
+    #[cfg(target_os = "windows")]
+    let platform = WindowsPlatform::new();
     #[cfg(target_os = "linux")]
     let platform = LinuxPlatform::new();
     let settings = Settings::read_saved(&platform).await;
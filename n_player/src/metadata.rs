@@ -0,0 +1,190 @@
+//! Where the tags shown for the tracks come from
+//!
+//! A [MetadataChain] asks its providers in order of priority, every one only filling the fields
+//! the ones before it left empty, so a source is added by implementing [MetadataProvider] and
+//! putting it in [metadata_chain]
+
+use crate::discogs::DiscogsProvider;
+use crate::musicbrainz::MusicBrainzProvider;
+use crate::plugins::{self, PluginProvider};
+use crate::settings::Settings;
+use n_audio::music_track::MusicTrack;
+use n_audio::{remove_ext, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A source of tags for the tracks, like the files themselves or an online database
+///
+/// It's called on a blocking thread, so it can read files and make requests
+pub trait MetadataProvider: Send + Sync {
+    /// Shown in the log when it fails
+    fn name(&self) -> &'static str;
+
+    /// The tags it knows for the file at `path`, `known` has the ones found by the providers
+    /// before it
+    fn provide(&self, path: &Path, known: &Metadata) -> io::Result<Metadata>;
+}
+
+/// Providers by priority, the first one reads the file itself and when it fails the track can't
+/// be played
+#[derive(Default)]
+pub struct MetadataChain {
+    providers: Vec<Box<dyn MetadataProvider>>,
+}
+
+impl MetadataChain {
+    /// Adds a provider after the ones already in the chain
+    pub fn with(mut self, provider: impl MetadataProvider + 'static) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    /// The tags of a track, the title falls back to the file name like in
+    /// [MusicTrack::get_meta]
+    pub fn read(&self, path: &Path) -> io::Result<Metadata> {
+        let mut metadata = Metadata::default();
+        for (i, provider) in self.providers.iter().enumerate() {
+            if is_complete(&metadata) {
+                break;
+            }
+            match provider.provide(path, &metadata) {
+                Ok(provided) => fill(&mut metadata, provided),
+                Err(e) if i == 0 => return Err(e),
+                Err(e) => eprintln!(
+                    "{} can't find the tags of {}: {e}",
+                    provider.name(),
+                    path.display()
+                ),
+            }
+        }
+        if metadata.title.is_empty() {
+            metadata.title = remove_ext(path);
+        }
+        Ok(metadata)
+    }
+}
+
+/// The providers the library is read with, the plugins and the online ones only when they're
+/// enabled
+pub fn metadata_chain(settings: &Settings) -> MetadataChain {
+    let mut chain = MetadataChain::default().with(TagsProvider);
+    if plugins::loaded() {
+        chain = chain.with(PluginProvider);
+    }
+    if settings.online_metadata {
+        chain = chain.with(MusicBrainzProvider);
+        if !settings.discogs_token.is_empty() {
+            chain = chain.with(DiscogsProvider {
+                token: settings.discogs_token.clone(),
+            });
+        }
+    }
+    chain.with(FileNameProvider {
+        root: PathBuf::from(&settings.path),
+    })
+}
+
+fn is_complete(metadata: &Metadata) -> bool {
+    metadata.time.length > 0.0
+        && !metadata.artist.is_empty()
+        && !metadata.title.is_empty()
+        && !metadata.album.is_empty()
+        && !metadata.album_artist.is_empty()
+        && !metadata.genre.is_empty()
+        && metadata.track.is_some()
+        && !metadata.date.is_empty()
+}
+
+/// Copies the fields of `provided` that `metadata` doesn't have
+pub fn fill(metadata: &mut Metadata, provided: Metadata) {
+    for (field, value) in [
+        (&mut metadata.artist, provided.artist),
+        (&mut metadata.title, provided.title),
+        (&mut metadata.album, provided.album),
+        (&mut metadata.album_artist, provided.album_artist),
+        (&mut metadata.genre, provided.genre),
+        (&mut metadata.date, provided.date),
+    ] {
+        if field.is_empty() {
+            *field = value;
+        }
+    }
+    metadata.track = metadata.track.or(provided.track);
+    if metadata.time.length == 0.0 {
+        metadata.time = provided.time;
+    }
+}
+
+/// The tags written in the file, with its length
+pub struct TagsProvider;
+
+impl MetadataProvider for TagsProvider {
+    fn name(&self) -> &'static str {
+        "Tags"
+    }
+
+    fn provide(&self, path: &Path, _: &Metadata) -> io::Result<Metadata> {
+        MusicTrack::new(path.to_string_lossy().to_string())?.get_tags()
+    }
+}
+
+/// Guesses from the names, like `01 - Artist - Title.mp3` or `Artist/Album/Title.flac` in the
+/// music directory
+pub struct FileNameProvider {
+    pub root: PathBuf,
+}
+
+impl MetadataProvider for FileNameProvider {
+    fn name(&self) -> &'static str {
+        "File name"
+    }
+
+    fn provide(&self, path: &Path, _: &Metadata) -> io::Result<Metadata> {
+        let mut metadata = Metadata::default();
+        let name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().replace('_', " "))
+            .unwrap_or_default();
+        // "01 - ", "01. " and "01 " are track numbers, "5 Seconds of Summer" isn't
+        let digits = name.chars().take_while(char::is_ascii_digit).count();
+        let numbered = (1..=3).contains(&digits)
+            && match name[digits..].chars().next() {
+                Some('.' | '-') => true,
+                Some(' ') => digits > 1,
+                _ => false,
+            };
+        let rest = if numbered {
+            metadata.track = name[..digits].parse().ok();
+            name[digits..].trim_start_matches(['.', '-', ' '])
+        } else {
+            name.as_str()
+        };
+        match rest.split_once(" - ") {
+            Some((artist, title)) => {
+                metadata.artist = artist.trim().to_string();
+                metadata.title = title.trim().to_string();
+            }
+            None => metadata.title = rest.trim().to_string(),
+        }
+        // the album folder is in the one of its artist
+        if let Some(folders) = path
+            .parent()
+            .and_then(|parent| parent.strip_prefix(&self.root).ok())
+        {
+            let mut folders = folders
+                .iter()
+                .rev()
+                .map(|folder| folder.to_string_lossy().to_string());
+            if let Some(album) = folders.next() {
+                metadata.album = album;
+            }
+            if let Some(artist) = folders.next() {
+                if metadata.artist.is_empty() {
+                    metadata.artist = artist.clone();
+                }
+                metadata.album_artist = artist;
+            }
+        }
+        Ok(metadata)
+    }
+}
@@ -0,0 +1,328 @@
+//! Looks up albums on [MusicBrainz](https://musicbrainz.org) to correct the tags of their tracks
+//!
+//! The web service asks every application to name itself in the user agent and to make at most
+//! one request per second
+
+use crate::metadata::MetadataProvider;
+use n_audio::Metadata;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const API: &str = "https://musicbrainz.org/ws/2";
+const USER_AGENT: &str = concat!(
+    "n_music/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/Enn3Developer/n_music )"
+);
+/// Releases the search scores lower than this are too different from the tags to be the album
+const MIN_SCORE: u32 = 90;
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+/// When the last request was made, the ones made at the same time wait for their turn
+static LAST_REQUEST: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// A release as MusicBrainz knows it, with the tracks of all its media in order
+#[derive(Clone, Debug)]
+pub struct Release {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+    pub tracks: Vec<ReleaseTrack>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReleaseTrack {
+    pub recording_id: String,
+    /// Position in its medium
+    pub number: u32,
+    pub title: String,
+    pub artist: String,
+}
+
+/// Tags of a track given by its release, written by [crate::write_release_tags]
+#[derive(Clone, Debug)]
+pub struct ReleaseTags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: String,
+    pub number: u32,
+    pub release_id: String,
+    pub recording_id: String,
+}
+
+impl Release {
+    /// Tags of the track at `index` in [Release::tracks]
+    pub fn tags(&self, index: usize) -> ReleaseTags {
+        let track = &self.tracks[index];
+        ReleaseTags {
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            album: self.title.clone(),
+            album_artist: self.artist.clone(),
+            number: track.number,
+            release_id: self.id.clone(),
+            recording_id: track.recording_id.clone(),
+        }
+    }
+
+    /// Pairs every file of an album with a track of the release, by title first and then by
+    /// number, `None` for the files that aren't on it
+    pub fn match_files(&self, files: &[Metadata]) -> Vec<Option<usize>> {
+        let titles = self
+            .tracks
+            .iter()
+            .map(|track| normalize(&track.title))
+            .collect::<Vec<_>>();
+        let mut taken = vec![false; self.tracks.len()];
+        let mut matches = vec![None; files.len()];
+        for (file, matched) in files.iter().zip(matches.iter_mut()) {
+            let title = normalize(&file.title);
+            if let Some(i) = (0..titles.len()).find(|i| !taken[*i] && titles[*i] == title) {
+                taken[i] = true;
+                *matched = Some(i);
+            }
+        }
+        // the numbers repeat on releases with more than one medium, they're only trusted when
+        // a single track has them
+        for (file, matched) in files.iter().zip(matches.iter_mut()) {
+            let Some(number) = file.track.filter(|_| matched.is_none()) else {
+                continue;
+            };
+            let mut tracks = (0..self.tracks.len()).filter(|i| self.tracks[*i].number == number);
+            if let (Some(i), None) = (tracks.next(), tracks.next()) {
+                if !taken[i] {
+                    taken[i] = true;
+                    *matched = Some(i);
+                }
+            }
+        }
+        matches
+    }
+}
+
+/// Lowercase letters and digits of a title, so punctuation and spacing don't tell them apart
+fn normalize(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct SearchResult {
+    releases: Vec<SearchedRelease>,
+}
+
+#[derive(Deserialize)]
+struct SearchedRelease {
+    id: String,
+    score: u32,
+    #[serde(rename = "track-count", default)]
+    track_count: usize,
+}
+
+#[derive(Deserialize)]
+struct ReleaseResult {
+    id: String,
+    title: String,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    media: Vec<Medium>,
+}
+
+#[derive(Deserialize)]
+struct ArtistCredit {
+    name: String,
+    #[serde(default)]
+    joinphrase: String,
+}
+
+#[derive(Deserialize)]
+struct Medium {
+    #[serde(default)]
+    tracks: Vec<TrackResult>,
+}
+
+#[derive(Deserialize)]
+struct TrackResult {
+    position: u32,
+    title: String,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    recording: Recording,
+}
+
+#[derive(Deserialize)]
+struct Recording {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct RecordingSearch {
+    recordings: Vec<SearchedRecording>,
+}
+
+#[derive(Deserialize)]
+struct SearchedRecording {
+    score: u32,
+    #[serde(rename = "first-release-date", default)]
+    first_release_date: String,
+    #[serde(default)]
+    releases: Vec<RecordingRelease>,
+}
+
+#[derive(Deserialize)]
+struct RecordingRelease {
+    title: String,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    media: Vec<SearchedMedium>,
+}
+
+/// The media of the search results only have the track of the recording, under `track`
+#[derive(Deserialize)]
+struct SearchedMedium {
+    #[serde(default)]
+    track: Vec<SearchedTrack>,
+}
+
+#[derive(Deserialize)]
+struct SearchedTrack {
+    number: String,
+}
+
+/// Fills the album, its artist, the date and the track number from the first release of the
+/// recording with the same title and artist
+pub struct MusicBrainzProvider;
+
+impl MetadataProvider for MusicBrainzProvider {
+    fn name(&self) -> &'static str {
+        "MusicBrainz"
+    }
+
+    fn provide(&self, _: &Path, known: &Metadata) -> io::Result<Metadata> {
+        if known.title.is_empty() || known.artist.is_empty() {
+            return Ok(Metadata::default());
+        }
+        let query = format!(
+            "recording:{} AND artist:{}",
+            phrase(&known.title),
+            phrase(&known.artist)
+        );
+        let result: RecordingSearch =
+            get("recording", &[("query", &query), ("limit", "5")]).map_err(io::Error::other)?;
+        let Some(recording) = result
+            .recordings
+            .into_iter()
+            .find(|recording| recording.score >= MIN_SCORE)
+        else {
+            return Ok(Metadata::default());
+        };
+        let mut metadata = Metadata {
+            date: recording.first_release_date,
+            ..Default::default()
+        };
+        if let Some(release) = recording.releases.into_iter().next() {
+            metadata.album = release.title;
+            metadata.album_artist = credited(&release.artist_credit);
+            metadata.track = release
+                .media
+                .first()
+                .and_then(|medium| medium.track.first())
+                .and_then(|track| track.number.parse().ok());
+        }
+        Ok(metadata)
+    }
+}
+
+/// Searches the release of an album by its tags, preferring the ones with as many tracks as the
+/// album has files
+///
+/// It blocks for a few seconds, it has to be called outside of the async tasks
+pub fn find_release(album: &str, artist: &str, files: usize) -> Result<Option<Release>, String> {
+    let mut query = format!("release:{}", phrase(album));
+    if !artist.is_empty() {
+        query.push_str(&format!(" AND artist:{}", phrase(artist)));
+    }
+    let result: SearchResult = get("release", &[("query", &query), ("limit", "10")])?;
+    let releases = result
+        .releases
+        .into_iter()
+        .filter(|release| release.score >= MIN_SCORE)
+        .collect::<Vec<_>>();
+    let Some(found) = releases
+        .iter()
+        .find(|release| release.track_count == files)
+        .or(releases.first())
+    else {
+        return Ok(None);
+    };
+    let release: ReleaseResult = get(
+        &format!("release/{}", found.id),
+        &[("inc", "recordings artist-credits")],
+    )?;
+    let album_artist = credited(&release.artist_credit);
+    let tracks = release
+        .media
+        .into_iter()
+        .flat_map(|medium| medium.tracks)
+        .map(|track| ReleaseTrack {
+            recording_id: track.recording.id,
+            number: track.position,
+            title: track.title,
+            artist: if track.artist_credit.is_empty() {
+                album_artist.clone()
+            } else {
+                credited(&track.artist_credit)
+            },
+        })
+        .collect();
+    Ok(Some(Release {
+        id: release.id,
+        title: release.title,
+        artist: album_artist,
+        tracks,
+    }))
+}
+
+/// The artists as they're credited, like "Artist feat. Other"
+fn credited(credits: &[ArtistCredit]) -> String {
+    credits
+        .iter()
+        .map(|credit| format!("{}{}", credit.name, credit.joinphrase))
+        .collect()
+}
+
+/// Quotes a value for the Lucene query of the search
+fn phrase(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn get<T: DeserializeOwned>(resource: &str, query: &[(&str, &str)]) -> Result<T, String> {
+    {
+        let mut last = LAST_REQUEST.lock().unwrap();
+        if let Some(elapsed) = last.map(|at| at.elapsed()) {
+            std::thread::sleep(REQUEST_INTERVAL.saturating_sub(elapsed));
+        }
+        *last = Some(Instant::now());
+    }
+    let mut request = ureq::get(&format!("{API}/{resource}"))
+        .set("User-Agent", USER_AGENT)
+        .timeout(Duration::from_secs(30))
+        .query("fmt", "json");
+    for (key, value) in query {
+        request = request.query(key, value);
+    }
+    request
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_json()
+        .map_err(|e| e.to_string())
+}
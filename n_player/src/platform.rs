@@ -1,5 +1,7 @@
 use crate::bus_server::Property;
+use crate::localization::{text, Text};
 use crate::runner::{Runner, RunnerMessage};
+use crate::MainWindow;
 use async_trait::async_trait;
 use flume::Sender;
 use std::path::PathBuf;
@@ -8,7 +10,50 @@ use tokio::sync::RwLock;
 
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
 fn open_link_desktop(link: String) {
-    open::that(link).unwrap();
+    if let Err(e) = open::that(&link) {
+        crate::toast_error(text(Text::CantOpenLink, &[("link", &link), ("error", &e)]));
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn reveal_file_desktop(path: PathBuf) {
+    // file managers that can select the file get it, the others just open its folder
+    #[cfg(target_os = "windows")]
+    let result = std::process::Command::new("explorer")
+        .arg("/select,")
+        .arg(&path)
+        .spawn()
+        .map(|_| ());
+    #[cfg(target_os = "macos")]
+    let result = std::process::Command::new("open")
+        .arg("-R")
+        .arg(&path)
+        .spawn()
+        .map(|_| ());
+    #[cfg(target_os = "linux")]
+    let result = open::that(path.parent().unwrap_or(&path));
+    if let Err(e) = result {
+        crate::toast_error(text(
+            Text::CantReveal,
+            &[("path", &path.display()), ("error", &e)],
+        ));
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+async fn trash_file_desktop(path: PathBuf) -> bool {
+    let p = path.clone();
+    match tokio::task::spawn_blocking(move || trash::delete(p)).await {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            crate::toast_error(text(
+                Text::CantDelete,
+                &[("path", &path.display()), ("error", &e)],
+            ));
+            false
+        }
+        Err(_) => false,
+    }
 }
 
 #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
@@ -40,6 +85,26 @@ async fn ask_file_desktop() -> Vec<PathBuf> {
     }
 }
 
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+async fn ask_audio_files_desktop() -> Vec<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .add_filter(text(Text::AudioFiles, &[]), &crate::AUDIO_EXTENSIONS)
+        .pick_files()
+        .await
+        .map_or(vec![], |files| {
+            files.into_iter().map(PathBuf::from).collect()
+        })
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+async fn ask_save_file_desktop(name: String) -> Option<PathBuf> {
+    rfd::AsyncFileDialog::new()
+        .set_file_name(name)
+        .save_file()
+        .await
+        .map(PathBuf::from)
+}
+
 #[allow(unused_variables)]
 #[async_trait]
 /// Abstraction over a number of platforms (desktop and mobile)
@@ -52,21 +117,44 @@ pub trait Platform {
     async fn ask_music_dir(&mut self) -> PathBuf;
     /// Ask underlying platform to ask user for files
     async fn ask_file(&mut self) -> Vec<PathBuf>;
+    /// Ask underlying platform to ask user for audio files, the other ones are hidden where it
+    /// can
+    async fn ask_audio_files(&mut self) -> Vec<PathBuf>
+    where
+        Self: Sized,
+    {
+        self.ask_file().await
+    }
+    /// Ask underlying platform to ask user where to save a file, suggesting `name`
+    async fn ask_save_file(&mut self, name: String) -> Option<PathBuf>;
+    /// Ask underlying platform to show a file in its file manager
+    async fn reveal_file(&mut self, path: PathBuf);
+    /// Ask underlying platform to move a file to the trash, returns whether it was moved
+    async fn trash_file(&mut self, path: PathBuf) -> bool;
     /// Notify the platform that a [Runner] is ready and save it in memory
     async fn add_runner(&mut self, runner: Arc<RwLock<Runner>>, tx: Sender<RunnerMessage>)
     where
         Self: Sized,
-    {}
+    {
+    }
     /// Notify the platform that some playback properties have changed and update those accordingly
-    async fn properties_changed<P: IntoIterator<Item=Property> + Send>(&mut self, properties: P)
+    async fn properties_changed<P: IntoIterator<Item = Property> + Send>(&mut self, properties: P)
+    where
+        Self: Sized,
+    {
+    }
+    /// Notify the platform that the main window was created, called on the UI thread
+    fn add_window(&mut self, window: &MainWindow)
     where
         Self: Sized,
-    {}
+    {
+    }
     /// Allows the platform to do operations once in a while
     async fn tick(&mut self)
     where
         Self: Sized,
-    {}
+    {
+    }
 }
 
 #[cfg(target_os = "linux")]
@@ -100,18 +188,37 @@ impl Platform for LinuxPlatform {
         ask_file_desktop().await
     }
 
+    async fn ask_audio_files(&mut self) -> Vec<PathBuf> {
+        ask_audio_files_desktop().await
+    }
+
+    async fn ask_save_file(&mut self, name: String) -> Option<PathBuf> {
+        ask_save_file_desktop(name).await
+    }
+
+    async fn reveal_file(&mut self, path: PathBuf) {
+        reveal_file_desktop(path)
+    }
+
+    async fn trash_file(&mut self, path: PathBuf) -> bool {
+        trash_file_desktop(path).await
+    }
+
     async fn add_runner(&mut self, runner: Arc<RwLock<Runner>>, tx: Sender<RunnerMessage>) {
-        let server = mpris_server::Server::new(
+        match mpris_server::Server::new(
             "n_music",
             crate::bus_server::linux::MPRISBridge::new(runner, tx.clone()),
         )
-            .await
-            .unwrap();
-        self.server = Some(server);
+        .await
+        {
+            Ok(server) => self.server = Some(server),
+            Err(e) => crate::report_error(text(Text::CantStartMediaControls, &[("error", &e)])),
+        }
     }
-    async fn properties_changed<P: IntoIterator<Item=Property> + Send>(&mut self, properties: P) {
+    async fn properties_changed<P: IntoIterator<Item = Property> + Send>(&mut self, properties: P) {
         if let Some(server) = &self.server {
             let mut new_properties = vec![];
+            let mut seeked = None;
             for p in properties {
                 new_properties.push(match p {
                     Property::Playing(playing) => {
@@ -121,35 +228,41 @@ impl Platform for LinuxPlatform {
                             mpris_server::PlaybackStatus::Paused
                         })
                     }
+                    Property::Stopped => mpris_server::Property::PlaybackStatus(
+                        mpris_server::PlaybackStatus::Stopped,
+                    ),
                     Property::Metadata(metadata) => {
-                        let mut meta = mpris_server::Metadata::new();
-
-                        meta.set_title(metadata.title);
-                        meta.set_artist(metadata.artists);
-                        meta.set_length(Some(mpris_server::Time::from_secs(
-                            metadata.length as i64,
-                        )));
-                        meta.set_art_url(metadata.image_path);
-                        meta.set_trackid(Some(
-                            mpris_server::zbus::zvariant::ObjectPath::from_string_unchecked(
-                                metadata.id,
-                            ),
-                        ));
-
-                        mpris_server::Property::Metadata(meta)
+                        mpris_server::Property::Metadata(metadata.into())
                     }
                     Property::Volume(volume) => mpris_server::Property::Volume(volume),
+                    // MPRIS clients poll the position themselves
+                    Property::Position(_, _) => continue,
+                    // bluez forwards it to the bluetooth devices, they don't poll
+                    Property::Seeked(position) => {
+                        seeked = Some(position);
+                        continue;
+                    }
                 });
             }
-            server.properties_changed(new_properties).await.unwrap()
+            if !new_properties.is_empty() {
+                if let Err(e) = server.properties_changed(new_properties).await {
+                    crate::toast_error(text(Text::CantUpdateMediaControls, &[("error", &e)]));
+                }
+            }
+            if let Some(position) = seeked {
+                let position = mpris_server::Time::from_millis((position * 1000.0) as i64);
+                if let Err(e) = server.emit(mpris_server::Signal::Seeked { position }).await {
+                    eprintln!("can't send the new position to the media controls: {e}");
+                }
+            }
         }
     }
 }
 
-#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[cfg(target_os = "macos")]
 pub struct DesktopPlatform {}
 
-#[cfg(any(target_os = "macos", target_os = "windows"))]
+#[cfg(target_os = "macos")]
 #[async_trait]
 impl Platform for DesktopPlatform {
     async fn open_link(&mut self, link: String) {
@@ -167,6 +280,92 @@ impl Platform for DesktopPlatform {
     async fn ask_file(&mut self) -> Vec<PathBuf> {
         ask_file_desktop().await
     }
+
+    async fn ask_audio_files(&mut self) -> Vec<PathBuf> {
+        ask_audio_files_desktop().await
+    }
+
+    async fn ask_save_file(&mut self, name: String) -> Option<PathBuf> {
+        ask_save_file_desktop(name).await
+    }
+
+    async fn reveal_file(&mut self, path: PathBuf) {
+        reveal_file_desktop(path)
+    }
+
+    async fn trash_file(&mut self, path: PathBuf) -> bool {
+        trash_file_desktop(path).await
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub struct WindowsPlatform {
+    tx: Option<Sender<RunnerMessage>>,
+    taskbar: Option<Sender<Property>>,
+}
+
+#[cfg(target_os = "windows")]
+impl WindowsPlatform {
+    pub fn new() -> Self {
+        Self {
+            tx: None,
+            taskbar: None,
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[async_trait]
+impl Platform for WindowsPlatform {
+    async fn open_link(&mut self, link: String) {
+        open_link_desktop(link)
+    }
+
+    async fn internal_dir(&self) -> PathBuf {
+        internal_dir_desktop().await
+    }
+
+    async fn ask_music_dir(&mut self) -> PathBuf {
+        ask_music_dir_desktop().await
+    }
+
+    async fn ask_file(&mut self) -> Vec<PathBuf> {
+        ask_file_desktop().await
+    }
+
+    async fn ask_audio_files(&mut self) -> Vec<PathBuf> {
+        ask_audio_files_desktop().await
+    }
+
+    async fn ask_save_file(&mut self, name: String) -> Option<PathBuf> {
+        ask_save_file_desktop(name).await
+    }
+
+    async fn reveal_file(&mut self, path: PathBuf) {
+        reveal_file_desktop(path)
+    }
+
+    async fn trash_file(&mut self, path: PathBuf) -> bool {
+        trash_file_desktop(path).await
+    }
+
+    async fn add_runner(&mut self, _runner: Arc<RwLock<Runner>>, tx: Sender<RunnerMessage>) {
+        self.tx = Some(tx);
+    }
+
+    fn add_window(&mut self, window: &MainWindow) {
+        if let Some(tx) = &self.tx {
+            self.taskbar = Some(crate::bus_server::windows::run(window, tx.clone()));
+        }
+    }
+
+    async fn properties_changed<P: IntoIterator<Item = Property> + Send>(&mut self, properties: P) {
+        if let Some(taskbar) = &self.taskbar {
+            for property in properties {
+                let _ = taskbar.send(property);
+            }
+        }
+    }
 }
 
 #[cfg(target_os = "android")]
@@ -174,6 +373,7 @@ pub struct AndroidPlatform {
     app: slint::android::AndroidApp,
     jvm: jni::JavaVM,
     callback: jni::objects::GlobalRef,
+    tx: Option<Sender<RunnerMessage>>,
 }
 
 #[cfg(target_os = "android")]
@@ -183,7 +383,12 @@ impl AndroidPlatform {
         jvm: jni::JavaVM,
         callback: jni::objects::GlobalRef,
     ) -> Self {
-        Self { app, jvm, callback }
+        Self {
+            app,
+            jvm,
+            callback,
+            tx: None,
+        }
     }
 }
 
@@ -242,13 +447,218 @@ impl Platform for AndroidPlatform {
         vec![]
     }
 
-    async fn add_runner(&mut self, runner: Arc<RwLock<Runner>>, tx: Sender<RunnerMessage>) {
+    async fn ask_save_file(&mut self, _name: String) -> Option<PathBuf> {
+        crate::toast_error(text(Text::ExportUnsupported, &[]));
+        None
+    }
+
+    async fn reveal_file(&mut self, _path: PathBuf) {
+        crate::toast_error(text(Text::RevealUnsupported, &[]));
+    }
+
+    async fn trash_file(&mut self, _path: PathBuf) -> bool {
+        crate::toast_error(text(Text::DeleteUnsupported, &[]));
+        false
+    }
+
+    async fn add_runner(&mut self, _runner: Arc<RwLock<Runner>>, tx: Sender<RunnerMessage>) {
+        self.tx = Some(tx);
         let mut env = self.jvm.attach_current_thread().unwrap();
-        env.call_method(&self.callback, "createNotification", "()V", &[])
+        env.call_method(&self.callback, "connectMediaSession", "()V", &[])
             .unwrap();
     }
 
+    async fn properties_changed<P: IntoIterator<Item = Property> + Send>(&mut self, properties: P) {
+        use jni::objects::JValue;
+
+        let mut env = self.jvm.attach_current_thread().unwrap();
+        for property in properties {
+            match property {
+                Property::Playing(playing) => {
+                    env.call_method(
+                        &self.callback,
+                        "setPlaying",
+                        "(Z)V",
+                        &[JValue::Bool(playing as u8)],
+                    )
+                    .unwrap();
+                }
+                Property::Metadata(metadata) => {
+                    let title = env.new_string(metadata.title.unwrap_or_default()).unwrap();
+                    let artist = env
+                        .new_string(
+                            metadata
+                                .artists
+                                .map(|artists| artists.join(", "))
+                                .unwrap_or_default(),
+                        )
+                        .unwrap();
+                    let artwork = env
+                        .new_string(metadata.image_path.unwrap_or_default())
+                        .unwrap();
+                    env.call_method(
+                        &self.callback,
+                        "setMetadata",
+                        "(Ljava/lang/String;Ljava/lang/String;Ljava/lang/String;J)V",
+                        &[
+                            (&title).into(),
+                            (&artist).into(),
+                            (&artwork).into(),
+                            JValue::Long((metadata.length * 1000.0) as i64),
+                        ],
+                    )
+                    .unwrap();
+                }
+                Property::Position(position, length) => {
+                    env.call_method(
+                        &self.callback,
+                        "setPosition",
+                        "(JJ)V",
+                        &[
+                            JValue::Long((position * 1000.0) as i64),
+                            JValue::Long((length * 1000.0) as i64),
+                        ],
+                    )
+                    .unwrap();
+                }
+                Property::Stopped => {
+                    env.call_method(
+                        &self.callback,
+                        "setPlaying",
+                        "(Z)V",
+                        &[JValue::Bool(false as u8)],
+                    )
+                    .unwrap();
+                }
+                // the system handles the volume of the media session
+                Property::Volume(_) => {}
+                // the position is sent every second anyway
+                Property::Seeked(_) => {}
+            }
+        }
+    }
+
     async fn tick(&mut self) {
-        while let Ok(message) = crate::ANDROID_TX.try_recv() {}
+        while let Ok(message) = crate::ANDROID_TX.try_recv() {
+            if let crate::MessageAndroidToRust::Runner(message) = message {
+                if let Some(tx) = &self.tx {
+                    let _ = tx.send(message);
+                }
+            }
+        }
+    }
+}
+
+/// Reads the folder picked through the Storage Access Framework, the paths of its files are the
+/// tree URI followed by their path inside it
+#[cfg(target_os = "android")]
+pub struct ContentStorage {
+    jvm: jni::JavaVM,
+    callback: jni::objects::GlobalRef,
+}
+
+#[cfg(target_os = "android")]
+impl ContentStorage {
+    pub fn new(jvm: jni::JavaVM, callback: jni::objects::GlobalRef) -> Self {
+        Self { jvm, callback }
+    }
+
+    /// Splits `path` into the tree URI and the path inside it
+    fn split(path: &str) -> (&str, &str) {
+        // the id of the tree is percent-encoded, so the first `/` after it starts the path
+        let start = path.find("/tree/").map(|i| i + "/tree/".len()).unwrap_or(0);
+        match path[start..].find('/') {
+            Some(i) => (&path[..start + i], &path[start + i + 1..]),
+            None => (path, ""),
+        }
+    }
+
+    fn open_file(&self, path: &str) -> std::io::Result<std::fs::File> {
+        use std::io;
+        use std::os::fd::FromRawFd;
+
+        let (tree, relative) = Self::split(path);
+        let mut env = self.jvm.attach_current_thread().map_err(io::Error::other)?;
+        let tree = env.new_string(tree).map_err(io::Error::other)?;
+        let relative = env.new_string(relative).map_err(io::Error::other)?;
+        let fd = env
+            .call_method(
+                &self.callback,
+                "openDocument",
+                "(Ljava/lang/String;Ljava/lang/String;)I",
+                &[(&tree).into(), (&relative).into()],
+            )
+            .and_then(|fd| fd.i())
+            .map_err(io::Error::other)?;
+        if fd < 0 {
+            return Err(io::Error::from(io::ErrorKind::NotFound));
+        }
+        // the descriptor was detached on the Java side, so the file owns it from now on
+        Ok(unsafe { std::fs::File::from_raw_fd(fd) })
+    }
+}
+
+#[cfg(target_os = "android")]
+impl n_audio::storage::Storage for ContentStorage {
+    fn handles(&self, path: &str) -> bool {
+        path.starts_with("content://")
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        let (tree, relative) = Self::split(path);
+        let Ok(mut env) = self.jvm.attach_current_thread() else {
+            return false;
+        };
+        let (Ok(tree), Ok(relative)) = (env.new_string(tree), env.new_string(relative)) else {
+            return false;
+        };
+        env.call_method(
+            &self.callback,
+            "documentExists",
+            "(Ljava/lang/String;Ljava/lang/String;)Z",
+            &[(&tree).into(), (&relative).into()],
+        )
+        .and_then(|exists| exists.z())
+        .unwrap_or(false)
+    }
+
+    fn audio_files(&self, root: &str) -> std::io::Result<Vec<String>> {
+        use jni::objects::{JObjectArray, JString};
+        use std::io;
+
+        let (tree, _) = Self::split(root);
+        let mut env = self.jvm.attach_current_thread().map_err(io::Error::other)?;
+        let java_tree = env.new_string(tree).map_err(io::Error::other)?;
+        let files: JObjectArray = env
+            .call_method(
+                &self.callback,
+                "listAudioFiles",
+                "(Ljava/lang/String;)[Ljava/lang/String;",
+                &[(&java_tree).into()],
+            )
+            .and_then(|files| files.l())
+            .map_err(io::Error::other)?
+            .into();
+        let len = env.get_array_length(&files).map_err(io::Error::other)?;
+        let mut paths = Vec::with_capacity(len as usize);
+        for i in 0..len {
+            let file: JString = env
+                .get_object_array_element(&files, i)
+                .map_err(io::Error::other)?
+                .into();
+            let relative: String = env.get_string(&file).map_err(io::Error::other)?.into();
+            paths.push(format!("{tree}/{relative}"));
+            // big libraries would fill the table of local references otherwise
+            let _ = env.delete_local_ref(file);
+        }
+        Ok(paths)
+    }
+
+    fn open(&self, path: &str) -> std::io::Result<Box<dyn n_audio::storage::MediaSource>> {
+        Ok(Box::new(self.open_file(path)?))
+    }
+
+    fn size(&self, path: &str) -> std::io::Result<u64> {
+        Ok(self.open_file(path)?.metadata()?.len())
     }
 }
@@ -0,0 +1,324 @@
+//! Experimental WebAssembly plugins, loaded at the start from [PLUGIN_DIR] in the internal
+//! directory when [crate::settings::Settings::plugins] is on
+//!
+//! The modules are interpreted without any access to the system, they can only write to the log,
+//! and every call is stopped after [PLUGIN_FUEL] instructions. The host and a plugin exchange
+//! UTF-8 strings in the memory of the plugin:
+//!
+//! - it exports `memory` and `alloc(len: i32) -> i32`, the arguments are written where `alloc`
+//!   says
+//! - the argument of every call is the track as JSON, see [PluginTrack]
+//! - the strings it returns are packed in an `i64` as `ptr << 32 | len`, 0 for none
+//!
+//! Every call is optional:
+//!
+//! - `on_track_change(ptr: i32, len: i32)` when the playing track changes
+//! - `provide_lyrics(ptr: i32, len: i32) -> i64` the lyrics of the track as text, shown in Now
+//!   Playing
+//! - `provide_metadata(ptr: i32, len: i32) -> i64` the tags it knows of the track, as JSON with
+//!   the fields of [PluginTrack], it's a [MetadataProvider]
+//!
+//! It can import `log(ptr: i32, len: i32)` from the `n_music` module
+
+use crate::log;
+use crate::metadata::{fill, MetadataProvider};
+use n_audio::{remove_ext, Metadata};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsStr;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use wasmi::{
+    Caller, Config, Engine, Extern, Instance, Linker, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+/// Folder of the plugins in the internal directory
+pub const PLUGIN_DIR: &str = "plugins";
+/// Instructions a plugin can run in a call
+const PLUGIN_FUEL: u64 = 100_000_000;
+/// Bytes of memory a plugin can grow to
+const PLUGIN_MEMORY: usize = 64 * 1024 * 1024;
+/// Longest string a plugin can return, in bytes
+const MAX_OUTPUT: usize = 1024 * 1024;
+
+/// The plugins loaded by [load], each is called by one thread at a time
+static PLUGINS: OnceCell<Vec<Mutex<Plugin>>> = OnceCell::new();
+
+/// A track as the plugins see it
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PluginTrack {
+    /// Name of the file, without the folders
+    pub file: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: String,
+    pub genre: String,
+    pub date: String,
+    pub track: Option<u32>,
+    /// In seconds, 0 when it isn't known yet
+    pub length: f64,
+}
+
+impl PluginTrack {
+    pub fn new(path: &Path, metadata: &Metadata) -> Self {
+        Self {
+            file: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            title: metadata.title.clone(),
+            artist: metadata.artist.clone(),
+            album: metadata.album.clone(),
+            album_artist: metadata.album_artist.clone(),
+            genre: metadata.genre.clone(),
+            date: metadata.date.clone(),
+            track: metadata.track,
+            length: metadata.time.length,
+        }
+    }
+}
+
+/// What `provide_metadata` returns, the fields it doesn't know can be left out
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct ProvidedTags {
+    title: String,
+    artist: String,
+    album: String,
+    album_artist: String,
+    genre: String,
+    date: String,
+    track: Option<u32>,
+}
+
+impl From<ProvidedTags> for Metadata {
+    fn from(tags: ProvidedTags) -> Self {
+        Self {
+            title: tags.title,
+            artist: tags.artist,
+            album: tags.album,
+            album_artist: tags.album_artist,
+            genre: tags.genre,
+            date: tags.date,
+            track: tags.track,
+            ..Default::default()
+        }
+    }
+}
+
+struct Plugin {
+    name: String,
+    store: Store<StoreLimits>,
+    instance: Instance,
+}
+
+impl Plugin {
+    fn load(engine: &Engine, path: &Path) -> Result<Self, String> {
+        let wasm = std::fs::read(path).map_err(|e| e.to_string())?;
+        let module = Module::new(engine, &wasm).map_err(|e| e.to_string())?;
+        let mut store = Store::new(
+            engine,
+            StoreLimitsBuilder::new().memory_size(PLUGIN_MEMORY).build(),
+        );
+        store.limiter(|limits| limits);
+        let name = remove_ext(path);
+        let mut linker = Linker::new(engine);
+        let plugin = name.clone();
+        linker
+            .func_wrap(
+                "n_music",
+                "log",
+                move |caller: Caller<'_, StoreLimits>, ptr: i32, len: i32| {
+                    let memory = caller.get_export("memory").and_then(Extern::into_memory);
+                    let message = memory.and_then(|memory| {
+                        let data = memory.data(&caller);
+                        let start = ptr as u32 as usize;
+                        data.get(start..start + len as u32 as usize)
+                            .map(String::from_utf8_lossy)
+                            .map(|message| message.to_string())
+                    });
+                    if let Some(message) = message {
+                        log(&format!("[{plugin}] {message}"));
+                    }
+                },
+            )
+            .map_err(|e| e.to_string())?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.start(&mut store))
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            name,
+            store,
+            instance,
+        })
+    }
+
+    fn has(&self, export: &str) -> bool {
+        self.instance.get_func(&self.store, export).is_some()
+    }
+
+    /// Writes `input` in the memory of the plugin, returning where
+    fn write(&mut self, input: &str) -> Result<(i32, i32), String> {
+        let alloc = self
+            .instance
+            .get_typed_func::<i32, i32>(&self.store, "alloc")
+            .map_err(|e| e.to_string())?;
+        let memory = self
+            .instance
+            .get_memory(&self.store, "memory")
+            .ok_or("no memory exported")?;
+        let len = input.len() as i32;
+        let ptr = alloc
+            .call(&mut self.store, len)
+            .map_err(|e| e.to_string())?;
+        memory
+            .write(&mut self.store, ptr as u32 as usize, input.as_bytes())
+            .map_err(|e| e.to_string())?;
+        Ok((ptr, len))
+    }
+
+    /// Calls `export` with `input`, for the calls that don't return anything
+    fn notify(&mut self, export: &str, input: &str) -> Result<(), String> {
+        self.store
+            .set_fuel(PLUGIN_FUEL)
+            .map_err(|e| e.to_string())?;
+        let func = self
+            .instance
+            .get_typed_func::<(i32, i32), ()>(&self.store, export)
+            .map_err(|e| e.to_string())?;
+        let arguments = self.write(input)?;
+        func.call(&mut self.store, arguments)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Calls `export` with `input` and reads the string it returns
+    fn call(&mut self, export: &str, input: &str) -> Result<Option<String>, String> {
+        self.store
+            .set_fuel(PLUGIN_FUEL)
+            .map_err(|e| e.to_string())?;
+        let func = self
+            .instance
+            .get_typed_func::<(i32, i32), i64>(&self.store, export)
+            .map_err(|e| e.to_string())?;
+        let arguments = self.write(input)?;
+        let packed = func
+            .call(&mut self.store, arguments)
+            .map_err(|e| e.to_string())? as u64;
+        let (ptr, len) = ((packed >> 32) as usize, (packed & 0xffff_ffff) as usize);
+        if len == 0 {
+            return Ok(None);
+        }
+        if len > MAX_OUTPUT {
+            return Err(format!("{len} bytes returned"));
+        }
+        let memory = self
+            .instance
+            .get_memory(&self.store, "memory")
+            .ok_or("no memory exported")?;
+        let mut output = vec![0; len];
+        memory
+            .read(&self.store, ptr, &mut output)
+            .map_err(|e| e.to_string())?;
+        String::from_utf8(output)
+            .map(Some)
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Loads every `.wasm` file in `dir`, it can only be done once
+///
+/// It blocks while the modules are compiled, it has to be called outside of the async tasks
+pub fn load(dir: &Path) {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config);
+    let mut plugins = vec![];
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new("wasm")) {
+                continue;
+            }
+            match Plugin::load(&engine, &path) {
+                Ok(plugin) => plugins.push(Mutex::new(plugin)),
+                Err(e) => log(&format!("can't load the plugin {}: {e}", path.display())),
+            }
+        }
+    }
+    let _ = PLUGINS.set(plugins);
+}
+
+/// Whether any plugin was loaded
+pub fn loaded() -> bool {
+    PLUGINS.get().is_some_and(|plugins| !plugins.is_empty())
+}
+
+/// Calls `export` of every plugin that has it, the ones that fail are written to the log
+fn each<T>(export: &str, mut call: impl FnMut(&mut Plugin) -> Result<T, String>) -> Vec<T> {
+    let mut results = vec![];
+    for plugin in PLUGINS.get().into_iter().flatten() {
+        let mut plugin = plugin.lock().unwrap();
+        if !plugin.has(export) {
+            continue;
+        }
+        match call(&mut plugin) {
+            Ok(result) => results.push(result),
+            Err(e) => log(&format!(
+                "the plugin {} failed in {export}: {e}",
+                plugin.name
+            )),
+        }
+    }
+    results
+}
+
+/// Tells the plugins the playing track changed
+pub fn on_track_change(track: &PluginTrack) {
+    let Ok(input) = serde_json::to_string(track) else {
+        return;
+    };
+    each("on_track_change", |plugin| {
+        plugin.notify("on_track_change", &input)
+    });
+}
+
+/// The lyrics of the first plugin that has them
+pub fn provide_lyrics(track: &PluginTrack) -> Option<String> {
+    let input = serde_json::to_string(track).ok()?;
+    let mut found = None;
+    each("provide_lyrics", |plugin| {
+        if found.is_none() {
+            found = plugin.call("provide_lyrics", &input)?;
+        }
+        Ok(())
+    });
+    found
+}
+
+/// Asks the plugins with `provide_metadata`, the first ones come first
+pub struct PluginProvider;
+
+impl MetadataProvider for PluginProvider {
+    fn name(&self) -> &'static str {
+        "Plugins"
+    }
+
+    fn provide(&self, path: &Path, known: &Metadata) -> io::Result<Metadata> {
+        let input = serde_json::to_string(&PluginTrack::new(path, known))?;
+        let mut metadata = Metadata::default();
+        let outputs = each("provide_metadata", |plugin| {
+            plugin.call("provide_metadata", &input)
+        });
+        for output in outputs.into_iter().flatten() {
+            match serde_json::from_str::<ProvidedTags>(&output) {
+                Ok(tags) => fill(&mut metadata, tags.into()),
+                Err(e) => log(&format!("a plugin gave tags that aren't valid: {e}")),
+            }
+        }
+        Ok(metadata)
+    }
+}
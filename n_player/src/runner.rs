@@ -1,11 +1,17 @@
-use flume::Receiver;
-use n_audio::queue::QueuePlayer;
-use n_audio::TrackTime;
+use crate::localization::{text, Text};
+use crate::{report_error, toast_error, toast_undo, ReplayGainMode};
+use flume::{Receiver, Sender};
+use n_audio::dsp::DspSettings;
+use n_audio::loudness::GainTarget;
+use n_audio::queue::{QueueItem, QueuePlayer};
+use n_audio::{OutputFormat, TrackTime};
+use std::collections::{HashSet, VecDeque};
 use std::fs::File;
+use std::io;
 use std::io::BufReader;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 pub async fn run(runner: Arc<RwLock<Runner>>, rx: Receiver<RunnerMessage>) {
@@ -32,9 +38,98 @@ pub enum RunnerMessage {
     TogglePause,
     Pause,
     Play,
+    /// Ends the current track and goes back to its start, playing starts it over
+    Stop,
     SetVolume(f64),
+    ToggleMute,
     PlayTrack(u16),
+    /// Replaces the queued tracks with these and plays the first one
+    PlayTracks(Vec<u16>),
+    /// Plays the track after the current one and the ones queued before
+    Enqueue(u16),
+    /// Removes the n-th entry of [`Runner::upcoming`]
+    RemoveUpcoming(usize),
+    /// Moves an entry of [`Runner::upcoming`] to another position
+    MoveUpcoming(usize, usize),
+    /// Removes the queued tracks and brings back the ones removed from the normal order
+    ClearQueue,
+    /// Brings the upcoming tracks back to before the last removal, move or clear
+    UndoQueue,
+    /// Makes again the last change of the upcoming tracks that was undone
+    RedoQueue,
+    /// Removes a track deleted from the disk from the library and the queue
+    Remove(u16),
+    /// A track that didn't open in time can be reached again
+    Available(u16),
     Seek(RunnerSeek),
+    SetDsp(DspSettings),
+    SetOutput(OutputFormat),
+    /// Which ReplayGain tags the volume follows, from the next track
+    SetReplayGain(ReplayGainMode),
+    /// Loudness the ReplayGain brings the tracks to, from the next track
+    SetGainTarget(GainTarget),
+    /// Goes back by the seconds when playback resumes after being paused for at least the
+    /// duration, `None` to never do it
+    SetRewind(Option<(Duration, f64)>),
+}
+
+/// Changes made by the runner that the app has to show
+#[derive(Debug)]
+pub enum RunnerEvent {
+    /// The library was replaced, the indices of the old tracks don't mean anything anymore
+    LibraryChanged,
+    /// The volume changed, from the app or from outside like the media controls
+    VolumeChanged(f64),
+    /// The track at `index` of the library that had `path` stopped being played, sent once for
+    /// every track that started
+    TrackEnded {
+        index: u16,
+        path: PathBuf,
+        end: TrackEnd,
+    },
+}
+
+/// Seconds of a track that count as a play even when it's cut before half of it
+pub const PLAYED_SECONDS: f64 = 4.0 * 60.0;
+
+/// How a track stopped being played
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TrackEnd {
+    /// It played to its end by itself
+    Finished,
+    /// Another track, a stop or its removal cut it at `position` seconds of `length`
+    Skipped { position: f64, length: f64 },
+}
+
+impl TrackEnd {
+    /// Share of the track that was reached, from 0 to 1
+    pub fn share(self) -> f64 {
+        match self {
+            TrackEnd::Finished => 1.0,
+            TrackEnd::Skipped { length, .. } if length <= 0.0 => 0.0,
+            TrackEnd::Skipped { position, length } => (position / length).min(1.0),
+        }
+    }
+
+    /// Whether it was listened to long enough to count as a play, half of it or
+    /// [PLAYED_SECONDS] like the scrobblers want
+    pub fn is_play(self) -> bool {
+        match self {
+            TrackEnd::Finished => true,
+            TrackEnd::Skipped { position, .. } => self.share() >= 0.5 || position >= PLAYED_SECONDS,
+        }
+    }
+}
+
+/// How many changes of the upcoming tracks can be undone
+const QUEUE_HISTORY: usize = 20;
+
+/// The upcoming tracks as they were before a change, see [RunnerMessage::UndoQueue]
+#[derive(Clone, Debug)]
+struct QueueState {
+    queued: VecDeque<u16>,
+    skipped: HashSet<u16>,
+    resume: Option<u16>,
 }
 
 #[derive(Debug)]
@@ -46,24 +141,71 @@ pub enum RunnerSeek {
 pub struct Runner {
     player: QueuePlayer,
     current_time: TrackTime,
+    previous_volume: f64,
+    queued: VecDeque<u16>,
+    // tracks removed from the upcoming ones, they're skipped once by the normal order
+    skipped: HashSet<u16>,
+    // where the normal order continues once the queued tracks are played
+    resume: Option<u16>,
+    // tracks deleted from the disk, they stay in the player so the indices don't change
+    removed: HashSet<u16>,
+    // tracks that didn't open in time, skipped until they can be reached again
+    unavailable: HashSet<u16>,
+    // music directory and files waiting for the current track to end to replace the library
+    library: Option<(String, Vec<String>)>,
+    rewind: Option<(Duration, f64)>,
+    replay_gain: ReplayGainMode,
+    stopped: bool,
+    paused_at: Option<Instant>,
+    // the track that started and whose end wasn't reported yet
+    playing: Option<u16>,
+    // changes of the upcoming tracks since the current track started, the last one at the end
+    undo: Vec<QueueState>,
+    redo: Vec<QueueState>,
+    events: Sender<RunnerEvent>,
 }
 
 impl Runner {
-    pub fn new(player: QueuePlayer) -> Self {
+    pub fn new(player: QueuePlayer, events: Sender<RunnerEvent>) -> Self {
         Self {
             player,
             current_time: TrackTime::default(),
+            previous_volume: 1.0,
+            queued: VecDeque::new(),
+            skipped: HashSet::new(),
+            resume: None,
+            removed: HashSet::new(),
+            unavailable: HashSet::new(),
+            library: None,
+            rewind: None,
+            replay_gain: ReplayGainMode::default(),
+            stopped: false,
+            paused_at: None,
+            playing: None,
+            undo: vec![],
+            redo: vec![],
+            events,
         }
     }
 
     pub async fn update(&mut self) {
+        // the position stays at the start until something plays again
+        if self.stopped {
+            return;
+        }
         if let Some(time) = self.player.get_time() {
             self.current_time = time;
         }
 
         if self.player.has_ended() {
-            if let Err(err) = self.player.play_next().await {
-                eprintln!("error happened: {err}");
+            if self.player.take_output_failure() {
+                // nothing was heard, and the next tracks would fail the same way
+                self.playing = None;
+                self.stop().await;
+                report_error(text(Text::CantOpenOutput, &[]));
+            } else {
+                self.report_end(true).await;
+                self.play_upcoming().await;
             }
         }
     }
@@ -72,67 +214,398 @@ impl Runner {
         println!("{message:?}");
         match message {
             RunnerMessage::PlayNext => {
+                self.report_end(false).await;
                 self.player.end_current().await.unwrap();
-                if let Err(err) = self.player.play_next().await {
-                    eprintln!("error happened: {err}");
-                }
+                self.play_upcoming().await;
             }
             RunnerMessage::PlayPrevious => {
                 if self.current_time.position > 3.0 {
                     self.player.seek_to(0, 0.0).await.unwrap();
                 } else {
+                    self.report_end(false).await;
                     self.player.end_current().await.unwrap();
-                    if let Err(err) = self.player.play_previous().await {
-                        eprintln!("error happened: {err}");
-                    }
+                    self.player.set_gain_mode(self.replay_gain.resolve(false));
+                    self.stopped = false;
+                    let result = self.player.play_previous().await;
+                    self.check_played(result);
                 }
             }
             RunnerMessage::TogglePause => {
+                if self.stopped {
+                    self.play_index(self.player.index(), false).await;
+                    return;
+                }
                 if self.player.is_paused() {
+                    self.rewind_after_pause().await;
                     self.player.unpause().await.unwrap();
                 } else {
                     self.player.pause().await.unwrap();
+                    self.paused_at = Some(Instant::now());
                 }
                 if !self.player.is_playing() {
-                    if let Err(err) = self.player.play_next().await {
-                        eprintln!("error happened: {err}");
-                    }
+                    self.play_upcoming().await;
                 }
             }
             RunnerMessage::Pause => {
+                if !self.player.is_paused() {
+                    self.paused_at = Some(Instant::now());
+                }
                 self.player.pause().await.unwrap();
             }
             RunnerMessage::Play => {
+                if self.stopped {
+                    self.play_index(self.player.index(), false).await;
+                    return;
+                }
+                if self.player.is_paused() {
+                    self.rewind_after_pause().await;
+                }
                 self.player.unpause().await.unwrap();
                 if !self.player.is_playing() {
-                    if let Err(err) = self.player.play_next().await {
-                        eprintln!("error happened: {err}");
-                    }
+                    self.play_upcoming().await;
                 }
             }
+            RunnerMessage::Stop => self.stop().await,
             RunnerMessage::SetVolume(volume) => {
                 self.player.set_volume(volume as f32).await.unwrap();
+                let _ = self.events.send(RunnerEvent::VolumeChanged(self.volume()));
+            }
+            RunnerMessage::ToggleMute => {
+                let volume = self.volume();
+                if volume > 0.0 {
+                    self.previous_volume = volume;
+                    self.player.set_volume(0.0).await.unwrap();
+                } else {
+                    self.player
+                        .set_volume(self.previous_volume as f32)
+                        .await
+                        .unwrap();
+                }
+                let _ = self.events.send(RunnerEvent::VolumeChanged(self.volume()));
             }
             RunnerMessage::PlayTrack(index) => {
+                self.resume = None;
+                self.report_end(false).await;
                 self.player.end_current().await.unwrap();
-                if let Err(err) = self.player.play_index(index).await {
-                    eprintln!("error happened: {err}");
+                self.play_index(index, false).await;
+            }
+            RunnerMessage::PlayTracks(indices) => {
+                let mut indices = VecDeque::from(indices);
+                if let Some(index) = indices.pop_front() {
+                    self.queued = indices;
+                    self.resume = None;
+                    self.report_end(false).await;
+                    self.player.end_current().await.unwrap();
+                    self.play_index(index, true).await;
+                }
+            }
+            RunnerMessage::Enqueue(index) => {
+                self.queued.push_back(index);
+            }
+            RunnerMessage::RemoveUpcoming(position) => {
+                if position < self.queued.len() {
+                    self.save_queue(Text::RemovedFromQueue);
+                    self.queued.remove(position);
+                } else if let Some((index, _)) = self.upcoming(position + 1).get(position) {
+                    let index = *index;
+                    self.save_queue(Text::RemovedFromQueue);
+                    self.skipped.insert(index);
                 }
             }
+            RunnerMessage::MoveUpcoming(from, to) => {
+                let mut upcoming = self.upcoming(from.max(to) + 1);
+                if from >= upcoming.len() {
+                    return;
+                }
+                self.save_queue(Text::QueueReordered);
+                let entry = upcoming.remove(from);
+                upcoming.insert(to.min(upcoming.len()), entry);
+                // everything up to the moved entry becomes queued, so the normal order skips it
+                for (index, queued) in &upcoming {
+                    if !queued {
+                        self.skipped.insert(*index);
+                    }
+                }
+                let rest = self.queued.split_off(self.queued.len().min(upcoming.len()));
+                self.queued = upcoming.into_iter().map(|(index, _)| index).collect();
+                self.queued.extend(rest);
+                self.resume.get_or_insert(self.player.index());
+            }
+            RunnerMessage::ClearQueue => {
+                if !self.queued.is_empty() || !self.skipped.is_empty() {
+                    self.save_queue(Text::QueueCleared);
+                    self.queued.clear();
+                    self.skipped.clear();
+                }
+            }
+            RunnerMessage::UndoQueue => {
+                if let Some(state) = self.undo.pop() {
+                    self.redo.push(self.queue_state());
+                    self.restore_queue(state);
+                }
+            }
+            RunnerMessage::RedoQueue => {
+                if let Some(state) = self.redo.pop() {
+                    self.undo.push(self.queue_state());
+                    self.restore_queue(state);
+                }
+            }
+            RunnerMessage::Remove(index) => {
+                self.removed.insert(index);
+                self.queued.retain(|queued| *queued != index);
+                if self.player.index() == index {
+                    let playback = self.playback();
+                    self.report_end(false).await;
+                    self.player.end_current().await.unwrap();
+                    if playback {
+                        self.play_upcoming().await;
+                    }
+                }
+            }
+            RunnerMessage::Available(index) => {
+                self.unavailable.remove(&index);
+            }
+            RunnerMessage::SetDsp(dsp) => {
+                self.player.set_dsp(dsp).await.unwrap();
+            }
+            RunnerMessage::SetOutput(format) => {
+                self.player.set_output(format).await.unwrap();
+            }
+            RunnerMessage::SetReplayGain(mode) => {
+                self.replay_gain = mode;
+            }
+            RunnerMessage::SetGainTarget(target) => {
+                self.player.set_gain_target(target);
+            }
+            RunnerMessage::SetRewind(rewind) => {
+                self.rewind = rewind;
+            }
             RunnerMessage::Seek(seek) => {
                 let seek = match seek {
                     RunnerSeek::Absolute(value) => value,
                     RunnerSeek::Relative(value) => self.current_time.position + value,
                 };
                 if let Err(e) = self.player.seek_to(seek.trunc() as u64, seek.fract()).await {
-                    eprintln!("error happened while asking to seek: {e}");
+                    toast_error(text(Text::CantSeek, &[("error", &e)]));
                 }
             }
         }
     }
 
+    /// Goes back a little when the pause lasted long enough to forget where the track was, like
+    /// in the middle of an audiobook
+    async fn rewind_after_pause(&mut self) {
+        let (Some(paused_at), Some((after, seconds))) = (self.paused_at.take(), self.rewind) else {
+            return;
+        };
+        if paused_at.elapsed() < after {
+            return;
+        }
+        let position = (self.current_time.position - seconds).max(0.0);
+        if let Err(e) = self
+            .player
+            .seek_to(position.trunc() as u64, position.fract())
+            .await
+        {
+            toast_error(text(Text::CantSeek, &[("error", &e)]));
+        }
+    }
+
+    /// Plays the first queued track, or the next one in order that wasn't removed from the
+    /// upcoming tracks
+    async fn play_upcoming(&mut self) {
+        if let Some((path, files)) = self.library.take() {
+            self.load_library(path, files).await;
+        }
+        let (index, queued) = if let Some(index) = self.queued.pop_front() {
+            self.resume.get_or_insert(self.player.index());
+            (index, true)
+        } else {
+            let from = self.resume.take().unwrap_or(self.player.index());
+            let len = self.player.len() as u16;
+            if len == 0 {
+                return;
+            }
+            let mut index = (from + 1) % len;
+            for _ in 0..len {
+                if !self.removed.contains(&index)
+                    && !self.unavailable.contains(&index)
+                    && !self.skipped.remove(&index)
+                {
+                    break;
+                }
+                index = (index + 1) % len;
+            }
+            (index, false)
+        };
+        self.play_index(index, queued).await;
+    }
+
+    /// Plays the track at `index`, `queued` when it comes from the queued tracks so the automatic
+    /// ReplayGain mode uses the album gain
+    async fn play_index(&mut self, index: u16, queued: bool) {
+        self.player.set_gain_mode(self.replay_gain.resolve(queued));
+        self.stopped = false;
+        let result = self.player.play_index(index).await;
+        self.check_played(result);
+    }
+
+    /// Tells why the current track can't be played, the ones that didn't open in time are
+    /// skipped until [RunnerMessage::Available] instead of hanging again
+    fn check_played(&mut self, result: io::Result<()>) {
+        // the saved upcoming tracks would bring back the one that just started
+        self.undo.clear();
+        self.redo.clear();
+        match result {
+            Ok(()) => self.playing = Some(self.player.index()),
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => {
+                self.unavailable.insert(self.player.index());
+                toast_error(text(Text::TrackUnavailable, &[]));
+            }
+            Err(err) => toast_error(text(Text::CantPlay, &[("error", &err)])),
+        }
+    }
+
+    /// Keeps the upcoming tracks as they are before a change, so it can be undone from the toast
+    /// that tells about it
+    fn save_queue(&mut self, change: Text) {
+        self.undo.push(self.queue_state());
+        if self.undo.len() > QUEUE_HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        toast_undo(text(change, &[]));
+    }
+
+    fn queue_state(&self) -> QueueState {
+        QueueState {
+            queued: self.queued.clone(),
+            skipped: self.skipped.clone(),
+            resume: self.resume,
+        }
+    }
+
+    fn restore_queue(&mut self, state: QueueState) {
+        self.queued = state.queued;
+        self.skipped = state.skipped;
+        self.resume = state.resume;
+    }
+
+    /// Tells the app how the track that started stopped, `finished` when it reached its end by
+    /// itself
+    async fn report_end(&mut self, finished: bool) {
+        let Some(index) = self.playing.take() else {
+            return;
+        };
+        let Some(path) = self.player.get_path_for_file(index).await else {
+            return;
+        };
+        let end = if finished {
+            TrackEnd::Finished
+        } else {
+            // the position is only refreshed twice a second
+            if let Some(time) = self.player.get_time() {
+                self.current_time = time;
+            }
+            TrackEnd::Skipped {
+                position: self.current_time.position,
+                length: self.current_time.length,
+            }
+        };
+        let _ = self
+            .events
+            .send(RunnerEvent::TrackEnded { index, path, end });
+    }
+
+    /// Replaces the tracks with `files` inside the music directory `path`, shuffled like at startup
+    async fn load_library(&mut self, path: String, files: Vec<String>) {
+        self.player.clear();
+        self.player.set_path(path.clone());
+        if let Err(e) = self.player.add_all(files).await {
+            toast_error(text(Text::CantRead, &[("path", &path), ("error", &e)]));
+        }
+        self.player.shrink_to_fit();
+        self.player.shuffle();
+        self.queued.clear();
+        self.skipped.clear();
+        self.resume = None;
+        self.undo.clear();
+        self.redo.clear();
+        self.removed.clear();
+        self.unavailable.clear();
+        let _ = self.events.send(RunnerEvent::LibraryChanged);
+    }
+
+    /// Returns up to `limit` tracks that will be played next, with whether they were queued
+    ///
+    /// The queued tracks come first, then the normal order that loops around the library
+    pub fn upcoming(&self, limit: usize) -> Vec<(u16, bool)> {
+        let mut upcoming = self
+            .queued
+            .iter()
+            .take(limit)
+            .map(|index| (*index, true))
+            .collect::<Vec<_>>();
+        let len = self.player.len() as u16;
+        if len == 0 {
+            return upcoming;
+        }
+        let mut index = self.resume.unwrap_or(self.player.index());
+        for _ in 0..len {
+            if upcoming.len() >= limit {
+                break;
+            }
+            index = (index + 1) % len;
+            if !self.skipped.contains(&index)
+                && !self.removed.contains(&index)
+                && !self.unavailable.contains(&index)
+            {
+                upcoming.push((index, false));
+            }
+        }
+        upcoming
+    }
+
+    /// Stops the current track and goes back to its start, used when quitting too so nothing
+    /// keeps playing in the background
+    pub async fn stop(&mut self) {
+        self.report_end(false).await;
+        if let Err(e) = self.player.end_current().await {
+            eprintln!("can't stop the player: {e}");
+        }
+        // there's nothing to start over when no track was played yet
+        self.stopped = self.player.is_playing();
+        self.paused_at = None;
+        self.current_time.position = 0.0;
+    }
+
+    /// Replaces the library with `files` inside the music directory `path` once the current track
+    /// ends, right away when no track was started
+    pub async fn set_library(&mut self, path: String, files: Vec<String>) {
+        if self.player.is_playing() {
+            self.library = Some((path, files));
+        } else {
+            self.load_library(path, files).await;
+        }
+    }
+
+    /// Tracks deleted from the disk while the app was running
+    pub fn removed(&self) -> &HashSet<u16> {
+        &self.removed
+    }
+
+    /// Tracks that didn't open in time
+    pub fn unavailable(&self) -> &HashSet<u16> {
+        &self.unavailable
+    }
+
     pub fn playback(&self) -> bool {
-        !self.player.is_paused() && self.player.is_playing()
+        !self.stopped && !self.player.is_paused() && self.player.is_playing()
+    }
+
+    /// Whether the playback was stopped, rather than paused
+    pub fn stopped(&self) -> bool {
+        self.stopped
     }
 
     pub fn volume(&self) -> f64 {
@@ -171,7 +644,20 @@ impl Runner {
         self.player.get_path_for_file(i).await
     }
 
-    pub async fn current_track(&self) -> Option<String> {
-        self.player.current_track_name().await
+    /// Adds a track to the library, returning its index
+    pub async fn add(&mut self, path: &Path) -> io::Result<u16> {
+        self.player.add(path.to_string_lossy().to_string()).await?;
+        Ok(self.player.len() as u16 - 1)
+    }
+
+    /// Points a track to the file it was moved to
+    pub async fn set_path_for_file(&mut self, i: u16, path: &Path) -> io::Result<()> {
+        self.player
+            .set_path_for_file(i, path.to_string_lossy().to_string())
+            .await
+    }
+
+    pub async fn current_item(&self) -> Option<QueueItem> {
+        self.player.current_item().await
     }
 }
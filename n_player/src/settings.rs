@@ -1,22 +1,158 @@
 use crate::platform::Platform;
-use crate::{FileTrack, Theme, WindowSize};
-use bitcode::{Decode, Encode};
+use crate::{
+    default_columns, now, Accent, BitDepth, Column, ColumnSettings, Equalizer, EqualizerPreset,
+    FileTrack, FolderSettings, Grouping, ImportMode, PlayRecord, PlayStats, ReplayGainMode, Theme,
+    WindowSize, DEFAULT_GENRE_RULES, DEFAULT_ORGANIZE_PATTERN,
+};
+use bitcode::Decode;
+use n_audio::loudness::GainTarget;
+use n_audio::{remove_ext, OutputFormat, SnapcastSource};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
-use std::hash::{DefaultHasher, Hash, Hasher};
-use std::io::{BufReader, BufWriter, Cursor};
+use std::io::{self, BufReader, BufWriter, Cursor};
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tempfile::NamedTempFile;
 
-#[derive(Debug, Decode, Encode)]
+// saved as JSON with the version of its format, see [versioned_json]
+#[derive(Debug, Deserialize, Serialize)]
+// the settings added since the file or a backup was saved take their default value
+#[serde(default)]
 pub struct Settings {
     pub path: String,
     pub volume: f64,
+    /// Volume last used with every output device, by name
+    pub device_volumes: HashMap<String, f64>,
+    /// Whether the playback pauses when the output device it was on is disconnected, like
+    /// headphones going out of range
+    pub pause_on_disconnect: bool,
+    /// Minutes the disconnected device has to come back in for the playback to resume, 0 to
+    /// never resume it
+    pub resume_within: u16,
     pub theme: Theme,
+    pub accent: Accent,
+    pub accent_color: u32,
     pub window_size: WindowSize,
     pub save_window_size: bool,
+    /// Where the window was left in physical pixels, kept along with the size
+    pub window_position: Option<(i32, i32)>,
+    pub maximized: bool,
+    pub show_remaining: bool,
+    pub auto_scroll: bool,
+    /// Whether the first-run wizard was completed
+    pub onboarded: bool,
+    /// Whether the system media controls and the media keys drive the player, read at the start
+    pub media_controls: bool,
+    /// Whether the WASM plugins are loaded, read at the start, see [crate::plugins]
+    pub plugins: bool,
+    pub grouping: Grouping,
+    pub folder_view: bool,
+    pub queue_open: bool,
+    pub close_to_tray: bool,
+    /// Whether closing the window while playing asks first, when it doesn't go to the tray
+    pub confirm_exit: bool,
+    pub start_minimized: bool,
+    pub autoplay: bool,
+    pub auto_dj: bool,
+    /// Mirrors the play counts in the tags of the files
+    pub sync_play_counts: bool,
+    /// Shows the track that starts in a corner of the screen while the window is in the background
+    pub osd: bool,
+    /// Whether hovering a track in the list plays a quiet snippet of it, next to the queue
+    pub preview: bool,
+    /// See [crate::normalize_genre]
+    pub genre_rules: String,
+    /// Saves the genres cleaned up by the rules in the tags of the files
+    pub write_genres: bool,
+    /// Whether the tags the files don't have are searched on MusicBrainz, and on Discogs with a
+    /// token, see [crate::metadata::metadata_chain]
+    pub online_metadata: bool,
+    pub discogs_token: String,
+    pub volume_step: u8,
+    /// Seconds skipped by the arrow keys and the buttons next to the playback ones
+    pub seek_step: u16,
+    /// Seconds skipped by the arrow keys with Shift
+    pub long_seek_step: u16,
+    /// Minutes of pause after which playback resumes a little earlier, 0 to never do it
+    pub rewind_after: u16,
+    /// Minutes from which the tracks start where they were left, like DJ mixes, 0 to only do it
+    /// in the folders remembering the positions
+    pub resume_longer_than: u16,
+    /// Seconds gone back by when resuming after [Settings::rewind_after]
+    pub rewind_seconds: u16,
+    /// Largest width and height of the covers embedded from the images next to the tracks
+    pub embedded_cover_size: u16,
+    pub font: Option<String>,
+    pub columns: Vec<ColumnSettings>,
+    pub sort_column: Option<Column>,
+    pub sort_ascending: bool,
+    pub equalizer: Equalizer,
+    pub replay_gain: ReplayGainMode,
+    /// Loudness the ReplayGain brings the tracks to in LUFS, one of [crate::LOUDNESS_TARGETS]
+    pub loudness_target: i8,
+    /// Whether the ReplayGain is lowered for the tracks that would clip
+    pub prevent_clipping: bool,
+    /// Rate in Hz the output stream is fixed to, 0 to follow the tracks
+    pub output_rate: u32,
+    pub bit_depth: BitDepth,
+    /// Whether the tracks with more bits than the output are dithered
+    pub dither: bool,
+    /// `source` of the Snapcast server the tracks are sent to instead of the device, like
+    /// `pipe:///tmp/snapfifo`, empty to play them here
+    pub snapcast: String,
+    pub presets: Vec<EqualizerPreset>,
+    pub organize_pattern: String,
+    pub import_mode: ImportMode,
+    /// Folder outside of the library whose new audio files are offered for import, like the
+    /// downloads
+    pub watched_folder: Option<String>,
+    /// Megabytes the local copies of the next tracks can take, 0 to not copy them
+    pub cache_size: u32,
     pub locale: Option<String>,
-    pub timestamp: Option<u64>,
+    pub last_track: Option<String>,
+    /// Audio files of the current music directory found by the last scan, the library starts
+    /// with them while the folder is walked again
+    pub files: Vec<String>,
+    /// Cached tags of the tracks of the current music directory
     pub tracks: Vec<FileTrack>,
+    /// Music directories opened before, the most recent first, see [Settings::open_folder]
+    pub recent_folders: Vec<String>,
+    /// Cached tags of the tracks of the recent folders by folder, so opening them again doesn't
+    /// read every file
+    pub libraries: HashMap<String, Vec<FileTrack>>,
+    /// Saved in their own file, see [Listening]
+    #[serde(skip)]
+    pub listening: Listening,
+    /// Estimated tempo of the tracks by file name without extension, like the stats, 0 when
+    /// there's no clear beat
+    pub bpm: HashMap<String, f32>,
+    /// Labels given by the user to the tracks by file name without extension, see
+    /// [crate::parse_labels]
+    pub labels: HashMap<String, Vec<String>>,
+    /// When the tracks entered the library by file name without extension, as unix timestamps
+    pub added: HashMap<String, u64>,
+    /// Playback overrides by folder relative to the music directory, see [crate::folder_settings]
+    pub folders: HashMap<String, FolderSettings>,
+    /// Where the tracks of the folders remembering it and the long ones were left by file name
+    /// without extension, in seconds
+    pub positions: HashMap<String, f64>,
+    /// Whether a backup was restored with [Settings::restore_backup], the running library would
+    /// save over it so nothing is saved until the app starts again
+    #[serde(skip)]
+    pub restored: bool,
+}
+
+/// The play stats and the history, they can't be found again like the rest of the settings so
+/// they're saved in their own file, as JSON with the version of its format
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Listening {
+    pub stats: HashMap<String, PlayStats>,
+    /// Every play, the oldest first, see [crate::listening_report]
+    pub history: Vec<PlayRecord>,
 }
 
 impl Settings {
@@ -24,17 +160,25 @@ impl Settings {
         if storage_file.exists() && storage_file.is_file() {
             let mut data = vec![];
             if let Ok(_) = zstd::stream::copy_decode(
-                File::open(storage_file).unwrap(),
+                File::open(&storage_file).unwrap(),
                 BufWriter::new(Cursor::new(&mut data)),
             ) {
-                if let Ok(storage) = bitcode::decode(&data) {
-                    storage
-                } else {
-                    eprintln!("not encoded");
-                    Self::default()
+                match from_versioned(&data) {
+                    Ok(storage) => storage,
+                    Err(e) => {
+                        if let Ok(legacy) = bitcode::decode::<LegacySettings>(&data) {
+                            // saved with bitcode by the older versions, it's JSON from the next save
+                            legacy.into()
+                        } else {
+                            eprintln!("can't read the settings: {e}");
+                            keep_aside(&storage_file);
+                            Self::default()
+                        }
+                    }
                 }
             } else {
                 eprintln!("bad file");
+                keep_aside(&storage_file);
                 Self::default()
             }
         } else {
@@ -44,10 +188,23 @@ impl Settings {
     }
 
     pub async fn read_saved<P: Deref<Target = impl Platform>>(platform: P) -> Self {
-        let storage_file = platform.internal_dir().await.join("config");
-        tokio::task::spawn_blocking(|| Self::read_from_file(storage_file))
-            .await
-            .unwrap()
+        let dir = platform.internal_dir().await;
+        tokio::task::spawn_blocking(move || {
+            let mut settings = Self::read_from_file(dir.join("config"));
+            let listening_file = dir.join(LISTENING_FILE);
+            match read_versioned(&listening_file) {
+                Ok(listening) => settings.listening = listening,
+                // the older versions didn't keep any
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    eprintln!("can't read the stats: {e}");
+                    keep_aside(&listening_file);
+                }
+            }
+            settings
+        })
+        .await
+        .unwrap()
     }
 
     #[cfg(target_os = "android")]
@@ -71,64 +228,410 @@ impl Settings {
         PathBuf::new()
     }
 
-    pub async fn check_timestamp(&self) -> bool {
-        if let Some(saved_timestamp) = &self.timestamp {
-            if let Ok(timestamp) = self.timestamp().await {
-                return &timestamp == saved_timestamp;
-            }
+    /// What the runner gets with [crate::runner::RunnerMessage::SetRewind]
+    pub fn rewind(&self) -> Option<(Duration, f64)> {
+        (self.rewind_after > 0).then(|| {
+            (
+                Duration::from_secs(self.rewind_after as u64 * 60),
+                self.rewind_seconds as f64,
+            )
+        })
+    }
+
+    /// What the player gets with [crate::runner::RunnerMessage::SetOutput]
+    pub fn output_format(&self) -> OutputFormat {
+        OutputFormat {
+            rate: (self.output_rate > 0).then_some(self.output_rate),
+            sample_format: self.bit_depth.into(),
+            dither: self.dither,
+            snapcast: SnapcastSource::from_uri(&self.snapcast),
         }
-        false
     }
 
-    pub async fn save_timestamp(&mut self) {
-        if let Ok(timestamp) = self.timestamp().await {
-            self.timestamp = Some(timestamp);
+    /// What the player gets with [crate::runner::RunnerMessage::SetGainTarget]
+    pub fn gain_target(&self) -> GainTarget {
+        GainTarget {
+            loudness: self.loudness_target as f64,
+            prevent_clipping: self.prevent_clipping,
         }
     }
 
-    pub async fn timestamp(&self) -> std::io::Result<u64> {
-        let mut hasher = DefaultHasher::default();
-        tokio::fs::metadata(&self.path)
-            .await?
-            .modified()?
-            .hash(&mut hasher);
-        Ok(hasher.finish())
+    /// Keeps the stats, the tempo, the labels, the date added, the position and the cached tags of
+    /// a track whose file was moved from `from` to `to`
+    pub fn rename_track(&mut self, from: &Path, to: &Path) {
+        if from == to {
+            return;
+        }
+        let path = to.to_string_lossy().to_string();
+        if let Some(track) = self
+            .tracks
+            .iter_mut()
+            .find(|track| track.path == from.to_string_lossy())
+        {
+            track.path = path;
+        }
+        let (from, to) = (remove_ext(from), remove_ext(to));
+        if let Some(stats) = self.listening.stats.remove(&from) {
+            self.listening.stats.entry(to.clone()).or_insert(stats);
+        }
+        if let Some(bpm) = self.bpm.remove(&from) {
+            self.bpm.entry(to.clone()).or_insert(bpm);
+        }
+        if let Some(labels) = self.labels.remove(&from) {
+            self.labels.entry(to.clone()).or_insert(labels);
+        }
+        if let Some(added) = self.added.remove(&from) {
+            self.added.entry(to.clone()).or_insert(added);
+        }
+        if let Some(position) = self.positions.remove(&from) {
+            self.positions.entry(to.clone()).or_insert(position);
+        }
+        if self.last_track.as_ref() == Some(&from) {
+            self.last_track = Some(to.clone());
+        }
+    }
+
+    /// Makes `path` the music directory, the one it replaces stays among the recent folders
+    ///
+    /// The cached tags move from [Settings::libraries] to [Settings::tracks] once the runner
+    /// switches library, the folders no longer recent lose theirs here
+    pub fn open_folder(&mut self, path: String) {
+        let previous = std::mem::replace(&mut self.path, path.clone());
+        self.files.clear();
+        for folder in [previous, path] {
+            self.recent_folders.retain(|recent| *recent != folder);
+            self.recent_folders.insert(0, folder);
+        }
+        self.recent_folders.truncate(RECENT_FOLDERS);
+        let recent = &self.recent_folders;
+        self.libraries.retain(|folder, _| recent.contains(folder));
     }
 
     pub async fn save<P: Deref<Target = impl Platform>>(&self, platform: P) {
+        if self.restored {
+            return;
+        }
         self.save_and_compress(platform.internal_dir().await).await
     }
 
     async fn save_and_compress(&self, config_dir: PathBuf) {
-        let storage_file = config_dir.join("config");
-        if storage_file.exists() {
-            tokio::fs::remove_file(&storage_file).await.unwrap();
-        }
-        let data = bitcode::encode(self);
-        tokio::task::spawn_blocking(|| {
-            zstd::stream::copy_encode(
-                BufReader::new(Cursor::new(data)),
-                File::create(storage_file).unwrap(),
-                9,
-            )
-            .unwrap();
+        if let Err(e) = self.compress_to(config_dir.join("config")).await {
+            eprintln!("can't save the settings: {e}");
+        }
+        if let Err(e) = self.save_listening(config_dir.join(LISTENING_FILE)).await {
+            eprintln!("can't save the stats: {e}");
+        }
+    }
+
+    async fn save_listening(&self, listening_file: PathBuf) -> io::Result<()> {
+        let data = versioned_json(&self.listening)?;
+        tokio::task::spawn_blocking(move || write_compressed(data, listening_file))
+            .await
+            .unwrap()
+    }
+
+    async fn compress_to(&self, storage_file: PathBuf) -> io::Result<()> {
+        let data = versioned_json(self)?;
+        tokio::task::spawn_blocking(move || write_compressed(data, storage_file))
+            .await
+            .unwrap()
+    }
+
+    /// Saves a copy of everything, the library cache and the stats included, named after the
+    /// current time, that's returned
+    ///
+    /// Like the settings, the backups are saved as JSON along with the version of their format, so
+    /// they can still be restored after the settings change
+    pub async fn back_up<P: Deref<Target = impl Platform>>(&self, platform: P) -> io::Result<u64> {
+        let dir = platform.internal_dir().await.join(BACKUP_DIR);
+        tokio::fs::create_dir_all(&dir).await?;
+        let time = now();
+        let data = versioned_json(Backup {
+            settings: self,
+            listening: &self.listening,
+        })?;
+        let backup = dir.join(time.to_string());
+        tokio::task::spawn_blocking(move || write_compressed(data, backup))
+            .await
+            .unwrap()?;
+        Ok(time)
+    }
+
+    /// Returns the times of the backups made with [Settings::back_up], the newest first
+    pub async fn backups<P: Deref<Target = impl Platform>>(platform: P) -> Vec<u64> {
+        let dir = platform.internal_dir().await.join(BACKUP_DIR);
+        let mut backups = vec![];
+        if let Ok(mut entries) = tokio::fs::read_dir(dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                if let Some(time) = entry
+                    .file_name()
+                    .to_str()
+                    .and_then(|name| name.parse().ok())
+                {
+                    backups.push(time);
+                }
+            }
+        }
+        backups.sort_unstable_by(|a, b| b.cmp(a));
+        backups
+    }
+
+    /// Reads the backup made at `time`, failing when it's missing, damaged or made by a newer
+    /// version of the app
+    pub async fn read_backup<P: Deref<Target = impl Platform>>(
+        platform: P,
+        time: u64,
+    ) -> io::Result<Self> {
+        let backup = platform
+            .internal_dir()
+            .await
+            .join(BACKUP_DIR)
+            .join(time.to_string());
+        tokio::task::spawn_blocking(move || {
+            let backup: Backup<Self, Listening> = read_versioned(&backup)?;
+            let mut settings = backup.settings;
+            settings.listening = backup.listening;
+            Ok(settings)
         })
         .await
-        .unwrap();
+        .unwrap()
+    }
+
+    /// Replaces the settings with the backup made at `time` and saves them, most of them are only
+    /// applied when the app starts so nothing is saved after that, see [Settings::restored]
+    pub async fn restore_backup<P: Deref<Target = impl Platform>>(
+        &mut self,
+        platform: P,
+        time: u64,
+    ) -> io::Result<()> {
+        let dir = platform.internal_dir().await;
+        let backup = Self::read_backup(platform, time).await?;
+        backup.compress_to(dir.join("config")).await?;
+        backup.save_listening(dir.join(LISTENING_FILE)).await?;
+        *self = backup;
+        self.restored = true;
+        Ok(())
+    }
+
+    pub async fn delete_backup<P: Deref<Target = impl Platform>>(
+        platform: P,
+        time: u64,
+    ) -> io::Result<()> {
+        let backup = platform
+            .internal_dir()
+            .await
+            .join(BACKUP_DIR)
+            .join(time.to_string());
+        tokio::fs::remove_file(backup).await
+    }
+}
+
+/// Folder of the backups made with [Settings::back_up], inside the internal directory
+const BACKUP_DIR: &str = "backups";
+/// File of the [Listening] part of the settings, inside the internal directory
+const LISTENING_FILE: &str = "stats";
+/// Format of the files saved as JSON, the settings, the backups and the stats, raised when they
+/// change in a way the older versions can't read, like a field changing type
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Deserialize, Serialize)]
+struct Versioned<T> {
+    version: u32,
+    #[serde(flatten)]
+    data: T,
+}
+
+/// Read first, so the files of newer versions are told apart from the damaged ones
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Backup<S, L> {
+    settings: S,
+    // missing from the backups made before the stats had their own file
+    #[serde(default)]
+    listening: L,
+}
+
+fn versioned_json<T: Serialize>(data: T) -> io::Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&Versioned {
+        version: FORMAT_VERSION,
+        data,
+    })?)
+}
+
+/// Reads a file written with [versioned_json] and [write_compressed], see [from_versioned]
+fn read_versioned<T: DeserializeOwned>(file: &Path) -> io::Result<T> {
+    let mut data = vec![];
+    zstd::stream::copy_decode(File::open(file)?, Cursor::new(&mut data))?;
+    from_versioned(&data)
+}
+
+/// Reads what [versioned_json] made, failing with a clear error when it's damaged or made by a
+/// newer version of the app
+fn from_versioned<T: DeserializeOwned>(data: &[u8]) -> io::Result<T> {
+    let version = serde_json::from_slice::<Version>(data)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "unknown format"))?
+        .version;
+    if version > FORMAT_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("made by a newer version of the app (format {version})"),
+        ));
+    }
+    Ok(serde_json::from_slice::<Versioned<T>>(data)?.data)
+}
+
+/// The settings as the versions before the JSON saved them with bitcode, only read to move them to
+/// the new format, the settings they didn't have take their default value
+#[derive(Decode)]
+struct LegacySettings {
+    path: String,
+    volume: f64,
+    theme: Theme,
+    window_size: WindowSize,
+    save_window_size: bool,
+    locale: Option<String>,
+    _timestamp: Option<u64>,
+    tracks: Vec<LegacyFileTrack>,
+}
+
+#[derive(Decode)]
+struct LegacyFileTrack {
+    path: String,
+    title: String,
+    artist: String,
+    length: f64,
+    image: Vec<u8>,
+}
+
+impl From<LegacySettings> for Settings {
+    fn from(legacy: LegacySettings) -> Self {
+        Self {
+            path: legacy.path,
+            volume: legacy.volume,
+            theme: legacy.theme,
+            window_size: legacy.window_size,
+            save_window_size: legacy.save_window_size,
+            locale: legacy.locale,
+            tracks: legacy.tracks.into_iter().map(FileTrack::from).collect(),
+            ..Self::default()
+        }
     }
 }
 
+impl From<LegacyFileTrack> for FileTrack {
+    fn from(legacy: LegacyFileTrack) -> Self {
+        Self {
+            path: legacy.path,
+            // never matches the file, so the tags they didn't have are read again
+            modified: 0,
+            title: legacy.title,
+            artist: legacy.artist,
+            album: String::new(),
+            genre: String::new(),
+            length: legacy.length,
+            format: String::new(),
+            image: legacy.image,
+            plays: 0,
+        }
+    }
+}
+
+/// Renames the file that can't be read to `.old`, so it isn't saved over and what it has can
+/// still be recovered
+fn keep_aside(file: &Path) {
+    if let Err(e) = std::fs::rename(file, file.with_extension("old")) {
+        eprintln!("can't keep {} aside: {e}", file.display());
+    }
+}
+
+/// Compresses `data` into `file`, written next to the old one and renamed over it since the app
+/// can be killed while saving
+fn write_compressed(data: Vec<u8>, file: PathBuf) -> io::Result<()> {
+    let mut temp = NamedTempFile::new_in(file.parent().unwrap())?;
+    zstd::stream::copy_encode(BufReader::new(Cursor::new(data)), temp.as_file_mut(), 9)?;
+    temp.as_file().sync_all()?;
+    temp.persist(file).map_err(|e| e.error)?;
+    Ok(())
+}
+/// How many music directories are remembered, the current one included
+const RECENT_FOLDERS: usize = 8;
+
 impl Default for Settings {
     fn default() -> Self {
         Self {
             path: Self::music_dir().to_str().unwrap().to_string(),
             volume: 1.0,
+            device_volumes: HashMap::new(),
+            pause_on_disconnect: true,
+            resume_within: 0,
             theme: Theme::default(),
+            accent: Accent::default(),
+            accent_color: 0xff1e88e5,
             window_size: WindowSize::default(),
             save_window_size: false,
+            window_position: None,
+            maximized: false,
+            show_remaining: false,
+            auto_scroll: true,
+            onboarded: false,
+            media_controls: true,
+            plugins: false,
+            grouping: Grouping::default(),
+            folder_view: false,
+            queue_open: false,
+            close_to_tray: false,
+            confirm_exit: false,
+            start_minimized: false,
+            autoplay: false,
+            auto_dj: false,
+            sync_play_counts: false,
+            osd: false,
+            preview: false,
+            genre_rules: DEFAULT_GENRE_RULES.to_string(),
+            write_genres: false,
+            online_metadata: false,
+            discogs_token: String::new(),
+            volume_step: 5,
+            seek_step: 5,
+            long_seek_step: 30,
+            rewind_after: 0,
+            resume_longer_than: 20,
+            rewind_seconds: 10,
+            embedded_cover_size: 600,
+            font: None,
+            columns: default_columns(),
+            sort_column: None,
+            sort_ascending: true,
+            equalizer: Equalizer::default(),
+            replay_gain: ReplayGainMode::default(),
+            loudness_target: -18,
+            prevent_clipping: true,
+            output_rate: 0,
+            bit_depth: BitDepth::default(),
+            dither: false,
+            snapcast: String::new(),
+            presets: vec![],
+            organize_pattern: DEFAULT_ORGANIZE_PATTERN.to_string(),
+            import_mode: ImportMode::default(),
+            watched_folder: None,
+            cache_size: 0,
             locale: None,
-            timestamp: None,
+            last_track: None,
+            files: vec![],
             tracks: vec![],
+            recent_folders: vec![],
+            libraries: HashMap::new(),
+            listening: Listening::default(),
+            bpm: HashMap::new(),
+            labels: HashMap::new(),
+            added: HashMap::new(),
+            folders: HashMap::new(),
+            positions: HashMap::new(),
+            restored: false,
         }
     }
 }
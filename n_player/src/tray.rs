@@ -0,0 +1,221 @@
+use crate::runner::RunnerMessage;
+use crate::{Localization, MainWindow};
+use flume::Sender;
+use slint::ComponentHandle;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tray_icon::{Icon, MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use zune_core::bytestream::ZCursor;
+use zune_core::colorspace::ColorSpace;
+use zune_core::options::DecoderOptions;
+use zune_image::image::Image;
+
+const ICON: &[u8] = include_bytes!("../assets/icons/icon.png");
+const SHOW: &str = "show";
+const TOGGLE_PAUSE: &str = "toggle_pause";
+const STOP: &str = "stop";
+const QUIT: &str = "quit";
+
+static AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    #[cfg(not(target_os = "linux"))]
+    static TRAY: RefCell<Option<TrayIcon>> = const { RefCell::new(None) };
+    static EVENTS: RefCell<Option<slint::Timer>> = const { RefCell::new(None) };
+    // lives on the thread that built the tray, the gtk one on linux
+    static ITEMS: RefCell<Option<Items>> = const { RefCell::new(None) };
+    #[cfg(target_os = "linux")]
+    static RELABEL: RefCell<Option<std::sync::mpsc::Sender<Labels>>> = const { RefCell::new(None) };
+}
+
+/// Labels of the tray menu, read from [Localization] on the UI thread
+struct Labels {
+    show: String,
+    toggle_pause: String,
+    stop: String,
+    quit: String,
+}
+
+impl Labels {
+    fn new(window: &MainWindow) -> Self {
+        let localization = window.global::<Localization>();
+        Self {
+            show: localization.get_show_window().into(),
+            toggle_pause: localization.get_play_pause().into(),
+            stop: localization.get_stop().into(),
+            quit: localization.get_quit().into(),
+        }
+    }
+}
+
+/// The menu items whose labels follow the language
+struct Items {
+    show: MenuItem,
+    toggle_pause: MenuItem,
+    stop: MenuItem,
+    quit: MenuItem,
+}
+
+/// Returns true once the tray icon has been created, hiding the window is safe only then
+///
+/// On macOS the icon is created after the event loop starts, check it from inside the event loop
+pub fn is_available() -> bool {
+    AVAILABLE.load(Ordering::Relaxed)
+}
+
+/// Adds the app to the system tray with a menu to show the window, toggle pause, stop and quit,
+/// must be called on the UI thread
+pub fn run(window: &MainWindow, tx: Sender<RunnerMessage>) {
+    let labels = Labels::new(window);
+
+    // on linux the tray lives in a gtk main loop, wait for it so is_available is already right
+    #[cfg(target_os = "linux")]
+    {
+        let (tx_ready, rx_ready) = std::sync::mpsc::channel();
+        let (tx_labels, rx_labels) = std::sync::mpsc::channel();
+        RELABEL.with(|relabel| *relabel.borrow_mut() = Some(tx_labels));
+        std::thread::spawn(move || {
+            if gtk::init().is_err() {
+                eprintln!("can't initialize gtk, the tray icon won't be shown");
+                let _ = tx_ready.send(());
+                return;
+            }
+            let _tray = build(labels);
+            let _ = tx_ready.send(());
+            // the menu items can only be changed from here
+            gtk::glib::timeout_add_local(Duration::from_millis(100), move || {
+                while let Ok(labels) = rx_labels.try_recv() {
+                    set_labels(labels);
+                }
+                gtk::glib::ControlFlow::Continue
+            });
+            gtk::main();
+        });
+        let _ = rx_ready.recv_timeout(Duration::from_secs(1));
+    }
+    #[cfg(target_os = "windows")]
+    TRAY.with(|tray| *tray.borrow_mut() = build(labels));
+    // on macOS the tray can only be created once the event loop is running
+    #[cfg(target_os = "macos")]
+    slint::Timer::single_shot(Duration::ZERO, move || {
+        TRAY.with(|tray| *tray.borrow_mut() = build(labels));
+    });
+
+    let window = window.as_weak();
+    let timer = slint::Timer::default();
+    timer.start(
+        slint::TimerMode::Repeated,
+        Duration::from_millis(100),
+        move || {
+            while let Ok(event) = MenuEvent::receiver().try_recv() {
+                match event.id().as_ref() {
+                    SHOW => show(&window),
+                    TOGGLE_PAUSE => {
+                        let _ = tx.send(RunnerMessage::TogglePause);
+                    }
+                    STOP => {
+                        let _ = tx.send(RunnerMessage::Stop);
+                    }
+                    QUIT => {
+                        let _ = slint::quit_event_loop();
+                    }
+                    _ => {}
+                }
+            }
+            while let Ok(event) = TrayIconEvent::receiver().try_recv() {
+                if let TrayIconEvent::Click {
+                    button: MouseButton::Left,
+                    button_state: MouseButtonState::Up,
+                    ..
+                } = event
+                {
+                    show(&window);
+                }
+            }
+        },
+    );
+    EVENTS.with(|events| *events.borrow_mut() = Some(timer));
+}
+
+/// Updates the tray menu to the current language, must be called on the UI thread
+pub fn relabel(window: &MainWindow) {
+    let labels = Labels::new(window);
+    #[cfg(target_os = "linux")]
+    RELABEL.with(|relabel| {
+        if let Some(tx_labels) = relabel.borrow().as_ref() {
+            let _ = tx_labels.send(labels);
+        }
+    });
+    #[cfg(not(target_os = "linux"))]
+    set_labels(labels);
+}
+
+fn set_labels(labels: Labels) {
+    ITEMS.with(|items| {
+        if let Some(items) = items.borrow().as_ref() {
+            items.show.set_text(labels.show);
+            items.toggle_pause.set_text(labels.toggle_pause);
+            items.stop.set_text(labels.stop);
+            items.quit.set_text(labels.quit);
+        }
+    });
+}
+
+fn show(window: &slint::Weak<MainWindow>) {
+    if let Some(window) = window.upgrade() {
+        if let Err(e) = window.show() {
+            eprintln!("can't show the window: {e}");
+        }
+    }
+}
+
+fn build(labels: Labels) -> Option<TrayIcon> {
+    let menu = Menu::new();
+    let items = Items {
+        show: MenuItem::with_id(SHOW, labels.show, true, None),
+        toggle_pause: MenuItem::with_id(TOGGLE_PAUSE, labels.toggle_pause, true, None),
+        stop: MenuItem::with_id(STOP, labels.stop, true, None),
+        quit: MenuItem::with_id(QUIT, labels.quit, true, None),
+    };
+    if let Err(e) = menu.append_items(&[
+        &items.show as &dyn tray_icon::menu::IsMenuItem,
+        &items.toggle_pause,
+        &items.stop,
+        &PredefinedMenuItem::separator(),
+        &items.quit,
+    ]) {
+        eprintln!("can't create the tray menu: {e}");
+        return None;
+    }
+    ITEMS.with(|cell| *cell.borrow_mut() = Some(items));
+    let mut builder = TrayIconBuilder::new()
+        .with_menu(Box::new(menu))
+        .with_tooltip("N Music");
+    if let Some(icon) = icon() {
+        builder = builder.with_icon(icon);
+    }
+    match builder.build() {
+        Ok(tray) => {
+            AVAILABLE.store(true, Ordering::Relaxed);
+            Some(tray)
+        }
+        Err(e) => {
+            eprintln!("can't create the tray icon: {e}");
+            None
+        }
+    }
+}
+
+fn icon() -> Option<Icon> {
+    let mut image = Image::read(ZCursor::new(ICON), DecoderOptions::new_fast()).ok()?;
+    image.convert_color(ColorSpace::RGBA).ok()?;
+    let (width, height) = image.dimensions();
+    Icon::from_rgba(
+        image.flatten_to_u8()[0].clone(),
+        width as u32,
+        height as u32,
+    )
+    .ok()
+}